@@ -0,0 +1,52 @@
+// Throughput of BatchPoseidon against one Poseidon::hash per message, for the
+// order layout (t = 12, F = 6, P = 53).
+//
+//     cargo bench --features simd --bench batch_poseidon
+//
+// A plain timing loop rather than a harness with statistics: the two paths
+// differ by far more than the run-to-run noise.
+
+use loopring_sign::poseidon::{batch::BatchPoseidon, permutation::Poseidon};
+use num_bigint::BigInt;
+use std::hint::black_box;
+use std::time::Instant;
+
+const MESSAGES: u64 = 2048;
+
+fn measure<F: FnMut() -> Vec<BigInt>>(name: &str, mut run: F) -> Vec<BigInt> {
+    // One untimed run so both paths start with their constants built
+    let hashes = run();
+    let start = Instant::now();
+    black_box(run());
+    let elapsed = start.elapsed();
+    println!(
+        "{:<16} {:>10.1} hashes/s ({:?} for {} messages)",
+        name,
+        MESSAGES as f64 / elapsed.as_secs_f64(),
+        elapsed,
+        MESSAGES
+    );
+    hashes
+}
+
+fn main() {
+    let messages: Vec<Vec<BigInt>> = (0..MESSAGES)
+        .map(|m| (0..11u64).map(|i| BigInt::from(m * 1000 + i)).collect())
+        .collect();
+
+    let poseidon = Poseidon::shared(12, 6, 53);
+    let scalar = measure("scalar", || {
+        messages
+            .iter()
+            .map(|message| poseidon.hash(message.clone()).unwrap())
+            .collect()
+    });
+
+    let batch4 = BatchPoseidon::<4>::shared(12, 6, 53);
+    let lanes4 = measure("batch, 4 lanes", || batch4.hash_many(&messages).unwrap());
+    let batch8 = BatchPoseidon::<8>::shared(12, 6, 53);
+    let lanes8 = measure("batch, 8 lanes", || batch8.hash_many(&messages).unwrap());
+
+    assert_eq!(scalar, lanes4);
+    assert_eq!(scalar, lanes8);
+}
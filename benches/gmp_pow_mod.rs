@@ -0,0 +1,62 @@
+// Cost of one pow_mod on num-bigint against GMP, including the limb copies the
+// gmp feature makes on every call (poseidon::bignum).
+//
+//     cargo bench --features gmp --bench gmp_pow_mod
+//
+// The two exponents the crate uses: p - 2 for every field inversion, and 5 for
+// the Poseidon S-boxes. A plain timing loop, like benches/batch_poseidon.rs.
+
+use loopring_sign::poseidon::field::SNARK_SCALAR_FIELD;
+use num_bigint::BigUint;
+use rug::{integer::Order, Integer};
+use std::hint::black_box;
+use std::time::Instant;
+
+const CALLS: u32 = 20_000;
+
+// The same conversions as bignum::pow_mod with the gmp feature
+fn gmp_pow_mod(base: &BigUint, exponent: &BigUint, m: &BigUint) -> BigUint {
+    let to_integer = |n: &BigUint| Integer::from_digits(&n.to_u64_digits(), Order::Lsf);
+    let result = to_integer(base)
+        .pow_mod(&to_integer(exponent), &to_integer(m))
+        .unwrap();
+    let mut digits = result.to_digits::<u32>(Order::Lsf);
+    if digits.is_empty() {
+        digits.push(0);
+    }
+    BigUint::new(digits)
+}
+
+fn measure<F: Fn(&BigUint) -> BigUint>(name: &str, bases: &[BigUint], run: F) -> f64 {
+    let start = Instant::now();
+    for i in 0..CALLS {
+        black_box(run(&bases[i as usize % bases.len()]));
+    }
+    let per_call = start.elapsed().as_secs_f64() * 1e9 / CALLS as f64;
+    println!("{:<28} {:>10.0} ns/call", name, per_call);
+    per_call
+}
+
+fn main() {
+    let p = SNARK_SCALAR_FIELD.to_biguint().unwrap();
+    let bases: Vec<BigUint> = (1..=64u32)
+        .map(|i| BigUint::from(7u8).pow(80 + i) % &p)
+        .collect();
+
+    for (label, exponent) in [("x^(p-2)", &p - 2u8), ("x^5", BigUint::from(5u8))] {
+        for base in &bases {
+            assert_eq!(base.modpow(&exponent, &p), gmp_pow_mod(base, &exponent, &p));
+        }
+        let num_bigint = measure(&format!("{} num-bigint", label), &bases, |base| {
+            base.modpow(&exponent, &p)
+        });
+        let gmp = measure(&format!("{} gmp + copies", label), &bases, |base| {
+            gmp_pow_mod(base, &exponent, &p)
+        });
+        println!(
+            "{:<28} {:>10.2}x",
+            format!("{} speed-up", label),
+            num_bigint / gmp
+        );
+    }
+}
@@ -0,0 +1,150 @@
+// #[derive(LoopringHash)] for structs whose fields form a payload preimage.
+//
+// Every hashed field is annotated with its bit width in the circuit and becomes
+// one Poseidon input:
+//
+//     #[derive(LoopringHash)]
+//     struct Withdrawal {
+//         #[loopring(bits = 160)]
+//         exchange: String,
+//         #[loopring(bits = 32)]
+//         account_id: u32,
+//         #[loopring(bits = 96, order = 2)]
+//         amount: BigInt,
+//         #[loopring(skip)]
+//         memo: String,
+//     }
+//
+// The inputs are in declaration order unless the fields give an `order`, in
+// which case all hashed fields must have one. The field values are converted
+// with loopring_sign's HashField trait and range checked against their width,
+// so the generated code only depends on the public API of loopring_sign.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, LitInt};
+
+// The largest width that always stays below SNARK_SCALAR_FIELD
+const MAX_BITS: u32 = 253;
+
+struct HashedField {
+    ident: syn::Ident,
+    bits: u32,
+    order: Option<u32>,
+}
+
+fn parse_field(field: &syn::Field) -> Result<Option<HashedField>, Error> {
+    let ident = field.ident.clone().unwrap();
+    let mut bits = None;
+    let mut order = None;
+    let mut skip = false;
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("loopring")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") {
+                skip = true;
+            } else if meta.path.is_ident("bits") {
+                let value: LitInt = meta.value()?.parse()?;
+                let value: u32 = value.base10_parse()?;
+                if value == 0 || value > MAX_BITS {
+                    return Err(meta.error(format!("bits must be between 1 and {}", MAX_BITS)));
+                }
+                bits = Some(value);
+            } else if meta.path.is_ident("order") {
+                let value: LitInt = meta.value()?.parse()?;
+                order = Some(value.base10_parse()?);
+            } else {
+                return Err(meta.error("expected `bits`, `order` or `skip`"));
+            }
+            Ok(())
+        })?;
+    }
+    if skip {
+        return Ok(None);
+    }
+    match bits {
+        Some(bits) => Ok(Some(HashedField { ident, bits, order })),
+        None => Err(Error::new_spanned(
+            field,
+            "field needs #[loopring(bits = N)] or #[loopring(skip)]",
+        )),
+    }
+}
+
+fn expand(input: DeriveInput) -> Result<proc_macro2::TokenStream, Error> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    &input.ident,
+                    "LoopringHash needs a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "LoopringHash can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut hashed = Vec::new();
+    for field in fields {
+        if let Some(field) = parse_field(field)? {
+            hashed.push(field);
+        }
+    }
+    if hashed.is_empty() {
+        return Err(Error::new_spanned(&input.ident, "no field is hashed"));
+    }
+
+    let ordered = hashed.iter().filter(|f| f.order.is_some()).count();
+    if ordered > 0 {
+        if ordered != hashed.len() {
+            return Err(Error::new(
+                Span::call_site(),
+                "either all hashed fields or none must have an `order`",
+            ));
+        }
+        hashed.sort_by_key(|f| f.order);
+        if hashed.windows(2).any(|w| w[0].order == w[1].order) {
+            return Err(Error::new(Span::call_site(), "duplicate `order`"));
+        }
+    }
+
+    let inputs = hashed.iter().map(|HashedField { ident, bits, .. }| {
+        let name = ident.to_string();
+        quote! {
+            ::loopring_sign::payload::hashable::HashField::to_field_element(
+                &self.#ident, #name, #bits,
+            )?
+        }
+    });
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::loopring_sign::payload::hashable::LoopringHash
+            for #ident #ty_generics #where_clause
+        {
+            fn poseidon_inputs(
+                &self,
+            ) -> ::std::result::Result<
+                ::std::vec::Vec<::loopring_sign::payload::hashable::BigInt>,
+                ::loopring_sign::util::errors::PayloadError,
+            > {
+                ::std::result::Result::Ok(::std::vec![#(#inputs),*])
+            }
+        }
+    })
+}
+
+#[proc_macro_derive(LoopringHash, attributes(loopring))]
+pub fn derive_loopring_hash(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,253 @@
+// Command line signer.
+//
+//     loopring_sign sign-batch --key <source> --input <file> --output <file>
+//                              [--format json|csv]
+//     loopring_sign serve-stdio --key <source>
+//     loopring_sign serve-http --key <source> --token-file <file> [--listen <addr>]
+//                              [--audit-log <file>] [--policy <file>]
+//                              [--verify-signatures] [--enable-keygen]
+//
+// sign-batch reads a payload file (see `payload::file` for the format), signs
+// every entry and writes the results as a JSON array. Nothing is sent over the
+// network, so the command can run on an air-gapped machine; only the payload and
+// result files have to be carried across.
+//
+// serve-stdio answers line-delimited JSON signing requests on stdin until the
+// input ends (see `signer::stdio` for the protocol), for services that keep the
+// key in a subprocess.
+//
+// serve-http (`service` feature) runs the HTTP signing service of
+// `loopring_sign::service` on the given address (127.0.0.1:8080 by default), with
+// every signing attempt appended to the audit log as a JSON line (stderr by
+// default). Clients authenticate with the bearer token read from --token-file, or
+// from LOOPRING_SIGN_TOKEN if no file is given; the service does not start
+// without one. --policy names a JSON file in the `service::OperationPolicy` form
+// that restricts the operations and payload types served. /v1/keygen, which
+// returns private keys, is only served with --enable-keygen. With
+// --verify-signatures, every signature is verified before it is returned (see
+// `signer::verifying`).
+//
+// The key source is given in the `signer::key_source` syntax, e.g.
+// `keystore:/media/usb/key.json` (passphrase prompted) or `file:key.txt`;
+// `--key-file <path>` is short for `--key file:<path>`. The format defaults to the
+// input file's extension.
+
+use loopring_sign::payload::file::{parse_csv, parse_json, sign_entries};
+#[cfg(feature = "service")]
+use loopring_sign::service::{JsonLinesSink, OperationPolicy, SigningService};
+use loopring_sign::signer::key_source::KeySource;
+use loopring_sign::signer::stdio;
+#[cfg(feature = "service")]
+use loopring_sign::signer::verifying::VerifyingSigner;
+use loopring_sign::util::errors::SignerError;
+use std::process::ExitCode;
+use std::{env, fs, io};
+
+const USAGE: &str = "usage: loopring_sign sign-batch --key <source> --input <file> --output <file> [--format json|csv]
+       loopring_sign serve-stdio --key <source>
+       loopring_sign serve-http --key <source> --token-file <file> [--listen <addr>] [--audit-log <file>]
+                                [--policy <file>] [--verify-signatures] [--enable-keygen]";
+
+#[cfg(feature = "service")]
+const DEFAULT_LISTEN: &str = "127.0.0.1:8080";
+#[cfg(feature = "service")]
+const TOKEN_VARIABLE: &str = "LOOPRING_SIGN_TOKEN";
+
+struct Options {
+    key: KeySource,
+    input: String,
+    output: String,
+    format: String,
+}
+
+fn key_source(key: Option<String>, key_file: Option<String>) -> Result<KeySource, String> {
+    match (key, key_file) {
+        (Some(source), None) => source.parse().map_err(|e: SignerError| e.to_string()),
+        (None, Some(path)) => Ok(KeySource::File(path.into())),
+        _ => Err("exactly one of --key and --key-file is required".to_string()),
+    }
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut key = None;
+    let mut key_file = None;
+    let mut input = None;
+    let mut output = None;
+    let mut format = None;
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let slot = match flag.as_str() {
+            "--key" => &mut key,
+            "--key-file" => &mut key_file,
+            "--input" => &mut input,
+            "--output" => &mut output,
+            "--format" => &mut format,
+            other => return Err(format!("unknown option {}", other)),
+        };
+        match args.next() {
+            Some(value) => *slot = Some(value.clone()),
+            None => return Err(format!("{} needs a value", flag)),
+        }
+    }
+
+    let input: String = input.ok_or("--input is required")?;
+    let format = format.unwrap_or_else(|| {
+        if input.to_ascii_lowercase().ends_with(".csv") {
+            "csv".to_string()
+        } else {
+            "json".to_string()
+        }
+    });
+    Ok(Options {
+        key: key_source(key, key_file)?,
+        input,
+        output: output.ok_or("--output is required")?,
+        format,
+    })
+}
+
+fn sign_batch(options: &Options) -> Result<usize, String> {
+    let signer = options.key.load().map_err(|e| e.to_string())?;
+
+    let content = fs::read_to_string(&options.input)
+        .map_err(|e| format!("cannot read {}: {}", options.input, e))?;
+    let entries = match options.format.as_str() {
+        "json" => parse_json(&content),
+        "csv" => parse_csv(&content),
+        other => return Err(format!("unknown format {}", other)),
+    }
+    .map_err(|e| e.to_string())?;
+
+    let results = sign_entries(&signer, &entries);
+    let failed = results.iter().filter(|r| r.get("error").is_some()).count();
+    let output = serde_json::to_string_pretty(&results).unwrap();
+    fs::write(&options.output, output + "\n")
+        .map_err(|e| format!("cannot write {}: {}", options.output, e))?;
+    Ok(failed)
+}
+
+fn serve_stdio(args: &[String]) -> Result<usize, String> {
+    let (key, key_file) = match args {
+        [flag, value] if flag == "--key" => (Some(value.clone()), None),
+        [flag, value] if flag == "--key-file" => (None, Some(value.clone())),
+        _ => return Err(USAGE.to_string()),
+    };
+    let signer = key_source(key, key_file)?
+        .load()
+        .map_err(|e| e.to_string())?;
+    stdio::serve(&signer, io::stdin().lock(), io::stdout().lock()).map_err(|e| e.to_string())?;
+    Ok(0)
+}
+
+#[cfg(feature = "service")]
+fn serve_http(args: &[String]) -> Result<usize, String> {
+    let mut key = None;
+    let mut key_file = None;
+    let mut listen = None;
+    let mut audit_log = None;
+    let mut token_file = None;
+    let mut policy_file = None;
+    let mut verify = false;
+    let mut keygen = false;
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let slot = match flag.as_str() {
+            "--verify-signatures" => {
+                verify = true;
+                continue;
+            }
+            "--enable-keygen" => {
+                keygen = true;
+                continue;
+            }
+            "--key" => &mut key,
+            "--key-file" => &mut key_file,
+            "--listen" => &mut listen,
+            "--audit-log" => &mut audit_log,
+            "--token-file" => &mut token_file,
+            "--policy" => &mut policy_file,
+            other => return Err(format!("unknown option {}", other)),
+        };
+        match args.next() {
+            Some(value) => *slot = Some(value.clone()),
+            None => return Err(format!("{} needs a value", flag)),
+        }
+    }
+
+    let token = match token_file {
+        Some(path) => {
+            fs::read_to_string(&path).map_err(|e| format!("cannot read {}: {}", path, e))?
+        }
+        None => env::var(TOKEN_VARIABLE).unwrap_or_default(),
+    };
+    let token = token.trim().to_string();
+    if token.is_empty() {
+        return Err(format!(
+            "serve-http needs a bearer token in --token-file or {}",
+            TOKEN_VARIABLE
+        ));
+    }
+    let policy = match policy_file {
+        Some(path) => {
+            let content =
+                fs::read_to_string(&path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+            let value = serde_json::from_str(&content).map_err(|e| format!("{}: {}", path, e))?;
+            OperationPolicy::from_json(&value).map_err(|e| format!("{}: {}", path, e))?
+        }
+        None => OperationPolicy::default(),
+    };
+
+    let signer = key_source(key, key_file)?
+        .load()
+        .map_err(|e| e.to_string())?;
+    let audit: Box<dyn io::Write + Send> = match audit_log {
+        Some(path) => Box::new(
+            fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .map_err(|e| format!("cannot open {}: {}", path, e))?,
+        ),
+        None => Box::new(io::stderr()),
+    };
+    let listen = listen.unwrap_or_else(|| DEFAULT_LISTEN.to_string());
+    let listener = std::net::TcpListener::bind(&listen)
+        .map_err(|e| format!("cannot listen on {}: {}", listen, e))?;
+    let signer = VerifyingSigner::with_verification(signer, verify);
+    let service =
+        SigningService::new(signer, JsonLinesSink::new(audit), policy).require_token(token);
+    let service = if keygen {
+        service.enable_keygen()
+    } else {
+        service
+    };
+    service.serve(listener).map_err(|e| e.to_string())?;
+    Ok(0)
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("sign-batch") => parse_options(&args[1..]).and_then(|options| sign_batch(&options)),
+        Some("serve-stdio") => serve_stdio(&args[1..]),
+        #[cfg(feature = "service")]
+        Some("serve-http") => serve_http(&args[1..]),
+        _ => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(0) => ExitCode::SUCCESS,
+        Ok(failed) => {
+            eprintln!(
+                "{} entries could not be signed, see the output file",
+                failed
+            );
+            ExitCode::from(2)
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,321 @@
+// Binary encodings of points, signatures and keys (`borsh` and `bincode`
+// features), for databases and binary RPC where the hex forms are wasteful.
+//
+// Both formats use the same layout: a fixed number of 32-byte little-endian
+// words, without length prefixes or tags.
+//
+//     FQ, MessageHash                      32 bytes
+//     Point                     x, y       64 bytes
+//     Signature                 Rx, Ry, S  96 bytes
+//     SignedMessage             A, R, S, message hash  192 bytes
+//     Account, LocalSigner, DualAuthKey    the private key, 32 bytes
+//
+// Key types only store the private key; the public key is recomputed on
+// decoding, so a stored pair cannot disagree. Decoding rejects what parsing the
+// hex forms rejects: coordinates and hashes not below SNARK_SCALAR_FIELD, S not
+// below JUBJUB_E, points off the curve and zero keys. Field elements with a
+// custom modulus (FQ::with_modulus) cannot be encoded.
+
+use crate::constants::snark_scalar_field_uint;
+use crate::keygen::dual_auth::DualAuthKey;
+use crate::keygen::l2_key::Account;
+use crate::poseidon::eddsa::{is_signing_key, MessageHash, Signature, SignedMessage};
+use crate::poseidon::field::{FQ, SNARK_SCALAR_FIELD};
+use crate::poseidon::jubjub::Point;
+use crate::signer::LocalSigner;
+use crate::util::errors::ConversionError;
+use num_bigint::{BigInt, Sign};
+use num_traits::Zero;
+
+const WORD: usize = 32;
+
+trait FixedWidth: Sized {
+    const WORDS: usize;
+
+    fn write_words(&self, out: &mut Vec<u8>) -> Result<(), ConversionError>;
+
+    // `bytes` is exactly WORDS * 32 bytes long
+    fn read_words(bytes: &[u8]) -> Result<Self, ConversionError>;
+
+    fn to_fixed_bytes(&self) -> Result<Vec<u8>, ConversionError> {
+        let mut out = Vec::with_capacity(Self::WORDS * WORD);
+        self.write_words(&mut out)?;
+        Ok(out)
+    }
+}
+
+fn read_below(bytes: &[u8], modulus: &BigInt) -> Result<BigInt, ConversionError> {
+    let n = BigInt::from_bytes_le(Sign::Plus, bytes);
+    if n >= *modulus {
+        return Err(ConversionError::ValueExceedsModulus);
+    }
+    Ok(n)
+}
+
+fn write_scalar(n: &BigInt, out: &mut Vec<u8>) -> Result<(), ConversionError> {
+    let (_, le) = n.to_bytes_le();
+    if le.len() > WORD {
+        return Err(ConversionError::ValueTooLarge);
+    }
+    out.extend_from_slice(&le);
+    out.resize(out.len() + WORD - le.len(), 0);
+    Ok(())
+}
+
+fn read_private_key(bytes: &[u8]) -> Result<BigInt, ConversionError> {
+    let n = BigInt::from_bytes_le(Sign::Plus, bytes);
+    if !is_signing_key(&n) {
+        return Err(ConversionError::ZeroKey);
+    }
+    Ok(n)
+}
+
+impl FixedWidth for FQ {
+    const WORDS: usize = 1;
+
+    fn write_words(&self, out: &mut Vec<u8>) -> Result<(), ConversionError> {
+        if self.m_uint() != snark_scalar_field_uint() {
+            return Err(ConversionError::ModulusMismatch);
+        }
+        out.extend_from_slice(&self.to_bytes_le());
+        Ok(())
+    }
+
+    fn read_words(bytes: &[u8]) -> Result<Self, ConversionError> {
+        Ok(FQ::new(read_below(bytes, &SNARK_SCALAR_FIELD)?))
+    }
+}
+
+impl FixedWidth for MessageHash {
+    const WORDS: usize = 1;
+
+    fn write_words(&self, out: &mut Vec<u8>) -> Result<(), ConversionError> {
+        out.extend_from_slice(&self.to_bytes_le());
+        Ok(())
+    }
+
+    fn read_words(bytes: &[u8]) -> Result<Self, ConversionError> {
+        Ok(MessageHash::new(read_below(bytes, &SNARK_SCALAR_FIELD)?))
+    }
+}
+
+impl FixedWidth for Point {
+    const WORDS: usize = 2;
+
+    fn write_words(&self, out: &mut Vec<u8>) -> Result<(), ConversionError> {
+        self.x().write_words(out)?;
+        self.y().write_words(out)
+    }
+
+    fn read_words(bytes: &[u8]) -> Result<Self, ConversionError> {
+        Point::from_bytes(bytes.try_into().unwrap())
+    }
+}
+
+impl FixedWidth for Signature {
+    const WORDS: usize = 3;
+
+    fn write_words(&self, out: &mut Vec<u8>) -> Result<(), ConversionError> {
+        self.image_of_r().write_words(out)?;
+        self.s().write_words(out)
+    }
+
+    fn read_words(bytes: &[u8]) -> Result<Self, ConversionError> {
+        Signature::from_bytes(bytes.try_into().unwrap())
+    }
+}
+
+impl FixedWidth for SignedMessage {
+    const WORDS: usize = 6;
+
+    fn write_words(&self, out: &mut Vec<u8>) -> Result<(), ConversionError> {
+        self.public_key().write_words(out)?;
+        self.sig().write_words(out)?;
+        if *self.msg() < BigInt::zero() || *self.msg() >= *SNARK_SCALAR_FIELD {
+            return Err(ConversionError::ValueExceedsModulus);
+        }
+        write_scalar(self.msg(), out)
+    }
+
+    fn read_words(bytes: &[u8]) -> Result<Self, ConversionError> {
+        Ok(SignedMessage::new(
+            Point::read_words(&bytes[..2 * WORD])?,
+            Signature::read_words(&bytes[2 * WORD..5 * WORD])?,
+            read_below(&bytes[5 * WORD..], &SNARK_SCALAR_FIELD)?,
+        ))
+    }
+}
+
+impl FixedWidth for Account {
+    const WORDS: usize = 1;
+
+    fn write_words(&self, out: &mut Vec<u8>) -> Result<(), ConversionError> {
+        write_scalar(self.private_scalar(), out)
+    }
+
+    fn read_words(bytes: &[u8]) -> Result<Self, ConversionError> {
+        Ok(Account::from_private_scalar(read_private_key(bytes)?))
+    }
+}
+
+impl FixedWidth for LocalSigner {
+    const WORDS: usize = 1;
+
+    fn write_words(&self, out: &mut Vec<u8>) -> Result<(), ConversionError> {
+        write_scalar(self.private_scalar(), out)
+    }
+
+    fn read_words(bytes: &[u8]) -> Result<Self, ConversionError> {
+        LocalSigner::new(read_private_key(bytes)?).map_err(|_| ConversionError::ZeroKey)
+    }
+}
+
+impl FixedWidth for DualAuthKey {
+    const WORDS: usize = 1;
+
+    fn write_words(&self, out: &mut Vec<u8>) -> Result<(), ConversionError> {
+        write_scalar(self.private_scalar(), out)
+    }
+
+    fn read_words(bytes: &[u8]) -> Result<Self, ConversionError> {
+        DualAuthKey::new(read_private_key(bytes)?).map_err(|_| ConversionError::ZeroKey)
+    }
+}
+
+macro_rules! impl_binary_formats {
+    ($($ty:ty),*) => {
+        $(
+            #[cfg(feature = "borsh")]
+            impl borsh::BorshSerialize for $ty {
+                fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+                    let bytes = self.to_fixed_bytes().map_err(|e| {
+                        std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string())
+                    })?;
+                    writer.write_all(&bytes)
+                }
+            }
+
+            #[cfg(feature = "borsh")]
+            impl borsh::BorshDeserialize for $ty {
+                fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+                    let mut bytes = vec![0u8; <$ty as FixedWidth>::WORDS * WORD];
+                    reader.read_exact(&mut bytes)?;
+                    <$ty as FixedWidth>::read_words(&bytes).map_err(|e| {
+                        std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())
+                    })
+                }
+            }
+
+            #[cfg(feature = "bincode")]
+            impl bincode::Encode for $ty {
+                fn encode<E: bincode::enc::Encoder>(
+                    &self,
+                    encoder: &mut E,
+                ) -> Result<(), bincode::error::EncodeError> {
+                    use bincode::enc::write::Writer;
+                    let bytes = self
+                        .to_fixed_bytes()
+                        .map_err(|e| bincode::error::EncodeError::OtherString(e.to_string()))?;
+                    encoder.writer().write(&bytes)
+                }
+            }
+
+            #[cfg(feature = "bincode")]
+            impl<Context> bincode::Decode<Context> for $ty {
+                fn decode<D: bincode::de::Decoder<Context = Context>>(
+                    decoder: &mut D,
+                ) -> Result<Self, bincode::error::DecodeError> {
+                    use bincode::de::read::Reader;
+                    let len = <$ty as FixedWidth>::WORDS * WORD;
+                    decoder.claim_bytes_read(len)?;
+                    let mut bytes = vec![0u8; len];
+                    decoder.reader().read(&mut bytes)?;
+                    <$ty as FixedWidth>::read_words(&bytes)
+                        .map_err(|e| bincode::error::DecodeError::OtherString(e.to_string()))
+                }
+            }
+
+            #[cfg(feature = "bincode")]
+            bincode::impl_borrow_decode!($ty);
+        )*
+    };
+}
+
+impl_binary_formats!(
+    FQ,
+    MessageHash,
+    Point,
+    Signature,
+    SignedMessage,
+    Account,
+    LocalSigner,
+    DualAuthKey
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signer::Signer;
+
+    const L2_KEY: &str = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+
+    fn signed() -> SignedMessage {
+        let signer = LocalSigner::from_hex(L2_KEY).unwrap();
+        signer.sign(&MessageHash::from_message("message")).unwrap()
+    }
+
+    #[cfg(feature = "borsh")]
+    #[test]
+    fn borsh_round_trips() {
+        let signed = signed();
+        let bytes = borsh::to_vec(&signed).unwrap();
+        assert_eq!(bytes.len(), 192);
+        let decoded: SignedMessage = borsh::from_slice(&bytes).unwrap();
+        assert_eq!(decoded.to_hex(), signed.to_hex());
+
+        let signer = LocalSigner::from_hex(L2_KEY).unwrap();
+        let restored: LocalSigner = borsh::from_slice(&borsh::to_vec(&signer).unwrap()).unwrap();
+        assert!(restored.public_key() == signer.public_key());
+        let key = DualAuthKey::from_entropy(b"binary test entropy").unwrap();
+        let restored: DualAuthKey = borsh::from_slice(&borsh::to_vec(&key).unwrap()).unwrap();
+        assert_eq!(restored.private_key_hex(), key.private_key_hex());
+
+        // Off-curve R, short input, zero key
+        let mut tampered = bytes.clone();
+        tampered[64] ^= 1;
+        assert!(borsh::from_slice::<SignedMessage>(&tampered).is_err());
+        assert!(borsh::from_slice::<Signature>(&bytes[64..150]).is_err());
+        assert!(borsh::from_slice::<LocalSigner>(&[0u8; 32]).is_err());
+    }
+
+    #[cfg(feature = "bincode")]
+    #[test]
+    fn bincode_round_trips() {
+        use crate::poseidon::{eddsa::SignatureScheme, jubjub::JUBJUB_E};
+        let config = bincode::config::standard();
+        let signed = signed();
+        let bytes = bincode::encode_to_vec(signed.sig(), config).unwrap();
+        assert_eq!(&bytes[..], &signed.sig().to_fixed_bytes().unwrap()[..]);
+        let (decoded, read): (Signature, usize) =
+            bincode::decode_from_slice(&bytes, config).unwrap();
+        assert_eq!(read, 96);
+        assert!(SignatureScheme::verify(
+            signed.public_key(),
+            &decoded,
+            signed.msg()
+        ));
+
+        let account = Account::from_private_scalar(BigInt::from(7));
+        let bytes = bincode::encode_to_vec(&account, config).unwrap();
+        let (restored, _): (Account, usize) = bincode::decode_from_slice(&bytes, config).unwrap();
+        assert_eq!(restored.public_key_x, account.public_key_x);
+
+        // S = JUBJUB_E is not a valid signature scalar
+        let mut out_of_range = signed.sig().to_fixed_bytes().unwrap();
+        out_of_range.truncate(64);
+        write_scalar(&JUBJUB_E, &mut out_of_range).unwrap();
+        assert!(bincode::decode_from_slice::<Signature, _>(&out_of_range, config).is_err());
+        let custom = FQ::with_modulus(BigInt::from(1), BigInt::from(7));
+        assert!(bincode::encode_to_vec(custom, config).is_err());
+    }
+}
@@ -0,0 +1,636 @@
+/*
+A client for the Loopring REST API (`client` feature).
+
+It fetches what payloads need from the API and submits what this crate signs,
+so applications do not keep a second HTTP layer next to it:
+
+    let client = LoopringClient::new(ChainProfile::mainnet());
+    let session = client.session("0x<owner>", LocalSigner::from_hex(l2_key)?)?;
+    let transfer = session.build_transfer("0x<payee>", "1.5 USDC", "0.01 USDC", valid_until)?;
+    let ecdsa = transfer.sign_ecdsa_on(client.profile(), l1_key)?; // or a wallet
+    let submitted = session.send_transfer(&transfer, &ecdsa)?;
+
+A Session fetches the account (id, nonce, keySeed, registered key), the API key
+(GET /api/v3/apiKey, signed with the L2 key) and the token config once; every
+build_* call fetches a fresh storageId. Sessions refuse signers whose key is not
+the account's registered key, since the relayer would reject everything they
+sign. Transfers also need the L1 (EIP-712) authorization of the owner, which is
+passed in rather than made here: the L1 key often sits in a wallet.
+
+Requests go through an HttpTransport. UreqTransport is the blocking HTTPS
+default; other stacks (an async runtime's client, a proxy, a test double) plug
+in with `LoopringClient::with_transport`.
+*/
+
+use crate::payload::order::Order;
+use crate::payload::token::{TokenInfo, TokenRegistry};
+use crate::payload::transfer::Transfer;
+use crate::poseidon::eddsa::MessageHash;
+use crate::signer::Signer;
+use crate::util::chain::ChainProfile;
+use crate::util::encoding::EncodingProfile;
+use crate::util::errors::ClientError;
+use crate::util::helpers::{generate_signature_base_string, HttpMethod};
+use crate::util::hex;
+use num_bigint::BigInt;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub method: HttpMethod,
+    // Including the query string
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    // A JSON body, for POST/PUT
+    pub body: Option<String>,
+}
+
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+pub trait HttpTransport: Send + Sync {
+    // Error statuses are responses; only requests without one are errors
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse, ClientError>;
+}
+
+pub struct UreqTransport {
+    agent: ureq::Agent,
+}
+
+impl UreqTransport {
+    pub fn new(timeout: Duration) -> Self {
+        UreqTransport {
+            agent: ureq::AgentBuilder::new().timeout(timeout).build(),
+        }
+    }
+}
+
+impl Default for UreqTransport {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(30))
+    }
+}
+
+impl HttpTransport for UreqTransport {
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse, ClientError> {
+        let mut call = self.agent.request(request.method.as_str(), &request.url);
+        for (name, value) in &request.headers {
+            call = call.set(name, value);
+        }
+        let result = match &request.body {
+            Some(body) => call
+                .set("Content-Type", "application/json")
+                .send_string(body),
+            None => call.call(),
+        };
+        let response = match result {
+            Ok(response) => response,
+            Err(ureq::Error::Status(_, response)) => response,
+            Err(e) => return Err(ClientError::Transport(e.to_string())),
+        };
+        let status = response.status();
+        let body = response
+            .into_string()
+            .map_err(|e| ClientError::Transport(e.to_string()))?;
+        Ok(HttpResponse { status, body })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountInfo {
+    pub account_id: u32,
+    pub owner: String,
+    pub frozen: bool,
+    // Number of key updates so far, part of the keySeed of the next key
+    pub nonce: u32,
+    // Empty when the account uses the default keySeed
+    pub key_seed: String,
+    // Zero while no L2 key is registered
+    pub public_key_x: BigInt,
+    pub public_key_y: BigInt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageId {
+    // For orders
+    pub order_id: u32,
+    // For transfers and other off-chain requests
+    pub offchain_id: u32,
+}
+
+// What the relayer answered to a submitted order or transfer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Submitted {
+    pub hash: String,
+    pub status: String,
+}
+
+fn invalid(what: &str) -> ClientError {
+    ClientError::InvalidResponse(format!("missing or invalid {}", what))
+}
+
+fn str_of<'a>(value: &'a Value, name: &str) -> Result<&'a str, ClientError> {
+    value[name].as_str().ok_or_else(|| invalid(name))
+}
+
+fn u32_of(value: &Value, name: &str) -> Result<u32, ClientError> {
+    value[name]
+        .as_u64()
+        .and_then(|n| u32::try_from(n).ok())
+        .ok_or_else(|| invalid(name))
+}
+
+fn key_coordinate(value: &Value, name: &str) -> Result<BigInt, ClientError> {
+    match value["publicKey"][name].as_str() {
+        None | Some("") => Ok(BigInt::from(0)),
+        Some(coordinate) => hex::decode_bigint(coordinate).map_err(|_| invalid(name)),
+    }
+}
+
+// Query keys and values, percent-encoded as URL components
+fn query(params: &[(&str, &str)]) -> String {
+    let profile = EncodingProfile::StrictRfc3986;
+    params
+        .iter()
+        .map(|(key, value)| {
+            format!(
+                "{}={}",
+                profile.encode_body(key),
+                profile.encode_body(value)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn submitted(response: &Value) -> Result<Submitted, ClientError> {
+    Ok(Submitted {
+        hash: str_of(response, "hash")?.to_string(),
+        status: str_of(response, "status")?.to_string(),
+    })
+}
+
+pub struct LoopringClient<T: HttpTransport = UreqTransport> {
+    profile: ChainProfile,
+    transport: T,
+}
+
+impl LoopringClient {
+    pub fn new(profile: ChainProfile) -> Self {
+        Self::with_transport(profile, UreqTransport::default())
+    }
+}
+
+impl<T: HttpTransport> LoopringClient<T> {
+    pub fn with_transport(profile: ChainProfile, transport: T) -> Self {
+        LoopringClient { profile, transport }
+    }
+
+    pub fn profile(&self) -> &ChainProfile {
+        &self.profile
+    }
+
+    fn send(&self, request: HttpRequest) -> Result<Value, ClientError> {
+        let response = self.transport.send(&request)?;
+        let body: Value = match serde_json::from_str(&response.body) {
+            Ok(body) => body,
+            // e.g. a proxy's error page
+            Err(_) if response.status != 200 => Value::Null,
+            Err(e) => return Err(ClientError::InvalidResponse(e.to_string())),
+        };
+        // Errors come as {"resultInfo": {"code": ..., "message": ...}}, some of
+        // them with status 200
+        let code = body["resultInfo"]["code"].as_i64().unwrap_or(0);
+        if response.status != 200 || code != 0 {
+            return Err(ClientError::Api {
+                status: response.status,
+                code,
+                message: body["resultInfo"]["message"]
+                    .as_str()
+                    .unwrap_or(&response.body)
+                    .to_string(),
+            });
+        }
+        Ok(body)
+    }
+
+    fn get(
+        &self,
+        path: &str,
+        params: &[(&str, &str)],
+        headers: Vec<(String, String)>,
+    ) -> Result<Value, ClientError> {
+        self.send(HttpRequest {
+            method: HttpMethod::Get,
+            url: if params.is_empty() {
+                self.profile.url(path)
+            } else {
+                format!("{}?{}", self.profile.url(path), query(params))
+            },
+            headers,
+            body: None,
+        })
+    }
+
+    fn post(
+        &self,
+        path: &str,
+        body: &Value,
+        headers: Vec<(String, String)>,
+    ) -> Result<Value, ClientError> {
+        self.send(HttpRequest {
+            method: HttpMethod::Post,
+            url: self.profile.url(path),
+            headers,
+            body: Some(body.to_string()),
+        })
+    }
+
+    pub fn account(&self, owner: &str) -> Result<AccountInfo, ClientError> {
+        let account = self.get("/api/v3/account", &[("owner", owner)], Vec::new())?;
+        Ok(AccountInfo {
+            account_id: u32_of(&account, "accountId")?,
+            owner: str_of(&account, "owner")?.to_string(),
+            frozen: account["frozen"].as_bool().unwrap_or(false),
+            nonce: u32_of(&account, "nonce")?,
+            key_seed: account["keySeed"].as_str().unwrap_or("").to_string(),
+            public_key_x: key_coordinate(&account, "x")?,
+            public_key_y: key_coordinate(&account, "y")?,
+        })
+    }
+
+    // The API key, requested with a signature of the L2 key
+    pub fn api_key<S: Signer>(&self, account_id: u32, signer: &S) -> Result<String, ClientError> {
+        let account_id = account_id.to_string();
+        let params = [("accountId", account_id.as_str())];
+        let base =
+            generate_signature_base_string("GET", &self.profile.url("/api/v3/apiKey"), params)?;
+        let signature = signer.sign(&MessageHash::from_message(&base))?.to_hex();
+        let response = self.get(
+            "/api/v3/apiKey",
+            &params,
+            vec![("X-API-SIG".to_string(), signature)],
+        )?;
+        Ok(str_of(&response, "apiKey")?.to_string())
+    }
+
+    // The next storage ids for requests that spend `sell_token_id`
+    pub fn storage_id(
+        &self,
+        account_id: u32,
+        sell_token_id: u32,
+        api_key: &str,
+    ) -> Result<StorageId, ClientError> {
+        let response = self.get(
+            "/api/v3/storageId",
+            &[
+                ("accountId", &account_id.to_string()),
+                ("sellTokenId", &sell_token_id.to_string()),
+            ],
+            vec![("X-API-KEY".to_string(), api_key.to_string())],
+        )?;
+        Ok(StorageId {
+            order_id: u32_of(&response, "orderId")?,
+            offchain_id: u32_of(&response, "offchainId")?,
+        })
+    }
+
+    pub fn tokens(&self) -> Result<TokenRegistry, ClientError> {
+        let response = self.get("/api/v3/exchange/tokens", &[], Vec::new())?;
+        let tokens = response.as_array().ok_or_else(|| invalid("token list"))?;
+        let tokens = tokens
+            .iter()
+            .map(|token| {
+                Ok(TokenInfo::new(
+                    u32_of(token, "tokenId")?,
+                    str_of(token, "symbol")?,
+                    u32_of(token, "decimals")?,
+                ))
+            })
+            .collect::<Result<Vec<_>, ClientError>>()?;
+        Ok(TokenRegistry::with_tokens(tokens))
+    }
+
+    // Signs the transfer with the L2 key and submits it with its L1
+    // authorization (the EIP-712 `ecdsaSignature`)
+    pub fn submit_transfer<S: Signer>(
+        &self,
+        transfer: &Transfer,
+        signer: &S,
+        api_key: &str,
+        ecdsa_signature: &str,
+    ) -> Result<Submitted, ClientError> {
+        self.profile.check_exchange(&transfer.exchange)?;
+        let signature = signer.sign(&MessageHash::new(transfer.hash()?))?.to_hex();
+        let body = json!({
+            "exchange": transfer.exchange,
+            "payerId": transfer.payer_id,
+            "payerAddr": transfer.payer_addr,
+            "payeeId": transfer.payee_id,
+            "payeeAddr": transfer.payee_addr,
+            "token": {"tokenId": transfer.token_id, "volume": transfer.amount.to_string()},
+            "maxFee": {"tokenId": transfer.fee_token_id, "volume": transfer.max_fee.to_string()},
+            "storageId": transfer.storage_id,
+            "validUntil": transfer.valid_until,
+            "eddsaSignature": signature,
+            "ecdsaSignature": ecdsa_signature,
+        });
+        let response = self.post(
+            "/api/v3/transfer",
+            &body,
+            vec![
+                ("X-API-KEY".to_string(), api_key.to_string()),
+                ("X-API-SIG".to_string(), ecdsa_signature.to_string()),
+            ],
+        )?;
+        submitted(&response)
+    }
+
+    pub fn submit_order<S: Signer>(
+        &self,
+        order: &Order,
+        signer: &S,
+        api_key: &str,
+    ) -> Result<Submitted, ClientError> {
+        self.profile.check_exchange(&order.exchange)?;
+        let signature = signer.sign(&MessageHash::new(order.hash()?))?.to_hex();
+        let mut body = json!({
+            "exchange": order.exchange,
+            "accountId": order.account_id,
+            "storageId": order.storage_id,
+            "sellToken": {"tokenId": order.sell_token_id, "volume": order.sell_volume.to_string()},
+            "buyToken": {"tokenId": order.buy_token_id, "volume": order.buy_volume.to_string()},
+            "allOrNone": false,
+            "fillAmountBOrS": order.fill_amount_b_or_s,
+            "validUntil": order.valid_until,
+            "maxFeeBips": order.max_fee_bips,
+            "eddsaSignature": signature,
+        });
+        if let Some(taker) = &order.taker {
+            body["taker"] = json!(taker);
+        }
+        let response = self.post(
+            "/api/v3/order",
+            &body,
+            vec![("X-API-KEY".to_string(), api_key.to_string())],
+        )?;
+        submitted(&response)
+    }
+
+    pub fn session<S: Signer>(
+        &self,
+        owner: &str,
+        signer: S,
+    ) -> Result<Session<'_, T, S>, ClientError> {
+        let account = self.account(owner)?;
+        let public_key = signer.public_key();
+        if account.public_key_x != public_key.x().to_bigint()
+            || account.public_key_y != public_key.y().to_bigint()
+        {
+            return Err(ClientError::KeyMismatch(account.account_id));
+        }
+        let api_key = self.api_key(account.account_id, &signer)?;
+        Ok(Session {
+            client: self,
+            signer,
+            account,
+            api_key,
+            tokens: self.tokens()?,
+        })
+    }
+}
+
+// An account's view of the API, see the module comment
+pub struct Session<'a, T: HttpTransport, S: Signer> {
+    client: &'a LoopringClient<T>,
+    signer: S,
+    account: AccountInfo,
+    api_key: String,
+    tokens: TokenRegistry,
+}
+
+impl<T: HttpTransport, S: Signer> Session<'_, T, S> {
+    pub fn account(&self) -> &AccountInfo {
+        &self.account
+    }
+
+    pub fn api_key(&self) -> &str {
+        &self.api_key
+    }
+
+    pub fn tokens(&self) -> &TokenRegistry {
+        &self.tokens
+    }
+
+    // A transfer of `amount` (e.g. "1.5 USDC") to an L1 address, paying at most
+    // `max_fee` (e.g. "0.01 USDC"). The payee id is left at 0, which the
+    // relayer resolves from the address.
+    pub fn build_transfer(
+        &self,
+        payee_addr: &str,
+        amount: &str,
+        max_fee: &str,
+        valid_until: u32,
+    ) -> Result<Transfer, ClientError> {
+        let (token, amount) = self.tokens.parse_amount(amount)?;
+        let (fee_token, max_fee) = self.tokens.parse_amount(max_fee)?;
+        let storage =
+            self.client
+                .storage_id(self.account.account_id, token.token_id, &self.api_key)?;
+        Ok(Transfer {
+            exchange: self.client.profile.exchange.clone(),
+            payer_id: self.account.account_id,
+            payer_addr: self.account.owner.clone(),
+            payee_id: 0,
+            payee_addr: payee_addr.to_string(),
+            token_id: token.token_id,
+            amount,
+            fee_token_id: fee_token.token_id,
+            max_fee,
+            valid_until,
+            storage_id: storage.offchain_id,
+        })
+    }
+
+    pub fn send_transfer(
+        &self,
+        transfer: &Transfer,
+        ecdsa_signature: &str,
+    ) -> Result<Submitted, ClientError> {
+        self.client
+            .submit_transfer(transfer, &self.signer, &self.api_key, ecdsa_signature)
+    }
+
+    // An order selling `sell` for `buy` (e.g. "1 ETH", "3000 USDC")
+    pub fn build_order(
+        &self,
+        sell: &str,
+        buy: &str,
+        max_fee_bips: u32,
+        valid_until: u32,
+    ) -> Result<Order, ClientError> {
+        let (sell_token, sell_volume) = self.tokens.parse_amount(sell)?;
+        let (buy_token, buy_volume) = self.tokens.parse_amount(buy)?;
+        let storage =
+            self.client
+                .storage_id(self.account.account_id, sell_token.token_id, &self.api_key)?;
+        Ok(Order {
+            exchange: self.client.profile.exchange.clone(),
+            storage_id: storage.order_id,
+            account_id: self.account.account_id,
+            sell_token_id: sell_token.token_id,
+            buy_token_id: buy_token.token_id,
+            sell_volume,
+            buy_volume,
+            valid_until,
+            max_fee_bips,
+            fill_amount_b_or_s: false,
+            taker: None,
+        })
+    }
+
+    pub fn send_order(&self, order: &Order) -> Result<Submitted, ClientError> {
+        self.client.submit_order(order, &self.signer, &self.api_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poseidon::eddsa::SignatureScheme;
+    use crate::signer::LocalSigner;
+    use std::sync::Mutex;
+
+    const L2_KEY: &str = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+    const OWNER: &str = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+
+    // Answers by path and records every request
+    struct FakeApi {
+        public_key_x: String,
+        requests: Mutex<Vec<HttpRequest>>,
+    }
+
+    impl FakeApi {
+        fn new(public_key_x: &str) -> Self {
+            FakeApi {
+                public_key_x: public_key_x.to_string(),
+                requests: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    impl HttpTransport for FakeApi {
+        fn send(&self, request: &HttpRequest) -> Result<HttpResponse, ClientError> {
+            self.requests.lock().unwrap().push(request.clone());
+            let path = request.url.trim_start_matches("https://api3.loopring.io");
+            let path = path.split('?').next().unwrap();
+            let (status, body) = match path {
+                "/api/v3/account" => (
+                    200,
+                    json!({"accountId": 11087, "owner": OWNER, "frozen": false, "nonce": 1,
+                           "keySeed": "",
+                           "publicKey": {"x": self.public_key_x,
+                                         "y": "0x13d161bf5df6053b499a8cc1be2aece9b2b6768d88c05687e1edc40e52bf44b9"}}),
+                ),
+                "/api/v3/apiKey" => (200, json!({"apiKey": "test-api-key"})),
+                "/api/v3/exchange/tokens" => (
+                    200,
+                    json!([{"tokenId": 0, "symbol": "ETH", "decimals": 18},
+                           {"tokenId": 6, "symbol": "USDC", "decimals": 6}]),
+                ),
+                "/api/v3/storageId" => (200, json!({"orderId": 4, "offchainId": 5})),
+                "/api/v3/transfer" => (200, json!({"hash": "0x01", "status": "processing"})),
+                _ => (
+                    400,
+                    json!({"resultInfo": {"code": 100001, "message": "invalid order"}}),
+                ),
+            };
+            Ok(HttpResponse {
+                status,
+                body: body.to_string(),
+            })
+        }
+    }
+
+    fn client(public_key_x: &str) -> LoopringClient<FakeApi> {
+        LoopringClient::with_transport(ChainProfile::mainnet(), FakeApi::new(public_key_x))
+    }
+
+    #[test]
+    fn builds_signs_and_submits_a_transfer() {
+        let client = client("0x1526f1c9babcdda49b20df32653f0c3ae07eae4c5b9b456e7e2c8fd7d79ce3b0");
+        let session = client
+            .session(OWNER, LocalSigner::from_hex(L2_KEY).unwrap())
+            .unwrap();
+        assert_eq!(session.account().account_id, 11087);
+        assert_eq!(session.api_key(), "test-api-key");
+
+        let transfer = session
+            .build_transfer(
+                "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+                "1.5 USDC",
+                "0.01 USDC",
+                1700000000,
+            )
+            .unwrap();
+        assert_eq!(transfer.amount, BigInt::from(1_500_000));
+        assert_eq!((transfer.token_id, transfer.storage_id), (6, 5));
+        let submitted = session.send_transfer(&transfer, "0xecdsa").unwrap();
+        assert_eq!(submitted.status, "processing");
+
+        let requests = client.transport.requests.lock().unwrap();
+        // The API key request is signed over its base string
+        let HttpRequest { url, headers, .. } = &requests[1];
+        assert!(url.ends_with("/api/v3/apiKey?accountId=11087"));
+        let base = generate_signature_base_string(
+            "GET",
+            "https://api3.loopring.io/api/v3/apiKey",
+            [("accountId", "11087")],
+        )
+        .unwrap();
+        let private_key = hex::decode_bigint(L2_KEY).unwrap();
+        assert_eq!(
+            headers[0].1,
+            SignatureScheme::sign_message(private_key, &base).to_hex()
+        );
+
+        let HttpRequest {
+            url, headers, body, ..
+        } = requests.last().unwrap();
+        assert!(url.ends_with("/api/v3/transfer"));
+        assert!(headers.contains(&("X-API-KEY".to_string(), "test-api-key".to_string())));
+        assert!(headers.contains(&("X-API-SIG".to_string(), "0xecdsa".to_string())));
+        let body: Value = serde_json::from_str(body.as_ref().unwrap()).unwrap();
+        assert_eq!(body["token"]["volume"], "1500000");
+        assert_eq!(body["eddsaSignature"], transfer.sign(L2_KEY).unwrap());
+    }
+
+    #[test]
+    fn reports_key_mismatches_and_api_errors() {
+        let other = client("0x01");
+        assert!(matches!(
+            other.session(OWNER, LocalSigner::from_hex(L2_KEY).unwrap()),
+            Err(ClientError::KeyMismatch(11087))
+        ));
+
+        let client = client("0x1526f1c9babcdda49b20df32653f0c3ae07eae4c5b9b456e7e2c8fd7d79ce3b0");
+        let session = client
+            .session(OWNER, LocalSigner::from_hex(L2_KEY).unwrap())
+            .unwrap();
+        let order = session
+            .build_order("1 ETH", "3000 USDC", 60, 1700000000)
+            .unwrap();
+        assert_eq!(order.storage_id, 4);
+        match session.send_order(&order) {
+            Err(ClientError::Api { status, code, .. }) => assert_eq!((status, code), (400, 100001)),
+            _ => panic!("expected an API error"),
+        }
+        assert!(matches!(
+            session.build_order("1 DOGE", "1 ETH", 60, 1700000000),
+            Err(ClientError::Token(_))
+        ));
+    }
+}
@@ -0,0 +1,277 @@
+/*
+The signing helpers of the Python reference SDK (hello_loopring's
+sig_utils/eddsa_utils.py) under their own names.
+
+Each `*EddsaSignHelper` class becomes a struct built from the same arguments,
+with the methods `serialize_data`, `hash` and `sign` (and `verify` for payloads),
+taking the payload dict in the same shape as a serde_json Value. A call site
+
+    signer = OrderEddsaSignHelper(private_key)
+    order["eddsaSignature"] = signer.sign(order)
+
+becomes
+
+    let signer = OrderEddsaSignHelper::new(private_key);
+    order["eddsaSignature"] = json!(signer.sign(&order)?);
+
+Signatures and hashes are those of the rest of the crate. Only helpers for the
+payloads the crate implements are here; a raw list of Poseidon inputs is signed
+with `eddsa::sign_poseidon_inputs`. New code should use the payload types and
+Signers directly, which do not reparse the dicts on every call.
+*/
+
+use crate::payload::file::{order_from_value, transfer_from_value, withdrawal_from_value};
+use crate::payload::{poseidon_hash, sign_hash};
+use crate::poseidon::eddsa::{sign_api_request, sign_api_request_body, Signature, SignatureScheme};
+use crate::util::encoding::EncodingProfile;
+use crate::util::errors::{PayloadError, RequestError};
+use crate::util::helpers::{
+    body_signature_base_string, generate_signature_base_string, sha256_snark, HttpMethod,
+};
+use crate::util::hex;
+use num_bigint::BigInt;
+use serde_json::Value;
+
+// The fields of a `requests.Request` that UrlEddsaSignHelper reads
+pub struct Request<'a> {
+    pub method: &'a str,
+    pub path: &'a str,
+    // request.params, signed for GET and DELETE
+    pub params: &'a [(&'a str, &'a str)],
+    // request.data, the serialized body signed for POST and PUT
+    pub data: &'a str,
+}
+
+// Signs API requests for the X-API-SIG header
+pub struct UrlEddsaSignHelper {
+    private_key: String,
+    host: String,
+}
+
+impl UrlEddsaSignHelper {
+    // `host` is prepended to request.path, e.g. "https://api3.loopring.io"
+    pub fn new(private_key: &str, host: &str) -> Self {
+        UrlEddsaSignHelper {
+            private_key: private_key.to_string(),
+            host: host.to_string(),
+        }
+    }
+
+    fn url(&self, request: &Request) -> String {
+        format!("{}{}", self.host, request.path)
+    }
+
+    // The signature base string
+    pub fn serialize_data(&self, request: &Request) -> Result<String, RequestError> {
+        let url = self.url(request);
+        match request.method.parse::<HttpMethod>()? {
+            HttpMethod::Get | HttpMethod::Delete => {
+                generate_signature_base_string(request.method, &url, request.params)
+            }
+            method => {
+                body_signature_base_string(method, &url, request.data, EncodingProfile::default())
+            }
+        }
+    }
+
+    pub fn hash(&self, request: &Request) -> Result<BigInt, RequestError> {
+        Ok(sha256_snark(&self.serialize_data(request)?))
+    }
+
+    pub fn sign(&self, request: &Request) -> Result<String, RequestError> {
+        let url = self.url(request);
+        match request.method.parse::<HttpMethod>()? {
+            HttpMethod::Get | HttpMethod::Delete => {
+                sign_api_request(request.method, &url, request.params, &self.private_key)
+            }
+            method => sign_api_request_body(method, &url, request.data, &self.private_key),
+        }
+    }
+}
+
+// The helpers for payload dicts, which are hashed with Poseidon
+pub trait EddsaSignHelper {
+    fn private_key(&self) -> &str;
+
+    // The Poseidon inputs of `data`
+    fn serialize_data(&self, data: &Value) -> Result<Vec<BigInt>, PayloadError>;
+
+    fn hash(&self, data: &Value) -> Result<BigInt, PayloadError> {
+        poseidon_hash(self.serialize_data(data)?)
+    }
+
+    fn sign(&self, data: &Value) -> Result<String, PayloadError> {
+        sign_hash(self.hash(data)?, self.private_key())
+    }
+
+    // Whether `sig` is this key's signature of `data`
+    fn verify(&self, data: &Value, sig: &str) -> Result<bool, PayloadError> {
+        let private_key =
+            hex::decode_secret(self.private_key()).map_err(|_| PayloadError::InvalidPrivateKey)?;
+        let sig = Signature::from_hex(sig)
+            .map_err(|e| PayloadError::InvalidField("sig", e.to_string()))?;
+        let public_key = SignatureScheme::base_point().mul_scalar(&private_key);
+        Ok(SignatureScheme::verify(
+            &public_key,
+            &sig,
+            &self.hash(data)?,
+        ))
+    }
+}
+
+// Orders of POST /api/v3/order
+pub struct OrderEddsaSignHelper {
+    private_key: String,
+}
+
+impl OrderEddsaSignHelper {
+    pub fn new(private_key: &str) -> Self {
+        OrderEddsaSignHelper {
+            private_key: private_key.to_string(),
+        }
+    }
+}
+
+impl EddsaSignHelper for OrderEddsaSignHelper {
+    fn private_key(&self) -> &str {
+        &self.private_key
+    }
+
+    fn serialize_data(&self, order: &Value) -> Result<Vec<BigInt>, PayloadError> {
+        order_from_value(order)?.poseidon_inputs()
+    }
+}
+
+// Transfers of POST /api/v3/transfer
+pub struct OriginTransferEddsaSignHelper {
+    private_key: String,
+}
+
+impl OriginTransferEddsaSignHelper {
+    pub fn new(private_key: &str) -> Self {
+        OriginTransferEddsaSignHelper {
+            private_key: private_key.to_string(),
+        }
+    }
+}
+
+impl EddsaSignHelper for OriginTransferEddsaSignHelper {
+    fn private_key(&self) -> &str {
+        &self.private_key
+    }
+
+    fn serialize_data(&self, transfer: &Value) -> Result<Vec<BigInt>, PayloadError> {
+        transfer_from_value(transfer)?.poseidon_inputs()
+    }
+}
+
+// Withdrawals of POST /api/v3/user/withdrawals
+pub struct WithdrawalEddsaSignHelper {
+    private_key: String,
+}
+
+impl WithdrawalEddsaSignHelper {
+    pub fn new(private_key: &str) -> Self {
+        WithdrawalEddsaSignHelper {
+            private_key: private_key.to_string(),
+        }
+    }
+}
+
+impl EddsaSignHelper for WithdrawalEddsaSignHelper {
+    fn private_key(&self) -> &str {
+        &self.private_key
+    }
+
+    fn serialize_data(&self, withdrawal: &Value) -> Result<Vec<BigInt>, PayloadError> {
+        withdrawal_from_value(withdrawal)?.poseidon_inputs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload::transfer::tests::transfer;
+    use serde_json::json;
+
+    const L2_KEY: &str = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+
+    #[test]
+    fn url_helper_signs_like_the_request_functions() {
+        let signer = UrlEddsaSignHelper::new(L2_KEY, "https://api3.loopring.io");
+        let get = Request {
+            method: "GET",
+            path: "/api/v3/apiKey",
+            params: &[("accountId", "12345")],
+            data: "",
+        };
+        let url = "https://api3.loopring.io/api/v3/apiKey";
+        assert_eq!(
+            signer.sign(&get).unwrap(),
+            sign_api_request("GET", url, &[("accountId", "12345")], L2_KEY).unwrap()
+        );
+        assert_eq!(
+            signer.serialize_data(&get).unwrap(),
+            "GET&https%3A%2F%2Fapi3.loopring.io%2Fapi%2Fv3%2FapiKey&accountId%3D12345"
+        );
+
+        let body = r#"{"accountId":12345}"#;
+        let post = Request {
+            method: "POST",
+            params: &[],
+            data: body,
+            ..get
+        };
+        assert_eq!(
+            signer.sign(&post).unwrap(),
+            sign_api_request_body(HttpMethod::Post, url, body, L2_KEY).unwrap()
+        );
+        assert!(matches!(
+            signer.hash(&Request {
+                method: "PATCH",
+                ..post
+            }),
+            Err(RequestError::UnsupportedMethod(_))
+        ));
+    }
+
+    #[test]
+    fn payload_helpers_sign_dicts() {
+        let transfer_dict = json!({
+            "exchange": "0x0BABA1Ad5bE3a5C0a66E7ac838a129Bf948f1eA4",
+            "payerId": 11087,
+            "payerAddr": "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "payeeId": 0,
+            "payeeAddr": "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "token": {"tokenId": 1, "volume": "1000000000000000000"},
+            "maxFee": {"tokenId": 0, "volume": "100000000000000"},
+            "validUntil": 1700000000,
+            "storageId": 5
+        });
+        let signer = OriginTransferEddsaSignHelper::new(L2_KEY);
+        let sig = signer.sign(&transfer_dict).unwrap();
+        assert_eq!(sig, transfer().sign(L2_KEY).unwrap());
+        assert_eq!(signer.serialize_data(&transfer_dict).unwrap().len(), 12);
+        assert!(signer.verify(&transfer_dict, &sig).unwrap());
+
+        let order_dict = json!({
+            "exchange": "0x0BABA1Ad5bE3a5C0a66E7ac838a129Bf948f1eA4",
+            "storageId": 2, "accountId": 11087,
+            "sellToken": {"tokenId": 1, "volume": "1000000000000000000"},
+            "buyToken": {"tokenId": 0, "volume": "2000000000"},
+            "validUntil": 1700000000, "maxFeeBips": 50, "fillAmountBOrS": false
+        });
+        let signer = OrderEddsaSignHelper::new(L2_KEY);
+        let order = order_from_value(&order_dict).unwrap();
+        assert_eq!(signer.hash(&order_dict).unwrap(), order.hash().unwrap());
+        assert_eq!(
+            signer.sign(&order_dict).unwrap(),
+            order.sign(L2_KEY).unwrap()
+        );
+        assert!(!signer.verify(&order_dict, &sig).unwrap());
+        assert!(matches!(
+            signer.sign(&json!({"exchange": "0x0"})),
+            Err(PayloadError::MissingField(_))
+        ));
+    }
+}
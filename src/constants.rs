@@ -0,0 +1,121 @@
+/*
+Field and curve parameters.
+
+All parameters live here; `poseidon::field` and `poseidon::jubjub` re-export the
+statics under their historic paths.
+
+  * SNARK_SCALAR_FIELD (q): the BN254 scalar field. Poseidon hashes, EdDSA message
+    hashes and the coordinates of Baby Jubjub points are elements of it.
+  * FR_ORDER / JUBJUB_E: the order of the Baby Jubjub curve, 8 * L. Both names
+    refer to the same number.
+  * JUBJUB_L: the order of the prime-order subgroup generated by the base point.
+    L2 private keys and signature scalars are taken modulo L.
+  * JUBJUB_C: the cofactor, 8.
+  * JUBJUB_A, JUBJUB_D: the curve a * x^2 + y^2 = 1 + d * x^2 * y^2 (EIP-2494).
+  * GENERATOR_X_DEC, GENERATOR_Y_DEC: the base point B, of order L (`generator()`).
+
+The `*_LIMBS` constants hold the same values as four little-endian 64-bit limbs,
+for fixed-width backends that need them at compile time.
+*/
+
+use crate::poseidon::{field::FQ, jubjub::Point};
+use num_bigint::{BigInt, BigUint};
+use std::str::FromStr;
+
+pub const SNARK_SCALAR_FIELD_DEC: &str =
+    "21888242871839275222246405745257275088548364400416034343698204186575808495617";
+pub const JUBJUB_E_DEC: &str =
+    "21888242871839275222246405745257275088614511777268538073601725287587578984328";
+pub const JUBJUB_L_DEC: &str =
+    "2736030358979909402780800718157159386076813972158567259200215660948447373041";
+pub const GENERATOR_X_DEC: &str =
+    "16540640123574156134436876038791482806971768689494387082833631921987005038935";
+pub const GENERATOR_Y_DEC: &str =
+    "20819045374670962167435360035096875258406992893633759881276124905556507972311";
+
+pub const JUBJUB_COFACTOR: u64 = 8;
+pub const JUBJUB_A_VALUE: u64 = 168700;
+pub const JUBJUB_D_VALUE: u64 = 168696;
+
+pub const SNARK_SCALAR_FIELD_LIMBS: [u64; 4] = [
+    0x43e1f593f0000001,
+    0x2833e84879b97091,
+    0xb85045b68181585d,
+    0x30644e72e131a029,
+];
+pub const JUBJUB_E_LIMBS: [u64; 4] = [
+    0x3b94bee1c9093788,
+    0x59f76dc1c9077053,
+    0xb85045b68181585d,
+    0x30644e72e131a029,
+];
+pub const JUBJUB_L_LIMBS: [u64; 4] = [
+    0x677297dc392126f1,
+    0xab3eedb83920ee0a,
+    0x370a08b6d0302b0b,
+    0x060c89ce5c263405,
+];
+
+lazy_static! {
+    pub static ref SNARK_SCALAR_FIELD: BigInt = BigInt::from_str(SNARK_SCALAR_FIELD_DEC).unwrap();
+    pub static ref FR_ORDER: BigInt = BigInt::from_str(JUBJUB_E_DEC).unwrap();
+
+    pub static ref JUBJUB_Q: BigInt = SNARK_SCALAR_FIELD.clone();
+    pub static ref JUBJUB_E: BigInt = BigInt::from_str(JUBJUB_E_DEC).unwrap();
+    pub static ref JUBJUB_C: BigInt = BigInt::from(JUBJUB_COFACTOR);
+    // L*B = 0, and C*L == #E
+    pub static ref JUBJUB_L: BigInt = BigInt::from_str(JUBJUB_L_DEC).unwrap();
+    pub static ref JUBJUB_A: BigInt = BigInt::from(JUBJUB_A_VALUE);
+    pub static ref JUBJUB_D: BigInt = BigInt::from(JUBJUB_D_VALUE);
+
+    static ref SNARK_SCALAR_FIELD_UINT: BigUint = SNARK_SCALAR_FIELD.magnitude().clone();
+    pub(crate) static ref GENERATOR: Point = Point::new(
+        FQ::new(BigInt::from_str(GENERATOR_X_DEC).unwrap()),
+        FQ::new(BigInt::from_str(GENERATOR_Y_DEC).unwrap()),
+    );
+}
+
+pub fn snark_scalar_field() -> &'static BigInt {
+    &SNARK_SCALAR_FIELD
+}
+
+pub fn snark_scalar_field_uint() -> &'static BigUint {
+    &SNARK_SCALAR_FIELD_UINT
+}
+
+pub fn jubjub_order() -> &'static BigInt {
+    &JUBJUB_E
+}
+
+pub fn subgroup_order() -> &'static BigInt {
+    &JUBJUB_L
+}
+
+pub fn generator() -> Point {
+    GENERATOR.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_limbs(limbs: &[u64; 4]) -> BigInt {
+        let bytes: Vec<u8> = limbs.iter().flat_map(|limb| limb.to_le_bytes()).collect();
+        BigInt::from_bytes_le(num_bigint::Sign::Plus, &bytes)
+    }
+
+    #[test]
+    fn limbs_match_values() {
+        assert_eq!(from_limbs(&SNARK_SCALAR_FIELD_LIMBS), *SNARK_SCALAR_FIELD);
+        assert_eq!(from_limbs(&JUBJUB_E_LIMBS), *JUBJUB_E);
+        assert_eq!(from_limbs(&JUBJUB_L_LIMBS), *JUBJUB_L);
+    }
+
+    #[test]
+    fn curve_parameters_are_consistent() {
+        assert_eq!(&*JUBJUB_L * &*JUBJUB_C, *JUBJUB_E);
+        assert_eq!(*FR_ORDER, *JUBJUB_E);
+        assert!(generator().is_in_subgroup());
+        assert!(generator() == Point::generate());
+    }
+}
@@ -0,0 +1,229 @@
+// Randomized round-trip checks for new platforms.
+//
+// self_check() compares a handful of fixed vectors; this runs the signing path
+// on many generated inputs and checks the results against each other instead,
+// which catches platform bugs (32-bit limb arithmetic, wasm, unusual allocators)
+// that happen to leave the reference vectors alone. Each iteration runs:
+//
+//  * sign-verify: a random key signs a random hash; the signature verifies,
+//    also after a round trip through the hex form the API uses
+//  * reject-tampered: the same signature does not verify for another hash
+//  * keygen-sign: keys derived from a random "ECDSA signature" pass
+//    validate_account_keys and sign a random request that verifies against them
+//
+// Inputs are derived from the seed, so a failure can be replayed with the seed
+// from the report. With the `rng` feature, run_random_diagnostics picks a fresh
+// seed each run. A panic inside a check is reported as a failure of that check
+// where the target unwinds on panic (not on wasm32, which aborts).
+
+use crate::keygen::l2_key::generate_l2_keys;
+use crate::keygen::validate::validate_account_keys;
+use crate::poseidon::eddsa::{verify_eddsa_hex, MessageHash, SignatureScheme};
+use crate::poseidon::field::SNARK_SCALAR_FIELD;
+use crate::poseidon::jubjub::JUBJUB_L;
+#[cfg(feature = "rng")]
+use crate::util::errors::SignerError;
+use num_bigint::{BigInt, Sign};
+use num_traits::{One, Zero};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha512};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+const DIAGNOSTICS_DOMAIN: &[u8] = b"loopring_sign diagnostics";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagnosticFailure {
+    pub check: &'static str,
+    pub iteration: usize,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagnosticsReport {
+    pub seed: u64,
+    pub iterations: usize,
+    pub checks_run: usize,
+    pub failures: Vec<DiagnosticFailure>,
+}
+
+impl DiagnosticsReport {
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "seed": self.seed.to_string(),
+            "iterations": self.iterations,
+            "checksRun": self.checks_run,
+            "ok": self.is_ok(),
+            "failures": self.failures.iter().map(|failure| json!({
+                "check": failure.check,
+                "iteration": failure.iteration,
+                "detail": failure.detail,
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
+
+// sha512(domain || seed || iteration || label)
+fn draw(seed: u64, iteration: usize, label: &str) -> Vec<u8> {
+    let mut hasher = Sha512::new();
+    hasher.update(DIAGNOSTICS_DOMAIN);
+    hasher.update(seed.to_be_bytes());
+    hasher.update((iteration as u64).to_be_bytes());
+    hasher.update(label.as_bytes());
+    hasher.finalize().to_vec()
+}
+
+fn draw_below(seed: u64, iteration: usize, label: &str, modulus: &BigInt) -> BigInt {
+    BigInt::from_bytes_le(Sign::Plus, &draw(seed, iteration, label)) % modulus
+}
+
+fn sign_verify(seed: u64, iteration: usize) -> Result<(), String> {
+    let mut private_key = draw_below(seed, iteration, "private key", &JUBJUB_L);
+    if private_key.is_zero() {
+        private_key = BigInt::one();
+    }
+    let hash = draw_below(seed, iteration, "hash", &SNARK_SCALAR_FIELD);
+    let message = MessageHash::from_prehashed(hash.clone()).map_err(|e| e.to_string())?;
+    let signed = SignatureScheme::sign_hash(private_key.clone(), &message);
+
+    let public_key = signed.public_key();
+    if *public_key != SignatureScheme::base_point().mul_scalar(&private_key) {
+        return Err("public key is not k·B".to_string());
+    }
+    if !SignatureScheme::verify(public_key, signed.sig(), &hash) {
+        return Err(format!("signature {} does not verify", signed.to_hex()));
+    }
+    let (x, y) = (public_key.x().to_hex(), public_key.y().to_hex());
+    match verify_eddsa_hex(&signed.to_hex(), &x, &y, &hash) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(format!(
+            "signature {} does not verify from hex",
+            signed.to_hex()
+        )),
+        Err(e) => Err(format!("hex round trip failed: {}", e)),
+    }
+}
+
+fn reject_tampered(seed: u64, iteration: usize) -> Result<(), String> {
+    let private_key = draw_below(seed, iteration, "private key", &JUBJUB_L) + 1u8;
+    let hash = draw_below(seed, iteration, "hash", &SNARK_SCALAR_FIELD);
+    let message = MessageHash::from_prehashed(hash.clone()).map_err(|e| e.to_string())?;
+    let signed = SignatureScheme::sign_hash(private_key, &message);
+    let other = (hash + 1u8) % &*SNARK_SCALAR_FIELD;
+
+    let public_key = signed.public_key();
+    let (x, y) = (public_key.x().to_hex(), public_key.y().to_hex());
+    match verify_eddsa_hex(&signed.to_hex(), &x, &y, &other) {
+        Ok(false) => Ok(()),
+        Ok(true) => Err("signature verifies for a different hash".to_string()),
+        Err(e) => Err(format!("hex round trip failed: {}", e)),
+    }
+}
+
+fn keygen_sign(seed: u64, iteration: usize) -> Result<(), String> {
+    let mut ecdsa_signature = draw(seed, iteration, "ecdsa signature");
+    ecdsa_signature.push(0x1b);
+    let account = generate_l2_keys(format!("0x{}", hex::encode(&ecdsa_signature)))?;
+    validate_account_keys(
+        &account.public_key_x,
+        &account.public_key_y,
+        Some(&account.private_key),
+    )
+    .map_err(|e| e.to_string())?;
+
+    let request = format!(
+        "GET&https%3A%2F%2Fapi3.loopring.io%2Fapi%2Fv3%2Fuser%2Fbalances&accountId%3D{}",
+        iteration
+    );
+    let signed = account.sign(&MessageHash::from_message(&request));
+    match verify_eddsa_hex(
+        &signed.to_hex(),
+        &account.public_key_x,
+        &account.public_key_y,
+        signed.msg(),
+    ) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(format!(
+            "request signature of {} does not verify",
+            account.public_key_x
+        )),
+        Err(e) => Err(format!("hex round trip failed: {}", e)),
+    }
+}
+
+// A check of one iteration: Err describes what went wrong
+type Check = fn(u64, usize) -> Result<(), String>;
+
+const CHECKS: [(&str, Check); 3] = [
+    ("sign-verify", sign_verify),
+    ("reject-tampered", reject_tampered),
+    ("keygen-sign", keygen_sign),
+];
+
+pub fn run_diagnostics(iterations: usize, seed: u64) -> DiagnosticsReport {
+    let mut report = DiagnosticsReport {
+        seed,
+        iterations,
+        checks_run: 0,
+        failures: Vec::new(),
+    };
+    for iteration in 0..iterations {
+        for (check, run) in CHECKS {
+            report.checks_run += 1;
+            let detail = match catch_unwind(AssertUnwindSafe(|| run(seed, iteration))) {
+                Ok(Ok(())) => continue,
+                Ok(Err(detail)) => detail,
+                Err(panic) => match panic.downcast_ref::<&str>() {
+                    Some(message) => format!("panicked: {}", message),
+                    None => match panic.downcast_ref::<String>() {
+                        Some(message) => format!("panicked: {}", message),
+                        None => "panicked".to_string(),
+                    },
+                },
+            };
+            report.failures.push(DiagnosticFailure {
+                check,
+                iteration,
+                detail,
+            });
+        }
+    }
+    report
+}
+
+// run_diagnostics with a seed from OS randomness
+#[cfg(feature = "rng")]
+pub fn run_random_diagnostics(iterations: usize) -> Result<DiagnosticsReport, SignerError> {
+    let mut seed = [0u8; 8];
+    getrandom::getrandom(&mut seed)
+        .map_err(|e| SignerError::Unavailable(format!("no OS randomness: {}", e)))?;
+    Ok(run_diagnostics(iterations, u64::from_be_bytes(seed)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_pass() {
+        let report = run_diagnostics(2, 7);
+        assert!(report.is_ok(), "{:?}", report.failures);
+        assert_eq!(report.checks_run, 6);
+
+        let json = report.to_json();
+        assert_eq!(json["seed"], "7");
+        assert_eq!(json["ok"], true);
+        assert_eq!(run_diagnostics(0, 7).checks_run, 0);
+    }
+
+    #[test]
+    fn inputs_follow_the_seed() {
+        assert_eq!(draw(1, 0, "hash"), draw(1, 0, "hash"));
+        assert_ne!(draw(1, 0, "hash"), draw(2, 0, "hash"));
+        assert_ne!(draw(1, 0, "hash"), draw(1, 1, "hash"));
+        assert_ne!(draw(1, 0, "hash"), draw(1, 0, "private key"));
+    }
+}
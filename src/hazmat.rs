@@ -0,0 +1,95 @@
+/*
+Low-level primitives, for protocols built on top of the crate's curve and hash.
+
+Nothing here is needed to sign API requests, payloads or derive keys; the
+functions re-exported at the crate root and the Signer implementations do that.
+These are the building blocks behind them, without the checks the high-level
+API does:
+
+ - `permute` runs the Poseidon permutation on a whole state. Hashing with it
+   needs a padding and a capacity that keep different inputs apart, which is
+   what Poseidon::hash and poseidon::transcript provide.
+ - `scalar_mult` and `base_mult` multiply points that are not checked to be on
+   the curve or in the subgroup, and are not constant time.
+ - `derive_nonce` returns the secret r of the signature of a message. Whoever
+   learns r for one signature can compute the private key from it, and a
+   signature made with any other r for the same message reveals it as well.
+ - `private_key_scalar` exposes the secret scalar of a KeyPair.
+
+Point has no public multiplication operators, and the permutation, the nonce
+and the private scalar are crate-internal outside this module.
+*/
+
+use crate::poseidon::curve::BabyJubjub;
+use crate::poseidon::eddsa::{Eddsa, KeyPair};
+use crate::poseidon::jubjub::Point;
+use crate::poseidon::permutation::Poseidon;
+use crate::util::errors::PoseidonError;
+use num_bigint::{BigInt, BigUint};
+
+// The permutation on `poseidon.width()` elements, reduced modulo the field first
+pub fn permute(poseidon: &Poseidon, state: Vec<BigUint>) -> Result<Vec<BigUint>, PoseidonError> {
+    if state.len() != poseidon.width() {
+        return Err(PoseidonError::StateWidthMismatch);
+    }
+    let state = state
+        .into_iter()
+        .map(|element| element % poseidon.modulus())
+        .collect();
+    Ok(poseidon.permute_state(state))
+}
+
+// scalar * point on Baby Jubjub, the scalar reduced modulo the curve order
+pub fn scalar_mult(point: &Point, scalar: &BigInt) -> Point {
+    point.mul_scalar(scalar)
+}
+
+// scalar * B, B the base point of Loopring's signatures
+pub fn base_mult(scalar: &BigInt) -> Point {
+    Point::generate().mul_scalar(scalar)
+}
+
+// r = sha512(k, M) mod L, the nonce SignatureScheme uses to sign `message`
+// (a MessageHash value) with the private key k
+pub fn derive_nonce(private_key_scalar: &BigInt, message: &BigInt) -> BigInt {
+    Eddsa::new(BabyJubjub).nonce(private_key_scalar, message)
+}
+
+pub fn private_key_scalar(keypair: &KeyPair) -> &BigInt {
+    keypair.private_key_scalar()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poseidon::eddsa::{MessageHash, SignatureScheme};
+    use num_traits::Zero;
+
+    #[test]
+    fn permute_matches_hash() {
+        let poseidon = Poseidon::shared(3, 6, 51);
+        let p = poseidon.modulus().clone();
+        let inputs = vec![BigUint::from(1u8), BigUint::from(2u8)];
+        let hash = poseidon.hash_uint(inputs.clone()).unwrap();
+
+        // Elements are reduced, so p + 1 permutes like 1
+        let state = vec![&p + 1u8, BigUint::from(2u8), BigUint::zero()];
+        assert_eq!(permute(&poseidon, state).unwrap()[0], hash);
+        assert!(matches!(
+            permute(&poseidon, inputs),
+            Err(PoseidonError::StateWidthMismatch)
+        ));
+    }
+
+    #[test]
+    fn nonce_is_the_one_signatures_use() {
+        let k = BigInt::from(123456789u64);
+        let hash = MessageHash::from_message("message");
+        let signed = SignatureScheme::sign_hash(k.clone(), &hash);
+
+        let r = derive_nonce(&k, hash.as_bigint());
+        assert!(base_mult(&r) == *signed.sig().image_of_r());
+        assert!(scalar_mult(&Point::generate(), &k) == *signed.public_key());
+        assert_eq!(*private_key_scalar(&KeyPair::new(k.clone())), k);
+    }
+}
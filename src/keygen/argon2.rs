@@ -0,0 +1,290 @@
+// Argon2id (RFC 9106, version 0x13), the memory-hard hash behind passphrase
+// keys. Only what key derivation needs: one call computes the tag, lanes are
+// filled one after another, and the memory is a plain Vec of 1 KiB blocks. The
+// block hash is BLAKE2b from blake2b_simd.
+//
+// The audited RustCrypto `argon2` crate would be the first choice. It is not a
+// dependency only because the package mirror this crate is built from does not
+// carry it, while blake2b_simd was already a dependency. The output is plain
+// RFC 9106, so stored records stay valid when the crate replaces this module:
+// the test below is the RFC's Argon2id vector, and passphrase keys only depend
+// on the tag.
+
+use blake2b_simd::Params;
+
+const VERSION: u32 = 0x13;
+// y of the RFC: 0 Argon2d, 1 Argon2i, 2 Argon2id
+const ARGON2ID: u32 = 2;
+const SYNC_POINTS: u32 = 4;
+const BLOCK_WORDS: usize = 128;
+const ADDRESSES_PER_BLOCK: u32 = BLOCK_WORDS as u32;
+
+type Block = [u64; BLOCK_WORDS];
+
+pub(crate) struct Argon2Input<'a> {
+    pub password: &'a [u8],
+    pub salt: &'a [u8],
+    // K and X of the RFC, empty if unused
+    pub secret: &'a [u8],
+    pub associated_data: &'a [u8],
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+fn blake2b(len: usize, parts: &[&[u8]]) -> Vec<u8> {
+    let mut state = Params::new().hash_length(len).to_state();
+    for part in parts {
+        state.update(part);
+    }
+    state.finalize().as_bytes().to_vec()
+}
+
+// H' of the RFC, BLAKE2b extended to outputs longer than 64 bytes
+fn hash_long(len: usize, parts: &[&[u8]]) -> Vec<u8> {
+    let len_le = (len as u32).to_le_bytes();
+    let mut prefixed = vec![&len_le[..]];
+    prefixed.extend_from_slice(parts);
+    if len <= 64 {
+        return blake2b(len, &prefixed);
+    }
+    let r = len.div_ceil(32) - 2;
+    let mut v = blake2b(64, &prefixed);
+    let mut out = Vec::with_capacity(len);
+    for _ in 1..r {
+        out.extend_from_slice(&v[..32]);
+        v = blake2b(64, &[&v]);
+    }
+    out.extend_from_slice(&v[..32]);
+    out.extend_from_slice(&blake2b(len - 32 * r, &[&v]));
+    out
+}
+
+fn block_from_bytes(bytes: &[u8]) -> Block {
+    let mut block = [0u64; BLOCK_WORDS];
+    for (word, chunk) in block.iter_mut().zip(bytes.chunks_exact(8)) {
+        *word = u64::from_le_bytes(chunk.try_into().unwrap());
+    }
+    block
+}
+
+// The BlaMka round function on the words a, b, c, d of v
+fn gb(v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize) {
+    let mul = |x: u64, y: u64| {
+        2u64.wrapping_mul(x & 0xffff_ffff)
+            .wrapping_mul(y & 0xffff_ffff)
+    };
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(mul(v[a], v[b]));
+    v[d] = (v[d] ^ v[a]).rotate_right(32);
+    v[c] = v[c].wrapping_add(v[d]).wrapping_add(mul(v[c], v[d]));
+    v[b] = (v[b] ^ v[c]).rotate_right(24);
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(mul(v[a], v[b]));
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = v[c].wrapping_add(v[d]).wrapping_add(mul(v[c], v[d]));
+    v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+fn permute(v: &mut [u64; 16]) {
+    gb(v, 0, 4, 8, 12);
+    gb(v, 1, 5, 9, 13);
+    gb(v, 2, 6, 10, 14);
+    gb(v, 3, 7, 11, 15);
+    gb(v, 0, 5, 10, 15);
+    gb(v, 1, 6, 11, 12);
+    gb(v, 2, 7, 8, 13);
+    gb(v, 3, 4, 9, 14);
+}
+
+// G(X, Y): P over the rows, then the columns, of the 8 x 8 matrix of 16-byte
+// registers of X xor Y, xored with X xor Y
+fn compress(x: &Block, y: &Block) -> Block {
+    let mut r = [0u64; BLOCK_WORDS];
+    for i in 0..BLOCK_WORDS {
+        r[i] = x[i] ^ y[i];
+    }
+    let mut z = r;
+    for row in 0..8 {
+        let mut v: [u64; 16] = z[16 * row..16 * row + 16].try_into().unwrap();
+        permute(&mut v);
+        z[16 * row..16 * row + 16].copy_from_slice(&v);
+    }
+    for column in 0..8 {
+        let index = |k: usize| 16 * (k / 2) + 2 * column + k % 2;
+        let mut v = [0u64; 16];
+        for (k, word) in v.iter_mut().enumerate() {
+            *word = z[index(k)];
+        }
+        permute(&mut v);
+        for (k, word) in v.iter().enumerate() {
+            z[index(k)] = *word;
+        }
+    }
+    for i in 0..BLOCK_WORDS {
+        z[i] ^= r[i];
+    }
+    z
+}
+
+// The next block of pseudo-random reference positions for Argon2i addressing
+fn next_addresses(address_input: &mut Block, addresses: &mut Block) {
+    let zero = [0u64; BLOCK_WORDS];
+    address_input[6] += 1;
+    *addresses = compress(&zero, &compress(&zero, address_input));
+}
+
+pub(crate) fn argon2id(input: &Argon2Input, tag_len: usize) -> Vec<u8> {
+    let lanes = input.parallelism;
+    let segment_length = input.memory_kib / (SYNC_POINTS * lanes);
+    let lane_length = segment_length * SYNC_POINTS;
+    let block_count = lane_length * lanes;
+
+    let le = |n: u32| n.to_le_bytes();
+    let length = |bytes: &[u8]| (bytes.len() as u32).to_le_bytes();
+    let h0 = blake2b(
+        64,
+        &[
+            &le(lanes),
+            &le(tag_len as u32),
+            &le(input.memory_kib),
+            &le(input.iterations),
+            &le(VERSION),
+            &le(ARGON2ID),
+            &length(input.password),
+            input.password,
+            &length(input.salt),
+            input.salt,
+            &length(input.secret),
+            input.secret,
+            &length(input.associated_data),
+            input.associated_data,
+        ],
+    );
+
+    let mut memory = vec![[0u64; BLOCK_WORDS]; block_count as usize];
+    for lane in 0..lanes {
+        for column in 0..2 {
+            let bytes = hash_long(1024, &[&h0, &le(column), &le(lane)]);
+            memory[(lane * lane_length + column) as usize] = block_from_bytes(&bytes);
+        }
+    }
+
+    for pass in 0..input.iterations {
+        for slice in 0..SYNC_POINTS {
+            for lane in 0..lanes {
+                // Argon2i addressing for the first half of the first pass
+                let data_independent = pass == 0 && slice < SYNC_POINTS / 2;
+                let mut address_input = [0u64; BLOCK_WORDS];
+                let mut addresses = [0u64; BLOCK_WORDS];
+                if data_independent {
+                    address_input[..6].copy_from_slice(&[
+                        pass as u64,
+                        lane as u64,
+                        slice as u64,
+                        block_count as u64,
+                        input.iterations as u64,
+                        ARGON2ID as u64,
+                    ]);
+                }
+                let start = if pass == 0 && slice == 0 { 2 } else { 0 };
+                if data_independent && start == 2 {
+                    next_addresses(&mut address_input, &mut addresses);
+                }
+
+                for index in start..segment_length {
+                    let column = slice * segment_length + index;
+                    let current = lane * lane_length + column;
+                    let previous = if column == 0 {
+                        current + lane_length - 1
+                    } else {
+                        current - 1
+                    };
+
+                    let pseudo_random = if data_independent {
+                        if index % ADDRESSES_PER_BLOCK == 0 {
+                            next_addresses(&mut address_input, &mut addresses);
+                        }
+                        addresses[(index % ADDRESSES_PER_BLOCK) as usize]
+                    } else {
+                        memory[previous as usize][0]
+                    };
+                    let j1 = pseudo_random & 0xffff_ffff;
+                    let j2 = pseudo_random >> 32;
+
+                    let ref_lane = if pass == 0 && slice == 0 {
+                        lane
+                    } else {
+                        (j2 % lanes as u64) as u32
+                    };
+                    let same_lane = ref_lane == lane;
+                    // Blocks that may be referenced: finished segments, and the
+                    // ones before this block in its own segment
+                    let finished = if pass == 0 {
+                        slice * segment_length
+                    } else {
+                        lane_length - segment_length
+                    };
+                    let area = if same_lane {
+                        finished + index - 1
+                    } else if index == 0 {
+                        finished - 1
+                    } else {
+                        finished
+                    } as u64;
+                    let x = (j1 * j1) >> 32;
+                    let relative = area - 1 - ((area * x) >> 32);
+                    let window_start = if pass == 0 || slice == SYNC_POINTS - 1 {
+                        0
+                    } else {
+                        (slice + 1) * segment_length
+                    } as u64;
+                    let ref_column = ((window_start + relative) % lane_length as u64) as u32;
+
+                    let reference = ref_lane * lane_length + ref_column;
+                    let mut block =
+                        compress(&memory[previous as usize], &memory[reference as usize]);
+                    if pass > 0 {
+                        for (word, old) in block.iter_mut().zip(memory[current as usize].iter()) {
+                            *word ^= old;
+                        }
+                    }
+                    memory[current as usize] = block;
+                }
+            }
+        }
+    }
+
+    let mut last = memory[(lane_length - 1) as usize];
+    for lane in 1..lanes {
+        for (word, other) in last
+            .iter_mut()
+            .zip(memory[(lane * lane_length + lane_length - 1) as usize].iter())
+        {
+            *word ^= other;
+        }
+    }
+    let bytes: Vec<u8> = last.iter().flat_map(|word| word.to_le_bytes()).collect();
+    hash_long(tag_len, &[&bytes])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rfc_9106_test_vector() {
+        // RFC 9106, section 5.3
+        let input = Argon2Input {
+            password: &[1; 32],
+            salt: &[2; 16],
+            secret: &[3; 8],
+            associated_data: &[4; 12],
+            memory_kib: 32,
+            iterations: 3,
+            parallelism: 4,
+        };
+        assert_eq!(
+            ::hex::encode(argon2id(&input, 32)),
+            "0d640df58d78766c08c037a34a8b53c9d01ef0452d75b65eb52520e96b01e659"
+        );
+    }
+}
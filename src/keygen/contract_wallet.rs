@@ -0,0 +1,101 @@
+// L2 key derivation for smart-contract wallets (e.g. Hebao).
+//
+// Contract wallets do not produce a plain 65-byte ECDSA signature of the keySeed.
+// Their signature is validated on-chain through EIP-1271 `isValidSignature`, and its
+// format is wallet specific: typically the owner's signature followed by a
+// signature-type byte, sometimes more. The Loopring wallet derives the L2 key from
+// the SHA-256 of exactly those bytes, so nothing may be stripped or normalized.
+//
+// The one transformation that is required is removing the ABI `bytes` envelope
+// (offset, length, right-padded data) that some RPC paths return the signature in,
+// because the frontend hashes the raw signature bytes, not the ABI encoding.
+
+use super::l2_key::{generate_l2_keys, Account};
+
+fn word_to_usize(word: &[u8]) -> Option<usize> {
+    if word[..24].iter().any(|&b| b != 0) {
+        return None;
+    }
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&word[24..32]);
+    usize::try_from(u64::from_be_bytes(bytes)).ok()
+}
+
+// Returns the inner bytes if `data` is an ABI-encoded `bytes` value
+fn unwrap_abi_bytes(data: &[u8]) -> Option<&[u8]> {
+    if data.len() < 64 || !data.len().is_multiple_of(32) {
+        return None;
+    }
+    if word_to_usize(&data[..32])? != 32 {
+        return None;
+    }
+    let length = word_to_usize(&data[32..64])?;
+    let padded = length.div_ceil(32) * 32;
+    if data.len() != 64 + padded || data[64 + length..].iter().any(|&b| b != 0) {
+        return None;
+    }
+    Some(&data[64..64 + length])
+}
+
+pub fn contract_wallet_signature_bytes(signature: &str) -> Result<Vec<u8>, String> {
+    let data = crate::util::hex::decode_bytes(signature)
+        .map_err(|_| String::from("You didn't pass a valid hex-string"))?;
+    Ok(unwrap_abi_bytes(&data).unwrap_or(&data).to_vec())
+}
+
+pub fn generate_l2_keys_from_contract_wallet(signature: &str) -> Result<Account, String> {
+    let bytes = contract_wallet_signature_bytes(signature)?;
+    generate_l2_keys(format!("0x{}", hex::encode(bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIGNATURE: &str = "0xf8214f068c55d1bebf1fbefced91eba5f4bbe14315e1ad71f61f21e094f5853a12eba239aeaa77538ae458eebe49ca2b732d211bf0943095b3502a3b0e6a08cd1c";
+
+    fn abi_wrap(data: &[u8]) -> String {
+        let mut encoded = vec![0u8; 64];
+        encoded[31] = 32;
+        encoded[56..64].copy_from_slice(&(data.len() as u64).to_be_bytes());
+        encoded.extend_from_slice(data);
+        encoded.resize(64 + data.len().div_ceil(32) * 32, 0);
+        format!("0x{}", hex::encode(encoded))
+    }
+
+    #[test]
+    fn raw_signature_is_hashed_unchanged() {
+        // With the EIP-1271 signature-type suffix the key differs from the bare
+        // ECDSA signature, exactly as in the Loopring wallet
+        let with_suffix = format!("{}02", SIGNATURE);
+        let account = generate_l2_keys_from_contract_wallet(&with_suffix).unwrap();
+        let expected = generate_l2_keys(with_suffix).unwrap();
+        assert_eq!(account.private_key, expected.private_key);
+        assert_ne!(
+            account.private_key,
+            generate_l2_keys(SIGNATURE.to_string()).unwrap().private_key
+        );
+    }
+
+    #[test]
+    fn abi_envelope_is_removed() {
+        let raw = hex::decode(format!("{}02", &SIGNATURE[2..])).unwrap();
+        let wrapped = abi_wrap(&raw);
+
+        assert_eq!(contract_wallet_signature_bytes(&wrapped).unwrap(), raw);
+        assert_eq!(
+            generate_l2_keys_from_contract_wallet(&wrapped)
+                .unwrap()
+                .private_key,
+            generate_l2_keys_from_contract_wallet(&format!("0x{}", hex::encode(&raw)))
+                .unwrap()
+                .private_key
+        );
+    }
+
+    #[test]
+    fn invalid_signatures_are_rejected() {
+        assert!(generate_l2_keys_from_contract_wallet("0x").is_err());
+        assert!(generate_l2_keys_from_contract_wallet("0xzz").is_err());
+    }
+}
@@ -0,0 +1,85 @@
+// Ephemeral dual-auth keys.
+//
+// A dual-auth key is a throwaway EdDSA keypair whose public key is bound into a
+// signed payload. Whoever holds the private half (e.g. the matching engine or the
+// claimer of a red packet) has to co-sign before the payload can be settled. It
+// is not derived from an L1 signature like the account key: it only needs to be
+// unpredictable, and is discarded after settlement.
+
+use crate::poseidon::{
+    eddsa::{KeyPair, MessageHash, SignatureScheme, SignedMessage},
+    jubjub::{Point, JUBJUB_L},
+};
+use crate::signer::{check_private_key, parse_private_key, Signer};
+use crate::util::{errors::SignerError, hex};
+use num_bigint::{BigInt, Sign};
+use sha2::{Digest, Sha256};
+
+pub struct DualAuthKey {
+    keypair: KeyPair,
+}
+
+impl DualAuthKey {
+    pub(crate) fn new(private_key: BigInt) -> Result<Self, SignerError> {
+        Ok(DualAuthKey {
+            keypair: KeyPair::new(check_private_key(private_key)?),
+        })
+    }
+
+    // secret = int(sha256(entropy), little-endian) mod JUBJUB_L, like the L2
+    // key derivation. The entropy should be at least 32 random bytes.
+    pub fn from_entropy(entropy: &[u8]) -> Result<Self, SignerError> {
+        let digest = Sha256::digest(entropy);
+        Self::new(BigInt::from_bytes_le(Sign::Plus, &digest) % &*JUBJUB_L)
+    }
+
+    // 32 bytes of OS randomness
+    #[cfg(feature = "rng")]
+    pub fn generate() -> Result<Self, SignerError> {
+        let mut entropy = [0u8; 32];
+        getrandom::getrandom(&mut entropy)
+            .map_err(|e| SignerError::Unavailable(format!("no OS randomness: {}", e)))?;
+        Self::from_entropy(&entropy)
+    }
+
+    // Restores the key on the co-signing side
+    pub fn from_hex(hex_private_key: &str) -> Result<Self, SignerError> {
+        Self::new(parse_private_key(hex_private_key)?)
+    }
+
+    #[cfg(any(feature = "borsh", feature = "bincode"))]
+    pub(crate) fn private_scalar(&self) -> &BigInt {
+        self.keypair.private_key_scalar()
+    }
+
+    // The form in which the key is handed to the co-signer
+    pub fn private_key_hex(&self) -> String {
+        hex::encode_secret(self.keypair.private_key_scalar())
+    }
+}
+
+impl Signer for DualAuthKey {
+    fn public_key(&self) -> &Point {
+        self.keypair.public_key()
+    }
+
+    fn sign(&self, hash: &MessageHash) -> Result<SignedMessage, SignerError> {
+        Ok(SignatureScheme::sign_with_keypair(&self.keypair, hash))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_round_trips_through_hex() {
+        let key = DualAuthKey::from_entropy(b"dual-auth test entropy").unwrap();
+        let restored = DualAuthKey::from_hex(&key.private_key_hex()).unwrap();
+        assert!(restored.public_key() == key.public_key());
+        assert!(DualAuthKey::from_hex("0x0").is_err());
+
+        let other = DualAuthKey::from_entropy(b"other entropy").unwrap();
+        assert!(other.public_key() != key.public_key());
+    }
+}
@@ -0,0 +1,240 @@
+// Accounts exported by other Loopring tooling.
+//
+// Two JSON shapes are in circulation:
+//
+//  * the Loopring wallet's "Export Account" file:
+//        {"address", "accountId", "level", "nonce", "apiKey",
+//         "publicX", "publicY", "privateKey"}
+//  * the exported account that hello_loopring and the Python SDK samples read:
+//        {"name", "chainId", "exchangeName", "exchangeAddress", "accountAddress",
+//         "accountId", "apiKey", "publicKeyX", "publicKeyY", "privateKey",
+//         "ecdsaKey", "whitelisted"}
+//
+// import_account recognizes either, recomputes the public key from the private
+// key and checks it against the exported one, so a file whose fields were
+// mixed up is rejected instead of signing with a key the relayer does not know.
+// Numbers may be JSON numbers or strings, and hex values may have lost their
+// leading zeros. The L1 key of hello_loopring files ("ecdsaKey") is not
+// imported.
+
+use super::l2_key::Account;
+use crate::poseidon::jubjub::JUBJUB_L;
+use crate::util::{address, errors::ImportError, hex};
+use num_bigint::BigInt;
+use num_traits::{Euclid, Zero};
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    LoopringWallet,
+    HelloLoopring,
+}
+
+// Field names of one format
+struct Fields {
+    address: &'static str,
+    public_key_x: &'static str,
+    public_key_y: &'static str,
+}
+
+impl ExportFormat {
+    fn detect(export: &Value) -> Option<Self> {
+        [ExportFormat::LoopringWallet, ExportFormat::HelloLoopring]
+            .into_iter()
+            .find(|format| export.get(format.fields().public_key_x).is_some())
+    }
+
+    fn fields(&self) -> Fields {
+        match self {
+            ExportFormat::LoopringWallet => Fields {
+                address: "address",
+                public_key_x: "publicX",
+                public_key_y: "publicY",
+            },
+            ExportFormat::HelloLoopring => Fields {
+                address: "accountAddress",
+                public_key_x: "publicKeyX",
+                public_key_y: "publicKeyY",
+            },
+        }
+    }
+}
+
+pub struct ImportedAccount {
+    pub format: ExportFormat,
+    pub account_id: u32,
+    // Checksummed L1 address of the account owner
+    pub owner: String,
+    pub api_key: Option<String>,
+    // Key nonce (Loopring wallet exports only)
+    pub nonce: Option<u32>,
+    // hello_loopring exports only
+    pub chain_id: Option<u64>,
+    pub exchange: Option<String>,
+    pub keys: Account,
+}
+
+fn field<'a>(export: &'a Value, name: &'static str) -> Result<&'a Value, ImportError> {
+    match export.get(name) {
+        None | Some(Value::Null) => Err(ImportError::MissingField(name)),
+        Some(value) => Ok(value),
+    }
+}
+
+fn string_field<'a>(export: &'a Value, name: &'static str) -> Result<&'a str, ImportError> {
+    field(export, name)?
+        .as_str()
+        .ok_or_else(|| ImportError::InvalidField(name, export[name].to_string()))
+}
+
+fn optional_string(export: &Value, name: &'static str) -> Result<Option<String>, ImportError> {
+    match string_field(export, name) {
+        Ok("") => Ok(None),
+        Ok(value) => Ok(Some(value.to_string())),
+        Err(ImportError::MissingField(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn u64_field(export: &Value, name: &'static str) -> Result<u64, ImportError> {
+    let value = field(export, name)?;
+    let parsed = match value {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    };
+    parsed.ok_or_else(|| ImportError::InvalidField(name, value.to_string()))
+}
+
+fn u32_field(export: &Value, name: &'static str) -> Result<u32, ImportError> {
+    let value = u64_field(export, name)?;
+    u32::try_from(value).map_err(|_| ImportError::InvalidField(name, value.to_string()))
+}
+
+fn optional<T>(result: Result<T, ImportError>) -> Result<Option<T>, ImportError> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(ImportError::MissingField(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn coordinate(export: &Value, name: &'static str) -> Result<BigInt, ImportError> {
+    let value = string_field(export, name)?;
+    hex::decode_bigint(value).map_err(|_| ImportError::InvalidField(name, value.to_string()))
+}
+
+pub fn import_account_value(export: &Value) -> Result<ImportedAccount, ImportError> {
+    let format = ExportFormat::detect(export).ok_or(ImportError::UnknownFormat)?;
+    let fields = format.fields();
+
+    let owner = address::normalize_address(string_field(export, fields.address)?)
+        .map_err(ImportError::InvalidAddress)?;
+    let private_key = hex::decode_secret(string_field(export, "privateKey")?)
+        .map_err(|_| ImportError::InvalidPrivateKey)?;
+    if private_key.rem_euclid(&JUBJUB_L).is_zero() {
+        return Err(ImportError::InvalidPrivateKey);
+    }
+    let keys = Account::from_private_scalar(private_key);
+    let public_key = keys.public_key();
+    if coordinate(export, fields.public_key_x)? != public_key.x().to_bigint()
+        || coordinate(export, fields.public_key_y)? != public_key.y().to_bigint()
+    {
+        return Err(ImportError::KeyMismatch);
+    }
+
+    let (nonce, chain_id, exchange) = match format {
+        ExportFormat::LoopringWallet => (optional(u32_field(export, "nonce"))?, None, None),
+        ExportFormat::HelloLoopring => (
+            None,
+            optional(u64_field(export, "chainId"))?,
+            optional_string(export, "exchangeAddress")?,
+        ),
+    };
+    Ok(ImportedAccount {
+        format,
+        account_id: u32_field(export, "accountId")?,
+        owner,
+        api_key: optional_string(export, "apiKey")?,
+        nonce,
+        chain_id,
+        exchange,
+        keys,
+    })
+}
+
+pub fn import_account(export: &str) -> Result<ImportedAccount, ImportError> {
+    let export: Value =
+        serde_json::from_str(export).map_err(|e| ImportError::InvalidJson(e.to_string()))?;
+    import_account_value(&export)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const L2_KEY: &str = "0x001fa186947c8c644cd11078f67e0bb21656432f55c4df76997b6acab2abda7f";
+    const X: &str = "0x29d178cdd6a40cd900c41565b6057a1d12c00a8c41ad367e2fe0100aab00fbe3";
+    const Y: &str = "0x29e339a045af33d5729eab3b64c617e6a78dcfd0988f95f215d443d77a864b9c";
+    const OWNER: &str = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+
+    #[test]
+    fn imports_both_formats() {
+        let wallet = json!({
+            "address": OWNER.to_lowercase(), "accountId": 11087, "level": "",
+            "nonce": 1, "apiKey": "abc", "publicX": X, "publicY": Y, "privateKey": L2_KEY,
+        });
+        let account = import_account(&wallet.to_string()).unwrap();
+        assert_eq!(account.format, ExportFormat::LoopringWallet);
+        assert_eq!(account.account_id, 11087);
+        assert_eq!(account.owner, OWNER);
+        assert_eq!(account.api_key.as_deref(), Some("abc"));
+        assert_eq!(account.nonce, Some(1));
+        assert_eq!(account.keys.private_key, L2_KEY);
+
+        // Unpadded private key, account id as a string
+        let hello_loopring = json!({
+            "name": "DEV Account 1", "chainId": 5, "exchangeName": "Loopring Exchange v2",
+            "exchangeAddress": "0x2e76EBd1c7c0C8e7c2B875b6d505a260C525d25e",
+            "accountAddress": OWNER, "accountId": "11087", "apiKey": "",
+            "publicKeyX": X, "publicKeyY": Y, "privateKey": &L2_KEY[4..],
+            "ecdsaKey": "", "whitelisted": false,
+        });
+        let account = import_account_value(&hello_loopring).unwrap();
+        assert_eq!(account.format, ExportFormat::HelloLoopring);
+        assert_eq!(account.chain_id, Some(5));
+        assert_eq!(account.api_key, None);
+        assert_eq!(account.keys.public_key_x, X);
+    }
+
+    #[test]
+    fn rejects_inconsistent_exports() {
+        let export = |x: &str, private_key: &str| {
+            json!({"address": OWNER, "accountId": 1, "publicX": x, "publicY": Y,
+                   "privateKey": private_key})
+        };
+        assert!(matches!(
+            import_account_value(&export(Y, L2_KEY)),
+            Err(ImportError::KeyMismatch)
+        ));
+        assert!(matches!(
+            import_account_value(&export(X, "0x00")),
+            Err(ImportError::InvalidPrivateKey)
+        ));
+        assert!(matches!(
+            import_account_value(&json!({"privateKey": L2_KEY})),
+            Err(ImportError::UnknownFormat)
+        ));
+        assert!(matches!(
+            import_account("{"),
+            Err(ImportError::InvalidJson(_))
+        ));
+        let mut no_id = export(X, L2_KEY);
+        no_id.as_object_mut().unwrap().remove("accountId");
+        assert!(matches!(
+            import_account_value(&no_id),
+            Err(ImportError::MissingField("accountId"))
+        ));
+    }
+}
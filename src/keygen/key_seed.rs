@@ -0,0 +1,250 @@
+// The keySeed message an account's L1 signer signs to derive its L2 key.
+//
+// GET /api/v3/account returns the keySeed to sign. When it is empty, which is
+// always the case for a counterfactual account (a smart wallet whose contract is
+// not deployed yet and that has never set a key), the Loopring frontend falls back
+// to the default message
+//
+//     Sign this message to access Loopring Exchange: {exchange} with key nonce: {nonce}
+//
+// with nonce 0. Such an account cannot validate a signature through EIP-1271 yet,
+// so the keySeed is signed by the wallet's owner EOA and the key is derived from
+// that plain ECDSA signature.
+//
+// A wrong signature still derives a key, just one the relayer never registered,
+// and the mistake only shows up when an update or a signed request is rejected.
+// With the `k256` feature, the signature can be checked first: it must recover
+// to the signer's address over the personal_sign (EIP-191) hash of the keySeed,
+//
+//     keccak256("\x19Ethereum Signed Message:\n" || len(keySeed) || keySeed)
+//
+// which catches signatures by another wallet and signatures of another message
+// (e.g. an old key nonce). For a counterfactual account, the address is the
+// owner EOA's: the frontend itself ecrecovers the owner before it derives the key,
+// and `generate_counterfactual_l2_keys` does the same. Deployed contract wallets
+// sign through EIP-1271, which needs the chain, so their signatures cannot be
+// checked this way.
+
+use super::contract_wallet::generate_l2_keys_from_contract_wallet;
+use super::l2_key::{generate_l2_keys, Account};
+#[cfg(feature = "k256")]
+use crate::payload::eip712::{address_of, keccak256};
+#[cfg(feature = "k256")]
+use crate::util::{address, errors::KeySeedError, hex};
+
+pub enum WalletKind {
+    // A regular L1 account
+    Eoa,
+    // A deployed smart wallet signing through EIP-1271
+    ContractWallet,
+    // A smart wallet that is not deployed yet and has no key nonce
+    Counterfactual,
+}
+
+pub fn default_key_seed(exchange: &str, nonce: u32) -> String {
+    format!(
+        "Sign this message to access Loopring Exchange: {} with key nonce: {}",
+        exchange, nonce
+    )
+}
+
+impl WalletKind {
+    // `api_key_seed` is the keySeed reported by GET /api/v3/account, if any
+    pub fn key_seed(&self, api_key_seed: Option<&str>, exchange: &str, nonce: u32) -> String {
+        match (self, api_key_seed) {
+            (WalletKind::Counterfactual, _) => default_key_seed(exchange, 0),
+            (_, Some(key_seed)) if !key_seed.is_empty() => key_seed.to_string(),
+            _ => default_key_seed(exchange, nonce),
+        }
+    }
+
+    // Derives the L2 keys from the signature of `key_seed`
+    pub fn derive_l2_keys(&self, signature: &str) -> Result<Account, String> {
+        match self {
+            WalletKind::Eoa | WalletKind::Counterfactual => generate_l2_keys(signature.to_string()),
+            WalletKind::ContractWallet => generate_l2_keys_from_contract_wallet(signature),
+        }
+    }
+}
+
+// The EIP-191 personal_sign digest of the keySeed message
+#[cfg(feature = "k256")]
+pub fn key_seed_digest(key_seed: &str) -> [u8; 32] {
+    let mut message = format!("\x19Ethereum Signed Message:\n{}", key_seed.len()).into_bytes();
+    message.extend_from_slice(key_seed.as_bytes());
+    keccak256(&message)
+}
+
+// The address whose key made `signature` (r || s || v) over `key_seed`
+#[cfg(feature = "k256")]
+pub fn recover_key_seed_signer(key_seed: &str, signature: &str) -> Result<[u8; 20], KeySeedError> {
+    use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+
+    let bytes = hex::decode_bytes(signature).map_err(|_| KeySeedError::InvalidSignature)?;
+    if bytes.len() != 65 {
+        return Err(KeySeedError::InvalidSignature);
+    }
+    let v = match bytes[64] {
+        0 | 1 => bytes[64],
+        27 | 28 => bytes[64] - 27,
+        _ => return Err(KeySeedError::InvalidSignature),
+    };
+    let mut signature =
+        Signature::from_slice(&bytes[..64]).map_err(|_| KeySeedError::InvalidSignature)?;
+    let mut recovery_id = RecoveryId::from_byte(v).ok_or(KeySeedError::InvalidSignature)?;
+    // Some signers do not normalize s; -s recovers the same key with the other parity
+    if let Some(normalized) = signature.normalize_s() {
+        signature = normalized;
+        recovery_id = RecoveryId::new(!recovery_id.is_y_odd(), recovery_id.is_x_reduced());
+    }
+    let key =
+        VerifyingKey::recover_from_prehash(&key_seed_digest(key_seed), &signature, recovery_id)
+            .map_err(|_| KeySeedError::InvalidSignature)?;
+    Ok(address_of(&key))
+}
+
+#[cfg(feature = "k256")]
+pub fn verify_key_seed_signature(
+    key_seed: &str,
+    signature: &str,
+    signer: &str,
+) -> Result<(), KeySeedError> {
+    let expected = address::parse_address(signer).map_err(KeySeedError::InvalidAddress)?;
+    let recovered = recover_key_seed_signer(key_seed, signature)?;
+    if recovered != expected {
+        return Err(KeySeedError::WrongSigner {
+            expected: address::to_checksum_address(&expected),
+            recovered: address::to_checksum_address(&recovered),
+        });
+    }
+    Ok(())
+}
+
+// generate_l2_keys, after checking that `signer` signed `key_seed`
+#[cfg(feature = "k256")]
+pub fn generate_l2_keys_verified(
+    signature: &str,
+    key_seed: &str,
+    signer: &str,
+) -> Result<Account, KeySeedError> {
+    verify_key_seed_signature(key_seed, signature, signer)?;
+    generate_l2_keys(signature.to_string()).map_err(|_| KeySeedError::InvalidSignature)
+}
+
+// The keys the frontend creates a counterfactual account with: `owner` signs
+// the nonce-0 default keySeed, the signature must recover to it, and the key is
+// derived from that plain ECDSA signature
+#[cfg(feature = "k256")]
+pub fn generate_counterfactual_l2_keys(
+    signature: &str,
+    exchange: &str,
+    owner: &str,
+) -> Result<Account, KeySeedError> {
+    let key_seed = WalletKind::Counterfactual.key_seed(None, exchange, 0);
+    generate_l2_keys_verified(signature, &key_seed, owner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXCHANGE: &str = "0x0BABA1Ad5bE3a5C0a66E7ac838a129Bf948f1eA4";
+
+    #[test]
+    fn key_seed_message() {
+        assert_eq!(
+            WalletKind::Eoa.key_seed(None, EXCHANGE, 3),
+            "Sign this message to access Loopring Exchange: 0x0BABA1Ad5bE3a5C0a66E7ac838a129Bf948f1eA4 with key nonce: 3"
+        );
+        assert_eq!(WalletKind::Eoa.key_seed(Some("seed"), EXCHANGE, 3), "seed");
+        assert_eq!(
+            WalletKind::Eoa.key_seed(Some(""), EXCHANGE, 3),
+            default_key_seed(EXCHANGE, 3)
+        );
+    }
+
+    #[test]
+    fn counterfactual_uses_nonce_zero() {
+        assert_eq!(
+            WalletKind::Counterfactual.key_seed(Some("seed"), EXCHANGE, 5),
+            default_key_seed(EXCHANGE, 0)
+        );
+    }
+
+    #[test]
+    fn counterfactual_key_is_derived_from_owner_signature() {
+        let signature = "0xf8214f068c55d1bebf1fbefced91eba5f4bbe14315e1ad71f61f21e094f5853a12eba239aeaa77538ae458eebe49ca2b732d211bf0943095b3502a3b0e6a08cd1c";
+        let account = WalletKind::Counterfactual
+            .derive_l2_keys(signature)
+            .unwrap();
+        assert_eq!(
+            account.private_key,
+            "0x001fa186947c8c644cd11078f67e0bb21656432f55c4df76997b6acab2abda7f"
+        );
+    }
+
+    #[cfg(feature = "k256")]
+    #[test]
+    fn counterfactual_known_answer() {
+        // personal_sign of the nonce-0 keySeed by the EIP-712 example key
+        // (keccak256("cow")); ECDSA as in RFC 6979, so any wallet produces it
+        let cow = "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826";
+        let signature = "0x978b60a3f6997e51ef71e8e3ded6b4aab80c3816cb913b0fd7af37fffd567b397edcbb353ebf335e76687140b7bdccc929c4980f76d17816b9079e649c0e25e91c";
+        let account = generate_counterfactual_l2_keys(signature, EXCHANGE, cow).unwrap();
+        assert_eq!(
+            account.private_key,
+            "0x002679a4dd654dbc208e414f496e3c6a2adc506502af6d80c23911e849e81d52"
+        );
+        assert_eq!(
+            account.public_key_x,
+            "0x052e075f252e16fdfb6f492038e966aeb0f781d8e110dc13970d7bd3eda793a1"
+        );
+        assert_eq!(
+            account.public_key_y,
+            "0x2f0cbf7ba92999ff21e0fd8d04c9450ca717e03720de8592184b1ba5098e8d1d"
+        );
+        // The owner is checked, as the frontend does before deriving
+        let other = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        assert!(matches!(
+            generate_counterfactual_l2_keys(signature, EXCHANGE, other),
+            Err(KeySeedError::WrongSigner { .. })
+        ));
+    }
+
+    #[cfg(feature = "k256")]
+    #[test]
+    fn key_seed_signature_recovers_to_signer() {
+        use crate::payload::eip712::sign_prehash;
+
+        // Address from the EIP-712 specification example
+        let key = ::hex::encode(keccak256(b"cow"));
+        let cow = "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826";
+        let key_seed = default_key_seed(EXCHANGE, 1);
+        let signature = sign_prehash(&key_seed_digest(&key_seed), &key).unwrap();
+
+        let account = generate_l2_keys_verified(&signature, &key_seed, cow).unwrap();
+        assert_eq!(
+            account.private_key,
+            generate_l2_keys(signature.clone()).unwrap().private_key
+        );
+        // v as 0/1 instead of 27/28
+        let v = u8::from_str_radix(&signature[130..], 16).unwrap() - 27;
+        let raw_v = format!("{}{:02x}", &signature[..130], v);
+        assert!(verify_key_seed_signature(&key_seed, &raw_v, cow).is_ok());
+
+        // Signed with an old key nonce
+        let old = sign_prehash(&key_seed_digest(&default_key_seed(EXCHANGE, 0)), &key).unwrap();
+        assert!(matches!(
+            generate_l2_keys_verified(&old, &key_seed, cow),
+            Err(KeySeedError::WrongSigner { expected, .. }) if expected == cow
+        ));
+        assert_eq!(
+            verify_key_seed_signature(&key_seed, "0x1234", cow),
+            Err(KeySeedError::InvalidSignature)
+        );
+        assert!(matches!(
+            verify_key_seed_signature(&key_seed, &signature, "0x1234"),
+            Err(KeySeedError::InvalidAddress(_))
+        ));
+    }
+}
@@ -2,63 +2,124 @@
 // 2. The user has to sign this message with their l1 key, generating a ECDSA Signature
 // 3. L2_EDDSA_KEY=eth.sign(keySeed)
 
-use std::ops::{Add, Mul};
-
 use num_bigint::{BigInt, Sign};
-use num_traits::{Num, Zero};
-use sha2::{Digest, Sha256};
+use sha2::{Digest, Sha256, Sha512};
+use sha3::Keccak256;
 
 use crate::{
-    poseidon::jubjub::{Point, JUBJUB_L},
-    util::helpers::to_bytes_32,
+    poseidon::{
+        eddsa::{KeyPair, MessageHash, SignatureScheme, SignedMessage},
+        jubjub::{Point, JUBJUB_L},
+    },
+    util::hex,
 };
 
 pub struct Account {
     pub private_key: String,
     pub public_key_x: String,
     pub public_key_y: String,
+    // The same key material as above, already parsed
+    keypair: KeyPair,
+}
+
+impl Account {
+    pub(crate) fn from_private_scalar(private_scalar: BigInt) -> Self {
+        let keypair = KeyPair::new(private_scalar);
+        let public_key = keypair.public_key();
+        Account {
+            private_key: hex::encode_secret(keypair.private_key_scalar()),
+            public_key_x: public_key.x().to_hex(),
+            public_key_y: public_key.y().to_hex(),
+            keypair,
+        }
+    }
+
+    pub fn private_scalar(&self) -> &BigInt {
+        self.keypair.private_key_scalar()
+    }
+
+    pub fn public_key(&self) -> &Point {
+        self.keypair.public_key()
+    }
+
+    pub fn keypair(&self) -> &KeyPair {
+        &self.keypair
+    }
+
+    pub fn sign(&self, hash: &MessageHash) -> SignedMessage {
+        SignatureScheme::sign_with_keypair(&self.keypair, hash)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeyDigest {
+    #[default]
+    Sha256,
+    Sha512,
+    Keccak256,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Endianness {
+    #[default]
+    Little,
+    Big,
+}
+
+// How the ECDSA signature is turned into the L2 secret:
+//
+//     secret = int(digest(signature), endianness) mod JUBJUB_L
+//
+// The default (SHA-256, little-endian) is what the Loopring SDKs and frontend do.
+// Other combinations reproduce keys created by forks that deviate from it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyDerivationSpec {
+    pub digest: KeyDigest,
+    pub endianness: Endianness,
+}
+
+impl KeyDerivationSpec {
+    fn derive(&self, signature: &[u8]) -> BigInt {
+        let hash = match self.digest {
+            KeyDigest::Sha256 => Sha256::digest(signature).to_vec(),
+            KeyDigest::Sha512 => Sha512::digest(signature).to_vec(),
+            KeyDigest::Keccak256 => Keccak256::digest(signature).to_vec(),
+        };
+        let n = match self.endianness {
+            Endianness::Little => BigInt::from_bytes_le(Sign::Plus, &hash),
+            Endianness::Big => BigInt::from_bytes_be(Sign::Plus, &hash),
+        };
+        n % &*JUBJUB_L
+    }
 }
 
 pub fn generate_l2_private_key(signed_message_ecdsa: String) -> Result<String, String> {
-    match hex::decode(signed_message_ecdsa.trim_start_matches("0x")) {
+    generate_l2_private_key_with(signed_message_ecdsa, &KeyDerivationSpec::default())
+}
+
+pub fn generate_l2_private_key_with(
+    signed_message_ecdsa: String,
+    spec: &KeyDerivationSpec,
+) -> Result<String, String> {
+    match hex::decode_bytes(&signed_message_ecdsa) {
         Ok(value) => {
-            let mut hasher = Sha256::new();
-            hasher.update(value);
-            let hash = BigInt::from_bytes_le(Sign::Plus, &hasher.finalize()[..]);
-            let hash_byte_array = to_bytes_32(&hash);
-
-            let mut big_int = BigInt::zero();
-
-            for (i, item) in hash_byte_array.iter().enumerate() {
-                let item_big_int = BigInt::from(*item);
-                let tmp = BigInt::from(256).pow(i.try_into().unwrap());
-                big_int = big_int.add(item_big_int.mul(tmp));
-            }
-            let secret_key = big_int % &*JUBJUB_L;
-            let secret_key_hex = format!("{:0>64}", secret_key.to_str_radix(16));
-
-            Ok(format!("0x{}", secret_key_hex))
+            let secret_key = spec.derive(&value);
+            Ok(hex::encode_secret(&secret_key))
         }
         Err(_) => Err(String::from("You didn't pass a valid hex-string")),
     }
 }
 
 pub fn generate_l2_keys(signed_message_ecdsa: String) -> Result<Account, String> {
-    match generate_l2_private_key(signed_message_ecdsa) {
-        Ok(secret_key) => {
-            let base_point = Point::generate();
-            let private_key =
-                BigInt::from_str_radix(secret_key.trim_start_matches("0x"), 16).unwrap();
-
-            let public_key = base_point.mul(&private_key);
-            let public_key_x = format!("0x{:0>64}", public_key.x().n().to_str_radix(16));
-            let public_key_y = format!("0x{:0>64}", public_key.y().n().to_str_radix(16));
-            Ok(Account {
-                private_key: secret_key,
-                public_key_x,
-                public_key_y,
-            })
-        }
+    generate_l2_keys_with(signed_message_ecdsa, &KeyDerivationSpec::default())
+}
+
+pub fn generate_l2_keys_with(
+    signed_message_ecdsa: String,
+    spec: &KeyDerivationSpec,
+) -> Result<Account, String> {
+    match hex::decode_bytes(&signed_message_ecdsa) {
+        Ok(value) => Ok(Account::from_private_scalar(spec.derive(&value))),
         Err(_) => Err(String::from("You didn't pass a valid hex-string")),
     }
 }
@@ -94,4 +155,63 @@ mod tests {
             "0x29e339a045af33d5729eab3b64c617e6a78dcfd0988f95f215d443d77a864b9c"
         );
     }
+
+    #[test]
+    fn key_derivation_spec_test() {
+        let signed_message_ecdsa = String::from("0xf8214f068c55d1bebf1fbefced91eba5f4bbe14315e1ad71f61f21e094f5853a12eba239aeaa77538ae458eebe49ca2b732d211bf0943095b3502a3b0e6a08cd1c");
+        let default_key = generate_l2_private_key(signed_message_ecdsa.clone()).unwrap();
+        assert_eq!(
+            generate_l2_private_key_with(
+                signed_message_ecdsa.clone(),
+                &KeyDerivationSpec::default()
+            )
+            .unwrap(),
+            default_key
+        );
+
+        let big_endian = KeyDerivationSpec {
+            digest: KeyDigest::Sha256,
+            endianness: Endianness::Big,
+        };
+        let expected = BigInt::from_bytes_be(
+            Sign::Plus,
+            &Sha256::digest(hex::decode_bytes(&signed_message_ecdsa).unwrap()),
+        ) % &*JUBJUB_L;
+        assert_eq!(
+            generate_l2_private_key_with(signed_message_ecdsa.clone(), &big_endian).unwrap(),
+            format!("0x{:0>64}", expected.to_str_radix(16))
+        );
+
+        let keccak = KeyDerivationSpec {
+            digest: KeyDigest::Keccak256,
+            endianness: Endianness::Little,
+        };
+        let account = generate_l2_keys_with(signed_message_ecdsa, &keccak).unwrap();
+        assert_ne!(account.private_key, default_key);
+    }
+
+    #[test]
+    fn typed_keys_match_hex_keys() {
+        let signed_message_ecdsa = String::from("0xf8214f068c55d1bebf1fbefced91eba5f4bbe14315e1ad71f61f21e094f5853a12eba239aeaa77538ae458eebe49ca2b732d211bf0943095b3502a3b0e6a08cd1c");
+        let account = generate_l2_keys(signed_message_ecdsa).unwrap();
+
+        assert_eq!(
+            account.private_scalar(),
+            &hex::decode_bigint(&account.private_key).unwrap()
+        );
+        assert_eq!(
+            account.public_key().x().to_bigint(),
+            hex::decode_bigint(&account.public_key_x).unwrap()
+        );
+
+        let hash = MessageHash::from_message(
+            "GET&https%3A%2F%2Fapi3.loopring.io%2Fapi%2Fv3%2FapiKey&accountId%3D1",
+        );
+        let signed = account.sign(&hash);
+        assert!(SignatureScheme::verify(
+            account.public_key(),
+            signed.sig(),
+            hash.as_bigint()
+        ));
+    }
 }
@@ -1 +1,10 @@
+mod argon2;
+pub mod contract_wallet;
+pub mod dual_auth;
+pub mod import;
+pub mod key_seed;
 pub mod l2_key;
+pub mod passphrase;
+pub mod provider;
+pub mod public_key;
+pub mod validate;
@@ -0,0 +1,273 @@
+/*
+L2 keys from a passphrase, for tooling that needs reproducible keys without a
+wallet (test accounts, bots, recovery drills).
+
+    secret = int_le(Argon2id(passphrase, salt, ad = PASSPHRASE_DOMAIN, 64 bytes)) mod JUBJUB_L
+
+Argon2id makes every guess cost the configured memory and passes, so a leaked
+public key is far more expensive to attack than with a fast hash; it does not
+make a weak passphrase strong. Salt and parameters are needed to derive the key
+again and are kept in a PassphraseRecord, stored next to the account as
+
+    {"version": 1, "kdf": "argon2id", "memoryKib": 65536, "iterations": 3,
+     "parallelism": 4, "salt": "<32 hex>"}
+
+The record is not secret, but it is input: records asking for more than
+MAX_MEMORY_KIB or MAX_ITERATIONS are rejected before anything is allocated. The passphrase is hashed as the UTF-8 bytes it is
+given in; tools that accept typed passphrases should normalize them (e.g. NFC)
+first. Keys derived this way have nothing to do with the keySeed keys of
+l2_key, and the Loopring wallets cannot recreate them.
+*/
+
+use super::argon2::{argon2id, Argon2Input};
+use super::l2_key::Account;
+use crate::poseidon::jubjub::JUBJUB_L;
+use crate::util::{errors::PassphraseError, hex};
+use num_bigint::{BigInt, Sign};
+use serde_json::{json, Value};
+
+pub const PASSPHRASE_DOMAIN: &str = "loopring_sign/passphrase/v1";
+pub const RECORD_VERSION: u64 = 1;
+pub const MIN_SALT_LEN: usize = 16;
+// Records come from files, so their parameters are capped: a record asking for
+// more would make derivation exhaust memory or run for hours
+pub const MAX_MEMORY_KIB: u32 = 4 * 1024 * 1024;
+pub const MAX_ITERATIONS: u32 = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PassphraseParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+// The second recommended option of RFC 9106: 64 MiB, 3 passes, 4 lanes
+impl Default for PassphraseParams {
+    fn default() -> Self {
+        PassphraseParams {
+            memory_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 4,
+        }
+    }
+}
+
+impl PassphraseParams {
+    fn validate(&self) -> Result<(), PassphraseError> {
+        if self.parallelism == 0 || self.parallelism >= 1 << 24 {
+            return Err(PassphraseError::InvalidParams(
+                "parallelism must be between 1 and 2^24 - 1",
+            ));
+        }
+        if self.iterations == 0 {
+            return Err(PassphraseError::InvalidParams(
+                "at least one pass is needed",
+            ));
+        }
+        if self.iterations > MAX_ITERATIONS {
+            return Err(PassphraseError::InvalidParams(
+                "at most 256 passes are allowed",
+            ));
+        }
+        if (self.memory_kib as u64) < 8 * self.parallelism as u64 {
+            return Err(PassphraseError::InvalidParams(
+                "memory must be at least 8 KiB per lane",
+            ));
+        }
+        if self.memory_kib > MAX_MEMORY_KIB {
+            return Err(PassphraseError::InvalidParams(
+                "memory must be at most 4 GiB",
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PassphraseRecord {
+    salt: Vec<u8>,
+    params: PassphraseParams,
+}
+
+impl PassphraseRecord {
+    pub fn new(salt: Vec<u8>, params: PassphraseParams) -> Result<Self, PassphraseError> {
+        if salt.len() < MIN_SALT_LEN {
+            return Err(PassphraseError::SaltTooShort(salt.len()));
+        }
+        params.validate()?;
+        Ok(PassphraseRecord { salt, params })
+    }
+
+    // A record with a fresh random salt, for a new key
+    #[cfg(feature = "rng")]
+    pub fn generate(params: PassphraseParams) -> Result<Self, PassphraseError> {
+        let mut salt = vec![0u8; MIN_SALT_LEN];
+        getrandom::getrandom(&mut salt).map_err(|e| PassphraseError::Rng(e.to_string()))?;
+        Self::new(salt, params)
+    }
+
+    pub fn salt(&self) -> &[u8] {
+        &self.salt
+    }
+
+    pub fn params(&self) -> &PassphraseParams {
+        &self.params
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "version": RECORD_VERSION,
+            "kdf": "argon2id",
+            "memoryKib": self.params.memory_kib,
+            "iterations": self.params.iterations,
+            "parallelism": self.params.parallelism,
+            "salt": ::hex::encode(&self.salt),
+        })
+    }
+
+    pub fn from_json(record: &Value) -> Result<Self, PassphraseError> {
+        let invalid = |reason: &str| PassphraseError::InvalidRecord(reason.to_string());
+        if record["version"].as_u64() != Some(RECORD_VERSION) {
+            return Err(invalid("unsupported version"));
+        }
+        if record["kdf"].as_str() != Some("argon2id") {
+            return Err(invalid("unsupported kdf"));
+        }
+        let number = |name: &str| {
+            record[name]
+                .as_u64()
+                .and_then(|n| u32::try_from(n).ok())
+                .ok_or_else(|| invalid(name))
+        };
+        let params = PassphraseParams {
+            memory_kib: number("memoryKib")?,
+            iterations: number("iterations")?,
+            parallelism: number("parallelism")?,
+        };
+        let salt = record["salt"]
+            .as_str()
+            .and_then(|s| hex::decode_bytes(s).ok())
+            .ok_or_else(|| invalid("salt"))?;
+        Self::new(salt, params)
+    }
+}
+
+// Takes as long and as much memory as the record's parameters say
+pub fn derive_l2_keys_from_passphrase(
+    passphrase: &str,
+    record: &PassphraseRecord,
+) -> Result<Account, PassphraseError> {
+    if passphrase.is_empty() {
+        return Err(PassphraseError::EmptyPassphrase);
+    }
+    let tag = argon2id(
+        &Argon2Input {
+            password: passphrase.as_bytes(),
+            salt: &record.salt,
+            secret: &[],
+            associated_data: PASSPHRASE_DOMAIN.as_bytes(),
+            memory_kib: record.params.memory_kib,
+            iterations: record.params.iterations,
+            parallelism: record.params.parallelism,
+        },
+        64,
+    );
+    // 512 bits modulo L, so the bias (and the chance of a zero key) is negligible
+    let secret = BigInt::from_bytes_le(Sign::Plus, &tag) % &*JUBJUB_L;
+    Ok(Account::from_private_scalar(secret))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Small enough for debug builds; real keys should use the defaults
+    const PARAMS: PassphraseParams = PassphraseParams {
+        memory_kib: 64,
+        iterations: 2,
+        parallelism: 2,
+    };
+
+    #[test]
+    fn keys_are_reproducible_from_the_record() {
+        let record = PassphraseRecord::new(vec![7; 16], PARAMS).unwrap();
+        let stored = record.to_json();
+        assert_eq!(stored["salt"], "07070707070707070707070707070707");
+        let restored = PassphraseRecord::from_json(&stored).unwrap();
+        assert_eq!(restored, record);
+
+        let account = derive_l2_keys_from_passphrase("correct horse battery", &record).unwrap();
+        let again = derive_l2_keys_from_passphrase("correct horse battery", &restored).unwrap();
+        assert_eq!(account.private_key, again.private_key);
+        assert_eq!(account.public_key_x, again.public_key_x);
+
+        let other_salt = PassphraseRecord::new(vec![8; 16], PARAMS).unwrap();
+        let other_params = PassphraseRecord::new(
+            vec![7; 16],
+            PassphraseParams {
+                iterations: 3,
+                ..PARAMS
+            },
+        )
+        .unwrap();
+        for (passphrase, record) in [
+            ("correct horse battery.", &record),
+            ("correct horse battery", &other_salt),
+            ("correct horse battery", &other_params),
+        ] {
+            let other = derive_l2_keys_from_passphrase(passphrase, record).unwrap();
+            assert_ne!(other.private_key, account.private_key);
+        }
+        assert!(*account.private_scalar() < *JUBJUB_L);
+    }
+
+    #[test]
+    fn rejects_weak_inputs_and_bad_records() {
+        assert_eq!(
+            PassphraseRecord::new(vec![7; 15], PARAMS),
+            Err(PassphraseError::SaltTooShort(15))
+        );
+        let too_little_memory = PassphraseParams {
+            memory_kib: 15,
+            ..PARAMS
+        };
+        let too_much_memory = PassphraseParams {
+            memory_kib: MAX_MEMORY_KIB + 1,
+            ..PARAMS
+        };
+        let too_many_passes = PassphraseParams {
+            iterations: MAX_ITERATIONS + 1,
+            ..PARAMS
+        };
+        for params in [too_little_memory, too_much_memory, too_many_passes] {
+            assert!(matches!(
+                PassphraseRecord::new(vec![7; 16], params),
+                Err(PassphraseError::InvalidParams(_))
+            ));
+        }
+        let record = PassphraseRecord::new(vec![7; 16], PARAMS).unwrap();
+        assert!(matches!(
+            derive_l2_keys_from_passphrase("", &record),
+            Err(PassphraseError::EmptyPassphrase)
+        ));
+
+        let mut stored = record.to_json();
+        stored["kdf"] = json!("scrypt");
+        assert_eq!(
+            PassphraseRecord::from_json(&stored),
+            Err(PassphraseError::InvalidRecord(
+                "unsupported kdf".to_string()
+            ))
+        );
+        let mut stored = record.to_json();
+        stored["memoryKib"] = json!(u32::MAX);
+        assert!(matches!(
+            PassphraseRecord::from_json(&stored),
+            Err(PassphraseError::InvalidParams(_))
+        ));
+        let mut stored = record.to_json();
+        stored["salt"] = json!("zz");
+        assert!(PassphraseRecord::from_json(&stored).is_err());
+        assert_eq!(PassphraseParams::default().memory_kib, 65536);
+    }
+}
@@ -0,0 +1,121 @@
+// Sources of L2 keys.
+//
+// A real account key is derived from the owner's ECDSA signature of the keySeed
+// message (see key_seed), so producing one needs the wallet. Code that takes a
+// KeyProvider instead can run with either:
+//
+//  * WalletKeyProvider: the real flow. A callback (the wallet, a signing
+//    service) signs the keySeed and the key is derived from that signature by
+//    the WalletKind's rule.
+//  * DeterministicKeyProvider: test keys derived from a label, the account id
+//    and the keySeed. They are stable across runs and machines, so fixtures and
+//    CI can pin them, and a new keySeed (a key nonce bump) gives a new key, as in
+//    production. Anyone who knows the label can derive them: never fund them.
+
+use super::key_seed::WalletKind;
+use super::l2_key::{generate_l2_keys, Account};
+use sha2::{Digest, Sha512};
+
+pub trait KeyProvider {
+    // The L2 keys of `account_id` for the keySeed message `key_seed`
+    fn l2_keys(&self, account_id: u32, key_seed: &str) -> Result<Account, String>;
+}
+
+pub struct WalletKeyProvider<F: Fn(&str) -> Result<String, String>> {
+    wallet: WalletKind,
+    sign: F,
+}
+
+impl<F: Fn(&str) -> Result<String, String>> WalletKeyProvider<F> {
+    // `sign` returns the 0x-prefixed signature of the message it is given
+    pub fn new(wallet: WalletKind, sign: F) -> Self {
+        WalletKeyProvider { wallet, sign }
+    }
+}
+
+impl<F: Fn(&str) -> Result<String, String>> KeyProvider for WalletKeyProvider<F> {
+    fn l2_keys(&self, _account_id: u32, key_seed: &str) -> Result<Account, String> {
+        self.wallet.derive_l2_keys(&(self.sign)(key_seed)?)
+    }
+}
+
+const DETERMINISTIC_DOMAIN: &[u8] = b"loopring_sign deterministic test key";
+
+pub struct DeterministicKeyProvider {
+    label: String,
+}
+
+impl DeterministicKeyProvider {
+    pub fn new(label: &str) -> Self {
+        DeterministicKeyProvider {
+            label: label.to_string(),
+        }
+    }
+}
+
+impl KeyProvider for DeterministicKeyProvider {
+    // The stand-in signature is sha512(domain || len || label || account id ||
+    // keySeed), from which the key is derived like from a real one
+    fn l2_keys(&self, account_id: u32, key_seed: &str) -> Result<Account, String> {
+        let mut hasher = Sha512::new();
+        hasher.update(DETERMINISTIC_DOMAIN);
+        hasher.update((self.label.len() as u64).to_be_bytes());
+        hasher.update(self.label.as_bytes());
+        hasher.update(account_id.to_be_bytes());
+        hasher.update(key_seed.as_bytes());
+        generate_l2_keys(format!("0x{}", hex::encode(hasher.finalize())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen::key_seed::default_key_seed;
+
+    const EXCHANGE: &str = "0x0BABA1Ad5bE3a5C0a66E7ac838a129Bf948f1eA4";
+
+    #[test]
+    fn deterministic_keys_are_stable() {
+        let provider = DeterministicKeyProvider::new("ci");
+        let key_seed = default_key_seed(EXCHANGE, 1);
+        let account = provider.l2_keys(11087, &key_seed).unwrap();
+        // Pinned: fixtures rely on these keys not changing
+        assert_eq!(
+            account.private_key,
+            "0x059ee4788e2a697e35e2f019227780f6335ee1d2f576e44c42374075c5a7f917"
+        );
+        assert_eq!(
+            provider.l2_keys(11087, &key_seed).unwrap().private_key,
+            account.private_key
+        );
+        for other in [
+            provider.l2_keys(11088, &key_seed).unwrap(),
+            provider
+                .l2_keys(11087, &default_key_seed(EXCHANGE, 2))
+                .unwrap(),
+            DeterministicKeyProvider::new("ci2")
+                .l2_keys(11087, &key_seed)
+                .unwrap(),
+        ] {
+            assert_ne!(other.private_key, account.private_key);
+        }
+    }
+
+    #[test]
+    fn wallet_provider_signs_the_key_seed() {
+        let signature = "0xf8214f068c55d1bebf1fbefced91eba5f4bbe14315e1ad71f61f21e094f5853a12eba239aeaa77538ae458eebe49ca2b732d211bf0943095b3502a3b0e6a08cd1c";
+        let key_seed = default_key_seed(EXCHANGE, 1);
+        let provider = WalletKeyProvider::new(WalletKind::Eoa, |message: &str| {
+            assert_eq!(message, key_seed);
+            Ok(signature.to_string())
+        });
+        assert_eq!(
+            provider.l2_keys(11087, &key_seed).unwrap().private_key,
+            "0x001fa186947c8c644cd11078f67e0bb21656432f55c4df76997b6acab2abda7f"
+        );
+
+        let rejected =
+            WalletKeyProvider::new(WalletKind::Eoa, |_: &str| Err("user rejected".to_string()));
+        assert!(rejected.l2_keys(11087, &key_seed).is_err());
+    }
+}
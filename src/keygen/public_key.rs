@@ -0,0 +1,207 @@
+// Typed L2 public keys from API responses.
+//
+// GET /api/v3/account reports the key as "publicKey": {"x": "0x...", "y": "0x..."},
+// and other sources (witnesses, circuit inputs) write the coordinates in decimal.
+// L2PublicKey parses either form once and holds a point that passed the checks
+// of validate_account_keys (on the curve, in the subgroup, not the neutral
+// point), so code that verifies signatures or compares keys gets a Point instead
+// of two strings.
+//
+// Unlike validate_account_keys, which diagnoses keys before they are registered,
+// parsing does not insist on 64 padded digits: the API pads them, but keys that
+// went through a bignum library often lost their leading zeros. A coordinate
+// needs its 0x prefix to be read as hex; without it, it is read as decimal.
+
+use crate::poseidon::eddsa::{KeyPair, Signature, SignatureScheme};
+use crate::poseidon::field::{FQ, SNARK_SCALAR_FIELD};
+use crate::poseidon::jubjub::Point;
+use crate::util::{errors::KeyValidationError, hex};
+use num_bigint::BigInt;
+use serde_json::{json, Value};
+use std::str::FromStr;
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct L2PublicKey(Point);
+
+fn parse_coordinate(value: &str, name: &'static str) -> Result<BigInt, KeyValidationError> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(KeyValidationError::Missing(name));
+    }
+    let n = if value.starts_with("0x") || value.starts_with("0X") {
+        hex::decode_bigint(value).map_err(|_| KeyValidationError::InvalidHex(name))?
+    } else if value.bytes().all(|b| b.is_ascii_digit()) {
+        BigInt::from_str(value).map_err(|_| KeyValidationError::InvalidCoordinate(name))?
+    } else {
+        return Err(KeyValidationError::InvalidCoordinate(name));
+    };
+    if n >= *SNARK_SCALAR_FIELD {
+        return Err(KeyValidationError::OutOfRange(name));
+    }
+    Ok(n)
+}
+
+fn coordinate_field(
+    object: &Value,
+    field: &str,
+    name: &'static str,
+) -> Result<BigInt, KeyValidationError> {
+    match object.get(field) {
+        None | Some(Value::Null) => Err(KeyValidationError::Missing(name)),
+        Some(Value::String(value)) => parse_coordinate(value, name),
+        Some(_) => Err(KeyValidationError::InvalidCoordinate(name)),
+    }
+}
+
+impl L2PublicKey {
+    pub fn new(point: Point) -> Result<Self, KeyValidationError> {
+        if !point.is_on_curve() {
+            return Err(KeyValidationError::NotOnCurve);
+        }
+        if !point.is_in_subgroup() {
+            return Err(KeyValidationError::NotInSubgroup);
+        }
+        if point == Point::infinity() {
+            return Err(KeyValidationError::Identity);
+        }
+        Ok(L2PublicKey(point))
+    }
+
+    pub fn from_coordinates(x: &str, y: &str) -> Result<Self, KeyValidationError> {
+        let x = parse_coordinate(x, "public key x")?;
+        let y = parse_coordinate(y, "public key y")?;
+        Self::new(Point::new(FQ::new(x), FQ::new(y)))
+    }
+
+    pub fn point(&self) -> &Point {
+        &self.0
+    }
+
+    // Whether k·B is this key
+    pub fn is_key_of(&self, private_key_scalar: &BigInt) -> bool {
+        KeyPair::new(private_key_scalar.clone()).public_key() == &self.0
+    }
+
+    pub fn verify(&self, sig: &Signature, hash: &BigInt) -> bool {
+        SignatureScheme::verify(&self.0, sig, hash)
+    }
+
+    // {"x": "0x<64 hex>", "y": "0x<64 hex>"}, as the API reports it
+    pub fn to_json(&self) -> Value {
+        json!({"x": self.0.x().to_hex(), "y": self.0.y().to_hex()})
+    }
+}
+
+impl TryFrom<Point> for L2PublicKey {
+    type Error = KeyValidationError;
+
+    fn try_from(point: Point) -> Result<Self, Self::Error> {
+        Self::new(point)
+    }
+}
+
+// Either the {"x", "y"} object or an account object with it under "publicKey"
+impl TryFrom<&Value> for L2PublicKey {
+    type Error = KeyValidationError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        let object = value.get("publicKey").unwrap_or(value);
+        let x = coordinate_field(object, "x", "public key x")?;
+        let y = coordinate_field(object, "y", "public key y")?;
+        Self::new(Point::new(FQ::new(x), FQ::new(y)))
+    }
+}
+
+// Fails only for a key that is zero modulo JUBJUB_L
+impl TryFrom<&KeyPair> for L2PublicKey {
+    type Error = KeyValidationError;
+
+    fn try_from(keypair: &KeyPair) -> Result<Self, Self::Error> {
+        Self::new(keypair.public_key().clone())
+    }
+}
+
+impl From<L2PublicKey> for Point {
+    fn from(key: L2PublicKey) -> Self {
+        key.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poseidon::eddsa::MessageHash;
+
+    const L2_KEY: &str = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+    const X: &str = "0x1526f1c9babcdda49b20df32653f0c3ae07eae4c5b9b456e7e2c8fd7d79ce3b0";
+    const Y: &str = "0x13d161bf5df6053b499a8cc1be2aece9b2b6768d88c05687e1edc40e52bf44b9";
+
+    #[test]
+    fn parses_account_responses() {
+        let account = json!({
+            "accountId": 12345, "owner": "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "frozen": false, "publicKey": {"x": X, "y": Y}, "tags": "", "nonce": 1,
+            "keyNonce": 1, "keySeed": "",
+        });
+        let key = L2PublicKey::try_from(&account).unwrap();
+        assert!(key == L2PublicKey::try_from(&account["publicKey"]).unwrap());
+        assert_eq!(key.to_json(), account["publicKey"]);
+
+        let private_key = hex::decode_secret(L2_KEY).unwrap();
+        assert!(key.is_key_of(&private_key));
+        assert!(!key.is_key_of(&BigInt::from(1)));
+        let keypair = KeyPair::new(private_key);
+        assert!(L2PublicKey::try_from(&keypair).unwrap() == key);
+
+        let hash = MessageHash::from_message("message");
+        let signed = SignatureScheme::sign_with_keypair(&keypair, &hash);
+        assert!(key.verify(signed.sig(), hash.as_bigint()));
+
+        // Decimal, and hex that lost its leading zeros
+        let (x, y) = (key.point().x().to_bigint(), key.point().y().to_bigint());
+        let decimal = L2PublicKey::from_coordinates(&x.to_string(), &y.to_string()).unwrap();
+        assert!(decimal == key);
+        assert!(matches!(
+            L2PublicKey::from_coordinates("0x0", "0x1"),
+            Err(KeyValidationError::Identity)
+        ));
+        assert!(matches!(
+            L2PublicKey::try_from(&KeyPair::new(BigInt::from(0))),
+            Err(KeyValidationError::Identity)
+        ));
+    }
+
+    #[test]
+    fn rejects_missing_and_invalid_keys() {
+        let parse = |value: Value| L2PublicKey::try_from(&value).err();
+        // No key registered yet
+        assert_eq!(
+            parse(json!({"publicKey": {"x": "", "y": ""}})),
+            Some(KeyValidationError::Missing("public key x"))
+        );
+        assert_eq!(
+            parse(json!({"x": X})),
+            Some(KeyValidationError::Missing("public key y"))
+        );
+        assert_eq!(
+            parse(json!({"x": &X[2..], "y": Y})),
+            Some(KeyValidationError::InvalidCoordinate("public key x"))
+        );
+        assert_eq!(
+            parse(json!({"x": X, "y": 5})),
+            Some(KeyValidationError::InvalidCoordinate("public key y"))
+        );
+        assert_eq!(
+            parse(json!({"x": "0xzz", "y": Y})),
+            Some(KeyValidationError::InvalidHex("public key x"))
+        );
+        assert_eq!(
+            parse(json!({"x": SNARK_SCALAR_FIELD.to_string(), "y": Y})),
+            Some(KeyValidationError::OutOfRange("public key x"))
+        );
+        assert_eq!(
+            parse(json!({"x": Y, "y": X})),
+            Some(KeyValidationError::NotOnCurve)
+        );
+    }
+}
@@ -0,0 +1,134 @@
+// Checks of an account's EdDSA key, in the form GET /api/v3/account reports it.
+//
+// When the relayer rejects a key ("invalid public key", a signature that never
+// verifies), the cause is usually one of a few things: a coordinate that lost
+// its leading zeros or prefix on the way through a spreadsheet or a bignum
+// library, x and y swapped or copied from different keys, or a private key that
+// is not the one registered. validate_account_keys runs the checks in that order
+// and returns the first that fails:
+//
+//  1. each coordinate is hex, 0x-prefixed, exactly 64 digits and below
+//     SNARK_SCALAR_FIELD
+//  2. the point is on Baby Jubjub, in the prime-order subgroup and not the
+//     neutral point
+//  3. with a private key: it is not zero mod JUBJUB_L and k·B is the point.
+//     On a mismatch, the error carries the public key k·B for comparison.
+
+use crate::poseidon::field::{FQ, SNARK_SCALAR_FIELD};
+use crate::poseidon::jubjub::{Point, JUBJUB_L};
+use crate::util::errors::KeyValidationError;
+use crate::util::hex;
+use num_bigint::BigInt;
+use num_traits::{Euclid, Zero};
+
+fn parse_coordinate(value: &str, name: &'static str) -> Result<BigInt, KeyValidationError> {
+    let n = hex::decode_bigint(value).map_err(|_| KeyValidationError::InvalidHex(name))?;
+    if n >= *SNARK_SCALAR_FIELD {
+        return Err(KeyValidationError::OutOfRange(name));
+    }
+    let trimmed = value.trim();
+    match trimmed.strip_prefix("0x") {
+        Some(digits) if digits.len() == 64 => Ok(n),
+        _ => Err(KeyValidationError::NotPadded(name)),
+    }
+}
+
+// The public key as a Point if every check passes
+pub fn validate_account_keys(
+    public_key_x: &str,
+    public_key_y: &str,
+    private_key: Option<&str>,
+) -> Result<Point, KeyValidationError> {
+    let x = parse_coordinate(public_key_x, "public key x")?;
+    let y = parse_coordinate(public_key_y, "public key y")?;
+    let public_key = Point::new(FQ::new(x), FQ::new(y));
+    if !public_key.is_on_curve() {
+        return Err(KeyValidationError::NotOnCurve);
+    }
+    if !public_key.is_in_subgroup() {
+        return Err(KeyValidationError::NotInSubgroup);
+    }
+    if public_key == Point::infinity() {
+        return Err(KeyValidationError::Identity);
+    }
+
+    if let Some(private_key) = private_key {
+        let k = hex::decode_secret(private_key)
+            .map_err(|_| KeyValidationError::InvalidHex("private key"))?;
+        if k.rem_euclid(&JUBJUB_L).is_zero() {
+            return Err(KeyValidationError::InvalidPrivateKey);
+        }
+        let expected = Point::generate().mul_scalar(&k);
+        if expected != public_key {
+            return Err(KeyValidationError::Mismatch {
+                public_key_x: expected.x().to_hex(),
+                public_key_y: expected.y().to_hex(),
+            });
+        }
+    }
+    Ok(public_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const L2_KEY: &str = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+    const X: &str = "0x1526f1c9babcdda49b20df32653f0c3ae07eae4c5b9b456e7e2c8fd7d79ce3b0";
+    const Y: &str = "0x13d161bf5df6053b499a8cc1be2aece9b2b6768d88c05687e1edc40e52bf44b9";
+
+    #[test]
+    fn accepts_matching_keys() {
+        let public_key = validate_account_keys(X, Y, Some(L2_KEY)).unwrap();
+        assert!(public_key.x().to_hex() == X);
+        assert!(validate_account_keys(X, Y, None).is_ok());
+
+        let other_key = "0x01";
+        let base = Point::generate();
+        assert_eq!(
+            validate_account_keys(X, Y, Some(other_key)).err(),
+            Some(KeyValidationError::Mismatch {
+                public_key_x: base.x().to_hex(),
+                public_key_y: base.y().to_hex(),
+            })
+        );
+        assert_eq!(
+            validate_account_keys(X, Y, Some("0x00")).err(),
+            Some(KeyValidationError::InvalidPrivateKey)
+        );
+    }
+
+    #[test]
+    fn reports_the_first_failing_check() {
+        let check = |x: &str, y: &str| validate_account_keys(x, y, None).err();
+        assert_eq!(
+            check("0xzz", Y),
+            Some(KeyValidationError::InvalidHex("public key x"))
+        );
+        // Lost prefix, lost leading zeros
+        assert_eq!(
+            check(&X[2..], Y),
+            Some(KeyValidationError::NotPadded("public key x"))
+        );
+        let unpadded = "0x29d178cdd6a40cd900c41565b6057a1d12c00a8c41ad367e2fe0100aab00fbe";
+        assert_eq!(
+            check(X, unpadded),
+            Some(KeyValidationError::NotPadded("public key y"))
+        );
+        assert_eq!(
+            check(X, &format!("0x{}", "f".repeat(64))),
+            Some(KeyValidationError::OutOfRange("public key y"))
+        );
+        // Swapped coordinates
+        assert_eq!(check(Y, X), Some(KeyValidationError::NotOnCurve));
+
+        let zero = format!("0x{:0>64}", 0);
+        let one = format!("0x{:0>64}", 1);
+        let minus_one = FQ::new(BigInt::from(-1)).to_hex();
+        assert_eq!(
+            check(&zero, &minus_one),
+            Some(KeyValidationError::NotInSubgroup)
+        );
+        assert_eq!(check(&zero, &one), Some(KeyValidationError::Identity));
+    }
+}
@@ -1,5 +1,31 @@
 #[macro_use]
 extern crate lazy_static;
+// Lets the code generated by loopring_sign_derive name this crate from inside it
+extern crate self as loopring_sign;
+#[cfg(any(feature = "borsh", feature = "bincode"))]
+mod binary;
+#[cfg(feature = "client")]
+pub mod client;
+pub mod compat;
+pub mod constants;
+pub mod diagnostics;
+pub mod hazmat;
 pub mod keygen;
+pub mod payload;
 pub mod poseidon;
-mod util;
+mod self_check;
+#[cfg(feature = "service")]
+pub mod service;
+pub mod signer;
+pub mod util;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+// The high-level API: request and payload signing, keys and their validation.
+// Raw primitives without its checks are in `hazmat`.
+pub use keygen::l2_key::{generate_l2_keys, Account};
+pub use keygen::public_key::L2PublicKey;
+pub use payload::{hashable::LoopringHash, order::Order, transfer::Transfer};
+pub use poseidon::eddsa::{sign_api_request, sign_api_request_body, sign_url, verify_eddsa_hex};
+pub use self_check::self_check;
+pub use signer::{LocalSigner, Signer};
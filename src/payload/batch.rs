@@ -0,0 +1,155 @@
+/*
+Signing a batch of payloads of different types as a whole.
+
+Onboarding flows sign several payloads for one submission, e.g. the account's
+first key update followed by the transfers that fund it. A half-signed batch is
+useless there, so SigningBatch either signs everything or returns nothing:
+
+    let mut batch = SigningBatch::new();
+    batch.push(&first_transfer).push(&second_transfer).push_custom(&layout);
+    let signed = batch.sign(&signer)?;    // one SignedMessage per item, in order
+
+Every preimage is derived and hashed before the first signature is made, so an
+invalid payload fails the batch without the signer being used. Payloads are
+either ones with a protocol layout (VersionedPayload: orders, transfers, ...),
+hashed for the batch's ProtocolVersion, or LoopringHash implementations (derived
+types, LayoutBuilder) for layouts this crate does not define.
+*/
+
+use super::hashable::LoopringHash;
+use super::version::{ProtocolVersion, VersionedPayload};
+use crate::poseidon::eddsa::{MessageHash, SignedMessage};
+use crate::signer::Signer;
+use crate::util::errors::{BatchSignError, PayloadError};
+use num_bigint::BigInt;
+
+enum BatchItem<'a> {
+    Versioned(&'a dyn VersionedPayload),
+    Custom(&'a dyn LoopringHash),
+}
+
+#[derive(Default)]
+pub struct SigningBatch<'a> {
+    version: ProtocolVersion,
+    items: Vec<BatchItem<'a>>,
+}
+
+impl<'a> SigningBatch<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_version(version: ProtocolVersion) -> Self {
+        SigningBatch {
+            version,
+            items: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, payload: &'a dyn VersionedPayload) -> &mut Self {
+        self.items.push(BatchItem::Versioned(payload));
+        self
+    }
+
+    pub fn push_custom(&mut self, payload: &'a dyn LoopringHash) -> &mut Self {
+        self.items.push(BatchItem::Custom(payload));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    // The payload hashes, in order; fails on the first invalid payload
+    pub fn hashes(&self) -> Result<Vec<BigInt>, BatchSignError> {
+        self.items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                let hash = match item {
+                    BatchItem::Versioned(payload) => payload.hash_for(self.version),
+                    BatchItem::Custom(payload) => payload.hash(),
+                };
+                hash.map_err(|error| BatchSignError::Payload { index, error })
+            })
+            .collect()
+    }
+
+    pub fn sign<S: Signer>(&self, signer: &S) -> Result<Vec<SignedMessage>, BatchSignError> {
+        if self.items.is_empty() {
+            return Err(BatchSignError::Payload {
+                index: 0,
+                error: PayloadError::MissingField("payload"),
+            });
+        }
+        self.hashes()?
+            .into_iter()
+            .enumerate()
+            .map(|(index, hash)| {
+                signer
+                    .sign(&MessageHash::new(hash))
+                    .map_err(|error| BatchSignError::Signer { index, error })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload::layout::LayoutBuilder;
+    use crate::payload::transfer::tests::transfer;
+    use crate::poseidon::eddsa::SignatureScheme;
+    use crate::signer::LocalSigner;
+
+    const L2_KEY: &str = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+
+    #[test]
+    fn signs_every_item_in_order() {
+        let signer = LocalSigner::from_hex(L2_KEY).unwrap();
+        let mut layout = LayoutBuilder::new();
+        layout
+            .push("accountId", 11087u32, 32)
+            .unwrap()
+            .push("nonce", 1u32, 32)
+            .unwrap();
+        let first = transfer();
+        let mut second = transfer();
+        second.storage_id += 2;
+
+        let mut batch = SigningBatch::new();
+        batch.push_custom(&layout).push(&first).push(&second);
+        let signed = batch.sign(&signer).unwrap();
+
+        assert_eq!(signed.len(), 3);
+        assert_eq!(*signed[0].msg(), layout.hash().unwrap());
+        assert_eq!(signed[1].to_hex(), first.sign(L2_KEY).unwrap());
+        assert_eq!(signed[2].to_hex(), second.sign(L2_KEY).unwrap());
+        assert!(signed
+            .iter()
+            .all(|s| SignatureScheme::verify(s.public_key(), s.sig(), s.msg())));
+    }
+
+    #[test]
+    fn fails_as_a_whole() {
+        let signer = LocalSigner::from_hex(L2_KEY).unwrap();
+        let valid = transfer();
+        let mut invalid = transfer();
+        invalid.payee_addr = "0x1234".to_string();
+
+        let mut batch = SigningBatch::new();
+        batch.push(&valid).push(&invalid).push(&valid);
+        assert!(matches!(
+            batch.sign(&signer),
+            Err(BatchSignError::Payload { index: 1, .. })
+        ));
+        assert!(matches!(
+            SigningBatch::new().sign(&signer),
+            Err(BatchSignError::Payload { index: 0, .. })
+        ));
+    }
+}
@@ -0,0 +1,175 @@
+/*
+Signed payload bundles, for moving payloads between an air-gapped signer and
+the machine that submits them (or an auditor).
+
+A bundle carries everything needed to check it without the private key:
+
+    {
+      "schema": 1,
+      "payload": {"type": "transfer", "exchange": "0x...", ...},
+      "hash": "0x<64 hex>",
+      "signature": "0x<192 hex>",
+      "publicKey": {"x": "0x<64 hex>", "y": "0x<64 hex>"}
+    }
+
+The payload is an object in the format of payload files (see payload::file).
+Importing recomputes its Poseidon hash, compares it with "hash" and verifies the
+signature under "publicKey"; whether that key belongs to the expected account
+is for the caller to check.
+*/
+
+use super::{
+    file::{order_from_value, transfer_from_value},
+    hash_to_hex,
+};
+use crate::poseidon::eddsa::{verify_eddsa_hex, MessageHash};
+use crate::signer::Signer;
+use crate::util::errors::{BundleError, PayloadError};
+use num_bigint::BigInt;
+use serde_json::{json, Value};
+
+pub const BUNDLE_SCHEMA_VERSION: u32 = 1;
+
+// A bundle whose hash and signature were checked on import
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifiedBundle {
+    pub payload: Value,
+    pub hash: BigInt,
+    pub signature: String,
+    pub public_key_x: String,
+    pub public_key_y: String,
+}
+
+pub(crate) fn payload_hash(payload: &Value) -> Result<BigInt, PayloadError> {
+    match payload.get("type").and_then(Value::as_str) {
+        Some("transfer") => transfer_from_value(payload)?.hash(),
+        Some("order") => order_from_value(payload)?.hash(),
+        Some(other) => Err(PayloadError::InvalidField("type", other.to_string())),
+        None => Err(PayloadError::MissingField("type")),
+    }
+}
+
+fn string_field<'a>(bundle: &'a Value, path: &[&str]) -> Result<&'a str, BundleError> {
+    path.iter()
+        .try_fold(bundle, |value, key| value.get(key))
+        .and_then(Value::as_str)
+        .ok_or_else(|| BundleError::Invalid(format!("missing string {}", path.join("."))))
+}
+
+pub fn export_bundle<S: Signer>(signer: &S, payload: &Value) -> Result<String, BundleError> {
+    let hash = payload_hash(payload)?;
+    let signed = signer.sign(&MessageHash::new(hash.clone()))?;
+    let public_key = signer.public_key();
+    let bundle = json!({
+        "schema": BUNDLE_SCHEMA_VERSION,
+        "payload": payload,
+        "hash": hash_to_hex(&hash),
+        "signature": signed.to_hex(),
+        "publicKey": {
+            "x": public_key.x().to_hex(),
+            "y": public_key.y().to_hex(),
+        },
+    });
+    Ok(bundle.to_string())
+}
+
+pub fn import_and_verify_bundle(content: &str) -> Result<VerifiedBundle, BundleError> {
+    let bundle: Value =
+        serde_json::from_str(content).map_err(|e| BundleError::Invalid(e.to_string()))?;
+    match bundle.get("schema").and_then(Value::as_u64) {
+        Some(version) if version == BUNDLE_SCHEMA_VERSION as u64 => {}
+        Some(version) => return Err(BundleError::UnsupportedSchema(version)),
+        None => return Err(BundleError::Invalid("missing schema".to_string())),
+    }
+    let payload = bundle
+        .get("payload")
+        .ok_or_else(|| BundleError::Invalid("missing payload".to_string()))?;
+    let recorded_hash = string_field(&bundle, &["hash"])?;
+    let signature = string_field(&bundle, &["signature"])?;
+    let x = string_field(&bundle, &["publicKey", "x"])?;
+    let y = string_field(&bundle, &["publicKey", "y"])?;
+
+    let hash = payload_hash(payload)?;
+    let actual_hash = hash_to_hex(&hash);
+    if !recorded_hash.eq_ignore_ascii_case(&actual_hash) {
+        return Err(BundleError::HashMismatch {
+            expected: recorded_hash.to_string(),
+            actual: actual_hash,
+        });
+    }
+    if !verify_eddsa_hex(signature, x, y, &hash)? {
+        return Err(BundleError::InvalidSignature);
+    }
+
+    Ok(VerifiedBundle {
+        payload: payload.clone(),
+        hash,
+        signature: signature.to_string(),
+        public_key_x: x.to_string(),
+        public_key_y: y.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload::transfer::tests::transfer;
+    use crate::signer::LocalSigner;
+
+    const L2_KEY: &str = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+
+    fn payload() -> Value {
+        json!({
+            "type": "transfer",
+            "exchange": "0x0BABA1Ad5bE3a5C0a66E7ac838a129Bf948f1eA4",
+            "payerId": 11087,
+            "payerAddr": "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "payeeId": 0,
+            "payeeAddr": "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "token": {"tokenId": 1, "volume": "1000000000000000000"},
+            "maxFee": {"tokenId": 0, "volume": "100000000000000"},
+            "validUntil": 1700000000,
+            "storageId": 5
+        })
+    }
+
+    #[test]
+    fn exported_bundle_verifies() {
+        let signer = LocalSigner::from_hex(L2_KEY).unwrap();
+        let bundle =
+            import_and_verify_bundle(&export_bundle(&signer, &payload()).unwrap()).unwrap();
+        assert_eq!(bundle.payload, payload());
+        assert_eq!(bundle.hash, transfer().hash().unwrap());
+        assert_eq!(bundle.signature, transfer().sign(L2_KEY).unwrap());
+    }
+
+    #[test]
+    fn tampered_bundles_are_rejected() {
+        let signer = LocalSigner::from_hex(L2_KEY).unwrap();
+        let bundle: Value =
+            serde_json::from_str(&export_bundle(&signer, &payload()).unwrap()).unwrap();
+
+        let mut changed_payload = bundle.clone();
+        changed_payload["payload"]["storageId"] = json!(6);
+        assert!(matches!(
+            import_and_verify_bundle(&changed_payload.to_string()),
+            Err(BundleError::HashMismatch { .. })
+        ));
+
+        let other = LocalSigner::from_hex("0x1234").unwrap();
+        let mut other_key = bundle.clone();
+        other_key["publicKey"]["x"] = json!(other.public_key().x().to_hex());
+        other_key["publicKey"]["y"] = json!(other.public_key().y().to_hex());
+        assert!(matches!(
+            import_and_verify_bundle(&other_key.to_string()),
+            Err(BundleError::InvalidSignature)
+        ));
+
+        let mut future = bundle;
+        future["schema"] = json!(2);
+        assert!(matches!(
+            import_and_verify_bundle(&future.to_string()),
+            Err(BundleError::UnsupportedSchema(2))
+        ));
+    }
+}
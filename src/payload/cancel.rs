@@ -0,0 +1,119 @@
+// Batch order cancellation.
+//
+// Cancelling several orders at once is a DELETE request whose list parameter
+// (orderHash or clientOrderId) is a comma-separated string. The request itself is
+// authorized with an EdDSA signature over the API signature base string, so the
+// commas go through the same double-encoding (`%252C`) as any other GET/DELETE
+// parameter.
+
+use super::sign_hash;
+use crate::util::chain::ChainProfile;
+use crate::util::encoding::EncodingProfile;
+use crate::util::errors::PayloadError;
+use crate::util::helpers::{sha256_snark, signature_base_string, HttpMethod, ParamOrdering};
+
+pub enum CancelTarget {
+    OrderHashes(Vec<String>),
+    ClientOrderIds(Vec<String>),
+}
+
+pub struct BatchCancelRequest {
+    pub account_id: u32,
+    pub target: CancelTarget,
+}
+
+impl BatchCancelRequest {
+    pub fn path(&self) -> &'static str {
+        match self.target {
+            CancelTarget::OrderHashes(_) => "/api/v3/orders/byHash",
+            CancelTarget::ClientOrderIds(_) => "/api/v3/orders/byClientOrderId",
+        }
+    }
+
+    pub fn params(&self) -> Result<Vec<(String, String)>, PayloadError> {
+        let (key, values) = match &self.target {
+            CancelTarget::OrderHashes(hashes) => ("orderHash", hashes),
+            CancelTarget::ClientOrderIds(ids) => ("clientOrderId", ids),
+        };
+        if values.is_empty() {
+            return Err(PayloadError::MissingField(key));
+        }
+        Ok(vec![
+            ("accountId".to_string(), self.account_id.to_string()),
+            (key.to_string(), values.join(",")),
+        ])
+    }
+
+    // `base_url` is the API host, e.g. "https://api3.loopring.io"
+    pub fn signature_base(&self, base_url: &str) -> Result<String, PayloadError> {
+        let url = format!("{}{}", base_url.trim_end_matches('/'), self.path());
+        Ok(signature_base_string(
+            HttpMethod::Delete,
+            &url,
+            self.params()?,
+            ParamOrdering::default(),
+            EncodingProfile::default(),
+        ))
+    }
+
+    pub fn sign(&self, base_url: &str, hex_private_key: &str) -> Result<String, PayloadError> {
+        let hash = sha256_snark(&self.signature_base(base_url)?);
+        sign_hash(hash, hex_private_key)
+    }
+
+    // Signs against the profile's API host. Cancels name no exchange, so the host
+    // is the only deployment-specific input.
+    pub fn sign_on(
+        &self,
+        profile: &ChainProfile,
+        hex_private_key: &str,
+    ) -> Result<String, PayloadError> {
+        self.sign(&profile.api_base_url, hex_private_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancel_by_hash_base_string() {
+        let request = BatchCancelRequest {
+            account_id: 11087,
+            target: CancelTarget::OrderHashes(vec!["0x1".to_string(), "0x2".to_string()]),
+        };
+
+        assert_eq!(
+            request.signature_base("https://api3.loopring.io/").unwrap(),
+            "DELETE&https%3A%2F%2Fapi3.loopring.io%2Fapi%2Fv3%2Forders%2FbyHash&accountId%3D11087%26orderHash%3D0x1%252C0x2"
+        );
+    }
+
+    #[test]
+    fn cancel_by_client_order_id_signs() {
+        let l2_key = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+        let request = BatchCancelRequest {
+            account_id: 11087,
+            target: CancelTarget::ClientOrderIds(vec!["a".to_string(), "b".to_string()]),
+        };
+
+        let base = request.signature_base("https://api3.loopring.io").unwrap();
+        assert!(base.ends_with("accountId%3D11087%26clientOrderId%3Da%252Cb"));
+
+        let signature = request.sign("https://api3.loopring.io", l2_key).unwrap();
+        assert_eq!(signature.len(), 2 + 3 * 64);
+        assert_eq!(
+            request.sign_on(&ChainProfile::mainnet(), l2_key).unwrap(),
+            signature
+        );
+    }
+
+    #[test]
+    fn empty_cancel_list_is_rejected() {
+        let request = BatchCancelRequest {
+            account_id: 11087,
+            target: CancelTarget::OrderHashes(vec![]),
+        };
+        assert!(request.params().is_err());
+    }
+}
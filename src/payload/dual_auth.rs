@@ -0,0 +1,113 @@
+// Dual-authorized transfers.
+//
+// The 3.6 transfer preimage carries a dual-auth public key (dualAuthKeyX,
+// dualAuthKeyY). When it is set, the payer's signature alone does not settle the
+// transfer: the holder of the dual-auth private key has to sign the same hash as
+// well, which is how a matching engine or a red-packet service approves the
+// settlement it negotiated.
+//
+// The SpotTrade order preimage of 3.6 has no dual-auth field, so orders are
+// approved by signing their order hash with the dual-auth key out of band;
+// `approve` works on any payload hash.
+//
+// The flow:
+//
+//     let key = DualAuthKey::generate()?;            // by the service
+//     let transfer = DualAuthTransfer::new(transfer, key.public_key().clone());
+//     let payer_signature = transfer.sign(payer_key)?;
+//     let approval = approve(&key, transfer.hash()?)?;  // at settlement
+
+use super::sign_hash;
+use super::transfer::Transfer;
+use super::version::{ProtocolVersion, VersionedPayload};
+use crate::poseidon::{
+    eddsa::{MessageHash, SignatureScheme, SignedMessage},
+    jubjub::Point,
+};
+use crate::signer::Signer;
+use crate::util::errors::PayloadError;
+use num_bigint::BigInt;
+
+pub struct DualAuthTransfer {
+    transfer: Transfer,
+    dual_auth_key: Point,
+}
+
+impl VersionedPayload for DualAuthTransfer {
+    fn poseidon_inputs_for(&self, version: ProtocolVersion) -> Result<Vec<BigInt>, PayloadError> {
+        self.transfer
+            .poseidon_inputs_with_dual_auth(version, Some(&self.dual_auth_key))
+    }
+}
+
+impl DualAuthTransfer {
+    pub fn new(transfer: Transfer, dual_auth_key: Point) -> Self {
+        DualAuthTransfer {
+            transfer,
+            dual_auth_key,
+        }
+    }
+
+    pub fn transfer(&self) -> &Transfer {
+        &self.transfer
+    }
+
+    pub fn dual_auth_key(&self) -> &Point {
+        &self.dual_auth_key
+    }
+
+    pub fn poseidon_inputs(&self) -> Result<Vec<BigInt>, PayloadError> {
+        self.poseidon_inputs_for(ProtocolVersion::CURRENT)
+    }
+
+    pub fn hash(&self) -> Result<BigInt, PayloadError> {
+        self.hash_for(ProtocolVersion::CURRENT)
+    }
+
+    // The payer's signature
+    pub fn sign(&self, hex_private_key: &str) -> Result<String, PayloadError> {
+        sign_hash(self.hash()?, hex_private_key)
+    }
+
+    pub fn verify_approval(&self, approval: &SignedMessage) -> Result<bool, PayloadError> {
+        Ok(approval.public_key() == &self.dual_auth_key
+            && *approval.msg() == self.hash()?
+            && SignatureScheme::verify(&self.dual_auth_key, approval.sig(), approval.msg()))
+    }
+}
+
+// Co-signs a payload hash with the dual-auth key
+pub fn approve<S: Signer>(dual_auth_key: &S, hash: BigInt) -> Result<SignedMessage, PayloadError> {
+    let hash = MessageHash::from_prehashed(hash)
+        .map_err(|e| PayloadError::InvalidField("hash", e.to_string()))?;
+    dual_auth_key
+        .sign(&hash)
+        .map_err(|e| PayloadError::InvalidField("dualAuthKey", e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen::dual_auth::DualAuthKey;
+    use crate::payload::transfer::tests::transfer;
+
+    #[test]
+    fn approval_is_bound_to_the_dual_auth_key() {
+        let key = DualAuthKey::from_entropy(b"matching engine").unwrap();
+        let dual = DualAuthTransfer::new(transfer(), key.public_key().clone());
+
+        let inputs = dual.poseidon_inputs().unwrap();
+        assert_eq!(inputs[8], key.public_key().x().to_bigint());
+        assert_eq!(inputs[9], key.public_key().y().to_bigint());
+        assert_ne!(dual.hash().unwrap(), transfer().hash().unwrap());
+
+        let approval = approve(&key, dual.hash().unwrap()).unwrap();
+        assert!(dual.verify_approval(&approval).unwrap());
+
+        let intruder = DualAuthKey::from_entropy(b"someone else").unwrap();
+        let forged = approve(&intruder, dual.hash().unwrap()).unwrap();
+        assert!(!dual.verify_approval(&forged).unwrap());
+        let unrelated = approve(&key, transfer().hash().unwrap()).unwrap();
+        assert!(!dual.verify_approval(&unrelated).unwrap());
+    }
+}
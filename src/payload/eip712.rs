@@ -0,0 +1,303 @@
+// EIP-712 typed-data hashing and L1 (ECDSA) authorization of payloads.
+// https://eips.ethereum.org/EIPS/eip-712
+//
+// Some flows authorize a transfer with an `ecdsaSignature` made by the account's L1
+// key over the EIP-712 hash of the transfer, instead of (or next to) the EdDSA
+// signature. The domain is the Loopring exchange contract:
+//
+//     EIP712Domain(name = "Loopring Protocol", version = "3.6.0",
+//                  chainId, verifyingContract = exchange)
+//
+// Hashing is always available; producing the signature requires the `k256` feature.
+
+use super::{check_range, transfer::Transfer, AMOUNT_BITS, FEE_TOKEN_ID_BITS};
+use crate::util::{address::parse_address, chain::ChainProfile, errors::PayloadError};
+use num_bigint::BigInt;
+use sha3::{Digest, Keccak256};
+
+pub const DOMAIN_NAME: &str = "Loopring Protocol";
+pub const DOMAIN_VERSION: &str = "3.6.0";
+
+const DOMAIN_TYPE: &str =
+    "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+// The typed transfer declares `uint16 tokenID`, narrower than the L2 token ids
+const TOKEN_ID_BITS: u32 = 16;
+const TRANSFER_TYPE: &str = "Transfer(address from,address to,uint16 tokenID,uint96 amount,uint16 feeTokenID,uint96 maxFee,uint32 validUntil,uint32 storageID)";
+
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    Keccak256::digest(data).into()
+}
+
+// ABI-encodes a `uint<bits>` into a 32-byte big-endian word. Negative values and
+// values wider than the declared type are rejected, as on the Poseidon path.
+fn uint_word(field: &'static str, value: &BigInt, bits: u32) -> Result<[u8; 32], PayloadError> {
+    check_range(field, value.clone(), bits)?;
+    let (_, bytes) = value.to_bytes_be();
+    let mut word = [0u8; 32];
+    word[32 - bytes.len()..].copy_from_slice(&bytes);
+    Ok(word)
+}
+
+fn address_word(address: &str) -> Result<[u8; 32], PayloadError> {
+    let bytes =
+        parse_address(address).map_err(|e| PayloadError::InvalidAddress(address.to_string(), e))?;
+    let mut word = [0u8; 32];
+    word[12..].copy_from_slice(&bytes);
+    Ok(word)
+}
+
+fn hash_struct(type_string: &str, words: &[[u8; 32]]) -> [u8; 32] {
+    let mut data = keccak256(type_string.as_bytes()).to_vec();
+    for word in words {
+        data.extend_from_slice(word);
+    }
+    keccak256(&data)
+}
+
+pub fn domain_separator(
+    name: &str,
+    version: &str,
+    chain_id: u64,
+    verifying_contract: &str,
+) -> Result<[u8; 32], PayloadError> {
+    Ok(hash_struct(
+        DOMAIN_TYPE,
+        &[
+            keccak256(name.as_bytes()),
+            keccak256(version.as_bytes()),
+            uint_word("chainId", &BigInt::from(chain_id), 256)?,
+            address_word(verifying_contract)?,
+        ],
+    ))
+}
+
+pub fn typed_data_hash(domain_separator: &[u8; 32], struct_hash: &[u8; 32]) -> [u8; 32] {
+    let mut data = vec![0x19, 0x01];
+    data.extend_from_slice(domain_separator);
+    data.extend_from_slice(struct_hash);
+    keccak256(&data)
+}
+
+impl Transfer {
+    pub fn eip712_hash(&self, chain_id: u64) -> Result<[u8; 32], PayloadError> {
+        let struct_hash = hash_struct(
+            TRANSFER_TYPE,
+            &[
+                address_word(&self.payer_addr)?,
+                address_word(&self.payee_addr)?,
+                uint_word("tokenID", &BigInt::from(self.token_id), TOKEN_ID_BITS)?,
+                uint_word("amount", &self.amount, AMOUNT_BITS)?,
+                uint_word(
+                    "feeTokenID",
+                    &BigInt::from(self.fee_token_id),
+                    FEE_TOKEN_ID_BITS,
+                )?,
+                uint_word("maxFee", &self.max_fee, AMOUNT_BITS)?,
+                uint_word("validUntil", &BigInt::from(self.valid_until), 32)?,
+                uint_word("storageID", &BigInt::from(self.storage_id), 32)?,
+            ],
+        );
+        let domain = domain_separator(DOMAIN_NAME, DOMAIN_VERSION, chain_id, &self.exchange)?;
+        Ok(typed_data_hash(&domain, &struct_hash))
+    }
+
+    // Same as `eip712_hash`, with the chain id taken from the profile. Fails if the
+    // transfer was built for another exchange.
+    pub fn eip712_hash_on(&self, profile: &ChainProfile) -> Result<[u8; 32], PayloadError> {
+        profile.check_exchange(&self.exchange)?;
+        self.eip712_hash(profile.chain_id)
+    }
+
+    // The `ecdsaSignature` the API expects: r || s || v followed by the signature
+    // type suffix 0x02 (EIP-712 typed data)
+    #[cfg(feature = "k256")]
+    pub fn sign_ecdsa(
+        &self,
+        chain_id: u64,
+        hex_l1_private_key: &str,
+    ) -> Result<String, PayloadError> {
+        let signature = sign_prehash(&self.eip712_hash(chain_id)?, hex_l1_private_key)?;
+        Ok(format!("{}02", signature))
+    }
+
+    #[cfg(feature = "k256")]
+    pub fn sign_ecdsa_on(
+        &self,
+        profile: &ChainProfile,
+        hex_l1_private_key: &str,
+    ) -> Result<String, PayloadError> {
+        profile.check_exchange(&self.exchange)?;
+        self.sign_ecdsa(profile.chain_id, hex_l1_private_key)
+    }
+}
+
+// Signs a 32-byte digest with an L1 key and returns 0x-prefixed r || s || v,
+// with v in {27, 28}
+#[cfg(feature = "k256")]
+pub fn sign_prehash(digest: &[u8; 32], hex_l1_private_key: &str) -> Result<String, PayloadError> {
+    let key_bytes = crate::util::hex::decode_bytes(hex_l1_private_key)
+        .map_err(|_| PayloadError::InvalidPrivateKey)?;
+    let signing_key = k256::ecdsa::SigningKey::from_slice(&key_bytes)
+        .map_err(|_| PayloadError::InvalidPrivateKey)?;
+    let (signature, recovery_id) = signing_key
+        .sign_prehash_recoverable(digest)
+        .map_err(|_| PayloadError::InvalidPrivateKey)?;
+
+    Ok(format!(
+        "0x{}{:02x}",
+        hex::encode(signature.to_bytes()),
+        27 + recovery_id.to_byte()
+    ))
+}
+
+// The Ethereum address of an L1 public key
+#[cfg(feature = "k256")]
+pub fn address_of(verifying_key: &k256::ecdsa::VerifyingKey) -> [u8; 20] {
+    let point = verifying_key.to_encoded_point(false);
+    let hash = keccak256(&point.as_bytes()[1..]);
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    address
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload::transfer::tests::transfer;
+
+    // The "Mail" example from the EIP-712 specification
+    #[test]
+    fn eip712_spec_example() {
+        let domain = domain_separator(
+            "Ether Mail",
+            "1",
+            1,
+            "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC",
+        )
+        .unwrap();
+        assert_eq!(
+            hex::encode(domain),
+            "f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f"
+        );
+
+        let person = "Person(string name,address wallet)";
+        let from = hash_struct(
+            person,
+            &[
+                keccak256(b"Cow"),
+                address_word("0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826").unwrap(),
+            ],
+        );
+        let to = hash_struct(
+            person,
+            &[
+                keccak256(b"Bob"),
+                address_word("0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB").unwrap(),
+            ],
+        );
+        let mail = hash_struct(
+            "Mail(Person from,Person to,string contents)Person(string name,address wallet)",
+            &[from, to, keccak256(b"Hello, Bob!")],
+        );
+        assert_eq!(
+            hex::encode(mail),
+            "c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e"
+        );
+
+        let digest = typed_data_hash(&domain, &mail);
+        assert_eq!(
+            hex::encode(digest),
+            "be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2"
+        );
+
+        #[cfg(feature = "k256")]
+        {
+            let key = hex::encode(keccak256(b"cow"));
+            assert_eq!(
+                sign_prehash(&digest, &key).unwrap(),
+                "0x4355c47d63924e8a72e509b65029052eb6c299d53a04e167c5775fd466751c9d07299936d304c153f6443dfa05f40ff007d72911b6f72307f996231605b915621c"
+            );
+        }
+    }
+
+    #[test]
+    fn transfer_hash_depends_on_chain() {
+        let transfer = transfer();
+        assert_ne!(
+            transfer.eip712_hash(1).unwrap(),
+            transfer.eip712_hash(5).unwrap()
+        );
+        assert_eq!(
+            transfer.eip712_hash_on(&ChainProfile::mainnet()).unwrap(),
+            transfer.eip712_hash(1).unwrap()
+        );
+
+        let taiko = ChainProfile::taiko(
+            "0x2e76ebd1c7c0c8e7c2b875b6d505a260c525d25e",
+            "https://taiko.example.org",
+        )
+        .unwrap();
+        assert!(transfer.eip712_hash_on(&taiko).is_err());
+    }
+
+    #[test]
+    fn transfer_hash_rejects_values_the_types_cannot_hold() {
+        let out_of_range = |transfer: Transfer| match transfer.eip712_hash(1) {
+            Err(PayloadError::OutOfRange { field, bits, .. }) => Some((field, bits)),
+            _ => None,
+        };
+        assert_eq!(
+            out_of_range(Transfer {
+                amount: BigInt::from(-1),
+                ..transfer()
+            }),
+            Some(("amount", 96))
+        );
+        assert_eq!(
+            out_of_range(Transfer {
+                max_fee: BigInt::from(1) << 96,
+                ..transfer()
+            }),
+            Some(("maxFee", 96))
+        );
+        assert_eq!(
+            out_of_range(Transfer {
+                token_id: 1 << 16,
+                ..transfer()
+            }),
+            Some(("tokenID", 16))
+        );
+        assert_eq!(
+            out_of_range(Transfer {
+                fee_token_id: 1 << 16,
+                ..transfer()
+            }),
+            Some(("feeTokenID", 16))
+        );
+        assert!(transfer().eip712_hash(1).is_ok());
+    }
+
+    #[cfg(feature = "k256")]
+    #[test]
+    fn transfer_ecdsa_signature_recovers_to_signer() {
+        use k256::ecdsa::{RecoveryId, Signature, SigningKey, VerifyingKey};
+
+        let key = hex::encode(keccak256(b"cow"));
+        let transfer = transfer();
+        let ecdsa_signature = transfer.sign_ecdsa(1, &key).unwrap();
+        assert!(ecdsa_signature.ends_with("02"));
+
+        let bytes = hex::decode(&ecdsa_signature[2..]).unwrap();
+        let signature = Signature::from_slice(&bytes[..64]).unwrap();
+        let recovery_id = RecoveryId::from_byte(bytes[64] - 27).unwrap();
+        let recovered = VerifyingKey::recover_from_prehash(
+            &transfer.eip712_hash(1).unwrap(),
+            &signature,
+            recovery_id,
+        )
+        .unwrap();
+
+        let expected = SigningKey::from_slice(&hex::decode(key).unwrap()).unwrap();
+        assert_eq!(address_of(&recovered), address_of(expected.verifying_key()));
+    }
+}
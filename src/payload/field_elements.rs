@@ -0,0 +1,168 @@
+// Conversions of addresses, hashes and hex strings into field elements.
+//
+// Poseidon inputs are elements below SNARK_SCALAR_FIELD (p, 254 bits), while a
+// lot of what goes into a custom payload is wider or has no fixed width: 32-byte
+// hashes, ids copied from L1 contracts, arbitrary hex data. The rules here are
+// the ones the built-in payloads and the circuits use, so that two builders that
+// put the same value into a preimage get the same elements:
+//
+//  * addresses are their 160-bit big-endian value, which always fits
+//  * hashes are converted by an explicit HashRule, since reducing, truncating
+//    and splitting each give different elements
+//  * hex values either must be below p (hex_to_field_element) or are split into
+//    31-byte chunks (hex_to_field_elements), never silently reduced
+
+use super::layout::ELEMENT_BITS;
+use crate::poseidon::field::SNARK_SCALAR_FIELD;
+use crate::util::{address, errors::PayloadError, hex};
+use num_bigint::{BigInt, Sign};
+use num_traits::One;
+
+// Bytes per element in hex_to_field_elements: 248 bits, always below p
+pub const CHUNK_BYTES: usize = 31;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashRule {
+    // int(hash, big-endian) mod p, one element. How request hashes and the
+    // ownership challenge are reduced.
+    Reduce,
+    // The low 253 bits, one element. How the circuits take sha256 hashes, e.g.
+    // the public data hash of a block.
+    Truncate,
+    // The high and the low 128 bits, two elements in that order. Lossless.
+    Split,
+}
+
+pub fn address_bytes_to_field_element(address: &[u8; 20]) -> BigInt {
+    BigInt::from_bytes_be(Sign::Plus, address)
+}
+
+// The address is validated like payload addresses, including its EIP-55 checksum
+pub fn address_to_field_element(address: &str) -> Result<BigInt, PayloadError> {
+    let bytes = address::parse_address(address)
+        .map_err(|e| PayloadError::InvalidAddress(address.to_string(), e))?;
+    Ok(address_bytes_to_field_element(&bytes))
+}
+
+pub fn hash_to_field_elements(hash: &[u8; 32], rule: HashRule) -> Vec<BigInt> {
+    match rule {
+        HashRule::Reduce => vec![BigInt::from_bytes_be(Sign::Plus, hash) % &*SNARK_SCALAR_FIELD],
+        HashRule::Truncate => {
+            let mask = (BigInt::one() << ELEMENT_BITS) - 1u8;
+            vec![BigInt::from_bytes_be(Sign::Plus, hash) & mask]
+        }
+        HashRule::Split => hash
+            .chunks(16)
+            .map(|half| BigInt::from_bytes_be(Sign::Plus, half))
+            .collect(),
+    }
+}
+
+// A 0x-prefixed hash as the API and block explorers show it; it must be exactly
+// 32 bytes, leading zeros included
+pub fn hex_hash_to_field_elements(
+    name: &'static str,
+    hash: &str,
+    rule: HashRule,
+) -> Result<Vec<BigInt>, PayloadError> {
+    let bytes = decode(name, hash)?;
+    let hash: [u8; 32] = bytes.try_into().map_err(|bytes: Vec<u8>| {
+        PayloadError::InvalidField(name, format!("{} bytes instead of 32", bytes.len()))
+    })?;
+    Ok(hash_to_field_elements(&hash, rule))
+}
+
+// A single element; values that are not below p are an error
+pub fn hex_to_field_element(name: &'static str, value: &str) -> Result<BigInt, PayloadError> {
+    let n = BigInt::from_bytes_be(Sign::Plus, &decode(name, value)?);
+    if n >= *SNARK_SCALAR_FIELD {
+        return Err(PayloadError::InvalidField(
+            name,
+            "not below SNARK_SCALAR_FIELD".to_string(),
+        ));
+    }
+    Ok(n)
+}
+
+// The bytes in CHUNK_BYTES chunks from the left, each one element; the last one
+// may be shorter. The byte length decides the chunks, so "0x00ff" and "0xff"
+// give the same single element, but 32 bytes with a leading zero give two.
+pub fn hex_to_field_elements(name: &'static str, value: &str) -> Result<Vec<BigInt>, PayloadError> {
+    Ok(decode(name, value)?
+        .chunks(CHUNK_BYTES)
+        .map(|chunk| BigInt::from_bytes_be(Sign::Plus, chunk))
+        .collect())
+}
+
+fn decode(name: &'static str, value: &str) -> Result<Vec<u8>, PayloadError> {
+    hex::decode_bytes(value).map_err(|e| PayloadError::InvalidField(name, e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_traits::Num;
+
+    const ADDRESS: &str = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+
+    #[test]
+    fn hash_rules() {
+        let hash = [0xffu8; 32];
+        let all_ones = |bits: u32| (BigInt::one() << bits) - 1u8;
+        assert_eq!(
+            hash_to_field_elements(&hash, HashRule::Reduce),
+            vec![all_ones(256) % &*SNARK_SCALAR_FIELD]
+        );
+        assert_eq!(
+            hash_to_field_elements(&hash, HashRule::Truncate),
+            vec![all_ones(253)]
+        );
+        assert_eq!(
+            hash_to_field_elements(&hash, HashRule::Split),
+            vec![all_ones(128), all_ones(128)]
+        );
+
+        let mut hash = [0u8; 32];
+        hash[0] = 1;
+        hash[31] = 2;
+        let hex = format!("0x{}", ::hex::encode(hash));
+        assert_eq!(
+            hex_hash_to_field_elements("hash", &hex, HashRule::Split).unwrap(),
+            vec![BigInt::one() << 120, BigInt::from(2)]
+        );
+        assert!(matches!(
+            hex_hash_to_field_elements("hash", "0x0102", HashRule::Reduce),
+            Err(PayloadError::InvalidField("hash", _))
+        ));
+    }
+
+    #[test]
+    fn addresses_and_hex_values() {
+        assert_eq!(
+            address_to_field_element(ADDRESS).unwrap(),
+            BigInt::from_str_radix(&ADDRESS[2..], 16).unwrap()
+        );
+        assert!(matches!(
+            address_to_field_element("0x5AAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"),
+            Err(PayloadError::InvalidAddress(..))
+        ));
+
+        assert_eq!(
+            hex_to_field_element("id", "0xff").unwrap(),
+            BigInt::from(255)
+        );
+        let p = format!("0x{}", SNARK_SCALAR_FIELD.to_str_radix(16));
+        assert!(hex_to_field_element("id", &p).is_err());
+        assert!(hex_to_field_element("id", "0xzz").is_err());
+
+        assert_eq!(
+            hex_to_field_elements("data", "0x00ff").unwrap(),
+            vec![BigInt::from(255)]
+        );
+        let data = format!("0x{}{}", "00".repeat(31), "01");
+        assert_eq!(
+            hex_to_field_elements("data", &data).unwrap(),
+            vec![BigInt::from(0), BigInt::one()]
+        );
+    }
+}
@@ -0,0 +1,269 @@
+/*
+Payload files for batch (offline) signing.
+
+A file is either a JSON array of payload objects or a CSV table. Objects use the
+field names of the Loopring API request bodies plus a "type" field:
+
+    {"type": "transfer", "exchange": "0x...", "payerId": 11087,
+     "payerAddr": "0x...", "payeeId": 0, "payeeAddr": "0x...",
+     "token": {"tokenId": 1, "volume": "1000000000000000000"},
+     "maxFee": {"tokenId": 0, "volume": "100000000000000"},
+     "validUntil": 1700000000, "storageId": 5}
+
+    {"type": "order", "exchange": "0x...", "accountId": 11087, "storageId": 2,
+     "sellToken": {"tokenId": 1, "volume": "..."},
+     "buyToken": {"tokenId": 0, "volume": "..."},
+     "validUntil": 1700000000, "maxFeeBips": 50, "fillAmountBOrS": false,
+     "taker": "0x..."}
+
+    {"type": "withdrawal", "exchange": "0x...", "accountId": 11087,
+     "owner": "0x...", "token": {"tokenId": 1, "volume": "..."},
+     "maxFee": {"tokenId": 0, "volume": "..."}, "to": "0x...",
+     "extraData": "", "minGas": 0, "validUntil": 1700000000, "storageId": 7}
+
+CSV files have a header row naming the same fields, nested ones as a dotted path
+(`token.tokenId`, `maxFee.volume`, ...). Values are not quoted and must not
+contain commas; an empty cell is a missing field.
+
+Every entry gets one result, in input order:
+
+    {"index": 0, "type": "transfer", "hash": "0x...", "signature": "0x..."}
+    {"index": 1, "error": "Missing field: storageId"}
+
+so a bad entry does not stop the rest of the batch.
+*/
+
+use super::{hash_to_hex, order::Order, transfer::Transfer, withdrawal::Withdrawal};
+use crate::poseidon::eddsa::MessageHash;
+use crate::signer::Signer;
+use crate::util::{errors::PayloadError, hex};
+use num_bigint::BigInt;
+use serde_json::{json, Map, Value};
+use std::str::FromStr;
+
+pub fn parse_json(content: &str) -> Result<Vec<Value>, PayloadError> {
+    match serde_json::from_str(content) {
+        Ok(Value::Array(entries)) => Ok(entries),
+        Ok(_) => Err(PayloadError::InvalidField(
+            "file",
+            "expected a JSON array".to_string(),
+        )),
+        Err(e) => Err(PayloadError::InvalidField("file", e.to_string())),
+    }
+}
+
+pub fn parse_csv(content: &str) -> Result<Vec<Value>, PayloadError> {
+    let mut lines = content.lines().filter(|line| !line.trim().is_empty());
+    let header: Vec<&str> = match lines.next() {
+        Some(line) => line.split(',').map(str::trim).collect(),
+        None => return Ok(Vec::new()),
+    };
+    lines
+        .enumerate()
+        .map(|(row, line)| {
+            let cells: Vec<&str> = line.split(',').map(str::trim).collect();
+            if cells.len() != header.len() {
+                return Err(PayloadError::InvalidField(
+                    "file",
+                    format!(
+                        "row {} has {} columns, expected {}",
+                        row + 1,
+                        cells.len(),
+                        header.len()
+                    ),
+                ));
+            }
+            let mut entry = Map::new();
+            for (name, cell) in header.iter().zip(cells) {
+                if !cell.is_empty() {
+                    entry.insert(name.to_string(), Value::String(cell.to_string()));
+                }
+            }
+            Ok(Value::Object(entry))
+        })
+        .collect()
+}
+
+// Looks a field up by dotted path, either nested (JSON) or flat (CSV)
+fn field<'a>(entry: &'a Value, path: &'static str) -> Result<&'a Value, PayloadError> {
+    if let Some(value) = entry.get(path) {
+        return Ok(value);
+    }
+    path.split('.')
+        .try_fold(entry, |value, key| value.get(key))
+        .ok_or(PayloadError::MissingField(path))
+}
+
+fn string_field(entry: &Value, path: &'static str) -> Result<String, PayloadError> {
+    match field(entry, path)? {
+        Value::String(s) => Ok(s.clone()),
+        other => Err(PayloadError::InvalidField(path, other.to_string())),
+    }
+}
+
+fn bigint_field(entry: &Value, path: &'static str) -> Result<BigInt, PayloadError> {
+    let value = field(entry, path)?;
+    let digits = match value {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        other => return Err(PayloadError::InvalidField(path, other.to_string())),
+    };
+    match BigInt::from_str(&digits) {
+        Ok(n) if n >= BigInt::from(0) => Ok(n),
+        _ => Err(PayloadError::InvalidField(path, digits)),
+    }
+}
+
+fn u32_field(entry: &Value, path: &'static str) -> Result<u32, PayloadError> {
+    let value = bigint_field(entry, path)?;
+    u32::try_from(&value).map_err(|_| PayloadError::InvalidField(path, value.to_string()))
+}
+
+fn bool_field(entry: &Value, path: &'static str) -> Result<bool, PayloadError> {
+    match field(entry, path)? {
+        Value::Bool(b) => Ok(*b),
+        Value::String(s) if s == "true" => Ok(true),
+        Value::String(s) if s == "false" => Ok(false),
+        other => Err(PayloadError::InvalidField(path, other.to_string())),
+    }
+}
+
+pub fn transfer_from_value(entry: &Value) -> Result<Transfer, PayloadError> {
+    Ok(Transfer {
+        exchange: string_field(entry, "exchange")?,
+        payer_id: u32_field(entry, "payerId")?,
+        payer_addr: string_field(entry, "payerAddr")?,
+        payee_id: u32_field(entry, "payeeId")?,
+        payee_addr: string_field(entry, "payeeAddr")?,
+        token_id: u32_field(entry, "token.tokenId")?,
+        amount: bigint_field(entry, "token.volume")?,
+        fee_token_id: u32_field(entry, "maxFee.tokenId")?,
+        max_fee: bigint_field(entry, "maxFee.volume")?,
+        valid_until: u32_field(entry, "validUntil")?,
+        storage_id: u32_field(entry, "storageId")?,
+    })
+}
+
+pub fn order_from_value(entry: &Value) -> Result<Order, PayloadError> {
+    Ok(Order {
+        exchange: string_field(entry, "exchange")?,
+        storage_id: u32_field(entry, "storageId")?,
+        account_id: u32_field(entry, "accountId")?,
+        sell_token_id: u32_field(entry, "sellToken.tokenId")?,
+        buy_token_id: u32_field(entry, "buyToken.tokenId")?,
+        sell_volume: bigint_field(entry, "sellToken.volume")?,
+        buy_volume: bigint_field(entry, "buyToken.volume")?,
+        valid_until: u32_field(entry, "validUntil")?,
+        max_fee_bips: u32_field(entry, "maxFeeBips")?,
+        fill_amount_b_or_s: bool_field(entry, "fillAmountBOrS")?,
+        taker: match field(entry, "taker") {
+            Ok(_) => Some(string_field(entry, "taker")?),
+            Err(_) => None,
+        },
+    })
+}
+
+// extraData is optional; missing, "" and "0x" are no data
+pub fn withdrawal_from_value(entry: &Value) -> Result<Withdrawal, PayloadError> {
+    let extra_data = match field(entry, "extraData") {
+        Ok(_) => match string_field(entry, "extraData")?.as_str() {
+            "" | "0x" => Vec::new(),
+            data => hex::decode_bytes(data)
+                .map_err(|_| PayloadError::InvalidField("extraData", data.to_string()))?,
+        },
+        Err(_) => Vec::new(),
+    };
+    Ok(Withdrawal {
+        exchange: string_field(entry, "exchange")?,
+        account_id: u32_field(entry, "accountId")?,
+        owner: string_field(entry, "owner")?,
+        token_id: u32_field(entry, "token.tokenId")?,
+        amount: bigint_field(entry, "token.volume")?,
+        fee_token_id: u32_field(entry, "maxFee.tokenId")?,
+        max_fee: bigint_field(entry, "maxFee.volume")?,
+        to: string_field(entry, "to")?,
+        extra_data,
+        min_gas: bigint_field(entry, "minGas")?,
+        valid_until: u32_field(entry, "validUntil")?,
+        storage_id: u32_field(entry, "storageId")?,
+    })
+}
+
+pub(crate) fn sign_entry<S: Signer>(
+    signer: &S,
+    entry: &Value,
+) -> Result<(String, BigInt, String), String> {
+    let payload_type = string_field(entry, "type").map_err(|e| e.to_string())?;
+    let hash = match payload_type.as_str() {
+        "transfer" => transfer_from_value(entry).and_then(|t| t.hash()),
+        "order" => order_from_value(entry).and_then(|o| o.hash()),
+        "withdrawal" => withdrawal_from_value(entry).and_then(|w| w.hash()),
+        other => return Err(format!("Unsupported payload type: {}", other)),
+    }
+    .map_err(|e| e.to_string())?;
+    let signed = signer
+        .sign(&MessageHash::new(hash.clone()))
+        .map_err(|e| e.to_string())?;
+    Ok((payload_type, hash, signed.to_hex()))
+}
+
+pub fn sign_entries<S: Signer>(signer: &S, entries: &[Value]) -> Vec<Value> {
+    entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| match sign_entry(signer, entry) {
+            Ok((payload_type, hash, signature)) => json!({
+                "index": index,
+                "type": payload_type,
+                "hash": hash_to_hex(&hash),
+                "signature": signature,
+            }),
+            Err(error) => json!({"index": index, "error": error}),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload::transfer::tests::transfer;
+    use crate::signer::LocalSigner;
+
+    const L2_KEY: &str = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+
+    const TRANSFER_JSON: &str = r#"[{
+        "type": "transfer",
+        "exchange": "0x0BABA1Ad5bE3a5C0a66E7ac838a129Bf948f1eA4",
+        "payerId": 11087,
+        "payerAddr": "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        "payeeId": 0,
+        "payeeAddr": "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+        "token": {"tokenId": 1, "volume": "1000000000000000000"},
+        "maxFee": {"tokenId": 0, "volume": "100000000000000"},
+        "validUntil": 1700000000,
+        "storageId": 5
+    }, {"type": "transfer", "exchange": "0x0BABA1Ad5bE3a5C0a66E7ac838a129Bf948f1eA4"}]"#;
+
+    #[test]
+    fn json_batch_is_signed_per_entry() {
+        let signer = LocalSigner::from_hex(L2_KEY).unwrap();
+        let results = sign_entries(&signer, &parse_json(TRANSFER_JSON).unwrap());
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["signature"], transfer().sign(L2_KEY).unwrap());
+        assert_eq!(results[1]["index"], 1);
+        assert_eq!(results[1]["error"], "Missing field: payerId");
+    }
+
+    #[test]
+    fn csv_rows_match_json_entries() {
+        let csv = "type,exchange,payerId,payerAddr,payeeId,payeeAddr,token.tokenId,token.volume,maxFee.tokenId,maxFee.volume,validUntil,storageId\n\
+                   transfer,0x0BABA1Ad5bE3a5C0a66E7ac838a129Bf948f1eA4,11087,0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed,0,0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359,1,1000000000000000000,0,100000000000000,1700000000,5\n";
+        let entries = parse_csv(csv).unwrap();
+        assert_eq!(
+            transfer_from_value(&entries[0]).unwrap().hash().unwrap(),
+            transfer().hash().unwrap()
+        );
+        assert!(parse_csv("type,exchange\ntransfer\n").is_err());
+    }
+}
@@ -0,0 +1,169 @@
+// Payload types defined outside the crate.
+//
+// A payload is hashed like the built-in ones by implementing LoopringHash: the
+// preimage is a list of field elements, hashed with Poseidon(t = inputs + 1,
+// F = 6, P = 53) and signed with the L2 key. With the `derive` feature,
+// #[derive(LoopringHash)] writes `poseidon_inputs` from field annotations (see
+// the loopring_sign_derive crate); the conversions it uses are the HashField
+// impls below.
+
+use super::{check_range, parse_address, poseidon_hash, sign_hash};
+use crate::util::errors::PayloadError;
+#[cfg(feature = "derive")]
+pub use loopring_sign_derive::LoopringHash;
+pub use num_bigint::BigInt;
+use num_traits::Zero;
+
+pub trait LoopringHash {
+    fn poseidon_inputs(&self) -> Result<Vec<BigInt>, PayloadError>;
+
+    fn hash(&self) -> Result<BigInt, PayloadError> {
+        poseidon_hash(self.poseidon_inputs()?)
+    }
+
+    fn sign(&self, hex_private_key: &str) -> Result<String, PayloadError> {
+        sign_hash(self.hash()?, hex_private_key)
+    }
+}
+
+// A payload field that becomes one Poseidon input of at most `bits` bits
+pub trait HashField {
+    fn to_field_element(&self, name: &'static str, bits: u32) -> Result<BigInt, PayloadError>;
+}
+
+macro_rules! impl_hash_field {
+    ($($t:ty),*) => {
+        $(
+            impl HashField for $t {
+                fn to_field_element(
+                    &self,
+                    name: &'static str,
+                    bits: u32,
+                ) -> Result<BigInt, PayloadError> {
+                    check_range(name, *self, bits)?;
+                    Ok(BigInt::from(*self))
+                }
+            }
+        )*
+    };
+}
+
+impl_hash_field!(u8, u16, u32, u64, u128);
+
+impl HashField for bool {
+    fn to_field_element(&self, name: &'static str, bits: u32) -> Result<BigInt, PayloadError> {
+        (*self as u8).to_field_element(name, bits)
+    }
+}
+
+impl HashField for BigInt {
+    fn to_field_element(&self, name: &'static str, bits: u32) -> Result<BigInt, PayloadError> {
+        check_range(name, self.clone(), bits)?;
+        Ok(self.clone())
+    }
+}
+
+// Strings are L1 addresses, packed as their 160-bit value
+impl HashField for String {
+    fn to_field_element(&self, name: &'static str, bits: u32) -> Result<BigInt, PayloadError> {
+        parse_address(self)?.to_field_element(name, bits)
+    }
+}
+
+// None is encoded as 0, like an order without taker
+impl<T: HashField> HashField for Option<T> {
+    fn to_field_element(&self, name: &'static str, bits: u32) -> Result<BigInt, PayloadError> {
+        match self {
+            Some(value) => value.to_field_element(name, bits),
+            None => Ok(BigInt::zero()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields_are_range_checked() {
+        assert_eq!(
+            255u32.to_field_element("fee", 8).unwrap(),
+            BigInt::from(255)
+        );
+        assert!(256u32.to_field_element("fee", 8).is_err());
+        assert_eq!(true.to_field_element("flag", 1).unwrap(), BigInt::from(1));
+        assert_eq!(
+            None::<String>.to_field_element("taker", 160).unwrap(),
+            BigInt::zero()
+        );
+        assert!("0x1234".to_string().to_field_element("taker", 160).is_err());
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derived_layout_matches_order() {
+        use crate::payload::order::Order;
+
+        // The order preimage, with two fields declared out of order
+        #[derive(LoopringHash)]
+        struct CustomOrder {
+            #[loopring(bits = 160, order = 1)]
+            exchange: String,
+            #[loopring(bits = 32, order = 3)]
+            account_id: u32,
+            #[loopring(bits = 32, order = 2)]
+            storage_id: u32,
+            #[loopring(bits = 32, order = 4)]
+            sell_token_id: u32,
+            #[loopring(bits = 32, order = 5)]
+            buy_token_id: u32,
+            #[loopring(bits = 96, order = 6)]
+            sell_volume: BigInt,
+            #[loopring(bits = 96, order = 7)]
+            buy_volume: BigInt,
+            #[loopring(bits = 32, order = 8)]
+            valid_until: u32,
+            #[loopring(bits = 6, order = 9)]
+            max_fee_bips: u32,
+            #[loopring(bits = 1, order = 10)]
+            fill_amount_b_or_s: bool,
+            #[loopring(bits = 160, order = 11)]
+            taker: Option<String>,
+            #[loopring(skip)]
+            _client_order_id: String,
+        }
+
+        let exchange = "0x0BABA1Ad5bE3a5C0a66E7ac838a129Bf948f1eA4".to_string();
+        let custom = CustomOrder {
+            exchange: exchange.clone(),
+            account_id: 11087,
+            storage_id: 2,
+            sell_token_id: 0,
+            buy_token_id: 1,
+            sell_volume: BigInt::from(1_000_000_000_000_000_000u64),
+            buy_volume: BigInt::from(3_000_000_000u64),
+            valid_until: 1700000000,
+            max_fee_bips: 60,
+            fill_amount_b_or_s: false,
+            taker: None,
+            _client_order_id: "ignored".to_string(),
+        };
+        let order = Order {
+            exchange,
+            storage_id: 2,
+            account_id: 11087,
+            sell_token_id: 0,
+            buy_token_id: 1,
+            sell_volume: BigInt::from(1_000_000_000_000_000_000u64),
+            buy_volume: BigInt::from(3_000_000_000u64),
+            valid_until: 1700000000,
+            max_fee_bips: 60,
+            fill_amount_b_or_s: false,
+            taker: None,
+        };
+        assert_eq!(custom.hash().unwrap(), order.hash().unwrap());
+
+        let l2_key = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+        assert_eq!(custom.sign(l2_key).unwrap(), order.sign(l2_key).unwrap());
+    }
+}
@@ -0,0 +1,141 @@
+// Packing of small fields into field elements, built at runtime.
+//
+// Circuits save constraints by concatenating several short fields into one
+// field element instead of spending a Poseidon input on each. LayoutBuilder does
+// the same for layouts that are only known at runtime (or for users who cannot
+// use #[derive(LoopringHash)]):
+//
+//     let mut layout = LayoutBuilder::new();
+//     layout
+//         .push("accountId", account_id, 32)?
+//         .push("tokenId", token_id, 16)?
+//         .push("amount", amount, 96)?;
+//     let hash = layout.hash()?;          // Poseidon(t = arity + 1, F = 6, P = 53)
+//
+// Fields are appended to the current element, the first one in the most
+// significant bits (element = element << bits | value). A field that does not fit
+// into the remaining ELEMENT_BITS starts the next element; fields are never split
+// across two elements. The number of elements is the Poseidon arity.
+
+use super::hashable::{HashField, LoopringHash};
+use crate::util::errors::PayloadError;
+use num_bigint::BigInt;
+
+// The most bits that always stay below SNARK_SCALAR_FIELD
+pub const ELEMENT_BITS: u32 = 253;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LayoutBuilder {
+    elements: Vec<BigInt>,
+    // Bits used in the last element
+    used: u32,
+}
+
+impl LayoutBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push<V: HashField>(
+        &mut self,
+        name: &'static str,
+        value: V,
+        bits: u32,
+    ) -> Result<&mut Self, PayloadError> {
+        if bits == 0 || bits > ELEMENT_BITS {
+            return Err(PayloadError::InvalidField(
+                name,
+                format!(
+                    "width of {} bits is not between 1 and {}",
+                    bits, ELEMENT_BITS
+                ),
+            ));
+        }
+        let value = value.to_field_element(name, bits)?;
+        match self.elements.last_mut() {
+            Some(element) if self.used + bits <= ELEMENT_BITS => {
+                *element = (&*element << bits) | value;
+                self.used += bits;
+            }
+            _ => {
+                self.elements.push(value);
+                self.used = bits;
+            }
+        }
+        Ok(self)
+    }
+
+    // Starts a new element even if the next field would still fit
+    pub fn align(&mut self) -> &mut Self {
+        if !self.elements.is_empty() {
+            self.used = ELEMENT_BITS;
+        }
+        self
+    }
+
+    // The Poseidon arity, one input per element
+    pub fn arity(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn elements(&self) -> &[BigInt] {
+        &self.elements
+    }
+}
+
+impl LoopringHash for LayoutBuilder {
+    fn poseidon_inputs(&self) -> Result<Vec<BigInt>, PayloadError> {
+        if self.elements.is_empty() {
+            return Err(PayloadError::MissingField("layout"));
+        }
+        Ok(self.elements.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_fields_share_an_element() {
+        let mut layout = LayoutBuilder::new();
+        layout
+            .push("accountId", 11087u32, 32)
+            .unwrap()
+            .push("tokenId", 1u32, 16)
+            .unwrap()
+            .push("flag", true, 1)
+            .unwrap();
+        assert_eq!(layout.arity(), 1);
+        assert_eq!(
+            layout.elements()[0],
+            (BigInt::from(11087) << 17) | (BigInt::from(1) << 1) | BigInt::from(1)
+        );
+
+        // 49 + 205 bits overflow the first element
+        layout.push("root", BigInt::from(7), 205).unwrap();
+        assert_eq!(layout.arity(), 2);
+        layout.align().push("nonce", 3u32, 32).unwrap();
+        assert_eq!(layout.arity(), 3);
+
+        let inputs = layout.poseidon_inputs().unwrap();
+        assert_eq!(
+            layout.hash().unwrap(),
+            crate::payload::poseidon_hash(inputs).unwrap()
+        );
+    }
+
+    #[test]
+    fn widths_are_enforced() {
+        let mut layout = LayoutBuilder::new();
+        assert!(matches!(
+            layout.push("tokenId", 1u32 << 16, 16),
+            Err(PayloadError::OutOfRange {
+                field: "tokenId",
+                ..
+            })
+        ));
+        assert!(layout.push("wide", 1u32, 254).is_err());
+        assert!(layout.hash().is_err());
+    }
+}
@@ -0,0 +1,144 @@
+// Loopring L2 payloads (orders, transfers, ...) and their EdDSA signing.
+//
+// Every payload is serialized into a fixed list of field elements, hashed with a
+// Poseidon instance of width `inputs + 1` (6 full rounds, 53 partial rounds) and
+// signed with the account's L2 key. This mirrors the `*EddsaSignHelper` classes
+// of the Python reference SDK.
+
+pub mod batch;
+pub mod bundle;
+pub mod cancel;
+pub mod dual_auth;
+pub mod eip712;
+pub mod field_elements;
+pub mod file;
+pub mod hashable;
+pub mod layout;
+pub mod order;
+pub mod ownership;
+pub mod preview;
+pub mod red_packet;
+pub mod signed;
+pub mod token;
+pub mod transfer;
+pub mod version;
+pub mod withdrawal;
+pub mod witness;
+
+use self::{order::Order, transfer::Transfer};
+use crate::poseidon::{
+    eddsa::{signing_key, MessageHash, SignatureScheme},
+    permutation::Poseidon,
+};
+use crate::util::errors::PayloadError;
+use num_bigint::{BigInt, Sign};
+use num_traits::Zero;
+
+// Payload hashes in the form the API reports them back ("hash" field of order and
+// transfer responses and websocket events): 0x-prefixed, zero-padded to 32 bytes.
+pub fn hash_to_hex(hash: &BigInt) -> String {
+    format!("0x{:0>64}", hash.to_str_radix(16))
+}
+
+pub fn order_hash(order: &Order) -> Result<String, PayloadError> {
+    Ok(hash_to_hex(&order.hash()?))
+}
+
+pub fn transfer_hash(transfer: &Transfer) -> Result<String, PayloadError> {
+    Ok(hash_to_hex(&transfer.hash()?))
+}
+
+// Bit widths the circuits give payload fields. Ids, storage ids and timestamps
+// are 32 bits wide, which their u32 types already guarantee.
+pub const AMOUNT_BITS: u32 = 96;
+// Fees are paid in fungible tokens, whose ids are below 2^16
+pub const FEE_TOKEN_ID_BITS: u32 = 16;
+pub const FEE_BIPS_BITS: u32 = 6;
+
+// Rejects values the circuit cannot represent before they are hashed, since the
+// API only reports them as an invalid signature
+pub(crate) fn check_range<V: Into<BigInt>>(
+    field: &'static str,
+    value: V,
+    bits: u32,
+) -> Result<(), PayloadError> {
+    let value = value.into();
+    if value.sign() == Sign::Minus || value.bits() > bits as u64 {
+        return Err(PayloadError::OutOfRange {
+            field,
+            value: value.to_string(),
+            bits,
+        });
+    }
+    Ok(())
+}
+
+pub(crate) fn poseidon_hash(inputs: Vec<BigInt>) -> Result<BigInt, PayloadError> {
+    let poseidon = Poseidon::shared(inputs.len() + 1, 6, 53);
+    Ok(poseidon.hash(inputs)?)
+}
+
+pub(crate) fn sign_hash(hash: BigInt, hex_private_key: &str) -> Result<String, PayloadError> {
+    let private_key = signing_key(hex_private_key).ok_or(PayloadError::InvalidPrivateKey)?;
+    Ok(SignatureScheme::sign_hash(private_key, &MessageHash::new(hash)).to_hex())
+}
+
+// L1 addresses are validated (including their EIP-55 checksum) and packed as
+// their 160-bit integer value
+pub(crate) fn parse_address(address: &str) -> Result<BigInt, PayloadError> {
+    field_elements::address_to_field_element(address)
+}
+
+pub(crate) fn parse_optional_address(address: &Option<String>) -> Result<BigInt, PayloadError> {
+    match address {
+        Some(address) => parse_address(address),
+        None => Ok(BigInt::zero()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::hex;
+    use num_traits::Num;
+
+    #[test]
+    fn parse_address_test() {
+        let address = parse_address("0x0BABA1Ad5bE3a5C0a66E7ac838a129Bf948f1eA4").unwrap();
+        assert_eq!(
+            address,
+            BigInt::from_str_radix("0BABA1Ad5bE3a5C0a66E7ac838a129Bf948f1eA4", 16).unwrap()
+        );
+        assert!(parse_address("0BABA1Ad5bE3a5C0a66E7ac838a129Bf948f1eA4").is_err());
+        assert!(parse_address("0x0BABA1Ad5bE3a5C0a66E7ac838a129Bf948f1e").is_err());
+        assert!(parse_address("0xZBABA1Ad5bE3a5C0a66E7ac838a129Bf948f1eA4").is_err());
+        // Same address with one character's case flipped
+        assert!(parse_address("0x0bABA1Ad5bE3a5C0a66E7ac838a129Bf948f1eA4").is_err());
+    }
+
+    #[test]
+    fn hash_hex_is_padded() {
+        assert_eq!(
+            hash_to_hex(&BigInt::from(255)),
+            "0x00000000000000000000000000000000000000000000000000000000000000ff"
+        );
+
+        let transfer = transfer::tests::transfer();
+        assert_eq!(
+            transfer_hash(&transfer).unwrap(),
+            hash_to_hex(&transfer.hash().unwrap())
+        );
+    }
+
+    #[test]
+    fn sign_hash_rejects_invalid_key() {
+        assert!(sign_hash(BigInt::from(1), "0xnot-a-key").is_err());
+        // Zero modulo JUBJUB_L, whose public key is the neutral point
+        let order = hex::encode_secret(&crate::poseidon::jubjub::JUBJUB_L);
+        assert!(matches!(
+            sign_hash(BigInt::from(1), &order),
+            Err(PayloadError::InvalidPrivateKey)
+        ));
+        assert!(sign_hash(BigInt::from(1), "0x00").is_err());
+    }
+}
@@ -0,0 +1,322 @@
+// Spot orders and block-trade (btrade) orders.
+//
+// The signed order preimage is the one the SpotTrade circuit checks:
+//
+//     [exchange, storageId, accountId, sellTokenId, buyTokenId,
+//      sellVolume, buyVolume, validUntil, maxFeeBips, fillAmountBOrS, taker]
+//
+// hashed with Poseidon(t = 12, F = 6, P = 53).
+//
+// The 3.1 exchange's order preimage was
+//
+//     [exchangeId, orderId, accountId, tokenS, tokenB, amountS, amountB,
+//      allOrNone, validSince, validUntil, maxFeeBips, buy, label]
+//
+// with Poseidon(t = 14, F = 6, P = 53). The storage id takes the place of the
+// order id and fillAmountBOrS that of `buy`; allOrNone, validSince and label,
+// which 3.6 dropped, are 0. 3.1 orders could not name a taker.
+
+use super::version::{ProtocolVersion, VersionedPayload};
+use super::{
+    check_range, parse_address, parse_optional_address, sign_hash, AMOUNT_BITS, FEE_BIPS_BITS,
+};
+use crate::util::chain::ChainProfile;
+use crate::util::errors::PayloadError;
+use num_bigint::BigInt;
+use num_traits::Zero;
+
+pub struct Order {
+    pub exchange: String,
+    pub storage_id: u32,
+    pub account_id: u32,
+    pub sell_token_id: u32,
+    pub buy_token_id: u32,
+    pub sell_volume: BigInt,
+    pub buy_volume: BigInt,
+    pub valid_until: u32,
+    pub max_fee_bips: u32,
+    pub fill_amount_b_or_s: bool,
+    // Restricts who may fill the order. None allows anyone (encoded as 0).
+    pub taker: Option<String>,
+}
+
+impl VersionedPayload for Order {
+    fn poseidon_inputs_for(&self, version: ProtocolVersion) -> Result<Vec<BigInt>, PayloadError> {
+        match version {
+            ProtocolVersion::V3_1 { exchange_id } => self.poseidon_inputs_v3_1(exchange_id),
+            ProtocolVersion::V3_6 => self.poseidon_inputs_v3_6(),
+        }
+    }
+}
+
+impl Order {
+    fn poseidon_inputs_v3_1(&self, exchange_id: u32) -> Result<Vec<BigInt>, PayloadError> {
+        if self.taker.is_some() {
+            return Err(PayloadError::InvalidField(
+                "taker",
+                "3.1 orders cannot restrict the taker".to_string(),
+            ));
+        }
+        check_range("sellToken.volume", self.sell_volume.clone(), AMOUNT_BITS)?;
+        check_range("buyToken.volume", self.buy_volume.clone(), AMOUNT_BITS)?;
+        check_range("maxFeeBips", self.max_fee_bips, FEE_BIPS_BITS)?;
+        Ok(vec![
+            BigInt::from(exchange_id),
+            BigInt::from(self.storage_id),
+            BigInt::from(self.account_id),
+            BigInt::from(self.sell_token_id),
+            BigInt::from(self.buy_token_id),
+            self.sell_volume.clone(),
+            self.buy_volume.clone(),
+            BigInt::zero(),
+            BigInt::zero(),
+            BigInt::from(self.valid_until),
+            BigInt::from(self.max_fee_bips),
+            BigInt::from(self.fill_amount_b_or_s as u8),
+            BigInt::zero(),
+        ])
+    }
+
+    fn poseidon_inputs_v3_6(&self) -> Result<Vec<BigInt>, PayloadError> {
+        check_range("sellToken.volume", self.sell_volume.clone(), AMOUNT_BITS)?;
+        check_range("buyToken.volume", self.buy_volume.clone(), AMOUNT_BITS)?;
+        check_range("maxFeeBips", self.max_fee_bips, FEE_BIPS_BITS)?;
+        Ok(vec![
+            parse_address(&self.exchange)?,
+            BigInt::from(self.storage_id),
+            BigInt::from(self.account_id),
+            BigInt::from(self.sell_token_id),
+            BigInt::from(self.buy_token_id),
+            self.sell_volume.clone(),
+            self.buy_volume.clone(),
+            BigInt::from(self.valid_until),
+            BigInt::from(self.max_fee_bips),
+            BigInt::from(self.fill_amount_b_or_s as u8),
+            parse_optional_address(&self.taker)?,
+        ])
+    }
+
+    // The layout of the current protocol version
+    pub fn poseidon_inputs(&self) -> Result<Vec<BigInt>, PayloadError> {
+        self.poseidon_inputs_for(ProtocolVersion::CURRENT)
+    }
+
+    pub fn hash(&self) -> Result<BigInt, PayloadError> {
+        self.hash_for(ProtocolVersion::CURRENT)
+    }
+
+    pub fn sign(&self, hex_private_key: &str) -> Result<String, PayloadError> {
+        sign_hash(self.hash()?, hex_private_key)
+    }
+
+    // Same as `sign`, failing if the order was built for another exchange than
+    // the profile's
+    pub fn sign_on(
+        &self,
+        profile: &ChainProfile,
+        hex_private_key: &str,
+    ) -> Result<String, PayloadError> {
+        profile.check_exchange(&self.exchange)?;
+        self.sign(hex_private_key)
+    }
+}
+
+// Block trades are negotiated off-book with a known counterparty and are settled
+// through the same SpotTrade circuit, which checks one preimage for every order.
+// The block-trade layout is therefore
+//
+//     [exchange, storageId, accountId, sellTokenId, buyTokenId,
+//      sellVolume, buyVolume, validUntil, maxFeeBips, fillAmountBOrS, taker]
+//
+// with the taker slot bound to the counterparty: it must be a non-zero address,
+// otherwise anyone observing the signed order could fill it. A spot order with
+// the same taker has the same hash; what differs is that a BlockTradeOrder cannot
+// be built, and so not signed, with the open (zero) taker.
+pub struct BlockTradeOrder {
+    order: Order,
+}
+
+impl VersionedPayload for BlockTradeOrder {
+    fn poseidon_inputs_for(&self, version: ProtocolVersion) -> Result<Vec<BigInt>, PayloadError> {
+        match version {
+            ProtocolVersion::V3_1 { .. } => {
+                Err(PayloadError::UnsupportedVersion("BlockTradeOrder"))
+            }
+            // `new` checked the taker slot
+            ProtocolVersion::V3_6 => self.order.poseidon_inputs_v3_6(),
+        }
+    }
+}
+
+impl BlockTradeOrder {
+    pub fn new(order: Order) -> Result<Self, PayloadError> {
+        let taker = order
+            .taker
+            .as_deref()
+            .ok_or(PayloadError::MissingField("taker"))?;
+        if parse_address(taker)?.is_zero() {
+            return Err(PayloadError::InvalidField(
+                "taker",
+                "a block trade needs its counterparty, not the zero address".to_string(),
+            ));
+        }
+        Ok(BlockTradeOrder { order })
+    }
+
+    pub fn order(&self) -> &Order {
+        &self.order
+    }
+
+    pub fn poseidon_inputs(&self) -> Result<Vec<BigInt>, PayloadError> {
+        self.poseidon_inputs_for(ProtocolVersion::CURRENT)
+    }
+
+    pub fn hash(&self) -> Result<BigInt, PayloadError> {
+        self.hash_for(ProtocolVersion::CURRENT)
+    }
+
+    pub fn sign(&self, hex_private_key: &str) -> Result<String, PayloadError> {
+        sign_hash(self.hash()?, hex_private_key)
+    }
+
+    pub fn sign_on(
+        &self,
+        profile: &ChainProfile,
+        hex_private_key: &str,
+    ) -> Result<String, PayloadError> {
+        profile.check_exchange(&self.order.exchange)?;
+        self.sign(hex_private_key)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::payload::hash_to_hex;
+
+    pub(crate) fn order(taker: Option<&str>) -> Order {
+        Order {
+            exchange: "0x0BABA1Ad5bE3a5C0a66E7ac838a129Bf948f1eA4".to_string(),
+            storage_id: 2,
+            account_id: 11087,
+            sell_token_id: 0,
+            buy_token_id: 1,
+            sell_volume: BigInt::from(1_000_000_000_000_000_000u64),
+            buy_volume: BigInt::from(3_000_000_000u64),
+            valid_until: 1700000000,
+            max_fee_bips: 60,
+            fill_amount_b_or_s: false,
+            taker: taker.map(|t| t.to_string()),
+        }
+    }
+
+    #[test]
+    fn order_inputs_follow_circuit_layout() {
+        let inputs = order(None).poseidon_inputs().unwrap();
+        assert_eq!(inputs.len(), 11);
+        assert_eq!(inputs[2], BigInt::from(11087));
+        assert_eq!(inputs[9], BigInt::from(0));
+        assert_eq!(inputs[10], BigInt::from(0));
+    }
+
+    #[test]
+    fn order_hash_and_signature_known_answer() {
+        let l2_key = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+        let order = order(None);
+
+        assert_eq!(
+            hash_to_hex(&order.hash().unwrap()),
+            "0x12147e2d3cc246fc2186113491aaae5d223e1ddd86368d32bdf4f668c90dfdf8"
+        );
+        assert_eq!(order.sign(l2_key).unwrap(), "0x1c99062a1f041fcbacf9c34605cb88a08235c35fc97b18874549d9ed2f9cff8117b5f6bfdd386e6f6421039733a920424e446a408acdea2a1156d38d0b8d46fc025839f5dbfe8a00e6458fb027cc5e6d85abb4fed3cb995d66597e66c6d6cf66");
+    }
+
+    #[test]
+    fn v3_1_order_known_answer() {
+        let v3_1 = ProtocolVersion::V3_1 { exchange_id: 2 };
+        let open = order(None);
+        let inputs = open.poseidon_inputs_for(v3_1).unwrap();
+        assert_eq!(inputs.len(), 13);
+        assert_eq!(inputs[0], BigInt::from(2));
+        assert_eq!(
+            hash_to_hex(&open.hash_for(v3_1).unwrap()),
+            "0x0457f1c575f791df620298c08458708579072fd8b1d1e6fbb094192dc0b55c09"
+        );
+
+        assert!(matches!(
+            order(Some("0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359")).hash_for(v3_1),
+            Err(PayloadError::InvalidField("taker", _))
+        ));
+    }
+
+    #[test]
+    fn sign_on_checks_the_profile_exchange() {
+        let l2_key = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+        let mainnet = ChainProfile::mainnet();
+        let sepolia = ChainProfile::sepolia(
+            "0x2e76ebd1c7c0c8e7c2b875b6d505a260c525d25e",
+            "https://uat.example.org",
+        )
+        .unwrap();
+        let open = order(None);
+        assert_eq!(
+            open.sign_on(&mainnet, l2_key).unwrap(),
+            open.sign(l2_key).unwrap()
+        );
+        assert!(matches!(
+            open.sign_on(&sepolia, l2_key),
+            Err(PayloadError::InvalidField("exchange", _))
+        ));
+        let block_trade =
+            BlockTradeOrder::new(order(Some("0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359")))
+                .unwrap();
+        assert!(block_trade.sign_on(&mainnet, l2_key).is_ok());
+        assert!(block_trade.sign_on(&sepolia, l2_key).is_err());
+    }
+
+    #[test]
+    fn block_trade_requires_taker() {
+        assert!(BlockTradeOrder::new(order(None)).is_err());
+        assert!(BlockTradeOrder::new(order(Some("0x1234"))).is_err());
+
+        let zero = "0x0000000000000000000000000000000000000000";
+        assert!(matches!(
+            BlockTradeOrder::new(order(Some(zero))),
+            Err(PayloadError::InvalidField("taker", _))
+        ));
+
+        let btrade =
+            BlockTradeOrder::new(order(Some("0x5e8a2c2d6fa8bc7a8e5d5a054b6bd1c0f7a4fe2c")))
+                .unwrap();
+        assert_eq!(
+            btrade.poseidon_inputs().unwrap()[10],
+            BigInt::parse_bytes(b"5e8a2c2d6fa8bc7a8e5d5a054b6bd1c0f7a4fe2c", 16).unwrap()
+        );
+        assert_eq!(
+            hash_to_hex(&btrade.hash().unwrap()),
+            "0x03b666e25ab333e50302594000481b1f74fbb2c242fe321a0a181e005cb7dcc6"
+        );
+        let l2_key = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+        assert_eq!(btrade.sign(l2_key).unwrap(), "0x2e29602179fc42db9e391a4c18684480a56ffdff5bcff1a38a43c528e88c711a0e313cd9b065e6bd26ba7c8dd9b27ec980722f6c2e1a4a2e10aa34ecdfb2c2d014cde38b5bbfe8a7802552b03259ef51e90a9b1acef211de1b54c2db706fb04b");
+    }
+
+    #[test]
+    fn order_rejects_out_of_range_fields() {
+        let mut order = order(None);
+        order.max_fee_bips = 64;
+        assert!(matches!(
+            order.hash(),
+            Err(PayloadError::OutOfRange {
+                field: "maxFeeBips",
+                bits: 6,
+                ..
+            })
+        ));
+
+        order.max_fee_bips = 63;
+        order.buy_volume = (BigInt::from(1) << 96) - 1;
+        assert!(order.hash().is_ok());
+        order.buy_volume += 1;
+        assert!(order.hash().is_err());
+    }
+}
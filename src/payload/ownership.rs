@@ -0,0 +1,93 @@
+// Proof of L2 key ownership.
+//
+// A service can authenticate a client by sending it a random challenge and
+// checking an EdDSA signature over it. The signed message must never collide with
+// an exchange payload, otherwise a malicious service could present a transfer
+// preimage as "challenge" and obtain a valid signature for it. The challenge is
+// therefore hashed with a dedicated Poseidon instance whose round constants are
+// generated from its own seed:
+//
+//     M = poseidon_ownership(sha256(challenge) mod SNARK_SCALAR_FIELD)
+//
+// with t = 2, F = 6, P = 53 and the seed OWNERSHIP_SEED. No protocol hash uses
+// this seed, so M is unrelated to any order, transfer or API request hash.
+
+use super::field_elements::{hash_to_field_elements, HashRule};
+use super::sign_hash;
+use crate::poseidon::{eddsa::verify_eddsa_hex, field::SNARK_SCALAR_FIELD, permutation::Poseidon};
+use crate::util::errors::{PayloadError, SignatureError};
+use num_bigint::BigInt;
+use sha2::{Digest, Sha256};
+
+pub const OWNERSHIP_SEED: &str = "loopring_sign_ownership_proof_v1";
+
+lazy_static! {
+    static ref OWNERSHIP_POSEIDON: Poseidon = Poseidon::new(
+        SNARK_SCALAR_FIELD.clone(),
+        2,
+        6,
+        53,
+        OWNERSHIP_SEED.to_string(),
+        BigInt::from(5),
+        None,
+        None,
+        128,
+    );
+}
+
+pub fn ownership_message(challenge: &[u8]) -> BigInt {
+    let digest: [u8; 32] = Sha256::digest(challenge).into();
+    // A single input always fits into a width-2 instance
+    OWNERSHIP_POSEIDON
+        .hash(hash_to_field_elements(&digest, HashRule::Reduce))
+        .unwrap()
+}
+
+pub fn prove_ownership(hex_private_key: &str, challenge: &[u8]) -> Result<String, PayloadError> {
+    sign_hash(ownership_message(challenge), hex_private_key)
+}
+
+// `pubkey_x_hex` and `pubkey_y_hex` as reported by GET /api/v3/account
+pub fn verify_ownership(
+    pubkey_x_hex: &str,
+    pubkey_y_hex: &str,
+    challenge: &[u8],
+    proof: &str,
+) -> Result<bool, SignatureError> {
+    verify_eddsa_hex(
+        proof,
+        pubkey_x_hex,
+        pubkey_y_hex,
+        &ownership_message(challenge),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poseidon::eddsa::sign_poseidon_inputs;
+    use num_bigint::Sign;
+
+    const L2_KEY: &str = "0x001fa186947c8c644cd11078f67e0bb21656432f55c4df76997b6acab2abda7f";
+    const PUBKEY_X: &str = "0x29d178cdd6a40cd900c41565b6057a1d12c00a8c41ad367e2fe0100aab00fbe3";
+    const PUBKEY_Y: &str = "0x29e339a045af33d5729eab3b64c617e6a78dcfd0988f95f215d443d77a864b9c";
+
+    #[test]
+    fn ownership_proof_round_trip() {
+        let proof = prove_ownership(L2_KEY, b"nonce-1234").unwrap();
+        assert!(verify_ownership(PUBKEY_X, PUBKEY_Y, b"nonce-1234", &proof).unwrap());
+        assert!(!verify_ownership(PUBKEY_X, PUBKEY_Y, b"nonce-1235", &proof).unwrap());
+    }
+
+    #[test]
+    fn ownership_hash_is_domain_separated() {
+        // The same single input hashed with the protocol parameters differs
+        let digest =
+            BigInt::from_bytes_be(Sign::Plus, &Sha256::digest(b"challenge")) % &*SNARK_SCALAR_FIELD;
+        let protocol_signature = sign_poseidon_inputs(vec![digest], L2_KEY).unwrap();
+        assert_ne!(
+            prove_ownership(L2_KEY, b"challenge").unwrap(),
+            protocol_signature
+        );
+    }
+}
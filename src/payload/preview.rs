@@ -0,0 +1,307 @@
+/*
+Human-readable previews of payloads about to be signed.
+
+Wallets and approval dialogs should show what a signature authorizes in the
+user's terms. A preview is derived from the same struct that is hashed and
+carries the hash, so the dialog and the signature cannot disagree:
+
+    let preview = SigningPreview::for_transfer(&transfer, &tokens, &SystemClock)?;
+    show(&preview.to_text());       // or preview.to_json() across FFI / wasm
+
+Amounts are rendered with the decimals and symbol from a TokenRegistry (e.g.
+the one `client::LoopringClient::tokens` returns). A token the registry does
+not know is shown as a raw volume with its token id, plus a warning, because
+the user cannot check it; so is a payload that has already expired.
+*/
+
+use super::file::{order_from_value, transfer_from_value};
+use super::hash_to_hex;
+use super::order::Order;
+use super::token::TokenRegistry;
+use super::transfer::Transfer;
+use crate::util::errors::PayloadError;
+use crate::util::time::{format_utc, Clock};
+use num_bigint::BigInt;
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreviewAmount {
+    // "Send", "Max fee", "Sell", "Buy"
+    pub label: &'static str,
+    pub token_id: u32,
+    pub symbol: Option<String>,
+    pub volume: BigInt,
+    // e.g. "1.5 USDC"
+    pub display: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SigningPreview {
+    // "transfer" or "order"
+    pub payload_type: &'static str,
+    pub account_id: u32,
+    pub exchange: String,
+    pub amounts: Vec<PreviewAmount>,
+    // Payee of a transfer, taker of an order (None: anyone may fill it)
+    pub destination: Option<String>,
+    // Further lines, e.g. ("Max fee", "0.60% of the bought amount")
+    pub details: Vec<(&'static str, String)>,
+    pub valid_until: u32,
+    // validUntil as ISO 8601 UTC
+    pub expires: String,
+    pub hash: String,
+    pub warnings: Vec<String>,
+}
+
+fn amount(
+    label: &'static str,
+    token_id: u32,
+    volume: &BigInt,
+    tokens: &TokenRegistry,
+    warnings: &mut Vec<String>,
+) -> PreviewAmount {
+    let token = tokens.by_id(token_id);
+    let display = match token {
+        Some(token) => format!("{} {}", token.format_volume(volume), token.symbol),
+        None => {
+            warnings.push(format!(
+                "Unknown token #{}: the amount is shown in its smallest unit",
+                token_id
+            ));
+            format!("{} (token #{})", volume, token_id)
+        }
+    };
+    PreviewAmount {
+        label,
+        token_id,
+        symbol: token.map(|token| token.symbol.clone()),
+        volume: volume.clone(),
+        display,
+    }
+}
+
+fn expiry_warning(valid_until: u32, clock: &impl Clock, warnings: &mut Vec<String>) {
+    if u64::from(valid_until) <= clock.now() {
+        warnings.push("Already expired: the relayer will reject it".to_string());
+    }
+}
+
+impl SigningPreview {
+    pub fn for_transfer(
+        transfer: &Transfer,
+        tokens: &TokenRegistry,
+        clock: &impl Clock,
+    ) -> Result<Self, PayloadError> {
+        let hash = hash_to_hex(&transfer.hash()?);
+        let mut warnings = Vec::new();
+        let amounts = vec![
+            amount(
+                "Send",
+                transfer.token_id,
+                &transfer.amount,
+                tokens,
+                &mut warnings,
+            ),
+            amount(
+                "Max fee",
+                transfer.fee_token_id,
+                &transfer.max_fee,
+                tokens,
+                &mut warnings,
+            ),
+        ];
+        let mut details = Vec::new();
+        if transfer.payee_id != 0 {
+            details.push(("Payee account", transfer.payee_id.to_string()));
+        }
+        expiry_warning(transfer.valid_until, clock, &mut warnings);
+        Ok(SigningPreview {
+            payload_type: "transfer",
+            account_id: transfer.payer_id,
+            exchange: transfer.exchange.clone(),
+            amounts,
+            destination: Some(transfer.payee_addr.clone()),
+            details,
+            valid_until: transfer.valid_until,
+            expires: format_utc(transfer.valid_until.into()),
+            hash,
+            warnings,
+        })
+    }
+
+    pub fn for_order(
+        order: &Order,
+        tokens: &TokenRegistry,
+        clock: &impl Clock,
+    ) -> Result<Self, PayloadError> {
+        let hash = hash_to_hex(&order.hash()?);
+        let mut warnings = Vec::new();
+        let amounts = vec![
+            amount(
+                "Sell",
+                order.sell_token_id,
+                &order.sell_volume,
+                tokens,
+                &mut warnings,
+            ),
+            amount(
+                "Buy",
+                order.buy_token_id,
+                &order.buy_volume,
+                tokens,
+                &mut warnings,
+            ),
+        ];
+        let details = vec![
+            (
+                "Max fee",
+                format!(
+                    "{}.{:02}% of the bought amount",
+                    order.max_fee_bips / 100,
+                    order.max_fee_bips % 100
+                ),
+            ),
+            (
+                "Fills",
+                if order.fill_amount_b_or_s {
+                    "until the buy amount is bought"
+                } else {
+                    "until the sell amount is sold"
+                }
+                .to_string(),
+            ),
+        ];
+        expiry_warning(order.valid_until, clock, &mut warnings);
+        Ok(SigningPreview {
+            payload_type: "order",
+            account_id: order.account_id,
+            exchange: order.exchange.clone(),
+            amounts,
+            destination: order.taker.clone(),
+            details,
+            valid_until: order.valid_until,
+            expires: format_utc(order.valid_until.into()),
+            hash,
+            warnings,
+        })
+    }
+
+    // A payload in the payload-file format (see payload::file)
+    pub fn for_value(
+        payload: &Value,
+        tokens: &TokenRegistry,
+        clock: &impl Clock,
+    ) -> Result<Self, PayloadError> {
+        match payload.get("type").and_then(Value::as_str) {
+            Some("transfer") => Self::for_transfer(&transfer_from_value(payload)?, tokens, clock),
+            Some("order") => Self::for_order(&order_from_value(payload)?, tokens, clock),
+            Some(other) => Err(PayloadError::InvalidField("type", other.to_string())),
+            None => Err(PayloadError::MissingField("type")),
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "type": self.payload_type,
+            "accountId": self.account_id,
+            "exchange": self.exchange,
+            "amounts": self.amounts.iter().map(|a| json!({
+                "label": a.label,
+                "tokenId": a.token_id,
+                "symbol": a.symbol,
+                "volume": a.volume.to_string(),
+                "display": a.display,
+            })).collect::<Vec<_>>(),
+            "destination": self.destination,
+            "details": self.details.iter().map(|(label, value)| json!({
+                "label": label,
+                "value": value,
+            })).collect::<Vec<_>>(),
+            "validUntil": self.valid_until,
+            "expires": self.expires,
+            "hash": self.hash,
+            "warnings": self.warnings,
+        })
+    }
+
+    // One "Label: value" line per item, for plain-text dialogs and logs
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![format!(
+            "Sign {} for account {}",
+            self.payload_type, self.account_id
+        )];
+        for amount in &self.amounts {
+            lines.push(format!("{}: {}", amount.label, amount.display));
+        }
+        let destination = match (self.payload_type, &self.destination) {
+            ("order", None) => "anyone".to_string(),
+            (_, destination) => destination.clone().unwrap_or_default(),
+        };
+        let destination_label = if self.payload_type == "order" {
+            "Taker"
+        } else {
+            "To"
+        };
+        lines.push(format!("{}: {}", destination_label, destination));
+        for (label, value) in &self.details {
+            lines.push(format!("{}: {}", label, value));
+        }
+        lines.push(format!("Expires: {}", self.expires));
+        lines.push(format!("Hash: {}", self.hash));
+        for warning in &self.warnings {
+            lines.push(format!("Warning: {}", warning));
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload::order::tests::order;
+    use crate::payload::token::TokenInfo;
+    use crate::payload::transfer::tests::transfer;
+    use crate::util::time::FixedClock;
+
+    fn tokens() -> TokenRegistry {
+        TokenRegistry::with_tokens([TokenInfo::new(0, "ETH", 18), TokenInfo::new(1, "LRC", 18)])
+    }
+
+    #[test]
+    fn previews_a_transfer() {
+        let transfer = transfer();
+        let preview =
+            SigningPreview::for_transfer(&transfer, &tokens(), &FixedClock(1_600_000_000)).unwrap();
+        assert_eq!(preview.hash, hash_to_hex(&transfer.hash().unwrap()));
+        assert_eq!(preview.amounts[0].display, "1 LRC");
+        assert_eq!(preview.amounts[1].display, "0.0001 ETH");
+        assert!(preview.warnings.is_empty());
+
+        let text = preview.to_text();
+        assert!(text.starts_with("Sign transfer for account 11087\nSend: 1 LRC\n"));
+        assert!(text.contains(&format!("To: {}\n", transfer.payee_addr)));
+        assert!(text.contains("Expires: 2023-11-14T22:13:20Z\n"));
+
+        let json = preview.to_json();
+        assert_eq!(json["amounts"][0]["volume"], "1000000000000000000");
+        assert_eq!(json["type"], "transfer");
+    }
+
+    #[test]
+    fn previews_orders_with_warnings() {
+        let order = order(None);
+        let only_eth = TokenRegistry::with_tokens([TokenInfo::new(0, "ETH", 18)]);
+        let preview =
+            SigningPreview::for_order(&order, &only_eth, &FixedClock(1_800_000_000)).unwrap();
+        assert_eq!(preview.amounts[1].symbol, None);
+        assert_eq!(preview.warnings.len(), 2);
+        assert!(preview.warnings[1].starts_with("Already expired"));
+
+        let text = preview.to_text();
+        assert!(text.contains("Taker: anyone\n"));
+        assert!(text.contains("Max fee: 0.60% of the bought amount\n"));
+
+        let value = json!({"type": "deposit"});
+        assert!(SigningPreview::for_value(&value, &only_eth, &FixedClock(0)).is_err());
+    }
+}
@@ -0,0 +1,240 @@
+// Red packets (luckyToken).
+//
+// There is no luckyToken preimage. The API's send request
+// (POST /api/v3/luckyToken/sendLuckyToken) embeds the funding transfer, from the
+// sender to the red packet agent account, as its `luckyToken` object, and the only
+// EdDSA signature in the request is that transfer's `eddsaSignature`: the regular
+// transfer preimage, Poseidon(t = 13, F = 6, P = 53). The packet parameters (split
+// mode, scope, number of shares, claim window, memo) sit next to `luckyToken` in
+// the request body, are authorized by the API key alone and are not signed by
+// anything here. `request_body` builds that body around a transfer signature.
+
+use super::transfer::Transfer;
+use super::version::{ProtocolVersion, VersionedPayload};
+use super::{parse_address, sign_hash};
+use crate::util::errors::PayloadError;
+use num_bigint::BigInt;
+use serde_json::{json, Value};
+
+pub enum RedPacketPartition {
+    // Every claimer receives the same share
+    Average,
+    // Shares are randomized
+    Random,
+}
+
+pub enum RedPacketScope {
+    Public,
+    // Only claimable through the shared link/code
+    Private,
+}
+
+impl RedPacketPartition {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RedPacketPartition::Average => "AVERAGE",
+            RedPacketPartition::Random => "RANDOM",
+        }
+    }
+}
+
+impl RedPacketScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RedPacketScope::Public => "PUBLIC",
+            RedPacketScope::Private => "PRIVATE",
+        }
+    }
+}
+
+pub struct RedPacket {
+    // The funding transfer, with the red packet agent as payee
+    transfer: Transfer,
+    pub partition: RedPacketPartition,
+    pub scope: RedPacketScope,
+    pub numbers: u32,
+    pub memo: String,
+    // Claim window of the packet, independent of the transfer's validUntil
+    pub valid_since: u32,
+    pub valid_until: u32,
+}
+
+impl VersionedPayload for RedPacket {
+    fn poseidon_inputs_for(&self, version: ProtocolVersion) -> Result<Vec<BigInt>, PayloadError> {
+        match version {
+            ProtocolVersion::V3_1 { .. } => Err(PayloadError::UnsupportedVersion("RedPacket")),
+            ProtocolVersion::V3_6 => self.transfer.poseidon_inputs_for(version),
+        }
+    }
+}
+
+impl RedPacket {
+    pub fn new(
+        transfer: Transfer,
+        partition: RedPacketPartition,
+        scope: RedPacketScope,
+        numbers: u32,
+        memo: String,
+        valid_since: u32,
+        valid_until: u32,
+    ) -> Result<Self, PayloadError> {
+        parse_address(&transfer.payee_addr)?;
+        if numbers == 0 {
+            return Err(PayloadError::InvalidField(
+                "numbers",
+                "a red packet needs at least one share".to_string(),
+            ));
+        }
+        // Each share has to receive at least one unit of the token
+        if transfer.amount < BigInt::from(numbers) {
+            return Err(PayloadError::InvalidField(
+                "amount",
+                format!(
+                    "{} cannot be split into {} shares",
+                    transfer.amount, numbers
+                ),
+            ));
+        }
+        if valid_until <= valid_since {
+            return Err(PayloadError::InvalidField(
+                "validUntil",
+                "the claim window is empty".to_string(),
+            ));
+        }
+        Ok(RedPacket {
+            transfer,
+            partition,
+            scope,
+            numbers,
+            memo,
+            valid_since,
+            valid_until,
+        })
+    }
+
+    pub fn transfer(&self) -> &Transfer {
+        &self.transfer
+    }
+
+    pub fn poseidon_inputs(&self) -> Result<Vec<BigInt>, PayloadError> {
+        self.transfer.poseidon_inputs()
+    }
+
+    pub fn hash(&self) -> Result<BigInt, PayloadError> {
+        self.hash_for(ProtocolVersion::CURRENT)
+    }
+
+    pub fn sign(&self, hex_private_key: &str) -> Result<String, PayloadError> {
+        sign_hash(self.hash()?, hex_private_key)
+    }
+
+    // The sendLuckyToken request body, with `eddsa_signature` (from `sign`) as the
+    // signature of the embedded transfer
+    pub fn request_body(&self, eddsa_signature: &str) -> Value {
+        let transfer = &self.transfer;
+        json!({
+            "luckyToken": {
+                "exchange": transfer.exchange,
+                "payerId": transfer.payer_id,
+                "payerAddr": transfer.payer_addr,
+                "payeeId": transfer.payee_id,
+                "payeeAddr": transfer.payee_addr,
+                "token": {"tokenId": transfer.token_id, "volume": transfer.amount.to_string()},
+                "maxFee": {"tokenId": transfer.fee_token_id, "volume": transfer.max_fee.to_string()},
+                "storageId": transfer.storage_id,
+                "validUntil": transfer.valid_until,
+                "eddsaSignature": eddsa_signature,
+            },
+            "type": {"partition": self.partition.as_str(), "scope": self.scope.as_str()},
+            "numbers": self.numbers,
+            "memo": self.memo,
+            "validSince": self.valid_since,
+            "validUntil": self.valid_until,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload::file::transfer_from_value;
+    use crate::payload::transfer::tests::transfer;
+
+    fn red_packet(numbers: u32) -> Result<RedPacket, PayloadError> {
+        RedPacket::new(
+            transfer(),
+            RedPacketPartition::Random,
+            RedPacketScope::Public,
+            numbers,
+            "Best wishes".to_string(),
+            1690000000,
+            1690086400,
+        )
+    }
+
+    #[test]
+    fn red_packet_signs_funding_transfer() {
+        let l2_key = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+        let red_packet = red_packet(10).unwrap();
+
+        assert_eq!(red_packet.hash().unwrap(), transfer().hash().unwrap());
+        assert_eq!(
+            red_packet.sign(l2_key).unwrap(),
+            transfer().sign(l2_key).unwrap()
+        );
+        assert_eq!(red_packet.partition.as_str(), "RANDOM");
+    }
+
+    // A send request with the API's field names. Only the `luckyToken` transfer
+    // is covered by the signature.
+    const SEND_REQUEST: &str = r#"{
+        "luckyToken": {
+            "exchange": "0x0BABA1Ad5bE3a5C0a66E7ac838a129Bf948f1eA4",
+            "payerId": 11087, "payerAddr": "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "payeeId": 0, "payeeAddr": "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "token": {"tokenId": 1, "volume": "1000000000000000000"},
+            "maxFee": {"tokenId": 0, "volume": "100000000000000"},
+            "storageId": 5, "validUntil": 1700000000,
+            "eddsaSignature": "0x16b40915f18ab7230e9960fa01297439f53b84c002491c20e3f73c5b2d468cf20979e2bda45dc57aa26681ef08f1a29d3e435193fed42a74b58bcd4f6f563a420148ade26cdbc00e9c9ec26e4c956f8b89ee6f0f8fa1460504a40e01750747c9"
+        },
+        "type": {"partition": "RANDOM", "scope": "PUBLIC"},
+        "numbers": 10, "memo": "Best wishes",
+        "validSince": 1690000000, "validUntil": 1690086400
+    }"#;
+
+    #[test]
+    fn request_body_signs_only_the_embedded_transfer() {
+        let l2_key = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+        let request: Value = serde_json::from_str(SEND_REQUEST).unwrap();
+        let red_packet = red_packet(10).unwrap();
+        let signature = red_packet.sign(l2_key).unwrap();
+        assert_eq!(red_packet.request_body(&signature), request);
+
+        // The signature is the embedded transfer's, so the packet parameters can
+        // change without it changing
+        let embedded = transfer_from_value(&request["luckyToken"]).unwrap();
+        assert_eq!(embedded.sign(l2_key).unwrap(), signature);
+        let mut other = red_packet;
+        other.numbers = 2;
+        other.memo = String::new();
+        assert_eq!(other.sign(l2_key).unwrap(), signature);
+    }
+
+    #[test]
+    fn red_packet_validates_shares() {
+        assert!(red_packet(0).is_err());
+
+        let mut small = transfer();
+        small.amount = BigInt::from(3);
+        let result = RedPacket::new(
+            small,
+            RedPacketPartition::Average,
+            RedPacketScope::Private,
+            4,
+            String::new(),
+            0,
+            1,
+        );
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,202 @@
+/*
+Signed payloads as the API returns and the relayer stores them.
+
+Orders, transfers and withdrawals come back with the request fields they were
+submitted with, the EdDSA signature in "eddsaSignature" and usually the payload
+hash the relayer computed in "hash". Order, Transfer and Withdrawal deserialize
+from those objects (the field names of payload::file), and SignedPayload<P>
+keeps the signature and the reported hash next to the payload.
+
+`re_hash_and_verify` recomputes the Poseidon hash from the fields, so it does
+not trust the reported hash, and accepts only if
+
+ - the reported hash, when there is one, is the recomputed one,
+ - R is in the prime-order subgroup, and
+ - the signature verifies against the expected key.
+
+Fields the preimage does not cover (payerAddr, owner, ...) are parsed but not
+verified by the signature.
+*/
+
+use super::file::{order_from_value, transfer_from_value, withdrawal_from_value};
+use super::version::{ProtocolVersion, VersionedPayload};
+use super::{order::Order, transfer::Transfer, withdrawal::Withdrawal};
+use crate::keygen::public_key::L2PublicKey;
+use crate::poseidon::eddsa::Signature;
+use crate::util::{errors::PayloadError, hex};
+use num_bigint::BigInt;
+use serde::de::{DeserializeOwned, Error};
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+macro_rules! deserialize_from_value {
+    ($($ty:ty => $parse:ident),* $(,)?) => {
+        $(
+            impl<'de> Deserialize<'de> for $ty {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    $parse(&Value::deserialize(deserializer)?).map_err(D::Error::custom)
+                }
+            }
+        )*
+    };
+}
+
+deserialize_from_value! {
+    Order => order_from_value,
+    Transfer => transfer_from_value,
+    Withdrawal => withdrawal_from_value,
+}
+
+pub struct SignedPayload<P> {
+    payload: P,
+    signature: Signature,
+    reported_hash: Option<BigInt>,
+}
+
+impl<P: VersionedPayload> SignedPayload<P> {
+    pub fn new(payload: P, signature: Signature, reported_hash: Option<BigInt>) -> Self {
+        SignedPayload {
+            payload,
+            signature,
+            reported_hash,
+        }
+    }
+
+    pub fn payload(&self) -> &P {
+        &self.payload
+    }
+
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    // The "hash" field of the response, if it had one
+    pub fn reported_hash(&self) -> Option<&BigInt> {
+        self.reported_hash.as_ref()
+    }
+
+    // Whether `public_key` signed exactly these fields; malformed fields are errors
+    pub fn re_hash_and_verify(&self, public_key: &L2PublicKey) -> Result<bool, PayloadError> {
+        let hash = self.payload.hash_for(ProtocolVersion::CURRENT)?;
+        if self
+            .reported_hash
+            .as_ref()
+            .is_some_and(|reported| *reported != hash)
+        {
+            return Ok(false);
+        }
+        if !self.signature.image_of_r().is_in_subgroup() {
+            return Ok(false);
+        }
+        Ok(public_key.verify(&self.signature, &hash))
+    }
+}
+
+fn signed_fields(value: &Value) -> Result<(Signature, Option<BigInt>), PayloadError> {
+    let signature = match value.get("eddsaSignature") {
+        Some(Value::String(sig)) => Signature::from_hex(sig)
+            .map_err(|e| PayloadError::InvalidField("eddsaSignature", e.to_string()))?,
+        Some(other) => {
+            return Err(PayloadError::InvalidField(
+                "eddsaSignature",
+                other.to_string(),
+            ))
+        }
+        None => return Err(PayloadError::MissingField("eddsaSignature")),
+    };
+    let reported_hash = match value.get("hash") {
+        Some(Value::String(hash)) => Some(
+            hex::decode_bigint(hash)
+                .map_err(|_| PayloadError::InvalidField("hash", hash.clone()))?,
+        ),
+        Some(other) => return Err(PayloadError::InvalidField("hash", other.to_string())),
+        None => None,
+    };
+    Ok((signature, reported_hash))
+}
+
+impl<'de, P: VersionedPayload + DeserializeOwned> Deserialize<'de> for SignedPayload<P> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        let (signature, reported_hash) = signed_fields(&value).map_err(D::Error::custom)?;
+        let payload = serde_json::from_value(value).map_err(D::Error::custom)?;
+        Ok(SignedPayload::new(payload, signature, reported_hash))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload::{hash_to_hex, transfer::tests::transfer, withdrawal::tests::withdrawal};
+    use serde_json::json;
+
+    const L2_KEY: &str = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+    const X: &str = "0x1526f1c9babcdda49b20df32653f0c3ae07eae4c5b9b456e7e2c8fd7d79ce3b0";
+    const Y: &str = "0x13d161bf5df6053b499a8cc1be2aece9b2b6768d88c05687e1edc40e52bf44b9";
+
+    fn key() -> L2PublicKey {
+        L2PublicKey::from_coordinates(X, Y).unwrap()
+    }
+
+    fn stored_transfer() -> Value {
+        let transfer = transfer();
+        json!({
+            "exchange": transfer.exchange, "payerId": transfer.payer_id,
+            "payerAddr": transfer.payer_addr, "payeeId": transfer.payee_id,
+            "payeeAddr": transfer.payee_addr,
+            "token": {"tokenId": transfer.token_id, "volume": transfer.amount.to_string()},
+            "maxFee": {"tokenId": transfer.fee_token_id, "volume": transfer.max_fee.to_string()},
+            "validUntil": transfer.valid_until, "storageId": transfer.storage_id,
+            "hash": hash_to_hex(&transfer.hash().unwrap()),
+            "eddsaSignature": transfer.sign(L2_KEY).unwrap(),
+        })
+    }
+
+    #[test]
+    fn stored_payloads_verify() {
+        let stored: SignedPayload<Transfer> = serde_json::from_value(stored_transfer()).unwrap();
+        assert!(stored.re_hash_and_verify(&key()).unwrap());
+        assert_eq!(stored.payload().payer_id, 11087);
+
+        let other_key =
+            L2PublicKey::try_from(&crate::poseidon::eddsa::KeyPair::new(BigInt::from(5))).unwrap();
+        assert!(!stored.re_hash_and_verify(&other_key).unwrap());
+
+        let withdrawal = withdrawal();
+        let stored = json!({
+            "exchange": withdrawal.exchange, "accountId": withdrawal.account_id,
+            "owner": withdrawal.owner,
+            "token": {"tokenId": withdrawal.token_id, "volume": withdrawal.amount.to_string()},
+            "maxFee": {"tokenId": withdrawal.fee_token_id, "volume": withdrawal.max_fee.to_string()},
+            "to": withdrawal.to, "extraData": "", "minGas": 0, "validUntil": withdrawal.valid_until,
+            "storageId": withdrawal.storage_id,
+            "eddsaSignature": withdrawal.sign(L2_KEY).unwrap(),
+        });
+        let stored: SignedPayload<Withdrawal> = serde_json::from_value(stored).unwrap();
+        assert!(stored.reported_hash().is_none());
+        assert!(stored.re_hash_and_verify(&key()).unwrap());
+    }
+
+    #[test]
+    fn tampered_payloads_fail() {
+        // A changed amount no longer matches the signature, nor the reported hash
+        let mut tampered = stored_transfer();
+        tampered["token"]["volume"] = json!("2000000000000000000");
+        let stored: SignedPayload<Transfer> = serde_json::from_value(tampered.clone()).unwrap();
+        assert!(!stored.re_hash_and_verify(&key()).unwrap());
+        tampered.as_object_mut().unwrap().remove("hash");
+        let stored: SignedPayload<Transfer> = serde_json::from_value(tampered).unwrap();
+        assert!(!stored.re_hash_and_verify(&key()).unwrap());
+
+        let mut unsigned = stored_transfer();
+        unsigned.as_object_mut().unwrap().remove("eddsaSignature");
+        let error = serde_json::from_value::<SignedPayload<Transfer>>(unsigned)
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "Missing field: eddsaSignature");
+        let error = serde_json::from_value::<Order>(json!({"exchange": "0x0"}))
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "Missing field: storageId");
+    }
+}
@@ -0,0 +1,199 @@
+// Token metadata and conversion from human-readable amounts to on-chain volumes.
+//
+// Payloads carry token amounts as integer volumes in the token's smallest unit
+// (e.g. wei for ETH), and the circuit limits them to 96 bits. The conversion is
+// done on the decimal string itself, never through floating point, so "1.5 ETH"
+// always becomes exactly 1500000000000000000.
+
+use crate::util::errors::TokenError;
+use num_bigint::BigInt;
+use num_traits::{One, Zero};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+pub struct TokenInfo {
+    pub token_id: u32,
+    pub symbol: String,
+    pub decimals: u32,
+}
+
+impl TokenInfo {
+    pub fn new(token_id: u32, symbol: &str, decimals: u32) -> Self {
+        TokenInfo {
+            token_id,
+            symbol: symbol.to_string(),
+            decimals,
+        }
+    }
+
+    // Converts a decimal string like "1.5" into the token's integer volume
+    pub fn to_volume(&self, amount: &str) -> Result<BigInt, TokenError> {
+        let invalid = || TokenError::InvalidAmount(amount.to_string());
+
+        let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+        if whole.is_empty() && fraction.is_empty() {
+            return Err(invalid());
+        }
+        if !whole
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+
+        // Extra fractional digits are only acceptable when they are zeros
+        let decimals = self.decimals as usize;
+        let (fraction, excess) = fraction.split_at(fraction.len().min(decimals));
+        if excess.chars().any(|c| c != '0') {
+            return Err(TokenError::TooManyDecimals(
+                amount.to_string(),
+                self.decimals,
+            ));
+        }
+
+        let digits = format!("{}{:0<width$}", whole, fraction, width = decimals);
+        let volume = digits.parse::<BigInt>().unwrap_or_else(|_| BigInt::zero());
+        if volume >= BigInt::one() << 96 {
+            return Err(TokenError::VolumeOutOfRange(amount.to_string()));
+        }
+        Ok(volume)
+    }
+
+    // Renders a volume back into a decimal string without trailing zeros
+    pub fn format_volume(&self, volume: &BigInt) -> String {
+        let digits = format!("{:0>width$}", volume, width = self.decimals as usize + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - self.decimals as usize);
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.is_empty() {
+            whole.to_string()
+        } else {
+            format!("{}.{}", whole, fraction)
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct TokenRegistry {
+    tokens: HashMap<u32, TokenInfo>,
+    symbols: HashMap<String, u32>,
+}
+
+impl TokenRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_tokens(tokens: impl IntoIterator<Item = TokenInfo>) -> Self {
+        let mut registry = Self::new();
+        for token in tokens {
+            registry.register(token);
+        }
+        registry
+    }
+
+    // Registering a token id or symbol again replaces the previous entry
+    pub fn register(&mut self, token: TokenInfo) {
+        self.symbols
+            .insert(token.symbol.to_uppercase(), token.token_id);
+        self.tokens.insert(token.token_id, token);
+    }
+
+    pub fn by_id(&self, token_id: u32) -> Option<&TokenInfo> {
+        self.tokens.get(&token_id)
+    }
+
+    // Symbols are matched case-insensitively
+    pub fn by_symbol(&self, symbol: &str) -> Option<&TokenInfo> {
+        self.symbols
+            .get(&symbol.to_uppercase())
+            .and_then(|token_id| self.tokens.get(token_id))
+    }
+
+    pub fn volume(&self, amount: &str, symbol: &str) -> Result<BigInt, TokenError> {
+        self.by_symbol(symbol)
+            .ok_or_else(|| TokenError::UnknownToken(symbol.to_string()))?
+            .to_volume(amount)
+    }
+
+    // Parses amounts of the form "1.5 ETH" into (token info, volume)
+    pub fn parse_amount(&self, amount: &str) -> Result<(&TokenInfo, BigInt), TokenError> {
+        let (value, symbol) = amount
+            .trim()
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| TokenError::InvalidAmount(amount.to_string()))?;
+        let token = self
+            .by_symbol(symbol.trim())
+            .ok_or_else(|| TokenError::UnknownToken(symbol.trim().to_string()))?;
+        Ok((token, token.to_volume(value)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> TokenRegistry {
+        TokenRegistry::with_tokens([TokenInfo::new(0, "ETH", 18), TokenInfo::new(6, "USDC", 6)])
+    }
+
+    #[test]
+    fn human_amounts_to_volumes() {
+        let registry = registry();
+
+        let (token, volume) = registry.parse_amount("1.5 ETH").unwrap();
+        assert_eq!(token.token_id, 0);
+        assert_eq!(volume, "1500000000000000000".parse::<BigInt>().unwrap());
+
+        assert_eq!(
+            registry.volume("25", "usdc").unwrap(),
+            BigInt::from(25_000_000)
+        );
+        assert_eq!(
+            registry.volume(".5", "USDC").unwrap(),
+            BigInt::from(500_000)
+        );
+        assert_eq!(
+            registry.volume("0.1000000", "USDC").unwrap(),
+            BigInt::from(100_000)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_amounts() {
+        let registry = registry();
+
+        assert!(matches!(
+            registry.volume("0.0000001", "USDC"),
+            Err(TokenError::TooManyDecimals(_, 6))
+        ));
+        assert!(matches!(
+            registry.volume("1e18", "ETH"),
+            Err(TokenError::InvalidAmount(_))
+        ));
+        assert!(matches!(
+            registry.volume("-1", "ETH"),
+            Err(TokenError::InvalidAmount(_))
+        ));
+        assert!(matches!(
+            registry.volume("1", "DAI"),
+            Err(TokenError::UnknownToken(_))
+        ));
+        assert!(matches!(
+            registry.volume("100000000000", "ETH"),
+            Err(TokenError::VolumeOutOfRange(_))
+        ));
+    }
+
+    #[test]
+    fn format_volume_round_trip() {
+        let eth = TokenInfo::new(0, "ETH", 18);
+        let volume = eth.to_volume("1.5").unwrap();
+        assert_eq!(eth.format_volume(&volume), "1.5");
+        assert_eq!(eth.format_volume(&BigInt::from(1)), "0.000000000000000001");
+        assert_eq!(
+            TokenInfo::new(1, "X", 0).format_volume(&BigInt::from(7)),
+            "7"
+        );
+    }
+}
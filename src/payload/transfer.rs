@@ -0,0 +1,244 @@
+// L2 transfers.
+//
+// The signed transfer preimage is:
+//
+//     [exchange, payerId, payeeId, tokenId, amount, feeTokenId, maxFee,
+//      payeeAddr, dualAuthKeyX, dualAuthKeyY, validUntil, storageId]
+//
+// hashed with Poseidon(t = 13, F = 6, P = 53). The dual-auth key is not used for
+// regular transfers and is encoded as zeros, see DualAuthTransfer otherwise.
+//
+// The 3.1 exchange signed internal transfers as
+//
+//     [exchangeId, accountFromId, accountToId, tokenId, amount, feeTokenId,
+//      fee, label, nonce]
+//
+// with Poseidon(t = 10, F = 6, P = 53). The storage id takes the place of the
+// nonce and the label, which 3.6 dropped, is 0; validUntil and the payee address
+// are not part of that preimage.
+
+use super::version::{ProtocolVersion, VersionedPayload};
+use super::{check_range, parse_address, sign_hash, AMOUNT_BITS, FEE_TOKEN_ID_BITS};
+use crate::poseidon::jubjub::Point;
+use crate::util::chain::ChainProfile;
+use crate::util::errors::PayloadError;
+use num_bigint::BigInt;
+use num_traits::Zero;
+
+pub struct Transfer {
+    pub exchange: String,
+    pub payer_id: u32,
+    // Not part of the EdDSA preimage, but required by the API and by the L1
+    // (EIP-712) authorization of the same transfer
+    pub payer_addr: String,
+    // 0 when sending to an L1 address that has no L2 account yet
+    pub payee_id: u32,
+    pub payee_addr: String,
+    pub token_id: u32,
+    pub amount: BigInt,
+    pub fee_token_id: u32,
+    pub max_fee: BigInt,
+    pub valid_until: u32,
+    pub storage_id: u32,
+}
+
+impl VersionedPayload for Transfer {
+    fn poseidon_inputs_for(&self, version: ProtocolVersion) -> Result<Vec<BigInt>, PayloadError> {
+        match version {
+            ProtocolVersion::V3_1 { exchange_id } => self.poseidon_inputs_v3_1(exchange_id),
+            ProtocolVersion::V3_6 => self.poseidon_inputs_v3_6(None),
+        }
+    }
+}
+
+impl Transfer {
+    pub(crate) fn poseidon_inputs_with_dual_auth(
+        &self,
+        version: ProtocolVersion,
+        dual_auth_key: Option<&Point>,
+    ) -> Result<Vec<BigInt>, PayloadError> {
+        match version {
+            ProtocolVersion::V3_1 { .. } => {
+                Err(PayloadError::UnsupportedVersion("DualAuthTransfer"))
+            }
+            ProtocolVersion::V3_6 => self.poseidon_inputs_v3_6(dual_auth_key),
+        }
+    }
+
+    fn poseidon_inputs_v3_1(&self, exchange_id: u32) -> Result<Vec<BigInt>, PayloadError> {
+        check_range("token.volume", self.amount.clone(), AMOUNT_BITS)?;
+        check_range("maxFee.tokenId", self.fee_token_id, FEE_TOKEN_ID_BITS)?;
+        check_range("maxFee.volume", self.max_fee.clone(), AMOUNT_BITS)?;
+        Ok(vec![
+            BigInt::from(exchange_id),
+            BigInt::from(self.payer_id),
+            BigInt::from(self.payee_id),
+            BigInt::from(self.token_id),
+            self.amount.clone(),
+            BigInt::from(self.fee_token_id),
+            self.max_fee.clone(),
+            BigInt::zero(),
+            BigInt::from(self.storage_id),
+        ])
+    }
+
+    fn poseidon_inputs_v3_6(
+        &self,
+        dual_auth_key: Option<&Point>,
+    ) -> Result<Vec<BigInt>, PayloadError> {
+        let (dual_auth_x, dual_auth_y) = match dual_auth_key {
+            Some(key) => (key.x().to_bigint(), key.y().to_bigint()),
+            None => (BigInt::zero(), BigInt::zero()),
+        };
+        check_range("token.volume", self.amount.clone(), AMOUNT_BITS)?;
+        check_range("maxFee.tokenId", self.fee_token_id, FEE_TOKEN_ID_BITS)?;
+        check_range("maxFee.volume", self.max_fee.clone(), AMOUNT_BITS)?;
+        Ok(vec![
+            parse_address(&self.exchange)?,
+            BigInt::from(self.payer_id),
+            BigInt::from(self.payee_id),
+            BigInt::from(self.token_id),
+            self.amount.clone(),
+            BigInt::from(self.fee_token_id),
+            self.max_fee.clone(),
+            parse_address(&self.payee_addr)?,
+            dual_auth_x,
+            dual_auth_y,
+            BigInt::from(self.valid_until),
+            BigInt::from(self.storage_id),
+        ])
+    }
+
+    // The layout of the current protocol version
+    pub fn poseidon_inputs(&self) -> Result<Vec<BigInt>, PayloadError> {
+        self.poseidon_inputs_for(ProtocolVersion::CURRENT)
+    }
+
+    pub fn hash(&self) -> Result<BigInt, PayloadError> {
+        self.hash_for(ProtocolVersion::CURRENT)
+    }
+
+    pub fn sign(&self, hex_private_key: &str) -> Result<String, PayloadError> {
+        sign_hash(self.hash()?, hex_private_key)
+    }
+
+    // Same as `sign`, failing if the transfer was built for another exchange
+    // than the profile's
+    pub fn sign_on(
+        &self,
+        profile: &ChainProfile,
+        hex_private_key: &str,
+    ) -> Result<String, PayloadError> {
+        profile.check_exchange(&self.exchange)?;
+        self.sign(hex_private_key)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::payload::hash_to_hex;
+
+    pub(crate) fn transfer() -> Transfer {
+        Transfer {
+            exchange: "0x0BABA1Ad5bE3a5C0a66E7ac838a129Bf948f1eA4".to_string(),
+            payer_id: 11087,
+            payer_addr: "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed".to_string(),
+            payee_id: 0,
+            payee_addr: "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359".to_string(),
+            token_id: 1,
+            amount: BigInt::from(1_000_000_000_000_000_000u64),
+            fee_token_id: 0,
+            max_fee: BigInt::from(100_000_000_000_000u64),
+            valid_until: 1700000000,
+            storage_id: 5,
+        }
+    }
+
+    #[test]
+    fn transfer_inputs_follow_circuit_layout() {
+        let inputs = transfer().poseidon_inputs().unwrap();
+        assert_eq!(inputs.len(), 12);
+        assert_eq!(inputs[1], BigInt::from(11087));
+        assert_eq!(inputs[11], BigInt::from(5));
+    }
+
+    #[test]
+    fn transfer_hash_and_signature_known_answer() {
+        let l2_key = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+        let transfer = transfer();
+
+        assert_eq!(
+            hash_to_hex(&transfer.hash().unwrap()),
+            "0x19fe38192b4434883c2a897c7190d658d17eaa0121b84928de801428a1ed9b5a"
+        );
+        assert_eq!(transfer.sign(l2_key).unwrap(), "0x16b40915f18ab7230e9960fa01297439f53b84c002491c20e3f73c5b2d468cf20979e2bda45dc57aa26681ef08f1a29d3e435193fed42a74b58bcd4f6f563a420148ade26cdbc00e9c9ec26e4c956f8b89ee6f0f8fa1460504a40e01750747c9");
+    }
+
+    #[test]
+    fn v3_1_transfer_known_answer() {
+        let v3_1 = ProtocolVersion::V3_1 { exchange_id: 2 };
+        let transfer = transfer();
+        let inputs = transfer.poseidon_inputs_for(v3_1).unwrap();
+        assert_eq!(inputs.len(), 9);
+        assert_eq!(inputs[8], BigInt::from(5));
+        assert_eq!(
+            hash_to_hex(&transfer.hash_for(v3_1).unwrap()),
+            "0x0187891a206445724940fe540080df69ea911e052e57e701c9c57e2bf6740765"
+        );
+    }
+
+    #[test]
+    fn sign_on_checks_the_profile_exchange() {
+        let l2_key = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+        let taiko = ChainProfile::taiko(
+            "0x2e76ebd1c7c0c8e7c2b875b6d505a260c525d25e",
+            "https://taiko.example.org",
+        )
+        .unwrap();
+        assert_eq!(
+            transfer()
+                .sign_on(&ChainProfile::mainnet(), l2_key)
+                .unwrap(),
+            transfer().sign(l2_key).unwrap()
+        );
+        assert!(matches!(
+            transfer().sign_on(&taiko, l2_key),
+            Err(PayloadError::InvalidField("exchange", _))
+        ));
+    }
+
+    #[test]
+    fn transfer_rejects_bad_payee() {
+        let mut transfer = transfer();
+        transfer.payee_addr = "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d35".to_string();
+        assert!(transfer.hash().is_err());
+    }
+
+    #[test]
+    fn transfer_rejects_out_of_range_fields() {
+        let mut fee_in_nft = transfer();
+        fee_in_nft.fee_token_id = 1 << 16;
+        let mut transfer = transfer();
+        transfer.amount = BigInt::from(1u128 << 96);
+        assert!(matches!(
+            transfer.hash(),
+            Err(PayloadError::OutOfRange {
+                field: "token.volume",
+                bits: 96,
+                ..
+            })
+        ));
+
+        transfer.amount = BigInt::from(-1);
+        assert!(transfer.hash().is_err());
+
+        assert!(matches!(
+            fee_in_nft.hash(),
+            Err(PayloadError::OutOfRange {
+                field: "maxFee.tokenId",
+                ..
+            })
+        ));
+    }
+}
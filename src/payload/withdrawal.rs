@@ -0,0 +1,159 @@
+// Offchain withdrawals (POST /api/v3/user/withdrawals).
+//
+// The signed withdrawal preimage is:
+//
+//     [exchange, accountId, tokenId, amount, feeTokenId, maxFee,
+//      onchainDataHash, validUntil, storageId]
+//
+// hashed with Poseidon(t = 10, F = 6, P = 53). The L1 side of the withdrawal
+// (gas limit, recipient, data for the recipient) is committed to through
+//
+//     onchainDataHash = bytes20(keccak256(abi.encodePacked(minGas, to, extraData)))
+//
+// as WithdrawTransaction.hashOnchainData computes it in the exchange contract.
+
+use super::eip712::keccak256;
+use super::version::{ProtocolVersion, VersionedPayload};
+use super::{check_range, parse_address, sign_hash, AMOUNT_BITS, FEE_TOKEN_ID_BITS};
+use crate::util::{address, errors::PayloadError};
+use num_bigint::{BigInt, Sign};
+
+pub struct Withdrawal {
+    pub exchange: String,
+    pub account_id: u32,
+    // Not part of the EdDSA preimage, but required by the API
+    pub owner: String,
+    pub token_id: u32,
+    pub amount: BigInt,
+    pub fee_token_id: u32,
+    pub max_fee: BigInt,
+    // The L1 recipient
+    pub to: String,
+    pub extra_data: Vec<u8>,
+    // Gas the recipient is called with; 0 for a plain transfer
+    pub min_gas: BigInt,
+    pub valid_until: u32,
+    pub storage_id: u32,
+}
+
+impl VersionedPayload for Withdrawal {
+    fn poseidon_inputs_for(&self, version: ProtocolVersion) -> Result<Vec<BigInt>, PayloadError> {
+        match version {
+            ProtocolVersion::V3_1 { .. } => Err(PayloadError::UnsupportedVersion("Withdrawal")),
+            ProtocolVersion::V3_6 => self.poseidon_inputs_v3_6(),
+        }
+    }
+}
+
+impl Withdrawal {
+    fn poseidon_inputs_v3_6(&self) -> Result<Vec<BigInt>, PayloadError> {
+        check_range("token.volume", self.amount.clone(), AMOUNT_BITS)?;
+        check_range("maxFee.tokenId", self.fee_token_id, FEE_TOKEN_ID_BITS)?;
+        check_range("maxFee.volume", self.max_fee.clone(), AMOUNT_BITS)?;
+        Ok(vec![
+            parse_address(&self.exchange)?,
+            BigInt::from(self.account_id),
+            BigInt::from(self.token_id),
+            self.amount.clone(),
+            BigInt::from(self.fee_token_id),
+            self.max_fee.clone(),
+            self.onchain_data_hash()?,
+            BigInt::from(self.valid_until),
+            BigInt::from(self.storage_id),
+        ])
+    }
+
+    // The 160-bit commitment to minGas, to and extraData
+    pub fn onchain_data_hash(&self) -> Result<BigInt, PayloadError> {
+        check_range("minGas", self.min_gas.clone(), 256)?;
+        let to = address::parse_address(&self.to)
+            .map_err(|e| PayloadError::InvalidAddress(self.to.clone(), e))?;
+        let (_, min_gas) = self.min_gas.to_bytes_be();
+        let mut packed = vec![0u8; 32 - min_gas.len()];
+        packed.extend_from_slice(&min_gas);
+        packed.extend_from_slice(&to);
+        packed.extend_from_slice(&self.extra_data);
+        Ok(BigInt::from_bytes_be(Sign::Plus, &keccak256(&packed)[..20]))
+    }
+
+    // The layout of the current protocol version
+    pub fn poseidon_inputs(&self) -> Result<Vec<BigInt>, PayloadError> {
+        self.poseidon_inputs_for(ProtocolVersion::CURRENT)
+    }
+
+    pub fn hash(&self) -> Result<BigInt, PayloadError> {
+        self.hash_for(ProtocolVersion::CURRENT)
+    }
+
+    pub fn sign(&self, hex_private_key: &str) -> Result<String, PayloadError> {
+        sign_hash(self.hash()?, hex_private_key)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    pub(crate) fn withdrawal() -> Withdrawal {
+        Withdrawal {
+            exchange: "0x0BABA1Ad5bE3a5C0a66E7ac838a129Bf948f1eA4".to_string(),
+            account_id: 11087,
+            owner: "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed".to_string(),
+            token_id: 1,
+            amount: BigInt::from(1_000_000_000_000_000_000u64),
+            fee_token_id: 0,
+            max_fee: BigInt::from(100_000_000_000_000u64),
+            to: "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed".to_string(),
+            extra_data: Vec::new(),
+            min_gas: BigInt::from(0),
+            valid_until: 1700000000,
+            storage_id: 7,
+        }
+    }
+
+    #[test]
+    fn withdrawal_inputs_follow_circuit_layout() {
+        let withdrawal = withdrawal();
+        let inputs = withdrawal.poseidon_inputs().unwrap();
+        assert_eq!(inputs.len(), 9);
+        assert_eq!(inputs[1], BigInt::from(11087));
+        assert_eq!(inputs[6], withdrawal.onchain_data_hash().unwrap());
+        assert_eq!(inputs[8], BigInt::from(7));
+
+        // 32-byte gas limit, 20-byte recipient, then the data, unpadded
+        let packed = hex::decode(
+            "0000000000000000000000000000000000000000000000000000000000030d40\
+             5aaeb6053f3e94c9b9a09f33669435e7ef1beaed\
+             cafe",
+        )
+        .unwrap();
+        let expected = BigInt::from_bytes_be(Sign::Plus, &keccak256(&packed)[..20]);
+        let with_data = Withdrawal {
+            min_gas: BigInt::from_str("200000").unwrap(),
+            extra_data: vec![0xca, 0xfe],
+            ..withdrawal
+        };
+        assert_eq!(with_data.onchain_data_hash().unwrap(), expected);
+        assert!(expected.bits() <= 160);
+    }
+
+    #[test]
+    fn withdrawal_rejects_invalid_fields() {
+        let mut bad_recipient = withdrawal();
+        bad_recipient.to = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAe".to_string();
+        assert!(matches!(
+            bad_recipient.hash(),
+            Err(PayloadError::InvalidAddress(..))
+        ));
+        let mut too_large = withdrawal();
+        too_large.amount = BigInt::from(1u128 << 96);
+        assert!(matches!(
+            too_large.hash(),
+            Err(PayloadError::OutOfRange {
+                field: "token.volume",
+                ..
+            })
+        ));
+    }
+}
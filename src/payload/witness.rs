@@ -0,0 +1,160 @@
+/*
+Circuit witnesses for signed payloads.
+
+A Loopring circuit checks a payload signature from the same values this crate
+derives while signing: the ordered Poseidon preimage, its hash, the signature
+(R.x, R.y, S) and the signer's public key (A.x, A.y). A Witness holds them as
+field elements so they can be fed to a circuit without re-deriving the packing
+of each payload type:
+
+    let witness = Witness::for_payload(&transfer, &signer)?;
+    std::fs::write("input.json", witness.to_json().to_string())?;
+
+`to_json` writes every element as a decimal string, which is what circom and
+snarkjs expect in input files:
+
+    {
+      "inputs": ["1", "0", ...],
+      "hash": "...",
+      "signature": {"Rx": "...", "Ry": "...", "S": "..."},
+      "publicKey": {"Ax": "...", "Ay": "..."}
+    }
+
+Payloads defined through LoopringHash are exported with
+`Witness::new(payload.poseidon_inputs()?, &signer)`.
+*/
+
+use super::version::{ProtocolVersion, VersionedPayload};
+use crate::poseidon::eddsa::MessageHash;
+use crate::signer::Signer;
+use crate::util::errors::PayloadError;
+use num_bigint::BigInt;
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Witness {
+    pub inputs: Vec<BigInt>,
+    pub hash: BigInt,
+    pub r_x: BigInt,
+    pub r_y: BigInt,
+    pub s: BigInt,
+    pub a_x: BigInt,
+    pub a_y: BigInt,
+}
+
+impl Witness {
+    // Hashes `inputs` as a payload preimage and signs the hash with `signer`
+    pub fn new<S: Signer>(inputs: Vec<BigInt>, signer: &S) -> Result<Self, PayloadError> {
+        Self::with_version(inputs, ProtocolVersion::CURRENT, signer)
+    }
+
+    pub fn for_payload<P: VersionedPayload, S: Signer>(
+        payload: &P,
+        signer: &S,
+    ) -> Result<Self, PayloadError> {
+        Self::for_payload_version(payload, ProtocolVersion::CURRENT, signer)
+    }
+
+    pub fn for_payload_version<P: VersionedPayload, S: Signer>(
+        payload: &P,
+        version: ProtocolVersion,
+        signer: &S,
+    ) -> Result<Self, PayloadError> {
+        Self::with_version(payload.poseidon_inputs_for(version)?, version, signer)
+    }
+
+    fn with_version<S: Signer>(
+        inputs: Vec<BigInt>,
+        version: ProtocolVersion,
+        signer: &S,
+    ) -> Result<Self, PayloadError> {
+        let hash = version.poseidon_hash(inputs.clone())?;
+        let signed = signer
+            .sign(&MessageHash::new(hash.clone()))
+            .map_err(|e| PayloadError::InvalidField("signer", e.to_string()))?;
+        let image_of_r = signed.sig().image_of_r();
+        Ok(Witness {
+            inputs,
+            hash,
+            r_x: image_of_r.x().to_bigint(),
+            r_y: image_of_r.y().to_bigint(),
+            s: signed.sig().s().to_bigint(),
+            a_x: signed.public_key().x().to_bigint(),
+            a_y: signed.public_key().y().to_bigint(),
+        })
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "inputs": self.inputs.iter().map(BigInt::to_string).collect::<Vec<_>>(),
+            "hash": self.hash.to_string(),
+            "signature": {
+                "Rx": self.r_x.to_string(),
+                "Ry": self.r_y.to_string(),
+                "S": self.s.to_string(),
+            },
+            "publicKey": {
+                "Ax": self.a_x.to_string(),
+                "Ay": self.a_y.to_string(),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload::order::tests::order;
+    use crate::payload::transfer::tests::transfer;
+    use crate::poseidon::eddsa::{Signature, SignatureScheme};
+    use crate::poseidon::{field::FQ, jubjub::Point};
+    use crate::signer::LocalSigner;
+
+    const KEY: &str = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+
+    #[test]
+    fn witness_matches_the_payload_signature() {
+        let signer = LocalSigner::from_hex(KEY).unwrap();
+        let transfer = transfer();
+        let witness = Witness::for_payload(&transfer, &signer).unwrap();
+
+        assert_eq!(witness.inputs, transfer.poseidon_inputs().unwrap());
+        assert_eq!(witness.hash, transfer.hash().unwrap());
+        assert_eq!(
+            format!(
+                "0x{:0>64}{:0>64}{:0>64}",
+                witness.r_x.to_str_radix(16),
+                witness.r_y.to_str_radix(16),
+                witness.s.to_str_radix(16)
+            ),
+            transfer.sign(KEY).unwrap()
+        );
+
+        let public_key = Point::new(FQ::new(witness.a_x.clone()), FQ::new(witness.a_y.clone()));
+        let signature = Signature::new(
+            Point::new(FQ::new(witness.r_x.clone()), FQ::new(witness.r_y.clone())),
+            FQ::new(witness.s.clone()),
+        );
+        assert!(SignatureScheme::verify(
+            &public_key,
+            &signature,
+            &witness.hash
+        ));
+    }
+
+    #[test]
+    fn json_uses_decimal_strings() {
+        let signer = LocalSigner::from_hex(KEY).unwrap();
+        let order = order(None);
+        let witness = Witness::new(order.poseidon_inputs().unwrap(), &signer).unwrap();
+        assert!(witness == Witness::for_payload(&order, &signer).unwrap());
+
+        let json = witness.to_json();
+        let inputs = json["inputs"].as_array().unwrap();
+        assert_eq!(inputs.len(), witness.inputs.len());
+        assert_eq!(inputs[0], witness.inputs[0].to_string());
+        assert_eq!(json["hash"], witness.hash.to_string());
+        assert_eq!(json["signature"]["S"], witness.s.to_string());
+        assert_eq!(json["publicKey"]["Ay"], witness.a_y.to_string());
+    }
+}
@@ -0,0 +1,78 @@
+// Lossless conversions between FQ and arkworks' `ark_bn254::Fr`.
+//
+// SNARK_SCALAR_FIELD is the BN254 scalar field, so every FQ over that modulus maps
+// to exactly one `Fr` and back. An FQ constructed with a different modulus
+// (`FQ::with_modulus`) is rejected rather than being reinterpreted.
+
+use super::field::{FQ, SNARK_SCALAR_FIELD};
+use crate::util::errors::ConversionError;
+use ark_bn254::Fr;
+use ark_ff::{BigInteger, PrimeField};
+use num_bigint::BigUint;
+
+impl TryFrom<&FQ> for Fr {
+    type Error = ConversionError;
+
+    fn try_from(value: &FQ) -> Result<Self, Self::Error> {
+        if value.m_uint() != SNARK_SCALAR_FIELD.magnitude() {
+            return Err(ConversionError::ModulusMismatch);
+        }
+        let bytes = value.n_uint().to_bytes_le();
+        Ok(Fr::from_le_bytes_mod_order(&bytes))
+    }
+}
+
+impl TryFrom<FQ> for Fr {
+    type Error = ConversionError;
+
+    fn try_from(value: FQ) -> Result<Self, Self::Error> {
+        Fr::try_from(&value)
+    }
+}
+
+impl From<&Fr> for FQ {
+    fn from(value: &Fr) -> Self {
+        let bytes = value.into_bigint().to_bytes_le();
+        FQ::from_biguint(BigUint::from_bytes_le(&bytes))
+    }
+}
+
+impl From<Fr> for FQ {
+    fn from(value: Fr) -> Self {
+        FQ::from(&value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigInt;
+    use std::str::FromStr;
+
+    #[test]
+    fn fr_round_trip() {
+        let n = BigInt::from_str(
+            "20693456676802104653139582814194312788878632719314804297029697306071204881418",
+        )
+        .unwrap();
+
+        let fr = Fr::try_from(FQ::new(n.clone())).unwrap();
+        assert_eq!(fr, Fr::from_str(&n.to_string()).unwrap());
+        assert_eq!(FQ::from(fr).to_bigint(), n);
+    }
+
+    #[test]
+    fn fr_arithmetic_agrees_with_fq() {
+        let a = FQ::new(BigInt::from_str("987654321987654321987654321").unwrap());
+        let b = FQ::new(BigInt::from_str("123456789123456789123456789").unwrap());
+
+        let fr_product = Fr::try_from(&a).unwrap() * Fr::try_from(&b).unwrap();
+        assert_eq!(*FQ::from(fr_product).n_uint(), *(a * b).n_uint());
+    }
+
+    #[test]
+    fn fr_rejects_other_moduli() {
+        let value = FQ::with_modulus(BigInt::from(3), BigInt::from(7));
+        assert!(Fr::try_from(value).is_err());
+    }
+}
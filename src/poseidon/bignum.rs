@@ -0,0 +1,65 @@
+/*
+Modular exponentiation for the field arithmetic.
+
+A large share of the time of a signature goes into exponentiations mod p: every
+affine point addition inverts by Fermat (n^(p-2)), and every round of the
+Poseidon, MiMC and circom permutations raises the state to the 5th power. With
+the `gmp` feature, pow_mod, and only pow_mod, runs on GMP (via rug) instead of
+num-bigint. Additions, multiplications and reductions stay on num-bigint, so the
+speed-up is limited to the exponentiations and depends on how much of a workload
+they are. Values stay BigUint everywhere and are copied in and out as limbs, so
+no other code changes and the results are identical. benches/gmp_pow_mod.rs
+measures the swap with those copies included: about 3x faster for the
+inversions and 14x for the x^5 S-boxes, for which num-bigint's modpow sets up
+Montgomery reduction on every call. The feature links the
+system GMP (libgmp 6.2, e.g. libgmp-dev on Debian and Ubuntu) instead of
+building it.
+*/
+
+use num_bigint::BigUint;
+
+// base^exponent mod m, for m > 0
+#[cfg(not(feature = "gmp"))]
+pub(crate) fn pow_mod(base: &BigUint, exponent: &BigUint, m: &BigUint) -> BigUint {
+    base.modpow(exponent, m)
+}
+
+#[cfg(feature = "gmp")]
+pub(crate) fn pow_mod(base: &BigUint, exponent: &BigUint, m: &BigUint) -> BigUint {
+    use rug::{integer::Order, Integer};
+
+    let to_integer = |n: &BigUint| Integer::from_digits(&n.to_u64_digits(), Order::Lsf);
+    // A non-negative exponent always has a result for m > 0
+    let result = to_integer(base)
+        .pow_mod(&to_integer(exponent), &to_integer(m))
+        .unwrap();
+    let mut digits = result.to_digits::<u32>(Order::Lsf);
+    if digits.is_empty() {
+        digits.push(0);
+    }
+    BigUint::new(digits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poseidon::field::SNARK_SCALAR_FIELD;
+    use num_traits::{One, Zero};
+
+    #[test]
+    fn agrees_with_num_bigint() {
+        let p = SNARK_SCALAR_FIELD.to_biguint().unwrap();
+        let exponents = [BigUint::zero(), BigUint::from(5u8), &p - 2u8];
+        let bases = [
+            BigUint::zero(),
+            BigUint::one(),
+            &p - 1u8,
+            BigUint::from(7u8).pow(90),
+        ];
+        for base in &bases {
+            for exponent in &exponents {
+                assert_eq!(pow_mod(base, exponent, &p), base.modpow(exponent, &p));
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+// BLAKE-512, the SHA-3 finalist (not BLAKE2b), which circomlibjs uses to derive
+// EdDSA keys and nonces. Only the one-shot hash of a short input is needed, so
+// the message is padded in memory and compressed block by block.
+
+const IV: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+// The first digits of pi
+const C: [u64; 16] = [
+    0x243f6a8885a308d3,
+    0x13198a2e03707344,
+    0xa4093822299f31d0,
+    0x082efa98ec4e6c89,
+    0x452821e638d01377,
+    0xbe5466cf34e90c6c,
+    0xc0ac29b7c97c50dd,
+    0x3f84d5b5b5470917,
+    0x9216d5d98979fb1b,
+    0xd1310ba698dfb5ac,
+    0x2ffd72dbd01adfb7,
+    0xb8e1afed6a267e96,
+    0xba7c9045f12c7f99,
+    0x24a19947b3916cf7,
+    0x0801f2e2858efc16,
+    0x636920d871574e69,
+];
+
+const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+const ROUNDS: usize = 16;
+
+// `counter` is the number of message bits up to and including this block, or
+// 0 for a block that only holds padding
+fn compress(h: &mut [u64; 8], block: &[u8], counter: u128) {
+    let mut m = [0u64; 16];
+    for (word, bytes) in m.iter_mut().zip(block.chunks(8)) {
+        *word = u64::from_be_bytes(bytes.try_into().unwrap());
+    }
+    let (t0, t1) = (counter as u64, (counter >> 64) as u64);
+    let mut v = [0u64; 16];
+    v[..8].copy_from_slice(h);
+    v[8..12].copy_from_slice(&C[..4]);
+    v[12] = t0 ^ C[4];
+    v[13] = t0 ^ C[5];
+    v[14] = t1 ^ C[6];
+    v[15] = t1 ^ C[7];
+
+    for round in 0..ROUNDS {
+        let s = &SIGMA[round % 10];
+        let mut g = |a: usize, b: usize, c: usize, d: usize, i: usize| {
+            v[a] = v[a]
+                .wrapping_add(v[b])
+                .wrapping_add(m[s[2 * i]] ^ C[s[2 * i + 1]]);
+            v[d] = (v[d] ^ v[a]).rotate_right(32);
+            v[c] = v[c].wrapping_add(v[d]);
+            v[b] = (v[b] ^ v[c]).rotate_right(25);
+            v[a] = v[a]
+                .wrapping_add(v[b])
+                .wrapping_add(m[s[2 * i + 1]] ^ C[s[2 * i]]);
+            v[d] = (v[d] ^ v[a]).rotate_right(16);
+            v[c] = v[c].wrapping_add(v[d]);
+            v[b] = (v[b] ^ v[c]).rotate_right(11);
+        };
+        g(0, 4, 8, 12, 0);
+        g(1, 5, 9, 13, 1);
+        g(2, 6, 10, 14, 2);
+        g(3, 7, 11, 15, 3);
+        g(0, 5, 10, 15, 4);
+        g(1, 6, 11, 12, 5);
+        g(2, 7, 8, 13, 6);
+        g(3, 4, 9, 14, 7);
+    }
+    for i in 0..8 {
+        h[i] ^= v[i] ^ v[i + 8];
+    }
+}
+
+pub(crate) fn blake512(message: &[u8]) -> [u8; 64] {
+    let bits = message.len() as u128 * 8;
+    // 0x80 .. 0x01 (or 0x81 for a single byte), then the 128-bit length, so the
+    // total is a multiple of the 128-byte block
+    let padding = 128 - (message.len() + 16) % 128;
+    let mut data = message.to_vec();
+    data.push(0x80);
+    data.resize(message.len() + padding, 0);
+    *data.last_mut().unwrap() |= 0x01;
+    data.extend_from_slice(&bits.to_be_bytes());
+
+    let mut h = IV;
+    for (i, block) in data.chunks(128).enumerate() {
+        let start = i as u128 * 1024;
+        let counter = if start < bits {
+            bits.min(start + 1024)
+        } else {
+            0
+        };
+        compress(&mut h, block, counter);
+    }
+
+    let mut digest = [0u8; 64];
+    for (bytes, word) in digest.chunks_mut(8).zip(h) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reference_vectors() {
+        // From the BLAKE submission: the empty message, one zero byte and 144
+        // zero bytes (two blocks, the second one partly message)
+        let cases: [(&[u8], &str); 3] = [
+            (b"", "a8cfbbd73726062df0c6864dda65defe58ef0cc52a5625090fa17601e1eecd1b628e94f396ae402a00acc9eab77b4d4c2e852aaaa25a636d80af3fc7913ef5b8"),
+            (&[0u8], "97961587f6d970faba6d2478045de6d1fabd09b61ae50932054d52bc29d31be4ff9102b9f69e2bbdb83be13d4b9c06091e5fa0b48bd081b634058be0ec49beb3"),
+            (&[0u8; 144], "313717d608e9cf758dcb1eb0f0c3cf9fc150b2d500fb33f51c52afc99d358a2f1374b8a38bba7974e7f6ef79cab16f22ce1e649d6e01ad9589c213045d545dde"),
+        ];
+        for (message, expected) in cases {
+            assert_eq!(hex::encode(blake512(message)), expected);
+        }
+    }
+}
@@ -0,0 +1,124 @@
+// Hiding commitments to a field element.
+//
+//     C = Poseidon(domain, value, blinding)      t = 4, F = 6, P = 53
+//
+// The domain tag is sha256_snark of a domain name, so commitments made for one
+// application cannot be opened in another; `commit` uses DEFAULT_DOMAIN. The
+// hash is the same Poseidon instance the payloads use, so a circuit over the
+// SNARK field can recompute C with its stock Poseidon gadget. The commitment
+// only hides `value` if `blinding` is uniformly random and never reused.
+
+use super::{field::FQ, permutation::FixedPoseidon};
+use crate::util::errors::CommitmentError;
+use crate::util::helpers::sha256_snark;
+use lazy_static::lazy_static;
+
+pub const DEFAULT_DOMAIN: &str = "loopring_sign/commitment/v1";
+
+lazy_static! {
+    static ref COMMITMENT_POSEIDON: FixedPoseidon<3> = FixedPoseidon::shared(6, 53);
+    static ref DEFAULT_DOMAIN_TAG: FQ = domain_tag(DEFAULT_DOMAIN);
+}
+
+pub fn domain_tag(domain: &str) -> FQ {
+    FQ::new(sha256_snark(domain))
+}
+
+fn commit_tagged(tag: &FQ, value: &FQ, blinding: &FQ) -> FQ {
+    FQ::new(COMMITMENT_POSEIDON.hash([tag.to_bigint(), value.to_bigint(), blinding.to_bigint()]))
+}
+
+pub fn commit(value: &FQ, blinding: &FQ) -> FQ {
+    commit_tagged(&DEFAULT_DOMAIN_TAG, value, blinding)
+}
+
+pub fn commit_in_domain(domain: &str, value: &FQ, blinding: &FQ) -> FQ {
+    commit_tagged(&domain_tag(domain), value, blinding)
+}
+
+// 64 bytes of OS randomness reduced into the field, so the bias is negligible
+#[cfg(feature = "rng")]
+pub fn random_blinding() -> Result<FQ, CommitmentError> {
+    let mut entropy = [0u8; 64];
+    getrandom::getrandom(&mut entropy)
+        .map_err(|e| CommitmentError::Unavailable(format!("no OS randomness: {}", e)))?;
+    Ok(FQ::from_biguint(num_bigint::BigUint::from_bytes_le(
+        &entropy,
+    )))
+}
+
+// What the committer reveals to open a commitment
+#[derive(Clone, PartialEq, Eq)]
+pub struct Opening {
+    pub domain: String,
+    pub value: FQ,
+    pub blinding: FQ,
+}
+
+impl Opening {
+    pub fn new(value: FQ, blinding: FQ) -> Self {
+        Self::in_domain(DEFAULT_DOMAIN, value, blinding)
+    }
+
+    pub fn in_domain(domain: &str, value: FQ, blinding: FQ) -> Self {
+        Opening {
+            domain: domain.to_string(),
+            value,
+            blinding,
+        }
+    }
+
+    pub fn commitment(&self) -> FQ {
+        commit_in_domain(&self.domain, &self.value, &self.blinding)
+    }
+}
+
+pub fn verify(commitment: &FQ, opening: &Opening) -> bool {
+    opening.commitment() == *commitment
+}
+
+// Returns the committed value if `opening` matches `commitment`
+pub fn open<'a>(commitment: &FQ, opening: &'a Opening) -> Result<&'a FQ, CommitmentError> {
+    if !verify(commitment, opening) {
+        return Err(CommitmentError::Mismatch);
+    }
+    Ok(&opening.value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigInt;
+
+    fn fq(n: u64) -> FQ {
+        FQ::new(BigInt::from(n))
+    }
+
+    #[test]
+    fn commitments_open_with_their_opening_only() {
+        let opening = Opening::new(fq(42), fq(0xdead_beef));
+        let commitment = commit(&fq(42), &fq(0xdead_beef));
+        assert!(opening.commitment() == commitment);
+        assert!(*open(&commitment, &opening).unwrap() == fq(42));
+
+        for wrong in [
+            Opening::new(fq(43), fq(0xdead_beef)),
+            Opening::new(fq(42), fq(0xdead_bef0)),
+            Opening::in_domain("other", fq(42), fq(0xdead_beef)),
+        ] {
+            assert!(!verify(&commitment, &wrong));
+            assert_eq!(
+                open(&commitment, &wrong).err(),
+                Some(CommitmentError::Mismatch)
+            );
+        }
+    }
+
+    #[test]
+    fn domains_are_separated() {
+        assert!(commit_in_domain(DEFAULT_DOMAIN, &fq(1), &fq(2)) == commit(&fq(1), &fq(2)));
+        assert!(commit_in_domain("a", &fq(1), &fq(2)) != commit_in_domain("b", &fq(1), &fq(2)));
+        // The tag is an input of its own, not mixed into the value
+        assert!(commit(&fq(1), &fq(2)) != commit(&fq(2), &fq(1)));
+    }
+}
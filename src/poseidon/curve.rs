@@ -0,0 +1,302 @@
+/*
+Curve parameters of the EdDSA scheme.
+
+Loopring signs on Baby Jubjub over the BN254 scalar field, but the construction
+(ethsnarks' EdDSA: sha512 nonce, Poseidon challenge H(R, A, M), s mod cofactor * L)
+is also used by other deployments with their own constants. A CurveParams
+implementation describes one such curve,
+
+    a * x^2 + y^2 = 1 + d * x^2 * y^2   over F_q,
+
+with a base point of prime order L, the cofactor and the Poseidon that computes
+the challenge. `eddsa::Eddsa` signs and verifies over any of them.
+
+BabyJubjub holds Loopring's parameters and is the default; its arithmetic is the
+one of `jubjub::Point`. CustomCurve holds parameters given at runtime and uses
+the generic formulas below, with the coordinates as elements of its own field.
+The formulas are complete only if d is a non-square and a a square, as for
+Baby Jubjub; CustomCurve does not check this.
+*/
+
+use super::field::{FQ, SNARK_SCALAR_FIELD};
+use super::jubjub::{Point, CURVE_A, CURVE_D, JUBJUB_C, JUBJUB_E, JUBJUB_L};
+use super::permutation::{FixedPoseidon, Poseidon};
+use crate::constants::GENERATOR;
+use crate::util::errors::CurveError;
+use num_bigint::{BigInt, BigUint};
+use num_traits::{One, Zero};
+
+lazy_static! {
+    // H(R, A, M) of the signature challenge
+    static ref CHALLENGE_POSEIDON: FixedPoseidon<5> = FixedPoseidon::shared(6, 52);
+}
+
+pub trait CurveParams: Send + Sync {
+    // q, the field of the coordinates
+    fn modulus(&self) -> &BigInt;
+    fn a(&self) -> &FQ;
+    fn d(&self) -> &FQ;
+    // L, the prime order of the generator
+    fn order(&self) -> &BigInt;
+    fn cofactor(&self) -> &BigInt;
+    fn generator(&self) -> &Point;
+    // H(Rx, Ry, Ax, Ay, M)
+    fn challenge_hash(&self, inputs: [BigInt; 5]) -> BigInt;
+
+    fn element(&self, n: BigInt) -> FQ {
+        FQ::with_modulus(n, self.modulus().clone())
+    }
+
+    fn identity(&self) -> Point {
+        Point::new(self.element(BigInt::zero()), self.element(BigInt::one()))
+    }
+
+    // Order of the whole curve, cofactor * L
+    fn curve_order(&self) -> BigInt {
+        self.order() * self.cofactor()
+    }
+
+    // x3 = (x1 * y2 + y1 * x2) / (1 + λ), y3 = (y1 * y2 - a * x1 * x2) / (1 - λ)
+    // with λ = d * x1 * x2 * y1 * y2
+    fn add(&self, p: &Point, q: &Point) -> Point {
+        let (x1, y1, x2, y2) = (p.x(), p.y(), q.x(), q.y());
+        let one = self.element(BigInt::one());
+        let lambda = self.d() * x1 * x2 * y1 * y2;
+        let x3 = (x1 * y2 + y1 * x2) / (&one + &lambda);
+        let y3 = (y1 * y2 - self.a() * x1 * x2) / (one - lambda);
+        Point::new(x3, y3)
+    }
+
+    // Double-and-add
+    fn mul(&self, p: &Point, scalar: &BigUint) -> Point {
+        let mut result = self.identity();
+        let mut doubled = p.clone();
+        for i in 0..scalar.bits() {
+            if scalar.bit(i) {
+                result = self.add(&result, &doubled);
+            }
+            doubled = self.add(&doubled, &doubled);
+        }
+        result
+    }
+
+    fn is_on_curve(&self, p: &Point) -> bool {
+        let xx = p.x() * p.x();
+        let yy = p.y() * p.y();
+        self.a() * &xx + &yy == self.element(BigInt::one()) + self.d() * &xx * yy
+    }
+
+    fn is_in_subgroup(&self, p: &Point) -> bool {
+        self.is_on_curve(p) && self.mul(p, self.order().magnitude()) == self.identity()
+    }
+}
+
+// Baby Jubjub as Loopring uses it (EIP-2494)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BabyJubjub;
+
+impl CurveParams for BabyJubjub {
+    fn modulus(&self) -> &BigInt {
+        &SNARK_SCALAR_FIELD
+    }
+
+    fn a(&self) -> &FQ {
+        &CURVE_A
+    }
+
+    fn d(&self) -> &FQ {
+        &CURVE_D
+    }
+
+    fn order(&self) -> &BigInt {
+        &JUBJUB_L
+    }
+
+    fn cofactor(&self) -> &BigInt {
+        &JUBJUB_C
+    }
+
+    fn generator(&self) -> &Point {
+        &GENERATOR
+    }
+
+    fn challenge_hash(&self, inputs: [BigInt; 5]) -> BigInt {
+        CHALLENGE_POSEIDON.hash(inputs)
+    }
+
+    fn element(&self, n: BigInt) -> FQ {
+        FQ::new(n)
+    }
+
+    fn identity(&self) -> Point {
+        Point::infinity()
+    }
+
+    fn curve_order(&self) -> BigInt {
+        JUBJUB_E.clone()
+    }
+
+    fn add(&self, p: &Point, q: &Point) -> Point {
+        p.clone() + q
+    }
+
+    fn mul(&self, p: &Point, scalar: &BigUint) -> Point {
+        p.mul_scalar_uint(scalar)
+    }
+
+    fn is_on_curve(&self, p: &Point) -> bool {
+        p.is_on_curve()
+    }
+
+    fn is_in_subgroup(&self, p: &Point) -> bool {
+        p.is_in_subgroup()
+    }
+}
+
+#[derive(Clone)]
+pub struct CustomCurve {
+    modulus: BigInt,
+    a: FQ,
+    d: FQ,
+    order: BigInt,
+    cofactor: BigInt,
+    generator: Point,
+    challenge: Poseidon,
+}
+
+impl CustomCurve {
+    // `challenge` is the Poseidon of width 6 the deployment uses for H(R, A, M)
+    pub fn new(
+        modulus: BigInt,
+        a: BigInt,
+        d: BigInt,
+        order: BigInt,
+        cofactor: BigInt,
+        generator: (BigInt, BigInt),
+        challenge: Poseidon,
+    ) -> Result<Self, CurveError> {
+        if modulus.bits() > 256 {
+            return Err(CurveError::ModulusTooLarge);
+        }
+        if challenge.width() != 6 || challenge.modulus() != modulus.magnitude() {
+            return Err(CurveError::ChallengeHash);
+        }
+        let element = |n: BigInt| FQ::with_modulus(n, modulus.clone());
+        let curve = CustomCurve {
+            a: element(a),
+            d: element(d),
+            generator: Point::new(element(generator.0), element(generator.1)),
+            modulus,
+            order,
+            cofactor,
+            challenge,
+        };
+        if !curve.is_on_curve(&curve.generator) {
+            return Err(CurveError::GeneratorNotOnCurve);
+        }
+        if curve.generator == curve.identity() || !curve.is_in_subgroup(&curve.generator) {
+            return Err(CurveError::GeneratorOrder);
+        }
+        Ok(curve)
+    }
+}
+
+impl CurveParams for CustomCurve {
+    fn modulus(&self) -> &BigInt {
+        &self.modulus
+    }
+
+    fn a(&self) -> &FQ {
+        &self.a
+    }
+
+    fn d(&self) -> &FQ {
+        &self.d
+    }
+
+    fn order(&self) -> &BigInt {
+        &self.order
+    }
+
+    fn cofactor(&self) -> &BigInt {
+        &self.cofactor
+    }
+
+    fn generator(&self) -> &Point {
+        &self.generator
+    }
+
+    fn challenge_hash(&self, inputs: [BigInt; 5]) -> BigInt {
+        // Five inputs always fit into a width-6 instance
+        self.challenge.hash(inputs).unwrap()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::constants::{GENERATOR_X_DEC, GENERATOR_Y_DEC};
+    use crate::poseidon::jubjub::{JUBJUB_A, JUBJUB_D};
+    use std::str::FromStr;
+
+    // Baby Jubjub with its generator replaced by `generator`, on the generic code path
+    pub(crate) fn custom_baby_jubjub(generator: &Point) -> Result<CustomCurve, CurveError> {
+        CustomCurve::new(
+            SNARK_SCALAR_FIELD.clone(),
+            JUBJUB_A.clone(),
+            JUBJUB_D.clone(),
+            JUBJUB_L.clone(),
+            JUBJUB_C.clone(),
+            (generator.x().to_bigint(), generator.y().to_bigint()),
+            Poseidon::shared(6, 6, 52),
+        )
+    }
+
+    #[test]
+    fn generic_arithmetic_matches_baby_jubjub() {
+        let curve = custom_baby_jubjub(&Point::generate()).unwrap();
+        let base = curve.generator().clone();
+        assert!(base.x().to_bigint() == BigInt::from_str(GENERATOR_X_DEC).unwrap());
+        assert!(base.y().to_bigint() == BigInt::from_str(GENERATOR_Y_DEC).unwrap());
+
+        let scalar = BigUint::from(123456789u32);
+        let generic = curve.mul(&base, &scalar);
+        let specialized = BabyJubjub.mul(&base, &scalar);
+        assert!(generic.x().to_bigint() == specialized.x().to_bigint());
+        assert!(generic.y().to_bigint() == specialized.y().to_bigint());
+        assert!(curve.is_in_subgroup(&generic));
+        assert_eq!(curve.curve_order(), BabyJubjub.curve_order());
+    }
+
+    #[test]
+    fn rejects_invalid_parameters() {
+        let base = Point::generate();
+        let off_curve = Point::new(base.x().clone(), base.x().clone());
+        assert!(matches!(
+            custom_baby_jubjub(&off_curve),
+            Err(CurveError::GeneratorNotOnCurve)
+        ));
+        // (0, -1) is on the curve but has order 2
+        let small_order = Point::new(FQ::zero(), FQ::zero() - FQ::one());
+        assert!(matches!(
+            custom_baby_jubjub(&small_order),
+            Err(CurveError::GeneratorOrder)
+        ));
+        assert!(matches!(
+            custom_baby_jubjub(&Point::infinity()),
+            Err(CurveError::GeneratorOrder)
+        ));
+        assert!(matches!(
+            CustomCurve::new(
+                SNARK_SCALAR_FIELD.clone(),
+                JUBJUB_A.clone(),
+                JUBJUB_D.clone(),
+                JUBJUB_L.clone(),
+                JUBJUB_C.clone(),
+                (base.x().to_bigint(), base.y().to_bigint()),
+                Poseidon::shared(3, 6, 52),
+            ),
+            Err(CurveError::ChallengeHash)
+        ));
+    }
+}
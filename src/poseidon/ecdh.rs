@@ -0,0 +1,150 @@
+// Diffie-Hellman key agreement between two L2 accounts.
+//
+// Both sides compute S = 8 * a * B = 8 * b * A from their own private key and
+// the other account's public key (publicKey.x / publicKey.y of the account API).
+// Multiplying by the cofactor first maps a public key with a small-order
+// component onto the prime-order subgroup, so a crafted key cannot make S leak
+// bits of the private key; a key that only has a small-order part maps to the
+// identity and is rejected.
+//
+// The coordinates of S are never used directly. They are hashed into a 32-byte
+// secret, by default with Poseidon (so the secret is also usable in circuits
+// over the same field), or with blake2b for use as a symmetric key.
+
+use super::{
+    field::FQ,
+    jubjub::{Point, JUBJUB_C, JUBJUB_L},
+    permutation::FixedPoseidon,
+};
+use crate::util::errors::EcdhError;
+use blake2b_simd::Params;
+use lazy_static::lazy_static;
+use num_bigint::BigInt;
+use num_traits::{Euclid, Zero};
+
+lazy_static! {
+    // Poseidon(t = 3, F = 6, P = 53), the payload instance for two inputs
+    static ref SECRET_POSEIDON: FixedPoseidon<2> = FixedPoseidon::shared(6, 53);
+}
+
+// blake2b personalization, so the secret differs from any other blake2b use
+const BLAKE2B_PERSONAL: &[u8] = b"Loopring_ECDH";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SharedSecretHash {
+    // Poseidon(S.x, S.y) as 32 little-endian bytes
+    #[default]
+    Poseidon,
+    // blake2b-256 of S.x || S.y, each 32 bytes little-endian
+    Blake2b,
+}
+
+pub fn derive_shared_secret(
+    my_private_key: &BigInt,
+    their_public_key: &Point,
+) -> Result<[u8; 32], EcdhError> {
+    derive_shared_secret_with(
+        my_private_key,
+        their_public_key,
+        SharedSecretHash::default(),
+    )
+}
+
+pub fn derive_shared_secret_with(
+    my_private_key: &BigInt,
+    their_public_key: &Point,
+    hash: SharedSecretHash,
+) -> Result<[u8; 32], EcdhError> {
+    let private_key = my_private_key.rem_euclid(&JUBJUB_L);
+    if private_key.is_zero() {
+        return Err(EcdhError::InvalidPrivateKey);
+    }
+    if !their_public_key.is_on_curve() {
+        return Err(EcdhError::InvalidPublicKey);
+    }
+    let cleared = their_public_key.mul_scalar(&JUBJUB_C);
+    if cleared == Point::infinity() {
+        return Err(EcdhError::InvalidPublicKey);
+    }
+    let shared = cleared.mul_scalar(&private_key);
+
+    Ok(match hash {
+        SharedSecretHash::Poseidon => {
+            let digest = SECRET_POSEIDON.hash([shared.x().to_bigint(), shared.y().to_bigint()]);
+            FQ::new(digest).to_bytes_le()
+        }
+        SharedSecretHash::Blake2b => {
+            let digest = Params::new()
+                .hash_length(32)
+                .personal(BLAKE2B_PERSONAL)
+                .to_state()
+                .update(&shared.x().to_bytes_le())
+                .update(&shared.y().to_bytes_le())
+                .finalize();
+            let mut secret = [0u8; 32];
+            secret.copy_from_slice(digest.as_bytes());
+            secret
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigUint;
+
+    #[test]
+    fn both_sides_derive_the_same_secret() {
+        let a = BigInt::from(0x1234_5678u64);
+        let b = BigInt::from(0x9abc_def0u64);
+        let public_a = Point::generate().mul_scalar(&a);
+        let public_b = Point::generate().mul_scalar(&b);
+
+        for hash in [SharedSecretHash::Poseidon, SharedSecretHash::Blake2b] {
+            let secret = derive_shared_secret_with(&a, &public_b, hash).unwrap();
+            assert_eq!(
+                secret,
+                derive_shared_secret_with(&b, &public_a, hash).unwrap()
+            );
+            assert_ne!(
+                secret,
+                derive_shared_secret_with(&(a.clone() + 1), &public_b, hash).unwrap()
+            );
+        }
+        assert_ne!(
+            derive_shared_secret(&a, &public_b).unwrap(),
+            derive_shared_secret_with(&a, &public_b, SharedSecretHash::Blake2b).unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_keys_are_rejected() {
+        let public_key = Point::generate().mul_scalar(&BigInt::from(7));
+        assert_eq!(
+            derive_shared_secret(&JUBJUB_L, &public_key),
+            Err(EcdhError::InvalidPrivateKey)
+        );
+
+        let off_curve = Point::new(FQ::new(BigInt::from(1)), FQ::new(BigInt::from(2)));
+        assert_eq!(
+            derive_shared_secret(&BigInt::from(3), &off_curve),
+            Err(EcdhError::InvalidPublicKey)
+        );
+
+        // (0, -1) has order 2, so clearing the cofactor gives the identity
+        let order_two = Point::new(FQ::new(BigInt::zero()), FQ::new(BigInt::from(-1)));
+        assert!(order_two.is_on_curve());
+        assert!(order_two.mul_scalar_uint(&BigUint::from(2u8)) == Point::infinity());
+        assert_eq!(
+            derive_shared_secret(&BigInt::from(3), &order_two),
+            Err(EcdhError::InvalidPublicKey)
+        );
+
+        // A small-order component does not change the secret
+        let mixed = public_key.clone() + order_two;
+        assert_eq!(
+            derive_shared_secret(&BigInt::from(3), &mixed),
+            derive_shared_secret(&BigInt::from(3), &public_key)
+        );
+    }
+}
@@ -28,15 +28,26 @@ For further information see: https://ed2519.cr.yp.to/eddsa-20150704.pdf
 */
 
 use super::{
+    curve::{BabyJubjub, CurveParams},
     field::{FQ, SNARK_SCALAR_FIELD},
     jubjub::{Point, JUBJUB_E, JUBJUB_L},
     permutation::Poseidon,
 };
-use crate::util::helpers::{generate_signature_base_string, sha256_snark, to_bytes_32};
+use crate::util::{
+    encoding::EncodingProfile,
+    errors::{ConversionError, PoseidonError, RequestError, SignatureError, SignaturePart},
+    helpers::{
+        body_signature_base_string, generate_signature_base_string, sha256_snark,
+        url_signature_base_string, HttpMethod, ParamPair, RequestHash,
+    },
+    hex,
+};
 use num_bigint::{BigInt, Sign};
-use num_traits::{Num, Zero};
+use num_traits::{Euclid, Zero};
 use sha2::{Digest, Sha512};
+use std::fmt;
 
+#[derive(Clone)]
 pub struct Signature {
     image_of_r: Point,
     s: FQ,
@@ -55,16 +66,86 @@ impl Signature {
         Signature { image_of_r, s }
     }
 
-    pub fn to_string(&self) -> String {
-        format!(
+    // Rx || Ry || S as 32-byte little-endian words, the layout of the binary
+    // encodings (the hex form is big-endian)
+    pub fn to_bytes(&self) -> [u8; 96] {
+        let mut bytes = [0u8; 96];
+        bytes[..64].copy_from_slice(&self.image_of_r.to_bytes());
+        bytes[64..].copy_from_slice(&self.s.to_bytes_le());
+        bytes
+    }
+
+    // Rejects what `from_hex` rejects, and an R off the curve
+    pub fn from_bytes(bytes: &[u8; 96]) -> Result<Self, ConversionError> {
+        let image_of_r = Point::from_bytes(bytes[..64].try_into().unwrap())?;
+        let s = BigInt::from_bytes_le(Sign::Plus, &bytes[64..]);
+        if s >= *JUBJUB_E {
+            return Err(ConversionError::ValueExceedsModulus);
+        }
+        Ok(Signature::new(image_of_r, FQ::new(s)))
+    }
+
+    // Parses the Rx || Ry || S hex form produced by `to_hex`
+    pub fn from_hex(signature: &str) -> Result<Self, SignatureError> {
+        let (rx, ry, s) = parse_signature_components(signature)?;
+        Ok(Signature::new(
+            Point::new(FQ::new(rx), FQ::new(ry)),
+            FQ::new(s),
+        ))
+    }
+}
+
+/*
+Splits a signature into its three 32-byte big-endian components.
+
+Rx and Ry are coordinates and must be below SNARK_SCALAR_FIELD. S is reduced
+modulo JUBJUB_E when signing, so a value at or above it was not produced by a
+compliant signer. The error names the first part that is malformed.
+*/
+pub fn parse_signature_components(
+    signature: &str,
+) -> Result<(BigInt, BigInt, BigInt), SignatureError> {
+    let digits = hex::strip(signature);
+    if digits.len() != 192 {
+        return Err(SignatureError::InvalidLength(digits.len()));
+    }
+
+    let parts = [
+        (SignaturePart::Rx, &*SNARK_SCALAR_FIELD),
+        (SignaturePart::Ry, &*SNARK_SCALAR_FIELD),
+        (SignaturePart::S, &*JUBJUB_E),
+    ];
+    let mut values = Vec::with_capacity(3);
+    for (i, (part, modulus)) in parts.into_iter().enumerate() {
+        let chunk = digits
+            .get(i * 64..(i + 1) * 64)
+            .ok_or(SignatureError::InvalidHex(part))?;
+        let value = hex::decode_bigint(chunk).map_err(|_| SignatureError::InvalidHex(part))?;
+        if value >= *modulus {
+            return Err(SignatureError::OutOfRange(part));
+        }
+        values.push(value);
+    }
+
+    let s = values.pop().unwrap();
+    let ry = values.pop().unwrap();
+    let rx = values.pop().unwrap();
+    Ok((rx, ry, s))
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
             "{} {} {}",
-            &self.image_of_r.x().n(),
-            &self.image_of_r.y().n(),
-            &self.s.n()
+            &self.image_of_r.x().n_uint(),
+            &self.image_of_r.y().n_uint(),
+            &self.s.n_uint()
         )
     }
 }
 
+#[derive(Clone)]
 pub struct SignedMessage {
     public_key: Point,
     sig: Signature,
@@ -91,50 +172,166 @@ impl SignedMessage {
         }
     }
 
-    pub fn to_string(&self) -> String {
+    pub fn to_hex(&self) -> String {
+        let image_of_r = self.sig().image_of_r();
         format!(
+            "{}{}{}",
+            image_of_r.x().to_hex(),
+            &image_of_r.y().to_hex()[2..],
+            &self.sig().s().to_hex()[2..]
+        )
+    }
+}
+
+impl fmt::Display for SignedMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
             "{} {} {} {}",
-            self.public_key.x().n(),
-            self.public_key.y().n(),
-            self.sig.to_string(),
+            self.public_key.x().n_uint(),
+            self.public_key.y().n_uint(),
+            self.sig,
             self.msg
         )
     }
+}
+/*
+A message hash ready to be signed.
 
-    pub fn to_hex(&self) -> String {
-        let r_x_hex = format!("{:0>64}", self.sig().image_of_r().x().n().to_str_radix(16));
-        let r_y_hex: String = format!("{:0>64}", self.sig().image_of_r().y().n().to_str_radix(16));
-        let s_hex: String = format!("{:0>64}", self.sig().s().n().to_str_radix(16));
-        format!("0x{}{}{}", r_x_hex, r_y_hex, s_hex)
+Signing takes a MessageHash instead of a bare integer so that an unhashed
+message, or a hash reduced with the wrong modulus, cannot be passed by accident.
+Values are produced by the crate's hash functions; a hash computed elsewhere has
+to go through `from_prehashed`, which checks that it is a field element.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageHash(BigInt);
+
+impl MessageHash {
+    pub(crate) fn new(hash: BigInt) -> Self {
+        MessageHash(hash)
+    }
+
+    // sha256_snark of the message, as used for API request signatures
+    pub fn from_message(message: &str) -> Self {
+        MessageHash(sha256_snark(message))
+    }
+
+    // The message hashed by a fork's rule instead of sha256_snark
+    pub fn from_message_with(message: &str, hashing: RequestHash) -> Self {
+        MessageHash(hashing.hash(message))
+    }
+
+    // Poseidon(t = inputs + 1, F = 6, P = 53), as used for L2 payloads
+    pub fn from_poseidon_inputs(inputs: Vec<BigInt>) -> Result<Self, PoseidonError> {
+        let poseidon = Poseidon::shared(inputs.len() + 1, 6, 53);
+        Ok(MessageHash(poseidon.hash(inputs)?))
+    }
+
+    pub fn from_prehashed(hash: BigInt) -> Result<Self, SignatureError> {
+        if hash < BigInt::zero() || hash >= *SNARK_SCALAR_FIELD {
+            return Err(SignatureError::MessageOutOfRange);
+        }
+        Ok(MessageHash(hash))
+    }
+
+    pub fn as_bigint(&self) -> &BigInt {
+        &self.0
+    }
+
+    // A message hash is always a field element, so it fits into 32 bytes
+    pub fn to_bytes_le(&self) -> [u8; 32] {
+        FQ::new(self.0.clone()).to_bytes_le()
     }
 }
-pub struct SignatureScheme;
 
-impl SignatureScheme {
-    // The variable B
-    pub fn base_point() -> Point {
-        Point::generate()
+/*
+The scheme over the curve `C`, Loopring's Baby Jubjub by default.
+
+The construction does not change with the curve: r = sha512(k || M) mod L with k
+and M as 32-byte little-endian elements of F_q, R = r*B, t = H(Rx, Ry, Ax, Ay, M)
+with the curve's challenge Poseidon, s = (r + k*t) mod (cofactor * L). With
+BabyJubjub this is SignatureScheme; CustomCurve serves deployments that only
+changed the constants.
+
+Points passed to `verify` must have their coordinates in the curve's field (as
+`CurveParams::element` builds them) and are expected to be validated by the
+caller, see `CurveParams::is_in_subgroup`.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct Eddsa<C: CurveParams = BabyJubjub> {
+    curve: C,
+}
+
+impl<C: CurveParams> Eddsa<C> {
+    pub fn new(curve: C) -> Self {
+        Eddsa { curve }
     }
 
-    pub fn sign(private_key_scalar: BigInt, hash: BigInt) -> SignedMessage {
-        let base_point = Self::base_point();
+    pub fn curve(&self) -> &C {
+        &self.curve
+    }
+
+    // A = k * B
+    pub fn public_key(&self, private_key_scalar: &BigInt) -> Point {
+        self.keypair(private_key_scalar.clone()).public_key
+    }
 
-        let public_key = &base_point * &private_key_scalar; // A = k * P -> Public key
+    // The key with its public key, for signing many messages
+    pub fn keypair(&self, private_key_scalar: BigInt) -> KeyPair {
+        let reduced_scalar = private_key_scalar.rem_euclid(&self.curve.curve_order());
+        let public_key = self
+            .curve
+            .mul(self.curve.generator(), reduced_scalar.magnitude());
+        KeyPair {
+            private_key_scalar,
+            reduced_scalar,
+            public_key,
+        }
+    }
 
-        let message = hash.clone(); // prehash message
-        let r = Self::hash_secret(FQ::new(private_key_scalar.clone()), &message);
+    // The hash must be an element of the curve's field
+    pub fn sign(
+        &self,
+        private_key_scalar: BigInt,
+        hash: &BigInt,
+    ) -> Result<SignedMessage, SignatureError> {
+        self.sign_with_keypair(&self.keypair(private_key_scalar), hash)
+    }
 
-        let image_of_r = &base_point * &r;
+    // The key pair must come from `keypair` of the same curve
+    pub fn sign_with_keypair(
+        &self,
+        keypair: &KeyPair,
+        hash: &BigInt,
+    ) -> Result<SignedMessage, SignatureError> {
+        if *hash < BigInt::zero() || hash >= self.curve.modulus() {
+            return Err(SignatureError::MessageOutOfRange);
+        }
+        Ok(self.sign_unchecked(keypair, hash.clone()))
+    }
 
-        let t = Self::hash_public(&image_of_r, &public_key, message);
-        let signature = (r + (private_key_scalar * t)) % &*JUBJUB_E;
+    fn sign_unchecked(&self, keypair: &KeyPair, message: BigInt) -> SignedMessage {
+        let r = self.nonce(&keypair.private_key_scalar, &message);
+        let image_of_r = self.curve.mul(self.curve.generator(), r.magnitude());
 
-        let signature_result = Signature::new(image_of_r, FQ::new(signature));
+        let t = self.challenge(&image_of_r, &keypair.public_key, message.clone());
+        let s = (r + (&keypair.reduced_scalar * t)) % self.curve.curve_order();
 
-        let signed_message = SignedMessage::new(public_key, signature_result, hash);
+        // Kept as an element of F_q like the coordinates, as SignatureScheme always
+        // has; cofactor * L exceeds q by about 2^-128 of its size
+        let sig = Signature::new(image_of_r, self.curve.element(s));
+        SignedMessage::new(keypair.public_key.clone(), sig, message)
+    }
 
-        //let signature_result = Sig
-        signed_message
+    // Checks s * B == R + t * A with t = H(R, A, M)
+    pub fn verify(&self, public_key: &Point, sig: &Signature, hash: &BigInt) -> bool {
+        let t = self.challenge(sig.image_of_r(), public_key, hash.clone());
+        let t = t.rem_euclid(&self.curve.curve_order());
+        let lhs = self.curve.mul(self.curve.generator(), sig.s().n_uint());
+        let rhs = self
+            .curve
+            .add(sig.image_of_r(), &self.curve.mul(public_key, t.magnitude()));
+        lhs == rhs
     }
 
     /*
@@ -149,85 +346,270 @@ impl SignatureScheme {
         (Implementation detail: To save time in the computation of `rB`, the signer
         can replace `r` with `r mod L` before computing `rB`.)
     */
-
-    fn hash_secret(k: FQ, arg: &BigInt) -> BigInt {
-        let mut key_bytes = to_bytes_32(k.n());
-        let hash_bytes = to_bytes_32(&arg);
-        key_bytes.extend(hash_bytes);
-
+    pub(crate) fn nonce(&self, private_key_scalar: &BigInt, message: &BigInt) -> BigInt {
         let mut hasher = Sha512::new();
-        hasher.update(key_bytes);
+        hasher.update(self.curve.element(private_key_scalar.clone()).to_bytes_le());
+        hasher.update(self.curve.element(message.clone()).to_bytes_le());
         let hash = BigInt::from_bytes_le(Sign::Plus, &hasher.finalize()[..]);
 
-        hash % JUBJUB_L.clone()
+        hash % self.curve.order()
     }
 
-    fn hash_public(image_of_r: &Point, public_key: &Point, message: BigInt) -> BigInt {
-        let mut input: Vec<BigInt> = Vec::new();
-        input.extend(image_of_r.as_scalar());
-        input.extend(public_key.as_scalar());
-        input.extend(vec![message]);
-
-        let poseidon = Poseidon::new(
-            SNARK_SCALAR_FIELD.clone(),
-            6,
-            6,
-            52,
-            format!("poseidon"),
-            BigInt::from(5),
-            None,
-            None,
-            128,
-        );
+    fn challenge(&self, image_of_r: &Point, public_key: &Point, message: BigInt) -> BigInt {
+        self.curve.challenge_hash([
+            image_of_r.x().to_bigint(),
+            image_of_r.y().to_bigint(),
+            public_key.x().to_bigint(),
+            public_key.y().to_bigint(),
+            message,
+        ])
+    }
+}
+
+/*
+A private key together with its public key A = k * B and k reduced modulo the
+curve order, computed once. Signing with it saves the multiplication k * B that
+`SignatureScheme::sign_hash` does on every call; the signatures are the same.
+*/
+#[derive(Clone)]
+pub struct KeyPair {
+    // As given; the nonce is derived from it
+    private_key_scalar: BigInt,
+    reduced_scalar: BigInt,
+    public_key: Point,
+}
 
-        poseidon.calculate_poseidon(input).unwrap()
+impl KeyPair {
+    // A key pair on Baby Jubjub, for SignatureScheme
+    pub fn new(private_key_scalar: BigInt) -> Self {
+        Eddsa::new(BabyJubjub).keypair(private_key_scalar)
+    }
+
+    pub(crate) fn private_key_scalar(&self) -> &BigInt {
+        &self.private_key_scalar
+    }
+
+    pub fn public_key(&self) -> &Point {
+        &self.public_key
     }
 }
 
-pub fn generate_eddsa_signature(
+pub struct SignatureScheme;
+
+impl SignatureScheme {
+    // The variable B
+    pub fn base_point() -> Point {
+        Point::generate()
+    }
+
+    // Signs a message hash produced by one of the crate's hash functions (payload
+    // Poseidon hashes, API request hashes)
+    pub fn sign_hash(private_key_scalar: BigInt, hash: &MessageHash) -> SignedMessage {
+        Self::sign_with_keypair(&KeyPair::new(private_key_scalar), hash)
+    }
+
+    // The same with the public key taken from `keypair` instead of recomputed
+    pub fn sign_with_keypair(keypair: &KeyPair, hash: &MessageHash) -> SignedMessage {
+        Eddsa::new(BabyJubjub).sign_unchecked(keypair, hash.as_bigint().clone())
+    }
+
+    // Hashes a raw message (e.g. an API signature base string) with `sha256_snark`
+    // and signs the result
+    pub fn sign_message(private_key_scalar: BigInt, message: &str) -> SignedMessage {
+        Self::sign_hash(private_key_scalar, &MessageHash::from_message(message))
+    }
+
+    #[deprecated(note = "use sign_hash with a MessageHash, or sign_message")]
+    pub fn sign(private_key_scalar: BigInt, hash: BigInt) -> SignedMessage {
+        Self::sign_hash(private_key_scalar, &MessageHash::new(hash))
+    }
+
+    // Checks s * B == R + t * A with t = H(R, A, M). Both points are expected to be
+    // validated (on the curve, in the subgroup) by the caller.
+    pub fn verify(public_key: &Point, sig: &Signature, hash: &BigInt) -> bool {
+        Eddsa::new(BabyJubjub).verify(public_key, sig, hash)
+    }
+
+    // The nonce r of Eddsa::sign
+    #[cfg(any(test, feature = "ff"))]
+    pub(crate) fn hash_secret(k: FQ, arg: &MessageHash) -> BigInt {
+        Eddsa::new(BabyJubjub).nonce(&k.to_bigint(), arg.as_bigint())
+    }
+
+    #[cfg(test)]
+    fn hash_public(image_of_r: &Point, public_key: &Point, message: BigInt) -> BigInt {
+        Eddsa::new(BabyJubjub).challenge(image_of_r, public_key, message)
+    }
+}
+
+pub fn sign_api_request<I>(
     request_type: &str,
     url: &str,
-    data: &[(&str, &str)],
+    data: I,
     hex_private_key: &str,
-) -> String {
-    let signature_base = generate_signature_base_string(request_type, url, data);
-    let hash = sha256_snark(&signature_base);
+) -> Result<String, RequestError>
+where
+    I: IntoIterator,
+    I::Item: ParamPair,
+{
+    sign_api_request_with(
+        request_type,
+        url,
+        data,
+        hex_private_key,
+        RequestHash::default(),
+    )
+}
 
-    let private_key_big_int =
-        match BigInt::from_str_radix(hex_private_key.trim_start_matches("0x"), 16) {
-            Ok(value) => value,
-            Err(_) => BigInt::zero(),
-        };
+// The same with the base string hashed by `hashing`
+pub fn sign_api_request_with<I>(
+    request_type: &str,
+    url: &str,
+    data: I,
+    hex_private_key: &str,
+    hashing: RequestHash,
+) -> Result<String, RequestError>
+where
+    I: IntoIterator,
+    I::Item: ParamPair,
+{
+    let signature_base = generate_signature_base_string(request_type, url, data)?;
+    sign_request_base(&signature_base, hex_private_key, hashing)
+}
 
-    let signed_message = SignatureScheme::sign(private_key_big_int, hash);
+// Signs a POST/PUT request with a body serialized by `helpers::post_body`; the
+// same body must be sent
+pub fn sign_api_request_body(
+    method: HttpMethod,
+    url: &str,
+    body: &str,
+    hex_private_key: &str,
+) -> Result<String, RequestError> {
+    sign_api_request_body_with(method, url, body, hex_private_key, RequestHash::default())
+}
 
-    signed_message.to_hex()
+pub fn sign_api_request_body_with(
+    method: HttpMethod,
+    url: &str,
+    body: &str,
+    hex_private_key: &str,
+    hashing: RequestHash,
+) -> Result<String, RequestError> {
+    let signature_base = body_signature_base_string(method, url, body, EncodingProfile::default())?;
+    sign_request_base(&signature_base, hex_private_key, hashing)
 }
 
-pub fn get_eddsa_sig_with_poseidon(inputs: Vec<BigInt>, private_key: String) -> String {
-    let p = SNARK_SCALAR_FIELD.clone();
-    let poseidon = Poseidon::new(
-        p,
-        inputs.len() + 1,
-        6,
-        53,
-        "poseidon".to_string(),
-        BigInt::from(5),
-        None,
-        None,
-        128,
+// Signs a GET/DELETE request from its full url, query included, e.g.
+// "https://api3.loopring.io/api/v3/user/balances?accountId=1&tokens=0,1"
+pub fn sign_url(method: &str, url: &str, hex_private_key: &str) -> Result<String, RequestError> {
+    sign_url_with(method, url, hex_private_key, RequestHash::default())
+}
+
+pub fn sign_url_with(
+    method: &str,
+    url: &str,
+    hex_private_key: &str,
+    hashing: RequestHash,
+) -> Result<String, RequestError> {
+    let signature_base = url_signature_base_string(method, url)?;
+    sign_request_base(&signature_base, hex_private_key, hashing)
+}
+
+// The one rule for private keys every signing path applies: a key that is zero
+// modulo JUBJUB_L has the neutral point as its public key, and its signatures
+// verify under that point for any message
+pub(crate) fn is_signing_key(key: &BigInt) -> bool {
+    !(key % &*JUBJUB_L).is_zero()
+}
+
+// None for malformed keys, and for keys is_signing_key rejects
+pub(crate) fn signing_key(hex_private_key: &str) -> Option<BigInt> {
+    let key = hex::decode_secret(hex_private_key).ok()?;
+    is_signing_key(&key).then_some(key)
+}
+
+fn sign_request_base(
+    signature_base: &str,
+    hex_private_key: &str,
+    hashing: RequestHash,
+) -> Result<String, RequestError> {
+    let private_key_big_int =
+        signing_key(hex_private_key).ok_or(RequestError::InvalidPrivateKey)?;
+    let hash = MessageHash::from_message_with(signature_base, hashing);
+    Ok(SignatureScheme::sign_hash(private_key_big_int, &hash).to_hex())
+}
+
+fn parse_coordinate(coordinate: &str) -> Result<BigInt, SignatureError> {
+    let value = hex::decode_bigint(coordinate).map_err(|_| SignatureError::InvalidPublicKey)?;
+    if value >= *SNARK_SCALAR_FIELD {
+        return Err(SignatureError::InvalidPublicKey);
+    }
+    Ok(value)
+}
+
+/*
+Verifies a hex signature (as produced by `to_hex`) against the public key in the
+form the API reports it (publicKey.x / publicKey.y of GET /api/v3/account).
+
+Malformed input is an error; a well-formed signature that does not match gives
+Ok(false).
+*/
+pub fn verify_eddsa_hex(
+    sig_hex: &str,
+    pubkey_x_hex: &str,
+    pubkey_y_hex: &str,
+    msg_hash: &BigInt,
+) -> Result<bool, SignatureError> {
+    let sig = Signature::from_hex(sig_hex)?;
+    let public_key = Point::new(
+        FQ::new(parse_coordinate(pubkey_x_hex)?),
+        FQ::new(parse_coordinate(pubkey_y_hex)?),
     );
+    if *msg_hash < BigInt::zero() || *msg_hash >= *SNARK_SCALAR_FIELD {
+        return Err(SignatureError::MessageOutOfRange);
+    }
+    if !public_key.is_in_subgroup() {
+        return Err(SignatureError::InvalidPoint("public key"));
+    }
+    if !sig.image_of_r().is_in_subgroup() {
+        return Err(SignatureError::InvalidPoint("R"));
+    }
+    Ok(SignatureScheme::verify(&public_key, &sig, msg_hash))
+}
 
-    let hash = poseidon.calculate_poseidon(inputs).unwrap();
+pub fn sign_poseidon_inputs(
+    inputs: Vec<BigInt>,
+    private_key: &str,
+) -> Result<String, PoseidonError> {
+    let private_key_big_int = signing_key(private_key).ok_or(PoseidonError::InvalidPrivateKey)?;
+    let hash = MessageHash::from_poseidon_inputs(inputs)?;
 
-    let private_key_big_int = match BigInt::from_str_radix(private_key.trim_start_matches("0x"), 16)
-    {
-        Ok(value) => value,
-        Err(_) => BigInt::zero(),
-    };
+    let result = SignatureScheme::sign_hash(private_key_big_int, &hash);
+    Ok(result.to_hex())
+}
+
+// The names before the sign_* scheme, kept so existing callers still build
 
-    let result = SignatureScheme::sign(private_key_big_int, hash);
-    result.to_hex()
+#[deprecated(note = "use sign_api_request")]
+pub fn generate_eddsa_signature<I>(
+    request_type: &str,
+    url: &str,
+    data: I,
+    hex_private_key: &str,
+) -> Result<String, RequestError>
+where
+    I: IntoIterator,
+    I::Item: ParamPair,
+{
+    sign_api_request(request_type, url, data, hex_private_key)
+}
+
+#[deprecated(note = "use sign_poseidon_inputs")]
+pub fn get_eddsa_sig_with_poseidon(
+    inputs: Vec<BigInt>,
+    private_key: String,
+) -> Result<String, PoseidonError> {
+    sign_poseidon_inputs(inputs, &private_key)
 }
 
 #[cfg(test)]
@@ -236,6 +618,7 @@ mod tests {
     use std::{str::FromStr, time::Instant};
 
     use super::*;
+    use num_bigint::BigUint;
     #[test]
     fn hash_secret_test() {
         let k = FQ::new(BigInt::one());
@@ -244,7 +627,7 @@ mod tests {
         )
         .unwrap();
 
-        let result = SignatureScheme::hash_secret(k, &arg);
+        let result = SignatureScheme::hash_secret(k, &MessageHash::new(arg));
         assert_eq!(
             result,
             BigInt::from_str(
@@ -311,7 +694,7 @@ mod tests {
         let private_key = BigInt::from(1);
         let start = Instant::now();
 
-        let signed = SignatureScheme::sign(private_key, msg_hash);
+        let signed = SignatureScheme::sign_hash(private_key, &MessageHash::new(msg_hash));
         let duration = start.elapsed();
         println!("{}", duration.as_secs());
         assert_eq!(signed.to_string(), "16540640123574156134436876038791482806971768689494387082833631921987005038935 20819045374670962167435360035096875258406992893633759881276124905556507972311 4991609103248925747358645194965349262579784734809679007552644294476920671344 423391641476660815714427268720766993055332927752794962916609674122318189741 4678160339597842896640121413028167917237396460457527040724180632868306529961 20693456676802104653139582814194312788878632719314804297029697306071204881418" )
@@ -327,24 +710,24 @@ mod tests {
             "20823375595941673465102915960468301465677704522962441935281926279865178787657",
         )
         .unwrap();
-        let signed = SignatureScheme::sign(key, msg);
+        let signed = SignatureScheme::sign_hash(key, &MessageHash::new(msg));
         assert_eq!(
-            *signed.sig().image_of_r().x().n(),
-            BigInt::from_str(
+            *signed.sig().image_of_r().x().n_uint(),
+            BigUint::from_str(
                 "2114973053955517366033592592501464590076342821657201629830614924692550700766"
             )
             .unwrap()
         );
         assert_eq!(
-            *signed.sig().image_of_r().y().n(),
-            BigInt::from_str(
+            *signed.sig().image_of_r().y().n_uint(),
+            BigUint::from_str(
                 "6713953096854639492359183468711112854151280690992619923536842965423886430417"
             )
             .unwrap()
         );
         assert_eq!(
-            *signed.sig().s().n(),
-            BigInt::from_str(
+            *signed.sig().s().n_uint(),
+            BigUint::from_str(
                 "21100876117443371431735908718802018647851328087147897184613053393129281831653"
             )
             .unwrap()
@@ -357,15 +740,269 @@ mod tests {
         let request_type = "POST";
         let url = "https://api3.loopring.io/api/v3/apiKey";
 
-        let result = generate_eddsa_signature(request_type, url, data, l2_key);
+        let result = sign_api_request(request_type, url, data, l2_key).unwrap();
         assert_eq!(result.as_str(), "0x15fdcda3ca2965d2ae43739cc6740e50c08d3f756c6161bcedb10fbc05290e000f3bc31e2293ba91ca7ac55cd20a86ae3541d3dfed63896cd474015ec60b8d40274f98b2d0a87ebf8cd0ee16dc9ec953a229cf0d6b2b61867ca80ba6e8ae1ed3");
+
+        let body = r#"{"accountId":"12345"}"#;
+        assert_eq!(
+            sign_api_request_body(HttpMethod::Post, url, body, l2_key).unwrap(),
+            result
+        );
     }
+
     #[test]
-    fn generate_eddsa_sig_with_poseidon() {
+    fn request_hash_strategies() {
+        let l2_key = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+        let data: &[(&str, &str)] = &[("accountId", "12345")];
+        let url = "https://api3.loopring.io/api/v3/apiKey";
+        let base = generate_signature_base_string("GET", url, data).unwrap();
+        let private_key = hex::decode_bigint(l2_key).unwrap();
+
+        assert_eq!(
+            sign_api_request_with("GET", url, data, l2_key, RequestHash::Sha256Snark).unwrap(),
+            sign_api_request("GET", url, data, l2_key).unwrap()
+        );
+        for hashing in [RequestHash::Keccak256, RequestHash::PoseidonBytes] {
+            let hash = MessageHash::from_message_with(&base, hashing);
+            assert!(hash != MessageHash::from_message(&base));
+            assert_eq!(
+                sign_api_request_with("GET", url, data, l2_key, hashing).unwrap(),
+                SignatureScheme::sign_hash(private_key.clone(), &hash).to_hex()
+            );
+        }
+    }
+    #[test]
+    fn parse_signature_round_trip() {
+        let l2_key = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+        let signed = SignatureScheme::sign_hash(
+            hex::decode_bigint(l2_key).unwrap(),
+            &MessageHash::new(BigInt::from(42)),
+        );
+
+        let signature = Signature::from_hex(&signed.to_hex()).unwrap();
+        assert_eq!(
+            signature.image_of_r().x().n_uint(),
+            signed.sig().image_of_r().x().n_uint()
+        );
+        assert_eq!(
+            signature.image_of_r().y().n_uint(),
+            signed.sig().image_of_r().y().n_uint()
+        );
+        assert_eq!(signature.s().n_uint(), signed.sig().s().n_uint());
+
+        // The byte form carries the same values, little-endian
+        let bytes = signed.sig().to_bytes();
+        let from_bytes = Signature::from_bytes(&bytes).unwrap();
+        assert_eq!(from_bytes.to_string(), signature.to_string());
+        let mut hex_bytes = hex::decode_bytes(&signed.to_hex()).unwrap();
+        hex_bytes[64..].reverse();
+        assert_eq!(&bytes[64..], &hex_bytes[64..]);
+
+        let mut s_too_large = bytes;
+        s_too_large[95] = 0xff;
+        assert!(matches!(
+            Signature::from_bytes(&s_too_large),
+            Err(ConversionError::ValueExceedsModulus)
+        ));
+    }
+
+    #[test]
+    fn parse_signature_reports_malformed_part() {
+        let valid = "0x15fdcda3ca2965d2ae43739cc6740e50c08d3f756c6161bcedb10fbc05290e000f3bc31e2293ba91ca7ac55cd20a86ae3541d3dfed63896cd474015ec60b8d40274f98b2d0a87ebf8cd0ee16dc9ec953a229cf0d6b2b61867ca80ba6e8ae1ed3";
+        assert!(parse_signature_components(valid).is_ok());
+
+        assert_eq!(
+            parse_signature_components(&valid[2..]).unwrap(),
+            parse_signature_components(&valid.to_uppercase().replace("0X", "0X ")).unwrap()
+        );
+        assert_eq!(
+            parse_signature_components(&valid[..100]).unwrap_err(),
+            SignatureError::InvalidLength(98)
+        );
+
+        let mut bad_ry = valid.to_string();
+        bad_ry.replace_range(70..71, "g");
+        assert_eq!(
+            parse_signature_components(&bad_ry).unwrap_err(),
+            SignatureError::InvalidHex(SignaturePart::Ry)
+        );
+
+        let big_s = format!("{}{}", &valid[..130], "f".repeat(64));
+        assert_eq!(
+            parse_signature_components(&big_s).unwrap_err(),
+            SignatureError::OutOfRange(SignaturePart::S)
+        );
+    }
+
+    #[test]
+    fn verify_eddsa_hex_test() {
+        // Key pair from the keygen tests
+        let l2_key = "0x001fa186947c8c644cd11078f67e0bb21656432f55c4df76997b6acab2abda7f";
+        let x = "0x29d178cdd6a40cd900c41565b6057a1d12c00a8c41ad367e2fe0100aab00fbe3";
+        let y = "0x29e339a045af33d5729eab3b64c617e6a78dcfd0988f95f215d443d77a864b9c";
+        let hash = sha256_snark(
+            "GET&https%3A%2F%2Fapi3.loopring.io%2Fapi%2Fv3%2FapiKey&accountId%3D12345",
+        );
+        let sig = SignatureScheme::sign_hash(
+            hex::decode_bigint(l2_key).unwrap(),
+            &MessageHash::new(hash.clone()),
+        )
+        .to_hex();
+
+        assert!(verify_eddsa_hex(&sig, x, y, &hash).unwrap());
+        assert!(!verify_eddsa_hex(&sig, x, y, &(hash + 1)).unwrap());
+
+        // Swapped coordinates are not a point on the curve
+        assert_eq!(
+            verify_eddsa_hex(&sig, y, x, &BigInt::one()).unwrap_err(),
+            SignatureError::InvalidPoint("public key")
+        );
+        assert_eq!(
+            verify_eddsa_hex(&sig, "0x29dz", y, &BigInt::one()).unwrap_err(),
+            SignatureError::InvalidPublicKey
+        );
+    }
+
+    #[test]
+    fn sign_poseidon_inputs_test() {
         let l2_key = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
         let inputs = vec![BigInt::from(2), BigInt::from(5), BigInt::from(7)];
-        let result = get_eddsa_sig_with_poseidon(inputs, l2_key.to_string());
+        let result = sign_poseidon_inputs(inputs, l2_key).unwrap();
+        assert!(sign_poseidon_inputs(vec![], l2_key).is_err());
+        // Malformed and zero keys used to sign with the scalar 0
+        let zero = format!("0x{:064x}", 0);
+        let order = format!("0x{:064x}", &*JUBJUB_L);
+        for key in ["0x12zz", "", &zero, &order] {
+            assert!(matches!(
+                sign_poseidon_inputs(vec![BigInt::from(2)], key),
+                Err(PoseidonError::InvalidPrivateKey)
+            ));
+            assert!(matches!(
+                sign_api_request(
+                    "GET",
+                    "https://api3.loopring.io/api/v3/apiKey",
+                    [("accountId", "1")],
+                    key
+                ),
+                Err(RequestError::InvalidPrivateKey)
+            ));
+        }
 
         assert_eq!(result, "0x0659e9406f7c3a0e1bd6ec42e69ca4a013e21253ff8abd216d9411b882b263502d99f4229cf3f10991e7999bf45b55f4afa9976e237df94378fd647fdb5a5eec0f944d06f57d08b23f3327334c43198a9c78d477a3f0f3e30f0c2c464f5319be".to_string());
     }
+
+    #[test]
+    #[allow(deprecated)]
+    fn deprecated_names_match_their_replacements() {
+        let l2_key = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+        let url = "https://api3.loopring.io/api/v3/apiKey";
+        assert_eq!(
+            generate_eddsa_signature("GET", url, [("accountId", "12345")], l2_key),
+            sign_api_request("GET", url, [("accountId", "12345")], l2_key)
+        );
+        let inputs = vec![BigInt::from(2), BigInt::from(5)];
+        assert_eq!(
+            get_eddsa_sig_with_poseidon(inputs.clone(), l2_key.to_string()).unwrap(),
+            sign_poseidon_inputs(inputs, l2_key).unwrap()
+        );
+        let hash = MessageHash::from_message("message");
+        assert_eq!(
+            SignatureScheme::sign(BigInt::from(3), hash.as_bigint().clone()).to_hex(),
+            SignatureScheme::sign_hash(BigInt::from(3), &hash).to_hex()
+        );
+    }
+
+    #[test]
+    fn message_hash_entry_points() {
+        let key = BigInt::from(1);
+        let base = "GET&https%3A%2F%2Fapi3.loopring.io%2Fapi%2Fv3%2FapiKey&accountId%3D12345";
+
+        assert_eq!(
+            SignatureScheme::sign_message(key.clone(), base).to_hex(),
+            SignatureScheme::sign_hash(key, &MessageHash::new(sha256_snark(base))).to_hex()
+        );
+        assert!(MessageHash::from_prehashed(SNARK_SCALAR_FIELD.clone()).is_err());
+        assert!(MessageHash::from_prehashed(BigInt::from(-1)).is_err());
+        assert!(MessageHash::from_prehashed(BigInt::from(42)).is_ok());
+    }
+
+    #[test]
+    fn sign_url_matches_param_signing() {
+        let l2_key = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+        let url = "https://api3.loopring.io/api/v3/apiKey";
+        assert_eq!(
+            sign_url("GET", &format!("{}?accountId=12345", url), l2_key),
+            sign_api_request("GET", url, [("accountId", "12345")], l2_key)
+        );
+        assert_eq!(
+            sign_url("PUT", url, l2_key),
+            Err(RequestError::NoQuery("PUT"))
+        );
+    }
+
+    #[test]
+    fn keypair_signatures_match() {
+        let hash = MessageHash::from_message("message");
+        for key in [
+            BigInt::from(1),
+            BigInt::from_str(
+                "1965533437444427599736796973543479035828634172708055838572430750620147597402",
+            )
+            .unwrap(),
+            // Above the curve order: only k mod E enters A and s
+            &*JUBJUB_E + 5u8,
+        ] {
+            let keypair = KeyPair::new(key.clone());
+            assert!(*keypair.public_key() == SignatureScheme::base_point().mul_scalar(&key));
+            assert_eq!(keypair.private_key_scalar(), &key);
+            let signed = SignatureScheme::sign_with_keypair(&keypair, &hash);
+            assert_eq!(
+                signed.to_hex(),
+                SignatureScheme::sign_hash(key, &hash).to_hex()
+            );
+            assert!(SignatureScheme::verify(
+                keypair.public_key(),
+                signed.sig(),
+                hash.as_bigint()
+            ));
+        }
+    }
+
+    #[test]
+    fn generic_curves() {
+        use crate::poseidon::curve::tests::custom_baby_jubjub;
+
+        let key = BigInt::from_str(
+            "1965533437444427599736796973543479035828634172708055838572430750620147597402",
+        )
+        .unwrap();
+        let hash = sha256_snark("message");
+
+        // Loopring's constants through the generic arithmetic give Loopring's signature
+        let loopring = Eddsa::new(custom_baby_jubjub(&Point::generate()).unwrap());
+        let signed = loopring.sign(key.clone(), &hash).unwrap();
+        let expected = SignatureScheme::sign_hash(key.clone(), &MessageHash::new(hash.clone()));
+        assert_eq!(signed.to_hex(), expected.to_hex());
+        assert!(loopring.verify(signed.public_key(), signed.sig(), &hash));
+        assert!(matches!(
+            loopring.sign(key.clone(), &SNARK_SCALAR_FIELD),
+            Err(SignatureError::MessageOutOfRange)
+        ));
+
+        // Another generator is another deployment
+        let other = Eddsa::new(
+            custom_baby_jubjub(&Point::generate().mul_scalar(&BigInt::from(5))).unwrap(),
+        );
+        let signed = other.sign(key.clone(), &hash).unwrap();
+        assert!(other.verify(signed.public_key(), signed.sig(), &hash));
+        assert!(
+            *signed.public_key() == SignatureScheme::base_point().mul_scalar(&(key.clone() * 5))
+        );
+        assert!(!Eddsa::<BabyJubjub>::default().verify(signed.public_key(), signed.sig(), &hash));
+        assert!(!SignatureScheme::verify(
+            signed.public_key(),
+            signed.sig(),
+            &hash
+        ));
+    }
 }
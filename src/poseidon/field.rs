@@ -1,87 +1,188 @@
-use num_bigint::BigInt;
-use num_traits::{self, Euclid, One};
-use std::{
-    ops::{Add, Div, Mul, Sub},
-    str::FromStr,
-};
-
-lazy_static! {
-    // This number is the base field of JubJub (elliptic cruve) and refers to the finite field over which the curve is defined.
-    // The operation on the point of the elliptic curve are carried out within this field.
-    // This is a large prime that specifies the order of the base field F_Q.
-    // The coordinates of points on the Jubjub Elliptic curve are done mudolo this prime number
-    pub static ref SNARK_SCALAR_FIELD: BigInt = BigInt::from_str(
-        "21888242871839275222246405745257275088548364400416034343698204186575808495617"
-    )
-    .unwrap();
-
-    // When dealing with cryptographic operations, like point multiplication, you are dealing with scalars.
-    // The constant FR_ORDER is the order of the scalar field, a prime number slightly different from SNARK_SCALAR_FIELD
-    // It indicates how many different scalars there are in the set that you can use for these cryptographic operations
-    pub static ref FR_ORDER: BigInt = BigInt::from_str(
-        "21888242871839275222246405745257275088614511777268538073601725287587578984328"
-    )
-    .unwrap();
-}
+use super::bignum::pow_mod;
+use num_bigint::{BigInt, BigUint};
+use num_traits::{self, Euclid, One, Zero};
+use std::borrow::Cow;
+use std::ops::{Add, Div, Mul, Sub};
+use std::sync::OnceLock;
+
+pub use crate::constants::{FR_ORDER, SNARK_SCALAR_FIELD};
 
 // Implementation of the base field F_Q.
 // It has the form: n mod m.
 // m is the field modulus.
+//
+// Every element is kept as its canonical representative 0 <= n < m, so the
+// arithmetic is done on unsigned integers. BigInt is only accepted at the edges
+// (`new`, `with_modulus`), where negative values are mapped to their positive
+// representative.
+//
+// Elements of SNARK_SCALAR_FIELD, which is nearly all of them, borrow the static
+// modulus instead of owning a copy, so arithmetic does not allocate one per
+// result. Only a custom modulus (`with_modulus`) is owned.
 pub struct FQ {
-    n: BigInt,
-    m: BigInt,
+    n: BigUint,
+    m: Cow<'static, BigUint>,
+    // n (and an owned m) as BigInt, made on first use of the deprecated `n`/`m`
+    signed: OnceLock<Box<(BigInt, BigInt)>>,
 }
 
+use crate::constants::snark_scalar_field_uint;
+
 impl FQ {
-    pub fn n(&self) -> &BigInt {
+    pub fn n_uint(&self) -> &BigUint {
         &self.n
     }
-    pub fn m(&self) -> &BigInt {
+    pub fn m_uint(&self) -> &BigUint {
         &self.m
     }
 
+    fn signed(&self) -> &(BigInt, BigInt) {
+        self.signed.get_or_init(|| {
+            let m = match &self.m {
+                Cow::Borrowed(_) => BigInt::zero(),
+                Cow::Owned(m) => BigInt::from(m.clone()),
+            };
+            Box::new((BigInt::from(self.n.clone()), m))
+        })
+    }
+
+    #[deprecated(note = "use n_uint, or to_bigint for an owned BigInt")]
+    pub fn n(&self) -> &BigInt {
+        &self.signed().0
+    }
+
+    #[deprecated(note = "use m_uint")]
+    pub fn m(&self) -> &BigInt {
+        match &self.m {
+            Cow::Borrowed(_) => &SNARK_SCALAR_FIELD,
+            Cow::Owned(_) => &self.signed().1,
+        }
+    }
+
+    // The value as a signed integer, for APIs that work with BigInt
+    pub fn to_bigint(&self) -> BigInt {
+        BigInt::from(self.n.clone())
+    }
+
     pub fn new(n: BigInt) -> Self {
-        Self::with_modulus(n, SNARK_SCALAR_FIELD.clone())
+        FQ {
+            n: n.rem_euclid(&SNARK_SCALAR_FIELD).into_parts().1,
+            m: Cow::Borrowed(snark_scalar_field_uint()),
+            signed: OnceLock::new(),
+        }
     }
 
     pub fn with_modulus(n: BigInt, m: BigInt) -> Self {
-        FQ { n: n % &m, m: m }
+        if m == *SNARK_SCALAR_FIELD {
+            return Self::new(n);
+        }
+        let n = n.rem_euclid(&m);
+        FQ {
+            n: n.into_parts().1,
+            m: Cow::Owned(m.into_parts().1),
+            signed: OnceLock::new(),
+        }
+    }
+
+    pub fn from_biguint(n: BigUint) -> Self {
+        FQ {
+            n: n % snark_scalar_field_uint(),
+            m: Cow::Borrowed(snark_scalar_field_uint()),
+            signed: OnceLock::new(),
+        }
     }
 
     pub fn one() -> Self {
         FQ {
-            n: BigInt::from(1),
-            m: SNARK_SCALAR_FIELD.clone(),
+            n: BigUint::one(),
+            m: Cow::Borrowed(snark_scalar_field_uint()),
+            signed: OnceLock::new(),
         }
     }
 
     pub fn zero() -> Self {
         FQ {
-            n: BigInt::from(0),
-            m: SNARK_SCALAR_FIELD.clone(),
+            n: BigUint::from(0u8),
+            m: Cow::Borrowed(snark_scalar_field_uint()),
+            signed: OnceLock::new(),
         }
     }
-    fn addition(n1: &BigInt, n2: &BigInt, modulus: &BigInt) -> Self {
-        let new_n = (n1 + n2) % modulus;
+    // A square root if the element is a quadratic residue (Tonelli-Shanks).
+    // Which of the two roots is returned is up to the algorithm; callers that
+    // need a canonical one choose the sign themselves.
+    pub fn sqrt(&self) -> Option<FQ> {
+        let m: &BigUint = &self.m;
+        if self.n.is_zero() {
+            return Some(self.clone());
+        }
+        let one = BigUint::one();
+        let m_minus_one = m - &one;
+        let euler = &m_minus_one >> 1;
+        if pow_mod(&self.n, &euler, m) != one {
+            return None;
+        }
+
+        // m - 1 = q * 2^s with q odd
+        let s = m_minus_one.trailing_zeros().unwrap_or(0);
+        let q = &m_minus_one >> s;
+        let mut z = BigUint::from(2u8);
+        while pow_mod(&z, &euler, m) == one {
+            z += 1u8;
+        }
+
+        let mut exponent = s;
+        let mut c = pow_mod(&z, &q, m);
+        let mut t = pow_mod(&self.n, &q, m);
+        let mut root = pow_mod(&self.n, &((&q + 1u8) >> 1), m);
+        while t != one {
+            // The least i with t^(2^i) = 1
+            let mut i = 0;
+            let mut t_pow = t.clone();
+            while t_pow != one {
+                t_pow = &t_pow * &t_pow % m;
+                i += 1;
+            }
+            let b = pow_mod(&c, &(BigUint::one() << (exponent - i - 1)), m);
+            root = root * &b % m;
+            c = &b * &b % m;
+            t = t * &c % m;
+            exponent = i;
+        }
+        Some(FQ {
+            n: root,
+            m: self.m.clone(),
+            signed: OnceLock::new(),
+        })
+    }
+
+    // The operations keep the left operand's modulus
+    fn addition(&self, rhs_n: &BigUint) -> Self {
+        let new_n = (&self.n + rhs_n) % self.m_uint();
         FQ {
             n: new_n,
-            m: modulus.clone(),
+            m: self.m.clone(),
+            signed: OnceLock::new(),
         }
     }
 
-    fn subtract(n1: &BigInt, n2: &BigInt, m: &BigInt) -> Self {
-        let new_n = (n1 - n2).rem_euclid(m);
+    fn subtract(&self, rhs_n: &BigUint) -> Self {
+        // The right operand may have a different (larger) modulus, so it is
+        // reduced first; adding m then keeps the difference non-negative
+        let m = self.m_uint();
+        let new_n = (&self.n + m - rhs_n % m) % m;
         FQ {
             n: new_n,
-            m: m.clone(),
+            m: self.m.clone(),
+            signed: OnceLock::new(),
         }
     }
 
-    fn multiply(n1: &BigInt, n2: &BigInt, modulus: &BigInt) -> Self {
-        let new_n = (n1 * n2) % modulus;
+    fn multiply(&self, rhs_n: &BigUint) -> Self {
+        let new_n = (&self.n * rhs_n) % self.m_uint();
         FQ {
             n: new_n,
-            m: modulus.clone(),
+            m: self.m.clone(),
+            signed: OnceLock::new(),
         }
     }
 
@@ -91,43 +192,44 @@ impl FQ {
     // So our final calculation looks like this: n1 * n2^(p-2) mod m.
     // Where n1 is the number of the first Point and n2 is the number of the second Point.
 
-    fn divide(n: &BigInt, m: &BigInt, rhs_n: &BigInt, rhs_m: &BigInt) -> Self {
-        let fermat_exponent = rhs_m - (BigInt::one() + BigInt::one());
-        let multiplicative_inverse: BigInt = rhs_n.modpow(&fermat_exponent, rhs_m);
-        let result = (n * multiplicative_inverse) % m;
+    fn divide(&self, rhs_n: &BigUint, rhs_m: &BigUint) -> Self {
+        let fermat_exponent = rhs_m - BigUint::from(2u8);
+        let multiplicative_inverse = pow_mod(rhs_n, &fermat_exponent, rhs_m);
+        let result = (&self.n * multiplicative_inverse) % self.m_uint();
 
         FQ {
             n: result,
-            m: m.clone(),
+            m: self.m.clone(),
+            signed: OnceLock::new(),
         }
     }
 }
 impl Add for FQ {
     type Output = Self;
     fn add(self, rhs: Self) -> Self::Output {
-        FQ::addition(&self.n, &rhs.n, &self.m)
+        self.addition(&rhs.n)
     }
 }
-impl<'a, 'b> Add<&'b FQ> for &'a FQ {
+impl<'b> Add<&'b FQ> for &FQ {
     type Output = FQ;
 
     fn add(self, rhs: &'b FQ) -> FQ {
-        FQ::addition(&self.n, &rhs.n, &self.m)
+        self.addition(&rhs.n)
     }
 }
 impl<'a> Add<&'a FQ> for FQ {
     type Output = FQ;
 
     fn add(self, rhs: &'a FQ) -> Self::Output {
-        FQ::addition(&self.n, &rhs.n, &self.m)
+        self.addition(&rhs.n)
     }
 }
 
-impl<'a> Add<FQ> for &'a FQ {
+impl Add<FQ> for &FQ {
     type Output = FQ;
 
     fn add(self, rhs: FQ) -> Self::Output {
-        FQ::addition(&self.n, &rhs.n, &self.m)
+        self.addition(&rhs.n)
     }
 }
 
@@ -135,42 +237,42 @@ impl Sub for FQ {
     type Output = Self;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        FQ::subtract(&self.n, &rhs.n, &self.m)
+        self.subtract(&rhs.n)
     }
 }
 
-impl<'a, 'b> Sub<&'b FQ> for &'a FQ {
+impl<'b> Sub<&'b FQ> for &FQ {
     type Output = FQ;
 
     fn sub(self, rhs: &'b FQ) -> Self::Output {
-        FQ::subtract(&self.n, &rhs.n, &self.m)
+        self.subtract(&rhs.n)
     }
 }
 
 impl<'a> Sub<&'a FQ> for FQ {
     type Output = FQ;
     fn sub(self, rhs: &'a FQ) -> Self::Output {
-        FQ::subtract(&self.n, &rhs.n, &self.m)
+        self.subtract(&rhs.n)
     }
 }
-impl<'a> Sub<FQ> for &'a FQ {
+impl Sub<FQ> for &FQ {
     type Output = FQ;
     fn sub(self, rhs: FQ) -> Self::Output {
-        FQ::subtract(&self.n, &rhs.n, &self.m)
+        self.subtract(&rhs.n)
     }
 }
 
 impl Mul for FQ {
     type Output = Self;
     fn mul(self, rhs: Self) -> Self::Output {
-        FQ::multiply(&self.n, &rhs.n, &self.m)
+        self.multiply(&rhs.n)
     }
 }
-impl<'a, 'b> Mul<&'b FQ> for &'a FQ {
+impl<'b> Mul<&'b FQ> for &FQ {
     type Output = FQ;
 
     fn mul(self, rhs: &'b FQ) -> Self::Output {
-        FQ::multiply(&self.n, &rhs.n, &self.m)
+        self.multiply(&rhs.n)
     }
 }
 
@@ -178,14 +280,14 @@ impl<'a> Mul<&'a FQ> for FQ {
     type Output = FQ;
 
     fn mul(self, rhs: &'a FQ) -> Self::Output {
-        FQ::multiply(&self.n, &rhs.n, &self.m)
+        self.multiply(&rhs.n)
     }
 }
 
-impl<'a> Mul<FQ> for &'a FQ {
+impl Mul<FQ> for &FQ {
     type Output = FQ;
     fn mul(self, rhs: FQ) -> Self::Output {
-        FQ::multiply(&self.n, &rhs.n, &self.m)
+        self.multiply(&rhs.n)
     }
 }
 
@@ -193,37 +295,58 @@ impl Div for FQ {
     type Output = Self;
 
     fn div(self, rhs: Self) -> Self::Output {
-        FQ::divide(&self.n, &self.m, &rhs.n, &rhs.m)
+        self.divide(&rhs.n, &rhs.m)
     }
 }
 
-impl<'a, 'b> Div<&'b FQ> for &'a FQ {
+impl<'b> Div<&'b FQ> for &FQ {
     type Output = FQ;
 
     fn div(self, rhs: &'b FQ) -> Self::Output {
-        FQ::divide(&self.n, &self.m, &rhs.n, &rhs.m)
+        self.divide(&rhs.n, &rhs.m)
     }
 }
 
 impl<'a> Div<&'a FQ> for FQ {
     type Output = FQ;
     fn div(self, rhs: &'a FQ) -> Self::Output {
-        FQ::divide(&self.n, &self.m, &rhs.n, &rhs.m)
+        self.divide(&rhs.n, &rhs.m)
     }
 }
 
-impl<'a> Div<FQ> for &'a FQ {
+impl Div<FQ> for &FQ {
     type Output = FQ;
     fn div(self, rhs: FQ) -> Self::Output {
-        FQ::divide(&self.n, &self.m, &rhs.n, &rhs.m)
+        self.divide(&rhs.n, &rhs.m)
     }
 }
 
+impl From<FQ> for BigInt {
+    fn from(value: FQ) -> Self {
+        BigInt::from(value.n)
+    }
+}
+
+impl From<&FQ> for BigInt {
+    fn from(value: &FQ) -> Self {
+        value.to_bigint()
+    }
+}
+
+impl PartialEq for FQ {
+    fn eq(&self, other: &Self) -> bool {
+        self.n == other.n && self.m == other.m
+    }
+}
+
+impl Eq for FQ {}
+
 impl Clone for FQ {
     fn clone(&self) -> Self {
         Self {
             n: self.n.clone(),
             m: self.m.clone(),
+            signed: OnceLock::new(),
         }
     }
 
@@ -236,6 +359,7 @@ impl Clone for FQ {
 mod tests {
 
     use super::*;
+    use std::str::FromStr;
 
     #[test]
     fn field_addition() {
@@ -251,7 +375,7 @@ mod tests {
         let field_1 = FQ::new(n1);
         let field_2 = FQ::new(n2);
         let result = field_1.add(field_2);
-        let real_result = BigInt::from_str(
+        let real_result = BigUint::from_str(
             "17039040678035688098169083453273431042237471845415528443436549201766984616476",
         )
         .unwrap();
@@ -276,7 +400,7 @@ mod tests {
 
         assert_eq!(
             result_1.n,
-            BigInt::from_str(
+            BigUint::from_str(
                 "16911001225623998484954629116385084127031100907862540193373456587741367357168",
             )
             .unwrap()
@@ -284,7 +408,7 @@ mod tests {
 
         assert_eq!(
             result_2.n,
-            BigInt::from_str(
+            BigUint::from_str(
                 "4977241646215276737291776628872190961517263492553494150324747598834441138449",
             )
             .unwrap()
@@ -315,7 +439,7 @@ mod tests {
 
         assert_eq!(
             result_1.n,
-            BigInt::from_str(
+            BigUint::from_str(
                 "18182554182870232023808950424673874478127155834326600840622566402557800401919"
             )
             .unwrap()
@@ -323,7 +447,7 @@ mod tests {
 
         assert_eq!(
             result_2.n,
-            BigInt::from_str(
+            BigUint::from_str(
                 "7078307911818432186422689430568175567157289995259698798344014234848622444761"
             )
             .unwrap()
@@ -355,7 +479,7 @@ mod tests {
 
         assert_eq!(
             result1.n,
-            BigInt::from_str(
+            BigUint::from_str(
                 "9916021784047275937858878444139751840705039734455470105457699170412095765019"
             )
             .unwrap()
@@ -363,12 +487,67 @@ mod tests {
 
         assert_eq!(
             result2.n,
-            BigInt::from_str(
+            BigUint::from_str(
                 "4046019741176394233170180050870245201959085245483667903544123842500354019676"
             )
             .unwrap()
         );
 
-        assert_eq!(result3.n, BigInt::from_str("1").unwrap());
+        assert_eq!(result3.n, BigUint::from_str("1").unwrap());
+    }
+
+    #[test]
+    fn sqrt_finds_a_root_of_residues_only() {
+        for n in [0u64, 1, 4, 5, 1234567] {
+            let square = FQ::new(BigInt::from(n)) * FQ::new(BigInt::from(n));
+            let root = square.sqrt().unwrap();
+            assert!(root.clone() * root == square);
+        }
+        // 5 generates the multiplicative group, so it is not a square
+        assert!(FQ::new(BigInt::from(5)).sqrt().is_none());
+    }
+
+    #[test]
+    fn negative_values_are_reduced_to_canonical_representative() {
+        let minus_one = FQ::new(BigInt::from(-1));
+        assert!(minus_one == FQ::zero() - FQ::one());
+        assert_eq!(minus_one.to_bigint(), &*SNARK_SCALAR_FIELD - 1);
+
+        let value = FQ::with_modulus(BigInt::from(-3), BigInt::from(7));
+        assert_eq!(*value.n_uint(), BigUint::from(4u8));
+    }
+
+    #[test]
+    fn results_keep_the_modulus() {
+        let custom = FQ::with_modulus(BigInt::from(5), BigInt::from(7));
+        let product = custom.clone() * &custom;
+        assert_eq!(*product.n_uint(), BigUint::from(4u8));
+        assert_eq!(*product.m_uint(), BigUint::from(7u8));
+        assert_eq!(*(product - custom).m_uint(), BigUint::from(7u8));
+
+        // Mixed moduli: the right operand exceeds n + m of the left one
+        let large = FQ::new(BigInt::from(1000));
+        let difference = FQ::with_modulus(BigInt::from(3), BigInt::from(7)) - &large;
+        assert_eq!(*difference.n_uint(), BigUint::from(4u8));
+        assert_eq!(*difference.m_uint(), BigUint::from(7u8));
+
+        let spelled_out = FQ::with_modulus(BigInt::from(-1), SNARK_SCALAR_FIELD.clone());
+        assert!(spelled_out == FQ::new(BigInt::from(-1)));
+        assert!(matches!(spelled_out.m, Cow::Borrowed(_)));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn deprecated_bigint_accessors() {
+        let element = FQ::new(BigInt::from(-1));
+        assert_eq!(*element.n(), &*SNARK_SCALAR_FIELD - 1);
+        assert_eq!(*element.n(), element.to_bigint());
+        assert_eq!(element.m(), &*SNARK_SCALAR_FIELD);
+        let custom = FQ::with_modulus(BigInt::from(12), BigInt::from(7));
+        assert_eq!(
+            (custom.n(), custom.m()),
+            (&BigInt::from(5), &BigInt::from(7))
+        );
+        assert_eq!(custom.m_uint(), &BigUint::from(7u8));
     }
 }
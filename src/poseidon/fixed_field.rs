@@ -0,0 +1,112 @@
+/*
+Fixed-modulus variant of FQ implementing the `ff` crate's Field/PrimeField traits.
+
+`ff::Field` requires a `Copy` type, which the BigInt-backed FQ cannot be, so this
+type stores the element as four 64-bit limbs in Montgomery form, generated by
+`ff`'s derive macro over SNARK_SCALAR_FIELD (the BN254 scalar field). 7 is used as
+the multiplicative generator, the same choice as the zkcrypto/halo2 BN254 crates.
+
+Canonical representations are 32 little-endian bytes, matching `FQ::to_bytes_le`.
+*/
+
+use super::field::{FQ, SNARK_SCALAR_FIELD};
+use crate::util::errors::ConversionError;
+use ff::PrimeField;
+use num_bigint::BigUint;
+
+#[derive(PrimeField)]
+#[PrimeFieldModulus = "21888242871839275222246405745257275088548364400416034343698204186575808495617"]
+#[PrimeFieldGenerator = "7"]
+#[PrimeFieldReprEndianness = "little"]
+pub struct FieldElement([u64; 4]);
+
+impl TryFrom<&FQ> for FieldElement {
+    type Error = ConversionError;
+
+    fn try_from(value: &FQ) -> Result<Self, Self::Error> {
+        if value.m_uint() != SNARK_SCALAR_FIELD.magnitude() {
+            return Err(ConversionError::ModulusMismatch);
+        }
+        let bytes = value.n_uint().to_bytes_le();
+        let mut repr = FieldElementRepr::default();
+        repr.as_mut()[..bytes.len()].copy_from_slice(&bytes);
+        Option::from(FieldElement::from_repr(repr)).ok_or(ConversionError::ValueExceedsModulus)
+    }
+}
+
+impl TryFrom<FQ> for FieldElement {
+    type Error = ConversionError;
+
+    fn try_from(value: FQ) -> Result<Self, Self::Error> {
+        FieldElement::try_from(&value)
+    }
+}
+
+impl From<&FieldElement> for FQ {
+    fn from(value: &FieldElement) -> Self {
+        let repr = value.to_repr();
+        FQ::from_biguint(BigUint::from_bytes_le(repr.as_ref()))
+    }
+}
+
+impl From<FieldElement> for FQ {
+    fn from(value: FieldElement) -> Self {
+        FQ::from(&value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ff::Field;
+    use num_bigint::BigInt;
+    use std::str::FromStr;
+
+    fn sample() -> (FQ, FQ) {
+        let a = BigInt::from_str(
+            "16975020951829843291561856284829257584634286376639034318405002894754175986822",
+        )
+        .unwrap();
+        let b = BigInt::from_str(
+            "64019726205844806607227168444173457603185468776494125031546307012808629654",
+        )
+        .unwrap();
+        (FQ::new(a), FQ::new(b))
+    }
+
+    // Written once against the trait, the way downstream generic code would use it
+    fn sum_of_squares<F: Field>(values: &[F]) -> F {
+        values.iter().fold(F::ZERO, |acc, v| acc + v.square())
+    }
+
+    #[test]
+    fn round_trip_through_fq() {
+        let (a, _) = sample();
+        let element = FieldElement::try_from(&a).unwrap();
+        assert_eq!(*FQ::from(element).n_uint(), *a.n_uint());
+    }
+
+    #[test]
+    fn arithmetic_agrees_with_fq() {
+        let (a, b) = sample();
+        let fa = FieldElement::try_from(&a).unwrap();
+        let fb = FieldElement::try_from(&b).unwrap();
+
+        assert_eq!(*FQ::from(fa * fb).n_uint(), *(&a * &b).n_uint());
+        assert_eq!(*FQ::from(fa - fb).n_uint(), *(&a - &b).n_uint());
+        assert_eq!(
+            *FQ::from(fa * fb.invert().unwrap()).n_uint(),
+            *(&a / &b).n_uint()
+        );
+        assert_eq!(
+            *FQ::from(sum_of_squares(&[fa, fb])).n_uint(),
+            *(&a * &a + &b * &b).n_uint()
+        );
+    }
+
+    #[test]
+    fn rejects_other_moduli() {
+        let value = FQ::with_modulus(BigInt::from(3), BigInt::from(7));
+        assert!(FieldElement::try_from(value).is_err());
+    }
+}
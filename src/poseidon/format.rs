@@ -0,0 +1,98 @@
+// Rendering of field elements and points for logs, fixtures and debugging.
+//
+// Signature mismatches between implementations are usually a matter of
+// representation (decimal vs hex, missing zero padding, byte order), so every
+// form the crate and the other SDKs use is available through one call:
+//
+//     fq.format(Encoding::Hex)        0x-prefixed, zero-padded to 64 digits
+//     fq.format(Encoding::Decimal)    as printed by the Python / JS SDKs
+//     fq.format(Encoding::BytesLe)    [b0, b1, ..., b31], the order the nonce and EdDSA hashes use
+//     fq.format(Encoding::BytesBe)    [b31, ..., b0], the EVM word
+//
+// A point renders as "(x, y)" with both coordinates in the same encoding.
+
+use super::{field::FQ, jubjub::Point};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Decimal,
+    Hex,
+    BytesLe,
+    BytesBe,
+}
+
+fn render_bytes(bytes: &[u8]) -> String {
+    let items: Vec<String> = bytes.iter().map(|b| b.to_string()).collect();
+    format!("[{}]", items.join(", "))
+}
+
+impl FQ {
+    pub fn to_hex(&self) -> String {
+        format!("0x{:0>64}", self.n_uint().to_str_radix(16))
+    }
+
+    // Field elements are below 2^254, so they always fit
+    pub fn to_bytes_le(&self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        let le = self.n_uint().to_bytes_le();
+        bytes[..le.len()].copy_from_slice(&le);
+        bytes
+    }
+
+    pub fn to_bytes_be(&self) -> [u8; 32] {
+        let mut bytes = self.to_bytes_le();
+        bytes.reverse();
+        bytes
+    }
+
+    pub fn format(&self, encoding: Encoding) -> String {
+        match encoding {
+            Encoding::Decimal => self.n_uint().to_string(),
+            Encoding::Hex => self.to_hex(),
+            Encoding::BytesLe => render_bytes(&self.to_bytes_le()),
+            Encoding::BytesBe => render_bytes(&self.to_bytes_be()),
+        }
+    }
+}
+
+impl Point {
+    pub fn format(&self, encoding: Encoding) -> String {
+        format!(
+            "({}, {})",
+            self.x().format(encoding),
+            self.y().format(encoding)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigInt;
+
+    #[test]
+    fn field_element_encodings() {
+        let fq = FQ::new(BigInt::from(258));
+        assert_eq!(fq.format(Encoding::Decimal), "258");
+        assert_eq!(
+            fq.format(Encoding::Hex),
+            format!("0x{}0102", "0".repeat(60))
+        );
+        assert!(fq.format(Encoding::BytesLe).starts_with("[2, 1, 0,"));
+        assert!(fq.format(Encoding::BytesBe).ends_with(", 0, 1, 2]"));
+    }
+
+    #[test]
+    fn point_encodings() {
+        let point = Point::new(FQ::new(BigInt::from(1)), FQ::new(BigInt::from(2)));
+        assert_eq!(point.format(Encoding::Decimal), "(1, 2)");
+        assert_eq!(
+            Point::generate().format(Encoding::Hex),
+            format!(
+                "({}, {})",
+                Point::generate().x().to_hex(),
+                Point::generate().y().to_hex()
+            )
+        );
+    }
+}
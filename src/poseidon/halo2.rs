@@ -0,0 +1,285 @@
+/*
+Export of Poseidon parameters for halo2 Poseidon chips.
+
+halo2's Poseidon (halo2_gadgets / halo2_poseidon, and the PSE fork over
+halo2curves' bn256) is parameterized by a `Spec<F, T, RATE>`: the number of
+full and partial rounds, the S-box, and `constants()` returning a vector of
+per-element round constants, the MDS matrix and its inverse. Its permutation has
+the same Hades structure as permutation.rs (F/2 full rounds, P partial rounds
+with the S-box on state[0], F/2 full rounds; each round adds the constants,
+applies the S-box and multiplies by the matrix by rows), so exporting the
+constants is enough to get identical permutations:
+
+  * this crate adds one constant per round to every element; halo2 wants one
+    per element, so each round constant is repeated t times;
+  * halo2's `full_rounds()` is the total F, as here.
+
+The sponge is not the same: halo2's `Hash` puts a domain tag in the capacity
+element, while `Poseidon::hash` writes the inputs to state[0..n], leaves
+the rest zero and outputs state[0]. To reproduce this crate's hashes, call
+halo2's `permute` (or the Pow5 chip's permutation) on that state directly.
+
+`to_json` writes field elements as decimal strings (`from_str_vartime`);
+`to_rust_spec` writes a Spec impl for halo2curves' `Fr`, with the elements as
+`Fr::from_raw` limbs.
+*/
+
+use super::field::SNARK_SCALAR_FIELD;
+use super::permutation::Poseidon;
+use crate::util::errors::ParameterError;
+use crate::util::math::mod_inverse;
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+use serde_json::{json, Value};
+use std::fmt::Write;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Halo2Params {
+    pub width: usize,
+    pub full_rounds: usize,
+    pub partial_rounds: usize,
+    pub alpha: BigUint,
+    // One row of t constants per round
+    pub round_constants: Vec<Vec<BigUint>>,
+    pub mds: Vec<Vec<BigUint>>,
+    pub mds_inv: Vec<Vec<BigUint>>,
+}
+
+// Gauss-Jordan elimination mod p
+fn invert(matrix: &[Vec<BigUint>], p: &BigUint) -> Option<Vec<Vec<BigUint>>> {
+    let t = matrix.len();
+    let mut rows: Vec<Vec<BigUint>> = matrix
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let mut row = row.clone();
+            row.extend((0..t).map(|j| {
+                if i == j {
+                    BigUint::one()
+                } else {
+                    BigUint::zero()
+                }
+            }));
+            row
+        })
+        .collect();
+
+    for col in 0..t {
+        let pivot = (col..t).find(|&r| !rows[r][col].is_zero())?;
+        rows.swap(col, pivot);
+        let inverse = mod_inverse(&rows[col][col], p)?;
+        for item in &mut rows[col] {
+            *item = &*item * &inverse % p;
+        }
+        let pivot_row = rows[col].clone();
+        for (r, row) in rows.iter_mut().enumerate() {
+            if r == col || row[col].is_zero() {
+                continue;
+            }
+            let factor = row[col].clone();
+            for (item, pivot_item) in row.iter_mut().zip(&pivot_row) {
+                *item = (&*item + p - &factor * pivot_item % p) % p;
+            }
+        }
+    }
+    Some(rows.into_iter().map(|row| row[t..].to_vec()).collect())
+}
+
+fn decimal_rows(rows: &[Vec<BigUint>]) -> Vec<Vec<String>> {
+    rows.iter()
+        .map(|row| row.iter().map(BigUint::to_string).collect())
+        .collect()
+}
+
+// `Fr::from_raw([..])`: four little-endian 64-bit limbs of the canonical value
+fn from_raw(value: &BigUint) -> String {
+    let mut limbs = value.to_u64_digits();
+    limbs.resize(4, 0);
+    let limbs: Vec<String> = limbs.iter().map(|l| format!("0x{:016x}", l)).collect();
+    format!("Fr::from_raw([{}])", limbs.join(", "))
+}
+
+fn rust_row(out: &mut String, row: &[BigUint], indent: &str) {
+    out.push_str(indent);
+    out.push_str("[\n");
+    for value in row {
+        let _ = writeln!(out, "{}    {},", indent, from_raw(value));
+    }
+    out.push_str(indent);
+    out.push_str("],\n");
+}
+
+impl Halo2Params {
+    pub fn new(poseidon: &Poseidon) -> Result<Self, ParameterError> {
+        let p = poseidon.modulus();
+        if p != SNARK_SCALAR_FIELD.magnitude() {
+            return Err(ParameterError::UnsupportedModulus);
+        }
+        let t = poseidon.width();
+        let mds = poseidon.mds_matrix().to_vec();
+        let mds_inv = invert(&mds, p).ok_or(ParameterError::SingularMatrix)?;
+        Ok(Halo2Params {
+            width: t,
+            full_rounds: poseidon.full_rounds(),
+            partial_rounds: poseidon.partial_rounds(),
+            alpha: poseidon.exponent().clone(),
+            round_constants: poseidon
+                .round_constants()
+                .iter()
+                .map(|c| vec![c.clone(); t])
+                .collect(),
+            mds,
+            mds_inv,
+        })
+    }
+
+    pub fn rate(&self) -> usize {
+        self.width - 1
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "width": self.width,
+            "rate": self.rate(),
+            "fullRounds": self.full_rounds,
+            "partialRounds": self.partial_rounds,
+            "alpha": self.alpha.to_string(),
+            "roundConstants": decimal_rows(&self.round_constants),
+            "mds": decimal_rows(&self.mds),
+            "mdsInv": decimal_rows(&self.mds_inv),
+        })
+    }
+
+    // A `Spec<Fr, T, RATE>` impl named `name`; the caller imports `Fr`, `Spec`,
+    // `Mds` and the `Field` trait
+    pub fn to_rust_spec(&self, name: &str) -> String {
+        let (t, rate) = (self.width, self.rate());
+        let alpha: Vec<String> = self
+            .alpha
+            .to_u64_digits()
+            .iter()
+            .map(u64::to_string)
+            .collect();
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "// Poseidon(t = {}, F = {}, P = {}) as computed by loopring_sign",
+            t, self.full_rounds, self.partial_rounds
+        );
+        let _ = writeln!(out, "#[derive(Debug, Clone, Copy)]\npub struct {};\n", name);
+        let _ = writeln!(out, "impl Spec<Fr, {}, {}> for {} {{", t, rate, name);
+        let _ = writeln!(
+            out,
+            "    fn full_rounds() -> usize {{\n        {}\n    }}\n",
+            self.full_rounds
+        );
+        let _ = writeln!(
+            out,
+            "    fn partial_rounds() -> usize {{\n        {}\n    }}\n",
+            self.partial_rounds
+        );
+        let _ = writeln!(
+            out,
+            "    fn sbox(val: Fr) -> Fr {{\n        val.pow_vartime([{}])\n    }}\n",
+            alpha.join(", ")
+        );
+        // Only used to generate constants, which constants() below supplies
+        out.push_str("    fn secure_mds() -> usize {\n        0\n    }\n\n");
+        let _ = writeln!(
+            out,
+            "    fn constants() -> (Vec<[Fr; {t}]>, Mds<Fr, {t}>, Mds<Fr, {t}>) {{",
+            t = t
+        );
+        out.push_str("        (\n            vec![\n");
+        for row in &self.round_constants {
+            rust_row(&mut out, row, "                ");
+        }
+        out.push_str("            ],\n");
+        for matrix in [&self.mds, &self.mds_inv] {
+            out.push_str("            [\n");
+            for row in matrix {
+                rust_row(&mut out, row, "                ");
+            }
+            out.push_str("            ],\n");
+        }
+        out.push_str("        )\n    }\n}\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigInt;
+
+    // halo2_gadgets' primitives::permute, on the exported parameters
+    fn halo2_permute(params: &Halo2Params, mut state: Vec<BigUint>) -> Vec<BigUint> {
+        let p = SNARK_SCALAR_FIELD.magnitude();
+        let r_f = params.full_rounds / 2;
+        for (round, constants) in params.round_constants.iter().enumerate() {
+            let full = round < r_f || round >= r_f + params.partial_rounds;
+            for (i, (word, rc)) in state.iter_mut().zip(constants).enumerate() {
+                *word = (&*word + rc) % p;
+                if full || i == 0 {
+                    *word = word.modpow(&params.alpha, p);
+                }
+            }
+            state = params
+                .mds
+                .iter()
+                .map(|row| row.iter().zip(&state).map(|(m, s)| m * s).sum::<BigUint>() % p)
+                .collect();
+        }
+        state
+    }
+
+    #[test]
+    fn exported_permutation_matches_native_hash() {
+        for t in [3, 13] {
+            let poseidon = Poseidon::shared(t, 6, 53);
+            let params = Halo2Params::new(&poseidon).unwrap();
+            assert_eq!(params.round_constants.len(), 59);
+
+            let inputs: Vec<BigUint> = (1..t as u32).map(BigUint::from).collect();
+            let mut state = inputs.clone();
+            state.push(BigUint::zero());
+            assert_eq!(
+                BigInt::from(halo2_permute(&params, state).swap_remove(0)),
+                poseidon.hash(inputs).unwrap()
+            );
+
+            let p = SNARK_SCALAR_FIELD.magnitude();
+            for i in 0..t {
+                for j in 0..t {
+                    let entry = (0..t)
+                        .map(|k| &params.mds[i][k] * &params.mds_inv[k][j])
+                        .sum::<BigUint>()
+                        % p;
+                    assert_eq!(entry, BigUint::from((i == j) as u8));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn spec_formats() {
+        let params = Halo2Params::new(&Poseidon::shared(3, 6, 53)).unwrap();
+        let json = params.to_json();
+        assert_eq!(json["rate"], 2);
+        assert_eq!(
+            json["roundConstants"][0][2],
+            params.round_constants[0][0].to_string()
+        );
+        assert_eq!(json["mdsInv"].as_array().unwrap().len(), 3);
+
+        let spec = params.to_rust_spec("LoopringPoseidon");
+        assert!(spec.contains("impl Spec<Fr, 3, 2> for LoopringPoseidon {"));
+        assert!(spec.contains("val.pow_vartime([5])"));
+        assert_eq!(spec.matches("Fr::from_raw").count(), 59 * 3 + 2 * 9);
+        assert_eq!(
+            from_raw(&(BigUint::from(1u8) << 64)),
+            "Fr::from_raw([0x0000000000000000, 0x0000000000000001, \
+             0x0000000000000000, 0x0000000000000000])"
+        );
+    }
+}
@@ -0,0 +1,159 @@
+/*
+Byte-oriented Poseidon hasher implementing the RustCrypto `digest` traits.
+
+Poseidon works on field elements, so the byte stream is packed into 31-byte
+little-endian chunks (31 bytes always fit below SNARK_SCALAR_FIELD). The chunks
+are absorbed four at a time, chained through the previous output:
+
+    h_0 = 0
+    h_i = poseidon(h_(i-1), c_1, c_2, c_3, c_4)
+    H   = poseidon(h_n, len(message))
+
+The final call binds the total byte length, so messages that only differ in
+trailing zero bytes do not collide. The instance is the same one (t = 6, F = 6,
+P = 52) used by the EdDSA challenge hash.
+
+The output is the resulting field element as 32 little-endian bytes, the same
+encoding `FQ::to_bytes_le` produces everywhere else in the crate.
+*/
+
+use super::field::FQ;
+use super::permutation::Poseidon;
+use digest::{
+    consts::U32, FixedOutput, FixedOutputReset, HashMarker, Output, OutputSizeUser, Reset, Update,
+};
+use num_bigint::{BigInt, Sign};
+use num_traits::Zero;
+
+const CHUNK_SIZE: usize = 31;
+const CHUNKS_PER_BLOCK: usize = 4;
+const BLOCK_SIZE: usize = CHUNK_SIZE * CHUNKS_PER_BLOCK;
+
+lazy_static! {
+    static ref POSEIDON: Poseidon = Poseidon::shared(6, 6, 52);
+}
+
+#[derive(Clone)]
+pub struct PoseidonHasher {
+    state: BigInt,
+    buffer: Vec<u8>,
+    length: u64,
+}
+
+impl PoseidonHasher {
+    pub fn new() -> Self {
+        PoseidonHasher {
+            state: BigInt::zero(),
+            buffer: Vec::with_capacity(BLOCK_SIZE),
+            length: 0,
+        }
+    }
+
+    fn absorb_block(&mut self) {
+        let mut inputs = vec![self.state.clone()];
+        inputs.extend(
+            self.buffer
+                .chunks(CHUNK_SIZE)
+                .map(|chunk| BigInt::from_bytes_le(Sign::Plus, chunk)),
+        );
+        // At most 5 inputs go into a width-6 instance, which is always accepted
+        self.state = POSEIDON.hash(inputs).unwrap();
+        self.buffer.clear();
+    }
+
+    fn finalize_field_element(&mut self) -> BigInt {
+        if !self.buffer.is_empty() {
+            self.absorb_block();
+        }
+        let inputs = vec![self.state.clone(), BigInt::from(self.length)];
+        POSEIDON.hash(inputs).unwrap()
+    }
+}
+
+impl Default for PoseidonHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HashMarker for PoseidonHasher {}
+
+impl OutputSizeUser for PoseidonHasher {
+    type OutputSize = U32;
+}
+
+impl Update for PoseidonHasher {
+    fn update(&mut self, mut data: &[u8]) {
+        self.length += data.len() as u64;
+        while !data.is_empty() {
+            let take = (BLOCK_SIZE - self.buffer.len()).min(data.len());
+            self.buffer.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            if self.buffer.len() == BLOCK_SIZE {
+                self.absorb_block();
+            }
+        }
+    }
+}
+
+impl FixedOutput for PoseidonHasher {
+    fn finalize_into(mut self, out: &mut Output<Self>) {
+        let hash = self.finalize_field_element();
+        out.copy_from_slice(&FQ::new(hash).to_bytes_le());
+    }
+}
+
+impl Reset for PoseidonHasher {
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl FixedOutputReset for PoseidonHasher {
+    fn finalize_into_reset(&mut self, out: &mut Output<Self>) {
+        let hash = self.finalize_field_element();
+        out.copy_from_slice(&FQ::new(hash).to_bytes_le());
+        Reset::reset(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use digest::Digest;
+
+    #[test]
+    fn digest_matches_manual_chaining() {
+        let message = b"loopring";
+        let result = PoseidonHasher::digest(message);
+
+        let chunk = BigInt::from_bytes_le(Sign::Plus, message);
+        let h_1 = POSEIDON.hash(vec![BigInt::zero(), chunk]).unwrap();
+        let expected = POSEIDON
+            .hash(vec![h_1, BigInt::from(message.len())])
+            .unwrap();
+
+        assert_eq!(result.to_vec(), FQ::new(expected).to_bytes_le());
+    }
+
+    #[test]
+    fn incremental_update_matches_one_shot() {
+        let message: Vec<u8> = (0..=255u8).collect();
+
+        let mut hasher = PoseidonHasher::new();
+        for part in message.chunks(7) {
+            Digest::update(&mut hasher, part);
+        }
+
+        assert_eq!(hasher.finalize(), PoseidonHasher::digest(&message));
+    }
+
+    #[test]
+    fn length_is_bound_into_the_digest() {
+        assert_ne!(PoseidonHasher::digest(b""), PoseidonHasher::digest(b"\0"));
+        assert_ne!(
+            PoseidonHasher::digest(b"abc"),
+            PoseidonHasher::digest(b"abc\0")
+        );
+    }
+}
@@ -18,27 +18,19 @@ intermediate uses a much faster form.
 */
 // ax^2 + y^2 = 1 + dx^2y^2
 
-use super::field::FQ;
-use crate::poseidon::field::SNARK_SCALAR_FIELD;
-use num_bigint::BigInt;
-use num_traits::{One, Zero};
-use std::{
-    ops::{Add, Div, Mul},
-    str::FromStr,
-};
+use super::field::{FQ, SNARK_SCALAR_FIELD};
+use crate::constants;
+use crate::util::errors::ConversionError;
+use num_bigint::{BigInt, BigUint, Sign};
+use num_traits::{Euclid, One, Zero};
+use std::ops::Add;
+
+pub use crate::constants::{JUBJUB_A, JUBJUB_C, JUBJUB_D, JUBJUB_E, JUBJUB_L, JUBJUB_Q};
 
 lazy_static! {
-    pub static ref JUBJUB_Q: BigInt = SNARK_SCALAR_FIELD.clone();
-    pub static ref JUBJUB_E: BigInt = BigInt::from_str(
-        "21888242871839275222246405745257275088614511777268538073601725287587578984328"
-    )
-    .unwrap();
-    pub static ref JUBJUB_C: BigInt = BigInt::from_str("8").unwrap();
-
-    //L is the order of the prime-order subgroup generated by the base point
-    pub static ref JUBJUB_L: BigInt = JUBJUB_E.clone().div(JUBJUB_C.clone()); // L*B = 0, and (2^C)*L == #E
-    pub static ref JUBJUB_A: BigInt = BigInt::from_str("168700").unwrap();
-    pub static ref JUBJUB_D: BigInt = BigInt::from_str("168696").unwrap();
+    // a and d as field elements, for the curve equation and the addition law
+    pub(crate) static ref CURVE_A: FQ = FQ::new(JUBJUB_A.clone());
+    pub(crate) static ref CURVE_D: FQ = FQ::new(JUBJUB_D.clone());
 }
 
 pub struct Point {
@@ -58,20 +50,9 @@ impl Point {
         &self.y
     }
 
-    // These numbers has beeen taken from https://eips.ethereum.org/EIPS/eip-2494
+    // The base point from https://eips.ethereum.org/EIPS/eip-2494
     pub fn generate() -> Self {
-        let x = BigInt::from_str(
-            "16540640123574156134436876038791482806971768689494387082833631921987005038935",
-        )
-        .unwrap();
-        let y = BigInt::from_str(
-            "20819045374670962167435360035096875258406992893633759881276124905556507972311",
-        )
-        .unwrap();
-        Point {
-            x: FQ::new(x),
-            y: FQ::new(y),
-        }
+        constants::generator()
     }
 
     pub fn infinity() -> Self {
@@ -81,7 +62,46 @@ impl Point {
         }
     }
     pub fn as_scalar(&self) -> Vec<BigInt> {
-        vec![self.x.n().clone(), self.y.n().clone()]
+        vec![self.x.to_bigint(), self.y.to_bigint()]
+    }
+
+    // x || y as 32-byte little-endian words, the layout of the binary encodings
+    pub fn to_bytes(&self) -> [u8; 64] {
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(&self.x.to_bytes_le());
+        bytes[32..].copy_from_slice(&self.y.to_bytes_le());
+        bytes
+    }
+
+    // Rejects coordinates not below SNARK_SCALAR_FIELD and points off the curve.
+    // Whether the point is in the subgroup is left to the caller.
+    pub fn from_bytes(bytes: &[u8; 64]) -> Result<Self, ConversionError> {
+        let coordinate = |word: &[u8]| {
+            let n = BigInt::from_bytes_le(Sign::Plus, word);
+            if n >= *SNARK_SCALAR_FIELD {
+                return Err(ConversionError::ValueExceedsModulus);
+            }
+            Ok(FQ::new(n))
+        };
+        let point = Point::new(coordinate(&bytes[..32])?, coordinate(&bytes[32..])?);
+        if !point.is_on_curve() {
+            return Err(ConversionError::NotOnCurve);
+        }
+        Ok(point)
+    }
+
+    // a * x^2 + y^2 = 1 + d * x^2 * y^2
+    pub fn is_on_curve(&self) -> bool {
+        let xx = &self.x * &self.x;
+        let yy = &self.y * &self.y;
+        &*CURVE_A * &xx + &yy == FQ::one() + &*CURVE_D * &xx * yy
+    }
+
+    // Whether the point lies in the prime-order subgroup generated by the base
+    // point, i.e. L * P is the neutral element. Points outside of it (small-order
+    // components) must not be accepted as public keys or signature commitments.
+    pub fn is_in_subgroup(&self) -> bool {
+        self.is_on_curve() && self.mul_scalar(&JUBJUB_L) == Self::infinity()
     }
 
     // Add Implementation for calculation in babyjub
@@ -91,42 +111,50 @@ impl Point {
     // y3 = (y1 * y2 − a * x1 * x2)/(1 − λ).
 
     fn add_points(x1: &FQ, y1: &FQ, x2: &FQ, y2: &FQ) -> Point {
-        let d = FQ::new(JUBJUB_D.clone());
-        let a = FQ::new(JUBJUB_A.clone());
-
-        let lambda = d * x1 * x2 * y1 * y2;
+        let lambda = &*CURVE_D * x1 * x2 * y1 * y2;
         let x3 = (x1 * y2 + y1 * x2) / (FQ::one() + &lambda);
-        let y3 = (y1 * y2 - a * x1 * x2) / (FQ::one() - &lambda);
+        let y3 = (y1 * y2 - &*CURVE_A * x1 * x2) / (FQ::one() - &lambda);
 
         Point { x: x3, y: y3 }
     }
     fn is_at_inifinity(&self) -> bool {
-        let zero = BigInt::from_str("0").unwrap();
-        self.x.n() == &zero && self.y.n() == &zero
+        self.x.n_uint().is_zero() && self.y.n_uint().is_zero()
     }
 
     // Add eliptic curve multiplication using the double-and-add method:
     // 1. https://en.wikipedia.org/wiki/Elliptic_curve_point_multiplication
     // 2. https://iden3-docs.readthedocs.io/en/latest/_downloads/33717d75ab84e11313cc0d8a090b636f/Baby-Jubjub.pdf
 
-    fn scalar_mul(point: &Point, mut scalar: BigInt) -> Point {
+    fn scalar_mul(point: &Point, scalar: &BigUint) -> Point {
         let mut p = Point::new(point.x.clone(), point.y.clone());
         let mut a = Self::infinity();
-        let mut i = 0;
 
-        while scalar != BigInt::zero() {
-            let bitwise_and = &scalar & BigInt::from(1);
-            if bitwise_and != BigInt::from(0) {
+        for i in 0..scalar.bits() {
+            if scalar.bit(i) {
                 a = a + &p;
             }
             let copy_p1 = p.clone();
             let copy_p2 = p.clone();
             p = copy_p1 + copy_p2;
-            scalar = scalar.div(BigInt::from(2));
-            i = i + 1;
         }
         a
     }
+
+    // Every point of the curve has an order dividing JUBJUB_E, so reducing a signed
+    // scalar modulo JUBJUB_E gives the same multiple
+    fn reduce_scalar(scalar: &BigInt) -> BigUint {
+        scalar.rem_euclid(&JUBJUB_E).into_parts().1
+    }
+
+    // scalar * self. Neither the point nor the timing is checked; outside the
+    // crate this is hazmat::scalar_mult.
+    pub(crate) fn mul_scalar(&self, scalar: &BigInt) -> Point {
+        Point::scalar_mul(self, &Point::reduce_scalar(scalar))
+    }
+
+    pub(crate) fn mul_scalar_uint(&self, scalar: &BigUint) -> Point {
+        Point::scalar_mul(self, scalar)
+    }
 }
 
 impl Add for Point {
@@ -149,36 +177,13 @@ impl<'a> Add<&'a Point> for Point {
     }
 }
 
-impl Mul<BigInt> for Point {
-    type Output = Point;
-
-    fn mul(self, scalar: BigInt) -> Self::Output {
-        Point::scalar_mul(&self, scalar)
-    }
-}
-impl<'a> Mul<&'a BigInt> for Point {
-    type Output = Point;
-
-    fn mul(self, scalar: &'a BigInt) -> Self::Output {
-        Point::scalar_mul(&self, scalar.clone())
+impl PartialEq for Point {
+    fn eq(&self, other: &Self) -> bool {
+        self.x == other.x && self.y == other.y
     }
 }
 
-impl<'a, 'b> Mul<&'b BigInt> for &'a Point {
-    type Output = Point;
-
-    fn mul(self, scalar: &'b BigInt) -> Self::Output {
-        Point::scalar_mul(self, scalar.clone())
-    }
-}
-
-impl Mul<Point> for BigInt {
-    type Output = Point;
-
-    fn mul(self, rhs: Point) -> Self::Output {
-        Point::scalar_mul(&rhs, self)
-    }
-}
+impl Eq for Point {}
 
 impl Clone for Point {
     fn clone(&self) -> Self {
@@ -196,8 +201,27 @@ impl Clone for Point {
 #[cfg(test)]
 mod tests {
     use num_traits::Zero;
+    use std::{ops::Div, str::FromStr};
 
     use super::*;
+    use num_bigint::BigUint;
+
+    #[test]
+    fn base_point_is_in_subgroup() {
+        let base = Point::generate();
+        assert!(base.is_on_curve());
+        assert!(base.is_in_subgroup());
+        assert!(Point::infinity().is_on_curve());
+
+        let off_curve = Point::new(base.x().clone(), base.x().clone());
+        assert!(!off_curve.is_on_curve());
+        assert!(!off_curve.is_in_subgroup());
+
+        // (0, -1) has order 2
+        let small_order = Point::new(FQ::zero(), FQ::zero() - FQ::one());
+        assert!(small_order.is_on_curve());
+        assert!(!small_order.is_in_subgroup());
+    }
 
     #[test]
     fn point_add_test_1() {
@@ -232,29 +256,29 @@ mod tests {
 
         let sum = point.add(other);
         assert_eq!(
-            *sum.x.n(),
-            BigInt::from_str(
+            *sum.x.n_uint(),
+            BigUint::from_str(
                 "3921821752680400551661691533275335336907961697969280331905459386565873550491",
             )
             .unwrap()
         );
         assert_eq!(
-            *sum.x.m(),
-            BigInt::from_str(
+            *sum.x.m_uint(),
+            BigUint::from_str(
                 "21888242871839275222246405745257275088548364400416034343698204186575808495617",
             )
             .unwrap()
         );
         assert_eq!(
-            *sum.y.n(),
-            BigInt::from_str(
+            *sum.y.n_uint(),
+            BigUint::from_str(
                 "8522068897570808837785568881356377871354274006792075192589502922612862896342",
             )
             .unwrap()
         );
         assert_eq!(
-            *sum.y.m(),
-            BigInt::from_str(
+            *sum.y.m_uint(),
+            BigUint::from_str(
                 "21888242871839275222246405745257275088548364400416034343698204186575808495617",
             )
             .unwrap()
@@ -294,29 +318,29 @@ mod tests {
 
         let sum = point.add(other);
         assert_eq!(
-            *sum.x.n(),
-            BigInt::from_str(
+            *sum.x.n_uint(),
+            BigUint::from_str(
                 "4991609103248925747358645194965349262579784734809679007552644294476920671344",
             )
             .unwrap()
         );
         assert_eq!(
-            *sum.x.m(),
-            BigInt::from_str(
+            *sum.x.m_uint(),
+            BigUint::from_str(
                 "21888242871839275222246405745257275088548364400416034343698204186575808495617",
             )
             .unwrap()
         );
         assert_eq!(
-            *sum.y.n(),
-            BigInt::from_str(
+            *sum.y.n_uint(),
+            BigUint::from_str(
                 "423391641476660815714427268720766993055332927752794962916609674122318189741",
             )
             .unwrap()
         );
         assert_eq!(
-            *sum.y.m(),
-            BigInt::from_str(
+            *sum.y.m_uint(),
+            BigUint::from_str(
                 "21888242871839275222246405745257275088548364400416034343698204186575808495617",
             )
             .unwrap()
@@ -359,31 +383,31 @@ mod tests {
             ),
         );
         let k = BigInt::from(1);
-        let a = &b * &k;
+        let a = b.mul_scalar(&k);
         assert_eq!(
-            *a.x.n(),
-            BigInt::from_str(
+            *a.x.n_uint(),
+            BigUint::from_str(
                 "16540640123574156134436876038791482806971768689494387082833631921987005038935"
             )
             .unwrap()
         );
         assert_eq!(
-            *a.x.m(),
-            BigInt::from_str(
+            *a.x.m_uint(),
+            BigUint::from_str(
                 "21888242871839275222246405745257275088548364400416034343698204186575808495617"
             )
             .unwrap()
         );
         assert_eq!(
-            *a.y.n(),
-            BigInt::from_str(
+            *a.y.n_uint(),
+            BigUint::from_str(
                 "20819045374670962167435360035096875258406992893633759881276124905556507972311"
             )
             .unwrap()
         );
         assert_eq!(
-            *a.y.m(),
-            BigInt::from_str(
+            *a.y.m_uint(),
+            BigUint::from_str(
                 "21888242871839275222246405745257275088548364400416034343698204186575808495617"
             )
             .unwrap()
@@ -407,31 +431,31 @@ mod tests {
             ),
         );
         let k = BigInt::from(2);
-        let a = &b * &k;
+        let a = b.mul_scalar(&k);
         assert_eq!(
-            *a.x.n(),
-            BigInt::from_str(
+            *a.x.n_uint(),
+            BigUint::from_str(
                 "17324563846726889236817837922625232543153115346355010501047597319863650987830"
             )
             .unwrap()
         );
         assert_eq!(
-            *a.x.m(),
-            BigInt::from_str(
+            *a.x.m_uint(),
+            BigUint::from_str(
                 "21888242871839275222246405745257275088548364400416034343698204186575808495617"
             )
             .unwrap()
         );
         assert_eq!(
-            *a.y.n(),
-            BigInt::from_str(
+            *a.y.n_uint(),
+            BigUint::from_str(
                 "20022170825455209233733649024450576091402881793145646502279487074566492066831"
             )
             .unwrap()
         );
         assert_eq!(
-            *a.y.m(),
-            BigInt::from_str(
+            *a.y.m_uint(),
+            BigUint::from_str(
                 "21888242871839275222246405745257275088548364400416034343698204186575808495617"
             )
             .unwrap()
@@ -456,35 +480,56 @@ mod tests {
             "456425617452149303537516185998917840598824274191970480768523181450944242406",
         )
         .unwrap();
-        let a = b * k;
+        let a = b.mul_scalar(&k);
 
         assert_eq!(
-            *a.x.n(),
-            BigInt::from_str(
+            *a.x.n_uint(),
+            BigUint::from_str(
                 "4991609103248925747358645194965349262579784734809679007552644294476920671344"
             )
             .unwrap()
         );
         assert_eq!(
-            *a.x.m(),
-            BigInt::from_str(
+            *a.x.m_uint(),
+            BigUint::from_str(
                 "21888242871839275222246405745257275088548364400416034343698204186575808495617"
             )
             .unwrap()
         );
         assert_eq!(
-            *a.y.n(),
-            BigInt::from_str(
+            *a.y.n_uint(),
+            BigUint::from_str(
                 "423391641476660815714427268720766993055332927752794962916609674122318189741"
             )
             .unwrap()
         );
         assert_eq!(
-            *a.y.m(),
-            BigInt::from_str(
+            *a.y.m_uint(),
+            BigUint::from_str(
                 "21888242871839275222246405745257275088548364400416034343698204186575808495617"
             )
             .unwrap()
         );
     }
+
+    #[test]
+    fn point_bytes_round_trip() {
+        let point = Point::generate().mul_scalar(&BigInt::from(12345));
+        let bytes = point.to_bytes();
+        assert_eq!(&bytes[..32], &point.x().to_bytes_le());
+        assert!(Point::from_bytes(&bytes).unwrap() == point);
+
+        let mut off_curve = bytes;
+        off_curve[0] ^= 1;
+        assert!(matches!(
+            Point::from_bytes(&off_curve),
+            Err(ConversionError::NotOnCurve)
+        ));
+        let mut too_large = bytes;
+        too_large[63] = 0xff;
+        assert!(matches!(
+            Point::from_bytes(&too_large),
+            Err(ConversionError::ValueExceedsModulus)
+        ));
+    }
 }
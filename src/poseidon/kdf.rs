@@ -0,0 +1,93 @@
+// Key derivation and pseudo-random functions inside the SNARK field.
+//
+//     PRF(key, context, x) = Poseidon(PRF_DOMAIN, key, tag(context), x)
+//     KDF(key, context)[i] = Poseidon(KDF_DOMAIN, key, tag(context), i)
+//
+// with t = 5, F = 6, P = 53 and tags from commitment::domain_tag. The fixed
+// domain tags keep PRF and KDF outputs (and commitments) apart even for equal
+// inputs; the context string names the purpose ("memo key", "storage salt",
+// ...), so outputs for different purposes are independent. The key must be
+// secret and uniformly distributed, e.g. an L2 private key or a KDF output.
+
+use super::{commitment::domain_tag, field::FQ, permutation::FixedPoseidon};
+use lazy_static::lazy_static;
+use num_bigint::BigInt;
+
+pub const PRF_DOMAIN: &str = "loopring_sign/prf/v1";
+pub const KDF_DOMAIN: &str = "loopring_sign/kdf/v1";
+
+lazy_static! {
+    static ref KEYED_POSEIDON: FixedPoseidon<4> = FixedPoseidon::shared(6, 53);
+    static ref PRF_TAG: FQ = domain_tag(PRF_DOMAIN);
+    static ref KDF_TAG: FQ = domain_tag(KDF_DOMAIN);
+}
+
+fn keyed_hash(domain: &FQ, key: &FQ, context: &FQ, input: BigInt) -> FQ {
+    FQ::new(KEYED_POSEIDON.hash([
+        domain.to_bigint(),
+        key.to_bigint(),
+        context.to_bigint(),
+        input,
+    ]))
+}
+
+// A PRF bound to one key and context, for evaluating it many times
+#[derive(Clone)]
+pub struct PoseidonPrf {
+    key: FQ,
+    context: FQ,
+}
+
+impl PoseidonPrf {
+    pub fn new(key: &FQ, context: &str) -> Self {
+        PoseidonPrf {
+            key: key.clone(),
+            context: domain_tag(context),
+        }
+    }
+
+    pub fn eval(&self, input: &FQ) -> FQ {
+        keyed_hash(&PRF_TAG, &self.key, &self.context, input.to_bigint())
+    }
+}
+
+pub fn prf(key: &FQ, context: &str, input: &FQ) -> FQ {
+    PoseidonPrf::new(key, context).eval(input)
+}
+
+// `n_outputs` sub-keys in counter mode; a longer derivation starts with the
+// same outputs as a shorter one
+pub fn poseidon_kdf(key: &FQ, context: &str, n_outputs: usize) -> Vec<FQ> {
+    let context = domain_tag(context);
+    (0..n_outputs)
+        .map(|i| keyed_hash(&KDF_TAG, key, &context, BigInt::from(i)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fq(n: u64) -> FQ {
+        FQ::new(BigInt::from(n))
+    }
+
+    #[test]
+    fn kdf_outputs_are_independent_per_key_and_context() {
+        let keys = poseidon_kdf(&fq(7), "memo key", 3);
+        assert_eq!(keys.len(), 3);
+        assert!(keys[0] != keys[1] && keys[1] != keys[2]);
+        assert!(poseidon_kdf(&fq(7), "memo key", 2)[..] == keys[..2]);
+        assert!(poseidon_kdf(&fq(8), "memo key", 1)[0] != keys[0]);
+        assert!(poseidon_kdf(&fq(7), "storage salt", 1)[0] != keys[0]);
+        assert!(poseidon_kdf(&fq(7), "memo key", 0).is_empty());
+    }
+
+    #[test]
+    fn prf_is_deterministic_and_separated_from_kdf() {
+        let prf_key = PoseidonPrf::new(&fq(7), "memo key");
+        assert!(prf_key.eval(&fq(1)) == prf(&fq(7), "memo key", &fq(1)));
+        assert!(prf_key.eval(&fq(1)) != prf_key.eval(&fq(2)));
+        assert!(prf_key.eval(&fq(0)) != poseidon_kdf(&fq(7), "memo key", 1)[0]);
+    }
+}
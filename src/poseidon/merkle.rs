@@ -0,0 +1,212 @@
+/*
+Streaming Merkle roots over Poseidon.
+
+`MerkleRootBuilder` consumes leaves one by one and only keeps the unfinished
+nodes of each level, at most `arity - 1` per level, so the memory use is
+O(arity * log n) however many leaves are pushed. This is enough to compute the
+commitment of a multi-million-entry airdrop or snapshot list without building
+the tree.
+
+Every inner node is Poseidon(t = arity + 1, F, P) of its children, left to
+right. The tree has a fixed depth, and positions without a leaf hold the empty
+leaf 0; the empty subtree of a level is
+
+    zero_0     = 0
+    zero_(i+1) = poseidon(zero_i, ..., zero_i)
+
+`finish()` uses the smallest depth that fits all pushed leaves (a single leaf is
+its own root), `finish_with_depth(d)` a given one, e.g. to match a tree of fixed
+height.
+*/
+
+use super::permutation::Poseidon;
+use crate::util::errors::MerkleError;
+use num_bigint::BigInt;
+use num_traits::Zero;
+
+pub struct MerkleRootBuilder {
+    poseidon: Poseidon,
+    arity: usize,
+    // Unfinished nodes per level, each shorter than `arity`
+    levels: Vec<Vec<BigInt>>,
+    leaves: u64,
+}
+
+impl MerkleRootBuilder {
+    // Fails if `arity` is below 2
+    pub fn new(arity: usize, n_rounds_f: usize, n_rounds_p: usize) -> Result<Self, MerkleError> {
+        if arity < 2 {
+            return Err(MerkleError::InvalidArity(arity));
+        }
+        Ok(Self::with_arity(arity, n_rounds_f, n_rounds_p))
+    }
+
+    fn with_arity(arity: usize, n_rounds_f: usize, n_rounds_p: usize) -> Self {
+        MerkleRootBuilder {
+            poseidon: Poseidon::shared(arity + 1, n_rounds_f, n_rounds_p),
+            arity,
+            levels: Vec::new(),
+            leaves: 0,
+        }
+    }
+
+    // Binary tree hashed with the payload parameters (t = 3, F = 6, P = 53)
+    pub fn binary() -> Self {
+        Self::with_arity(2, 6, 53)
+    }
+
+    // Quad tree hashed like the Loopring state trees (t = 5, F = 6, P = 52).
+    // The empty leaf is still 0, not the protocol's default leaf.
+    pub fn quad() -> Self {
+        Self::with_arity(4, 6, 52)
+    }
+
+    pub fn leaves(&self) -> u64 {
+        self.leaves
+    }
+
+    fn hash(&self, children: &[BigInt]) -> BigInt {
+        // Exactly `arity` inputs go into a width arity + 1 instance
+        self.poseidon.hash(children.to_vec()).unwrap()
+    }
+
+    pub fn push(&mut self, leaf: BigInt) {
+        self.leaves += 1;
+        let mut node = leaf;
+        let mut level = 0;
+        loop {
+            if self.levels.len() == level {
+                self.levels.push(Vec::with_capacity(self.arity));
+            }
+            self.levels[level].push(node);
+            if self.levels[level].len() < self.arity {
+                return;
+            }
+            let children = std::mem::take(&mut self.levels[level]);
+            node = self.hash(&children);
+            level += 1;
+        }
+    }
+
+    // Smallest depth whose tree holds all pushed leaves
+    pub fn depth(&self) -> usize {
+        let mut depth = 0;
+        let mut capacity: u128 = 1;
+        while capacity < self.leaves as u128 {
+            capacity *= self.arity as u128;
+            depth += 1;
+        }
+        depth
+    }
+
+    pub fn finish(self) -> BigInt {
+        let depth = self.depth();
+        // The depth always fits the pushed leaves
+        self.finish_with_depth(depth).unwrap()
+    }
+
+    pub fn finish_with_depth(mut self, depth: usize) -> Result<BigInt, MerkleError> {
+        let too_many = MerkleError::TooManyLeaves {
+            depth,
+            leaves: self.leaves,
+        };
+        if self.levels.len() > depth + 1 {
+            return Err(too_many);
+        }
+        self.levels.resize(depth + 1, Vec::new());
+
+        let mut zero = BigInt::zero();
+        let mut carry: Option<BigInt> = None;
+        for level in 0..depth {
+            let mut nodes = std::mem::take(&mut self.levels[level]);
+            nodes.extend(carry.take());
+            if !nodes.is_empty() {
+                nodes.resize(self.arity, zero.clone());
+                carry = Some(self.hash(&nodes));
+            }
+            zero = self.hash(&vec![zero; self.arity]);
+        }
+
+        let mut top = std::mem::take(&mut self.levels[depth]);
+        top.extend(carry);
+        match top.len() {
+            0 => Ok(zero),
+            1 => Ok(top.pop().unwrap()),
+            _ => Err(too_many),
+        }
+    }
+}
+
+impl Extend<BigInt> for MerkleRootBuilder {
+    fn extend<I: IntoIterator<Item = BigInt>>(&mut self, leaves: I) {
+        for leaf in leaves {
+            self.push(leaf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves(count: u64) -> Vec<BigInt> {
+        (1..=count).map(BigInt::from).collect()
+    }
+
+    // Builds every level in memory
+    fn full_tree_root(leaves: &[BigInt], arity: usize, depth: usize) -> BigInt {
+        let poseidon = Poseidon::shared(arity + 1, 6, 53);
+        let mut level = leaves.to_vec();
+        level.resize(arity.pow(depth as u32), BigInt::zero());
+        while level.len() > 1 {
+            level = level
+                .chunks(arity)
+                .map(|children| poseidon.hash(children.to_vec()).unwrap())
+                .collect();
+        }
+        level.pop().unwrap()
+    }
+
+    #[test]
+    fn streaming_root_matches_full_tree() {
+        for count in [1, 2, 5, 8, 9] {
+            let mut builder = MerkleRootBuilder::binary();
+            builder.extend(leaves(count));
+            let depth = builder.depth();
+            assert_eq!(builder.finish(), full_tree_root(&leaves(count), 2, depth));
+        }
+
+        let mut builder = MerkleRootBuilder::new(3, 6, 53).unwrap();
+        builder.extend(leaves(7));
+        assert_eq!(builder.depth(), 2);
+        assert_eq!(builder.finish(), full_tree_root(&leaves(7), 3, 2));
+        assert!(matches!(
+            MerkleRootBuilder::new(1, 6, 53),
+            Err(MerkleError::InvalidArity(1))
+        ));
+    }
+
+    #[test]
+    fn fixed_depth_pads_with_empty_subtrees() {
+        let mut builder = MerkleRootBuilder::binary();
+        builder.extend(leaves(3));
+        assert_eq!(
+            builder.finish_with_depth(4).unwrap(),
+            full_tree_root(&leaves(3), 2, 4)
+        );
+        assert_eq!(
+            MerkleRootBuilder::binary().finish_with_depth(3).unwrap(),
+            full_tree_root(&[], 2, 3)
+        );
+
+        let mut builder = MerkleRootBuilder::binary();
+        builder.extend(leaves(5));
+        assert_eq!(
+            builder.finish_with_depth(2),
+            Err(MerkleError::TooManyLeaves {
+                depth: 2,
+                leaves: 5
+            })
+        );
+    }
+}
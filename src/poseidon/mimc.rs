@@ -0,0 +1,161 @@
+/*
+MiMC over the SNARK field, for circuits and contracts that predate Poseidon.
+
+  * MiMC-p/p as in ethsnarks (mimc.py): x^7, 91 rounds, round constants from
+    the keccak256 chain c_0 = H(H("mimc")), c_i = H(c_{i-1}) with H on 32-byte
+    big-endian integers. `mimc_hash` is its Miyaguchi-Preneel compression:
+    k_i = k_{i-1} + m_i + E_{k_{i-1}}(m_i).
+  * MiMC-sponge as in circomlib (MiMCSponge, also used by Tornado Cash): a
+    Feistel network with x^5 and 220 rounds, constants keccak256("mimcsponge")
+    chained and reduced into the field, the first and last one being zero.
+    Inputs are added to the left half, one permutation per input.
+*/
+
+use super::bignum::pow_mod;
+use super::field::{FQ, SNARK_SCALAR_FIELD};
+use lazy_static::lazy_static;
+use num_bigint::BigUint;
+use num_traits::Zero;
+use sha3::{Digest, Keccak256};
+
+pub const MIMC_SEED: &str = "mimc";
+pub const MIMC_EXPONENT: u32 = 7;
+pub const MIMC_ROUNDS: usize = 91;
+
+pub const SPONGE_SEED: &str = "mimcsponge";
+pub const SPONGE_EXPONENT: u32 = 5;
+pub const SPONGE_ROUNDS: usize = 220;
+
+lazy_static! {
+    static ref MIMC_CONSTANTS: Vec<BigUint> = mimc_constants(MIMC_SEED, MIMC_ROUNDS);
+    static ref SPONGE_CONSTANTS: Vec<BigUint> = sponge_constants(SPONGE_SEED, SPONGE_ROUNDS);
+}
+
+fn keccak(data: &[u8]) -> [u8; 32] {
+    Keccak256::digest(data).into()
+}
+
+fn to_bytes_be_32(n: &BigUint) -> [u8; 32] {
+    let be = n.to_bytes_be();
+    let mut bytes = [0u8; 32];
+    bytes[32 - be.len()..].copy_from_slice(&be);
+    bytes
+}
+
+// The ethsnarks constants are left unreduced, as in the reference
+pub fn mimc_constants(seed: &str, rounds: usize) -> Vec<BigUint> {
+    let mut current = BigUint::from_bytes_be(&keccak(seed.as_bytes()));
+    (0..rounds)
+        .map(|_| {
+            current = BigUint::from_bytes_be(&keccak(&to_bytes_be_32(&current)));
+            current.clone()
+        })
+        .collect()
+}
+
+pub fn sponge_constants(seed: &str, rounds: usize) -> Vec<BigUint> {
+    let p = SNARK_SCALAR_FIELD.magnitude();
+    let mut constants = vec![BigUint::zero(); rounds];
+    let mut current = keccak(seed.as_bytes());
+    for constant in constants.iter_mut().take(rounds.saturating_sub(1)).skip(1) {
+        current = keccak(&current);
+        *constant = BigUint::from_bytes_be(&current) % p;
+    }
+    constants
+}
+
+// The MiMC-p/p block cipher E_k(x)
+pub fn mimc_cipher(x: &FQ, k: &FQ) -> FQ {
+    let p = SNARK_SCALAR_FIELD.magnitude();
+    let exponent = BigUint::from(MIMC_EXPONENT);
+    let mut x = x.n_uint().clone();
+    for constant in MIMC_CONSTANTS.iter() {
+        x = pow_mod(&((x + k.n_uint() + constant) % p), &exponent, p);
+    }
+    FQ::from_biguint(x + k.n_uint())
+}
+
+pub fn mimc_hash(inputs: &[FQ], k: &FQ) -> FQ {
+    inputs
+        .iter()
+        .fold(k.clone(), |k, x| &k + x + mimc_cipher(x, &k))
+}
+
+// One MiMCFeistel permutation of (xL, xR) under key k
+pub fn mimc_sponge_permutation(left: &FQ, right: &FQ, k: &FQ) -> (FQ, FQ) {
+    let p = SNARK_SCALAR_FIELD.magnitude();
+    let exponent = BigUint::from(SPONGE_EXPONENT);
+    let mut left = left.n_uint().clone();
+    let mut right = right.n_uint().clone();
+    for (i, constant) in SPONGE_CONSTANTS.iter().enumerate() {
+        let t = pow_mod(&((&left + k.n_uint() + constant) % p), &exponent, p);
+        let mixed = (right + t) % p;
+        if i + 1 < SPONGE_ROUNDS {
+            right = std::mem::replace(&mut left, mixed);
+        } else {
+            right = mixed;
+        }
+    }
+    (FQ::from_biguint(left), FQ::from_biguint(right))
+}
+
+// circomlib's MiMCSponge(nInputs, 220, nOutputs)
+pub fn mimc_sponge(inputs: &[FQ], k: &FQ, n_outputs: usize) -> Vec<FQ> {
+    let mut left = FQ::zero();
+    let mut right = FQ::zero();
+    for input in inputs {
+        (left, right) = mimc_sponge_permutation(&(left + input), &right, k);
+    }
+    let mut outputs = Vec::with_capacity(n_outputs);
+    for i in 0..n_outputs {
+        if i > 0 {
+            (left, right) = mimc_sponge_permutation(&left, &right, k);
+        }
+        outputs.push(left.clone());
+    }
+    outputs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigInt;
+    use std::str::FromStr;
+
+    fn fq(n: &str) -> FQ {
+        FQ::new(BigInt::from_str(n).unwrap())
+    }
+
+    #[test]
+    fn ethsnarks_constant_hash() {
+        // H(123) from ethsnarks' mimc.py
+        let h = keccak(&to_bytes_be_32(&BigUint::from(123u8)));
+        assert_eq!(
+            BigUint::from_bytes_be(&h).to_string(),
+            "38632140595220392354280998614525578145353818029287874088356304829962854601866"
+        );
+        assert_eq!(MIMC_CONSTANTS.len(), MIMC_ROUNDS);
+        assert!(SPONGE_CONSTANTS[0].is_zero() && SPONGE_CONSTANTS[SPONGE_ROUNDS - 1].is_zero());
+
+        let one = fq("1");
+        assert!(
+            mimc_hash(std::slice::from_ref(&one), &FQ::zero())
+                == FQ::zero() + &one + mimc_cipher(&one, &FQ::zero())
+        );
+        assert!(
+            mimc_hash(&[one.clone(), one.clone()], &FQ::zero()) != mimc_hash(&[one], &FQ::zero())
+        );
+    }
+
+    #[test]
+    fn tornado_cash_zero_node() {
+        // MerkleTreeWithHistory.zeros(0) and zeros(1): hashLeftRight(z, z)
+        let zero =
+            fq("21663839004416932945382355908790599225266501822907911457504978515578255421292");
+        let node = mimc_sponge(&[zero.clone(), zero], &FQ::zero(), 1);
+        assert_eq!(
+            node[0].to_bigint().to_string(),
+            "16923532097304556005972200564242292693309333953544141029519619077135960040221"
+        );
+    }
+}
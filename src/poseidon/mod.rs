@@ -1,5 +1,32 @@
+#[cfg(feature = "ark")]
+mod ark;
+#[cfg(feature = "simd")]
+pub mod batch;
+mod bignum;
+mod blake512;
+pub mod circom;
+pub mod commitment;
+pub mod curve;
+pub mod ecdh;
 pub mod eddsa;
 pub mod field;
+#[cfg(feature = "ff")]
+pub mod fixed_field;
+pub mod format;
+pub mod halo2;
+pub mod hasher;
 pub mod jubjub;
+pub mod kdf;
+pub mod mac;
+pub mod merkle;
+pub mod mimc;
+pub mod nonce_guard;
+pub mod pedersen;
 pub mod permutation;
 pub mod poseidon_constants;
+pub mod quad_tree;
+#[cfg(feature = "ark")]
+pub mod r1cs;
+pub mod transcript;
+#[cfg(any(feature = "ruint", feature = "primitive-types"))]
+mod u256;
@@ -0,0 +1,118 @@
+/*
+Nonce-reuse detection for a signing session.
+
+The nonce `r` is derived deterministically from the key and the message (see
+`SignatureScheme::hash_secret`), so signing the same message twice yields the
+same R and is harmless. The same R for two different messages, however, lets
+anyone solve for the secret key:
+
+    s1 - s2 = k * (t1 - t2)
+
+A NonceGuard remembers every (key fingerprint, R) pair it has seen together with
+the message, and refuses a signature whose R was already used for another
+message. It is meant as a debugging aid around custom key handling or changes to
+the nonce derivation; it keeps every signature of the session in memory.
+*/
+
+use super::{
+    eddsa::{MessageHash, SignatureScheme, SignedMessage},
+    field::FQ,
+};
+use crate::util::errors::SignatureError;
+use num_bigint::BigInt;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+#[derive(Default)]
+pub struct NonceGuard {
+    seen: HashMap<([u8; 32], [u8; 32]), BigInt>,
+}
+
+// Identifies a key or a point without keeping its coordinates around
+pub(crate) fn fingerprint(x: &FQ, y: &FQ) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(x.to_bytes_le());
+    hasher.update(y.to_bytes_le());
+    hasher.finalize().into()
+}
+
+impl NonceGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+
+    pub fn record(&mut self, signed: &SignedMessage) -> Result<(), SignatureError> {
+        let public_key = signed.public_key();
+        let image_of_r = signed.sig().image_of_r();
+        let entry = (
+            fingerprint(public_key.x(), public_key.y()),
+            fingerprint(image_of_r.x(), image_of_r.y()),
+        );
+
+        match self.seen.get(&entry) {
+            Some(msg) if msg != signed.msg() => Err(SignatureError::NonceReuse),
+            Some(_) => Ok(()),
+            None => {
+                self.seen.insert(entry, signed.msg().clone());
+                Ok(())
+            }
+        }
+    }
+
+    // Signs and records the signature. A signature that would reuse a nonce is
+    // never returned.
+    pub fn sign(
+        &mut self,
+        private_key_scalar: BigInt,
+        hash: &MessageHash,
+    ) -> Result<SignedMessage, SignatureError> {
+        let signed = SignatureScheme::sign_hash(private_key_scalar, hash);
+        self.record(&signed)?;
+        Ok(signed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poseidon::eddsa::Signature;
+
+    #[test]
+    fn signing_same_message_twice_is_allowed() {
+        let mut guard = NonceGuard::new();
+        guard
+            .sign(BigInt::from(7), &MessageHash::new(BigInt::from(1)))
+            .unwrap();
+        guard
+            .sign(BigInt::from(7), &MessageHash::new(BigInt::from(1)))
+            .unwrap();
+        guard
+            .sign(BigInt::from(7), &MessageHash::new(BigInt::from(2)))
+            .unwrap();
+        assert_eq!(guard.len(), 2);
+    }
+
+    #[test]
+    fn reused_nonce_is_rejected() {
+        let mut guard = NonceGuard::new();
+        let signed = guard
+            .sign(BigInt::from(7), &MessageHash::new(BigInt::from(1)))
+            .unwrap();
+
+        // Same key and R, different message
+        let forged = SignedMessage::new(
+            signed.public_key().clone(),
+            Signature::new(signed.sig().image_of_r().clone(), signed.sig().s().clone()),
+            BigInt::from(2),
+        );
+        assert_eq!(guard.record(&forged), Err(SignatureError::NonceReuse));
+    }
+}
@@ -0,0 +1,241 @@
+/*
+Windowed Pedersen hash on Baby Jubjub, following ethsnarks (pedersen.py), from
+which the Loopring circuits are derived.
+
+The message bits are split into 3-bit windows, the first bit of a window being
+its least significant one. Window j uses the base point
+
+    B(j / 62) * 16^(j % 62)
+
+where B(i) = HashToPoint(name padded with spaces to 28 bytes || "%04X" of i).
+A window w adds ((w & 3) + 1) times its base point, negated if w > 3; 62
+windows per base point keep the encoding injective (Zcash Sapling spec,
+theorem 5.4.1). The result is a point of the prime-order subgroup.
+
+HashToPoint takes sha256 of the data as y (big-endian, reduced into the field),
+incrementing y until x can be recovered from the curve equation, and clears
+the cofactor. Of the two x values the odd one is taken before clearing, which
+gives the points of ethsnarks; the other choice would flip the sign of every
+final x. The tests check the known-answer vectors of ethsnarks' test_pedersen.py.
+*/
+
+use super::{
+    field::FQ,
+    jubjub::{Point, CURVE_A, CURVE_D, JUBJUB_C, JUBJUB_L},
+};
+use crate::util::errors::PedersenError;
+use num_bigint::{BigInt, BigUint};
+use num_traits::Zero;
+use sha2::{Digest, Sha256};
+
+pub const WINDOWS_PER_BASE_POINT: usize = 62;
+pub const MAX_NAME_LENGTH: usize = 28;
+
+fn negate(point: &Point) -> Point {
+    Point::new(FQ::zero() - point.x().clone(), point.y().clone())
+}
+
+// x^2 = (y^2 - 1) / (d * y^2 - a)
+fn recover_odd_x(y: &FQ) -> Option<FQ> {
+    let yy = y * y;
+    let denominator = &*CURVE_D * &yy - &*CURVE_A;
+    if denominator == FQ::zero() {
+        return None;
+    }
+    let x = ((yy - FQ::one()) / denominator).sqrt()?;
+    if x.n_uint().bit(0) {
+        Some(x)
+    } else {
+        Some(FQ::zero() - x)
+    }
+}
+
+pub fn hash_to_point(data: &[u8]) -> Point {
+    let digest = Sha256::digest(data);
+    let mut y = FQ::from_biguint(BigUint::from_bytes_be(&digest));
+    loop {
+        if let Some(x) = recover_odd_x(&y) {
+            let point = Point::new(x, y.clone()).mul_scalar(&JUBJUB_C);
+            // A point of small order clears to the identity; also skip it
+            if point != Point::infinity() {
+                debug_assert!(point.mul_scalar(&JUBJUB_L) == Point::infinity());
+                return point;
+            }
+        }
+        y = y + FQ::one();
+    }
+}
+
+pub fn base_point(name: &str, index: usize) -> Result<Point, PedersenError> {
+    if !name.is_ascii() || name.len() > MAX_NAME_LENGTH {
+        return Err(PedersenError::InvalidName(name.to_string()));
+    }
+    if index > 0xffff {
+        return Err(PedersenError::TooManyWindows);
+    }
+    let data = format!("{:<28}{:04X}", name, index);
+    Ok(hash_to_point(data.as_bytes()))
+}
+
+// Each window must be below 8
+pub fn pedersen_hash_windows(name: &str, windows: &[u8]) -> Result<Point, PedersenError> {
+    if windows.is_empty() {
+        return Err(PedersenError::EmptyInput);
+    }
+    let mut result = Point::infinity();
+    let mut current = Point::infinity();
+    for (j, &window) in windows.iter().enumerate() {
+        if window > 7 {
+            return Err(PedersenError::InvalidWindow(window));
+        }
+        if j % WINDOWS_PER_BASE_POINT == 0 {
+            current = base_point(name, j / WINDOWS_PER_BASE_POINT)?;
+        } else {
+            for _ in 0..4 {
+                current = current.clone() + &current;
+            }
+        }
+        let segment = current.mul_scalar_uint(&BigUint::from((window & 0b11) + 1));
+        result = if window > 0b11 {
+            result + &negate(&segment)
+        } else {
+            result + &segment
+        };
+    }
+    Ok(result)
+}
+
+// A trailing window of fewer than 3 bits is padded with zeros
+pub fn pedersen_hash_bits(name: &str, bits: &[bool]) -> Result<Point, PedersenError> {
+    let windows: Vec<u8> = bits
+        .chunks(3)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .fold(0u8, |w, (k, &bit)| w | ((bit as u8) << k))
+        })
+        .collect();
+    pedersen_hash_windows(name, &windows)
+}
+
+// The bytes are read most significant bit first
+pub fn pedersen_hash_bytes(name: &str, data: &[u8]) -> Result<Point, PedersenError> {
+    let bits: Vec<bool> = data
+        .iter()
+        .flat_map(|byte| (0..8).rev().map(move |k| byte & (1 << k) != 0))
+        .collect();
+    pedersen_hash_bits(name, &bits)
+}
+
+// Hashes field elements as `bits` little-endian bits each, the layout circuits
+// use when they feed field elements into a Pedersen gadget
+pub fn pedersen_hash_scalars(
+    name: &str,
+    scalars: &[BigInt],
+    bits: usize,
+) -> Result<Point, PedersenError> {
+    let mut message = Vec::with_capacity(scalars.len() * bits);
+    for scalar in scalars {
+        if scalar < &BigInt::zero() || scalar.bits() > bits as u64 {
+            return Err(PedersenError::ScalarOutOfRange(scalar.to_string()));
+        }
+        let magnitude = scalar.magnitude();
+        message.extend((0..bits as u64).map(|k| magnitude.bit(k)));
+    }
+    pedersen_hash_bits(name, &message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_add_signed_multiples_of_their_base_points() {
+        let name = "test";
+        let b0 = base_point(name, 0).unwrap();
+        assert!(b0.is_in_subgroup());
+        assert!(recover_odd_x(b0.y()).is_some());
+
+        // Windows 1 and 5: 2 * B0, then -(2 * 16 * B0)
+        let expected = b0.mul_scalar_uint(&BigUint::from(2u8))
+            + &negate(&b0.mul_scalar_uint(&BigUint::from(32u8)));
+        assert!(pedersen_hash_windows(name, &[1, 5]).unwrap() == expected);
+
+        // Window 62 starts over with the next base point
+        let mut windows = vec![0u8; WINDOWS_PER_BASE_POINT];
+        windows.push(0);
+        let b1 = base_point(name, 1).unwrap();
+        let mut expected = Point::infinity();
+        let mut current = b0.clone();
+        for j in 0..WINDOWS_PER_BASE_POINT {
+            if j > 0 {
+                current = current.mul_scalar_uint(&BigUint::from(16u8));
+            }
+            expected = expected + &current;
+        }
+        expected = expected + &b1;
+        assert!(pedersen_hash_windows(name, &windows).unwrap() == expected);
+    }
+
+    fn point(x: &str, y: &str) -> Point {
+        let coordinate = |c: &str| FQ::new(c.parse::<BigInt>().unwrap());
+        Point::new(coordinate(x), coordinate(y))
+    }
+
+    // ethsnarks hashes a scalar as 3-bit windows of its bit_length() bits
+    fn scalar_windows(scalar: &BigInt) -> Vec<u8> {
+        let bits: Vec<bool> = (0..scalar.bits()).map(|k| scalar.bit(k)).collect();
+        bits.chunks(3)
+            .map(|chunk| chunk.iter().rev().fold(0u8, |w, &bit| w << 1 | bit as u8))
+            .collect()
+    }
+
+    #[test]
+    fn matches_ethsnarks_vectors() {
+        // test_pedersen.py, test_hash_scalars_known
+        let vectors = [
+            (
+                "267",
+                "6790798216812059804926342266703617627640027902964190490794793207272357201212",
+                "2522797517250455013248440571887865304858084343310097011302610004060289809689",
+            ),
+            (
+                "6453482891510615431577168724743356132495662554103773572771861111634748265227",
+                "6545697115159207040330446958704617656199928059562637738348733874272425400594",
+                "16414097465381367987194277536478439232201417933379523927469515207544654431390",
+            ),
+        ];
+        for (scalar, x, y) in vectors {
+            let windows = scalar_windows(&scalar.parse().unwrap());
+            assert!(pedersen_hash_windows("test", &windows).unwrap() == point(x, y));
+        }
+        // 267 = 0b100_001_011
+        assert_eq!(scalar_windows(&BigInt::from(267)), [3, 1, 4]);
+    }
+
+    #[test]
+    fn bits_bytes_and_scalars_agree() {
+        // 0b1010_0000: bits 1,0,1 / 0,0,0 / 0,0 -> windows 5, 0, 0
+        let from_bytes = pedersen_hash_bytes("test", &[0b1010_0000]).unwrap();
+        assert!(from_bytes == pedersen_hash_windows("test", &[5, 0, 0]).unwrap());
+        assert!(
+            pedersen_hash_scalars("test", &[BigInt::from(5)], 3).unwrap()
+                == pedersen_hash_windows("test", &[5]).unwrap()
+        );
+        assert!(from_bytes != pedersen_hash_bytes("other", &[0b1010_0000]).unwrap());
+
+        assert!(matches!(
+            pedersen_hash_bytes("test", &[]),
+            Err(PedersenError::EmptyInput)
+        ));
+        assert!(matches!(
+            base_point("a name that is far too long for it", 0),
+            Err(PedersenError::InvalidName(_))
+        ));
+        assert!(matches!(
+            pedersen_hash_scalars("test", &[BigInt::from(8)], 3),
+            Err(PedersenError::ScalarOutOfRange(_))
+        ));
+    }
+}
@@ -8,18 +8,22 @@ Starkad and Poseidon: New Hash Functions for Zero Knowledge Proof Systems
  The reference implementation in Python from Loopring can be found here:
  - https://github.com/Loopring/hello_loopring/blob/loopring-v3/sdk/ethsnarks/poseidon/permutation.py
  */
-use crate::util::errors::PoseidonError;
+use super::bignum::pow_mod;
+use super::field::SNARK_SCALAR_FIELD;
+use crate::util::errors::{ParameterError, PoseidonError};
 use blake2b_simd::Params;
-use num_bigint::BigInt;
-use num_traits::{Euclid, Zero};
+use num_bigint::{BigInt, BigUint};
+use num_traits::{Euclid, One, Zero};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
 
 trait AsBytes {
     fn as_bytes(&self) -> Vec<u8>;
 }
 
-impl AsBytes for BigInt {
+impl AsBytes for BigUint {
     fn as_bytes(&self) -> Vec<u8> {
-        self.to_bytes_le().1
+        self.to_bytes_le()
     }
 }
 
@@ -29,54 +33,276 @@ impl AsBytes for &str {
     }
 }
 
+// Maps a (possibly negative) BigInt to its representative in [0, p)
+fn to_field(value: &BigInt, p: &BigUint) -> BigUint {
+    value
+        .rem_euclid(&BigInt::from(p.clone()))
+        .magnitude()
+        .clone()
+}
+
+// All state, constants and arithmetic are unsigned; the BigInt signatures of the
+// public functions only convert at the boundary.
+//
+// An instance is immutable after construction, so it is Send + Sync and can be
+// shared between threads. The constants are reference counted: clones are cheap
+// and share them.
+#[derive(Clone)]
 pub struct Poseidon {
-    p: BigInt,
+    p: BigUint,
     t: usize,
     n_rounds_f: usize,
     n_rounds_p: usize,
-    _seed: String,
-    e: BigInt,
-    constants_c: Option<Vec<BigInt>>,
-    constants_m: Option<Vec<Vec<BigInt>>>,
-    _security_target: usize,
+    seed: String,
+    e: BigUint,
+    constants_c: Arc<Vec<BigUint>>,
+    constants_m: Arc<Vec<Vec<BigUint>>>,
+    security_target: usize,
+}
+
+lazy_static! {
+    // Instances with the protocol parameters, by (t, F, P)
+    static ref SHARED: RwLock<HashMap<(usize, usize, usize), Poseidon>> =
+        RwLock::new(HashMap::new());
+}
+
+fn gcd(mut a: BigUint, mut b: BigUint) -> BigUint {
+    while !b.is_zero() {
+        let r = &a % &b;
+        a = b;
+        b = r;
+    }
+    a
+}
+
+/*
+Parameter checks of the reference implementation (ethsnarks `poseidon_params`),
+from iacr.org/2019/458 § 3 Cryptanalysis Summary of Starkad and Poseidon Hashes:
+
+  - interpolation:     R_F + R_P > ratio * min(n, M) + log2(t)
+  - Groebner (rounds): R_F + R_P > ratio * (2 + min(n, M))
+  - Groebner (S-boxes): R_F + t * R_P > ratio * M
+
+with n = floor(log2(p)) and M the security target. The ratios depend on the
+S-box exponent; the e = 5 values apply to any exponent other than 3.
+*/
+fn validate_parameters(
+    p: &BigUint,
+    t: usize,
+    n_rounds_f: usize,
+    n_rounds_p: usize,
+    e: &BigUint,
+    security_target: usize,
+) -> Result<(), ParameterError> {
+    if t < 2 {
+        return Err(ParameterError::WidthTooSmall(t));
+    }
+    if n_rounds_f == 0 || !n_rounds_f.is_multiple_of(2) {
+        return Err(ParameterError::InvalidFullRounds(n_rounds_f));
+    }
+    if n_rounds_p == 0 {
+        return Err(ParameterError::NoPartialRounds);
+    }
+    if *e < BigUint::from(3u8) || !gcd(e.clone(), p - BigUint::from(1u8)).is_one() {
+        return Err(ParameterError::ExponentNotCoprime);
+    }
+
+    let n = p.bits() - 1;
+    if n < security_target as u64 {
+        return Err(ParameterError::FieldTooSmall {
+            bits: n,
+            security_target,
+        });
+    }
+
+    let (interpolation_ratio, grobner_rounds_ratio, grobner_sboxes_ratio) =
+        if *e == BigUint::from(3u8) {
+            (0.63, 0.32, 0.18)
+        } else {
+            (0.43, 0.21, 0.14)
+        };
+    let m = security_target as f64;
+    let min_n_m = (n as f64).min(m);
+    let rounds = (n_rounds_f + n_rounds_p) as f64;
+
+    if rounds <= interpolation_ratio * min_n_m + (t as f64).log2() {
+        return Err(ParameterError::InsufficientRounds("interpolation"));
+    }
+    if rounds <= (2.0 + min_n_m) * grobner_rounds_ratio {
+        return Err(ParameterError::InsufficientRounds(
+            "Groebner basis (rounds)",
+        ));
+    }
+    if (n_rounds_f + t * n_rounds_p) as f64 <= m * grobner_sboxes_ratio {
+        return Err(ParameterError::InsufficientRounds(
+            "Groebner basis (S-boxes)",
+        ));
+    }
+    Ok(())
 }
 
 // The 'state' is the internal state that goes thorugh each
 // permutation in a sponge function
 
 impl Poseidon {
-    pub fn new(
+    // Like `new`, but rejects parameters that do not give a secure permutation
+    // for `security_target` bits, and supplied constants of the wrong size
+    #[allow(clippy::too_many_arguments)]
+    pub fn try_new(
         p: BigInt,
         t: usize,
         n_rounds_f: usize,
         n_rounds_p: usize,
         seed: String,
         e: BigInt,
-        mut constants_c: Option<Vec<BigInt>>,
-        mut constants_m: Option<Vec<Vec<BigInt>>>,
+        constants_c: Option<Vec<BigInt>>,
+        constants_m: Option<Vec<Vec<BigInt>>>,
         security_target: usize,
-    ) -> Self {
-        constants_c.get_or_insert_with(|| {
-            Poseidon::poseidon_constants(
-                &p,
-                &format!("{}_constants", seed),
-                &n_rounds_f + &n_rounds_p,
-            )
-        });
-        constants_m
-            .get_or_insert_with(|| Self::poseidon_matrix(&p, &format!("{}_matrix_0000", seed), &t));
-        Poseidon {
+    ) -> Result<Self, ParameterError> {
+        validate_parameters(
+            p.magnitude(),
+            t,
+            n_rounds_f,
+            n_rounds_p,
+            e.magnitude(),
+            security_target,
+        )?;
+        if let Some(constants) = &constants_c {
+            if constants.len() != n_rounds_f + n_rounds_p {
+                return Err(ParameterError::ConstantsLength {
+                    expected: n_rounds_f + n_rounds_p,
+                    actual: constants.len(),
+                });
+            }
+        }
+        if let Some(matrix) = &constants_m {
+            if matrix.len() != t || matrix.iter().any(|row| row.len() != t) {
+                return Err(ParameterError::MatrixShape);
+            }
+        }
+        Ok(Self::new(
             p,
             t,
             n_rounds_f,
             n_rounds_p,
-            _seed: seed,
+            seed,
             e,
             constants_c,
             constants_m,
-            _security_target: security_target,
+            security_target,
+        ))
+    }
+
+    // Constants that are not supplied are derived from the seed, so every
+    // instance holds a full set and the permutation cannot run without them
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        p: BigInt,
+        t: usize,
+        n_rounds_f: usize,
+        n_rounds_p: usize,
+        seed: String,
+        e: BigInt,
+        constants_c: Option<Vec<BigInt>>,
+        constants_m: Option<Vec<Vec<BigInt>>>,
+        security_target: usize,
+    ) -> Self {
+        let p = p.magnitude().clone();
+        let constants_c = match constants_c {
+            Some(constants) => constants.iter().map(|c| to_field(c, &p)).collect(),
+            None => Self::constants(&p, &format!("{}_constants", seed), n_rounds_f + n_rounds_p),
+        };
+        let constants_m = match constants_m {
+            Some(matrix) => matrix
+                .iter()
+                .map(|row| row.iter().map(|c| to_field(c, &p)).collect())
+                .collect(),
+            None => Self::matrix(&p, &format!("{}_matrix_0000", seed), t),
+        };
+        Poseidon {
+            p,
+            t,
+            n_rounds_f,
+            n_rounds_p,
+            seed,
+            e: e.magnitude().clone(),
+            constants_c: Arc::new(constants_c),
+            constants_m: Arc::new(constants_m),
+            security_target,
+        }
+    }
+
+    // The instance with the protocol parameters (SNARK_SCALAR_FIELD, seed
+    // "poseidon", e = 5, 128 bit security) for the given width and rounds. The
+    // constants are generated once per process and shared by all callers.
+    pub fn shared(t: usize, n_rounds_f: usize, n_rounds_p: usize) -> Poseidon {
+        let key = (t, n_rounds_f, n_rounds_p);
+        if let Some(poseidon) = SHARED.read().unwrap().get(&key) {
+            return poseidon.clone();
         }
+        let poseidon = Poseidon::new(
+            SNARK_SCALAR_FIELD.clone(),
+            t,
+            n_rounds_f,
+            n_rounds_p,
+            "poseidon".to_string(),
+            BigInt::from(5),
+            None,
+            None,
+            128,
+        );
+        SHARED
+            .write()
+            .unwrap()
+            .entry(key)
+            .or_insert(poseidon)
+            .clone()
+    }
+    // Read-only views of the parameters, e.g. to embed the exact constants in a
+    // constraint system. Constants and matrix entries are reduced mod p.
+    pub fn modulus(&self) -> &BigUint {
+        &self.p
+    }
+
+    pub fn width(&self) -> usize {
+        self.t
+    }
+
+    // Number of inputs per call, t - 1
+    pub fn rate(&self) -> usize {
+        self.t - 1
+    }
+
+    pub fn full_rounds(&self) -> usize {
+        self.n_rounds_f
+    }
+
+    pub fn partial_rounds(&self) -> usize {
+        self.n_rounds_p
+    }
+
+    pub fn exponent(&self) -> &BigUint {
+        &self.e
+    }
+
+    pub fn seed(&self) -> &str {
+        &self.seed
+    }
+
+    pub fn security_target(&self) -> usize {
+        self.security_target
+    }
+
+    // One constant per round, F + P in total, added to every state element
+    pub fn round_constants(&self) -> &[BigUint] {
+        &self.constants_c
     }
+
+    // The t x t MDS matrix, by rows
+    pub fn mds_matrix(&self) -> &[Vec<BigUint>] {
+        &self.constants_m
+    }
+
     // poseidon
     /*
       Main instansiation of the Poseidon permutation
@@ -105,7 +331,31 @@ impl Poseidon {
       to form a sponge construct.
     */
 
-    pub fn calculate_poseidon(&self, inputs: Vec<BigInt>) -> Result<BigInt, PoseidonError> {
+    // Accepts anything convertible into BigInt: BigInts, FQ values, unsigned
+    // integers, ... Negative values are mapped to their field representative.
+    pub fn hash<I>(&self, inputs: I) -> Result<BigInt, PoseidonError>
+    where
+        I: IntoIterator,
+        I::Item: Into<BigInt>,
+    {
+        let inputs = inputs
+            .into_iter()
+            .map(|input| to_field(&input.into(), &self.p))
+            .collect();
+        Ok(BigInt::from(self.hash_uint(inputs)?))
+    }
+
+    #[deprecated(note = "use hash")]
+    pub fn calculate_poseidon<I>(&self, inputs: I) -> Result<BigInt, PoseidonError>
+    where
+        I: IntoIterator,
+        I::Item: Into<BigInt>,
+    {
+        self.hash(inputs)
+    }
+
+    // Inputs already reduced into the field
+    pub fn hash_uint(&self, inputs: Vec<BigUint>) -> Result<BigUint, PoseidonError> {
         if inputs.is_empty() {
             return Err(PoseidonError::EmptyInputError);
         }
@@ -119,32 +369,48 @@ impl Poseidon {
         // interpreted as integers or binary strings.
         // A wider state (a larger "t") means more data is being processed during each round
         // of the permutation, which might influence the algorithm's overall efficiency and throughput.
+        Ok(self.permute(inputs))
+    }
 
-        let mut state: Vec<BigInt> = vec![BigInt::zero(); self.t];
+    // Callers ensure 1 <= inputs.len() < t
+    fn permute(&self, inputs: Vec<BigUint>) -> BigUint {
+        let mut state: Vec<BigUint> = vec![BigUint::zero(); self.t];
 
         for (i, input_value) in inputs.into_iter().enumerate() {
-            state[i] = input_value;
+            state[i] = input_value % &self.p;
         }
-        if let Some(ref constants) = self.constants_c {
-            for (i, constant_c) in constants.into_iter().enumerate() {
-                for state_item in &mut state {
-                    *state_item += constant_c;
-                }
-                state = self.poseidon_sbox(state, i);
-                state = self.poseidon_mix(state);
+        self.permute_state(state).swap_remove(0)
+    }
+
+    // The permutation on a full state of t reduced elements, for sponge
+    // constructions that chain several calls through the whole state
+    pub(crate) fn permute_state(&self, mut state: Vec<BigUint>) -> Vec<BigUint> {
+        debug_assert_eq!(state.len(), self.t);
+        for (i, constant_c) in self.constants_c.iter().enumerate() {
+            for state_item in &mut state {
+                *state_item += constant_c;
             }
+            state = self.poseidon_sbox(state, i);
+            state = self.poseidon_mix(state);
         }
-        Ok(state[0].clone())
+        state
     }
 
     pub fn poseidon_constants(p: &BigInt, seed: &str, n: usize) -> Vec<BigInt> {
-        let mut result: Vec<BigInt> = Vec::with_capacity(n);
-        let mut current_seed: BigInt = Self::calculate_blake2b::<&str>(&seed);
-        result.push(&current_seed % p);
+        Self::constants(p.magnitude(), seed, n)
+            .into_iter()
+            .map(BigInt::from)
+            .collect()
+    }
+
+    fn constants(p: &BigUint, seed: &str, n: usize) -> Vec<BigUint> {
+        let mut result: Vec<BigUint> = Vec::with_capacity(n);
+        let mut currentseed: BigUint = Self::calculate_blake2b::<&str>(&seed);
+        result.push(&currentseed % p);
 
         for _ in 1..n {
-            current_seed = Self::calculate_blake2b::<BigInt>(&current_seed);
-            result.push(&current_seed % p);
+            currentseed = Self::calculate_blake2b::<BigUint>(&currentseed);
+            result.push(&currentseed % p);
         }
         result
     }
@@ -162,15 +428,23 @@ impl Poseidon {
     */
 
     pub fn poseidon_matrix(p: &BigInt, seed: &str, t: &usize) -> Vec<Vec<BigInt>> {
-        let c: Vec<BigInt> = Self::poseidon_constants(&p, &seed, t * 2);
-        let mut matrix: Vec<Vec<BigInt>> = Vec::new();
-
-        for i in 0..*t {
-            let mut row: Vec<BigInt> = Vec::new();
-            for j in 0..*t {
-                let base = (&c[i] - &c[t + j]).rem_euclid(p);
-                let exponent = p - 2;
-                let modular_inverse = base.modpow(&exponent, p);
+        Self::matrix(p.magnitude(), seed, *t)
+            .into_iter()
+            .map(|row| row.into_iter().map(BigInt::from).collect())
+            .collect()
+    }
+
+    fn matrix(p: &BigUint, seed: &str, t: usize) -> Vec<Vec<BigUint>> {
+        let c: Vec<BigUint> = Self::constants(p, seed, t * 2);
+        let exponent = p - BigUint::from(2u8);
+        let mut matrix: Vec<Vec<BigUint>> = Vec::new();
+
+        for i in 0..t {
+            let mut row: Vec<BigUint> = Vec::new();
+            for j in 0..t {
+                // c[i] - c[t + j] mod p, kept non-negative
+                let base = (&c[i] + p - &c[t + j]) % p;
+                let modular_inverse = pow_mod(&base, &exponent, p);
                 row.push(modular_inverse);
             }
             matrix.push(row);
@@ -187,40 +461,38 @@ impl Poseidon {
     - the last R_f rounds have a full S-Box layer
     */
 
-    fn poseidon_sbox(&self, mut state: Vec<BigInt>, i: usize) -> Vec<BigInt> {
+    fn poseidon_sbox(&self, mut state: Vec<BigUint>, i: usize) -> Vec<BigUint> {
         let half_f = self.n_rounds_f / 2;
 
         if i < half_f || i >= half_f + self.n_rounds_p {
             for state_item in &mut state {
-                let new_state = state_item.modpow(&self.e, &self.p);
+                let new_state = pow_mod(state_item, &self.e, &self.p);
                 *state_item = new_state;
             }
         } else {
-            state[0] = state[0].modpow(&self.e, &self.p);
+            state[0] = pow_mod(&state[0], &self.e, &self.p);
         }
         state
     }
 
-    fn poseidon_mix(&self, state: Vec<BigInt>) -> Vec<BigInt> {
+    fn poseidon_mix(&self, state: Vec<BigUint>) -> Vec<BigUint> {
         /*
         The mixing layer is a matrix vector product of the state with the mixing matrix
           - https://mathinsight.org/matrix_vector_multiplication
         */
 
-        let mut new_state: Vec<BigInt> = Vec::new();
-        if let Some(constant_m) = &self.constants_m {
-            for i in 0..constant_m.len() {
-                let mut sum = BigInt::zero();
-                for j in 0..state.len() {
-                    sum += &constant_m[i][j] * &state[j]
-                }
-                new_state.push(sum.rem_euclid(&self.p))
+        let mut new_state: Vec<BigUint> = Vec::with_capacity(self.t);
+        for row in self.constants_m.iter() {
+            let mut sum = BigUint::zero();
+            for (constant, state_item) in row.iter().zip(&state) {
+                sum += constant * state_item
             }
+            new_state.push(sum % &self.p)
         }
         new_state
     }
 
-    fn calculate_blake2b<T: AsBytes>(seed: &T) -> BigInt {
+    fn calculate_blake2b<T: AsBytes>(seed: &T) -> BigUint {
         let hash = Params::new()
             .hash_length(32)
             .key(b"")
@@ -228,8 +500,30 @@ impl Poseidon {
             .to_state()
             .update(&seed.as_bytes())
             .finalize();
-        let result = BigInt::from_bytes_le(num_bigint::Sign::Plus, hash.as_bytes());
-        result
+        BigUint::from_bytes_le(hash.as_bytes())
+    }
+}
+
+// A protocol instance that takes exactly N inputs. Its width is N + 1 by
+// construction, so hashing cannot fail; used where the arity is fixed, like the
+// EdDSA challenge hash.
+#[derive(Clone)]
+pub struct FixedPoseidon<const N: usize> {
+    poseidon: Poseidon,
+}
+
+impl<const N: usize> FixedPoseidon<N> {
+    pub fn shared(n_rounds_f: usize, n_rounds_p: usize) -> Self {
+        const { assert!(N > 0, "a Poseidon hash needs at least one input") };
+        FixedPoseidon {
+            poseidon: Poseidon::shared(N + 1, n_rounds_f, n_rounds_p),
+        }
+    }
+
+    pub fn hash(&self, inputs: [BigInt; N]) -> BigInt {
+        let p = &self.poseidon.p;
+        let inputs = inputs.iter().map(|input| to_field(input, p)).collect();
+        BigInt::from(self.poseidon.permute(inputs))
     }
 }
 
@@ -239,13 +533,13 @@ mod tests {
 
     use num_traits::One;
 
-    use crate::poseidon::field::SNARK_SCALAR_FIELD;
+    use crate::poseidon::field::{FQ, SNARK_SCALAR_FIELD};
 
     use super::*;
 
     #[test]
     fn test_blake2bhash() {
-        let n = BigInt::from_str(
+        let n = BigUint::from_str(
             "14132513739920849383792069751007754351800355055139761101807090020635929082500",
         )
         .unwrap();
@@ -253,7 +547,7 @@ mod tests {
         let hash = Poseidon::calculate_blake2b(&n);
         assert_eq!(
             hash,
-            BigInt::from_str(
+            BigUint::from_str(
                 "2944673226682481007627110343206629017840128596422012786319796010373889882365"
             )
             .unwrap()
@@ -264,7 +558,7 @@ mod tests {
         let hash = Poseidon::calculate_blake2b(&"poseidon_matrix_0000");
         assert_eq!(
             hash,
-            BigInt::from_str(
+            BigUint::from_str(
                 "14132513739920849383792069751007754351800355055139761101807090020635929082500"
             )
             .unwrap()
@@ -364,7 +658,7 @@ mod tests {
         let e = BigInt::from_str("5").unwrap();
         let poseidon = Poseidon::new(p, max_input + 1, 6, 53, seed, e, None, None, 128);
         let inputs = vec![BigInt::from_str("1").unwrap()];
-        let state = poseidon.calculate_poseidon(inputs);
+        let state = poseidon.hash(inputs);
         let result = match state {
             Ok(value) => format!("{}", value),
             Err(e) => {
@@ -387,7 +681,7 @@ mod tests {
         let e = BigInt::from_str("5").unwrap();
         let poseidon = Poseidon::new(p, max_input + 1, 6, 53, seed, e, None, None, 128);
         let inputs = vec![BigInt::one(), BigInt::from(2)];
-        let state = poseidon.calculate_poseidon(inputs);
+        let state = poseidon.hash(inputs);
         let result = match state {
             Ok(value) => format!("{}", value),
             Err(e) => {
@@ -419,7 +713,7 @@ mod tests {
             BigInt::from(7),
             BigInt::from(8),
         ];
-        let state = poseidon.calculate_poseidon(inputs);
+        let state = poseidon.hash(inputs);
         let result = match state {
             Ok(value) => format!("{}", value),
             Err(e) => {
@@ -434,4 +728,180 @@ mod tests {
             .unwrap()
         )
     }
+
+    #[test]
+    fn try_new_accepts_loopring_parameters() {
+        for (t, p_rounds) in [(6, 52), (12, 53), (13, 53)] {
+            let poseidon = Poseidon::try_new(
+                SNARK_SCALAR_FIELD.clone(),
+                t,
+                6,
+                p_rounds,
+                "poseidon".to_string(),
+                BigInt::from(5),
+                None,
+                None,
+                128,
+            );
+            assert!(poseidon.is_ok());
+        }
+    }
+
+    #[test]
+    fn try_new_rejects_insecure_parameters() {
+        let try_new = |t, f, p_rounds, e: u32, security_target| {
+            Poseidon::try_new(
+                SNARK_SCALAR_FIELD.clone(),
+                t,
+                f,
+                p_rounds,
+                "poseidon".to_string(),
+                BigInt::from(e),
+                None,
+                None,
+                security_target,
+            )
+            .err()
+        };
+
+        assert_eq!(
+            try_new(1, 6, 53, 5, 128),
+            Some(ParameterError::WidthTooSmall(1))
+        );
+        assert_eq!(
+            try_new(6, 5, 53, 5, 128),
+            Some(ParameterError::InvalidFullRounds(5))
+        );
+        assert_eq!(
+            try_new(6, 6, 0, 5, 128),
+            Some(ParameterError::NoPartialRounds)
+        );
+        // p - 1 is divisible by 3 for BN254, so x^3 is not a permutation
+        assert_eq!(
+            try_new(6, 6, 53, 3, 128),
+            Some(ParameterError::ExponentNotCoprime)
+        );
+        assert_eq!(
+            try_new(6, 6, 40, 5, 128),
+            Some(ParameterError::InsufficientRounds("interpolation"))
+        );
+        assert!(matches!(
+            try_new(6, 6, 53, 5, 256),
+            Some(ParameterError::FieldTooSmall { bits: 253, .. })
+        ));
+    }
+
+    #[test]
+    fn hash_accepts_generic_inputs() {
+        let poseidon = Poseidon::new(
+            SNARK_SCALAR_FIELD.clone(),
+            9,
+            6,
+            53,
+            "poseidon".to_string(),
+            BigInt::from(5),
+            None,
+            None,
+            128,
+        );
+        let expected = poseidon.hash(vec![BigInt::one(), BigInt::from(2)]).unwrap();
+
+        assert_eq!(poseidon.hash([1u64, 2]).unwrap(), expected);
+        assert_eq!(
+            poseidon
+                .hash([FQ::one(), FQ::new(BigInt::from(2))])
+                .unwrap(),
+            expected
+        );
+        assert_eq!(poseidon.hash((1..=2).map(BigInt::from)).unwrap(), expected);
+        #[allow(deprecated)]
+        let shim = poseidon.calculate_poseidon([1u64, 2]).unwrap();
+        assert_eq!(shim, expected);
+    }
+
+    #[test]
+    fn shared_instance_matches_new() {
+        let poseidon = Poseidon::new(
+            SNARK_SCALAR_FIELD.clone(),
+            3,
+            6,
+            53,
+            "poseidon".to_string(),
+            BigInt::from(5),
+            None,
+            None,
+            128,
+        );
+        let shared = Poseidon::shared(3, 6, 53);
+        assert_eq!(
+            shared.hash([1u64, 2]).unwrap(),
+            poseidon.hash([1u64, 2]).unwrap()
+        );
+        assert!(Arc::ptr_eq(
+            &shared.constants_c,
+            &Poseidon::shared(3, 6, 53).constants_c
+        ));
+    }
+
+    #[test]
+    fn accessors_expose_generated_constants() {
+        let poseidon = Poseidon::shared(6, 6, 52);
+        assert_eq!(
+            (poseidon.width(), poseidon.rate(), poseidon.full_rounds()),
+            (6, 5, 6)
+        );
+        assert_eq!(poseidon.partial_rounds(), 52);
+        assert_eq!(poseidon.seed(), "poseidon");
+        assert_eq!(*poseidon.exponent(), BigUint::from(5u8));
+        assert_eq!(poseidon.security_target(), 128);
+        assert_eq!(poseidon.modulus(), SNARK_SCALAR_FIELD.magnitude());
+
+        let p = SNARK_SCALAR_FIELD.clone();
+        let constants: Vec<BigInt> = poseidon
+            .round_constants()
+            .iter()
+            .cloned()
+            .map(BigInt::from)
+            .collect();
+        assert_eq!(
+            constants,
+            Poseidon::poseidon_constants(&p, "poseidon_constants", 58)
+        );
+        let matrix = poseidon.mds_matrix();
+        assert_eq!(matrix.len(), 6);
+        assert_eq!(
+            BigInt::from(matrix[2][3].clone()),
+            Poseidon::poseidon_matrix(&p, "poseidon_matrix_0000", &6)[2][3]
+        );
+    }
+
+    #[test]
+    fn fixed_arity_matches_checked_hash() {
+        let fixed = FixedPoseidon::<2>::shared(6, 53);
+        assert_eq!(
+            fixed.hash([BigInt::from(1), BigInt::from(-2)]),
+            Poseidon::shared(3, 6, 53)
+                .hash([BigInt::from(1), BigInt::from(-2)])
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn instances_are_shared_across_threads() {
+        fn assert_send_sync<T: Send + Sync + Clone>() {}
+        assert_send_sync::<Poseidon>();
+
+        let poseidon = Poseidon::shared(3, 6, 53);
+        let expected = poseidon.hash([1u64, 2]).unwrap();
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                let clone = poseidon.clone();
+                let (poseidon, expected) = (&poseidon, &expected);
+                scope.spawn(move || {
+                    assert_eq!(clone.hash([1u64, 2]).unwrap(), *expected);
+                    assert!(Arc::ptr_eq(&clone.constants_m, &poseidon.constants_m));
+                });
+            }
+        });
+    }
 }
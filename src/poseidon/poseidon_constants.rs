@@ -1,3 +1,4 @@
+#![allow(clippy::useless_vec)]
 use std::{str::FromStr, vec};
 
 use num_bigint::BigInt;
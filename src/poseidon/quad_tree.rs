@@ -0,0 +1,205 @@
+/*
+Merkle proofs of the Loopring state trees.
+
+The account tree and every account's balance tree are quad trees: an inner node
+is Poseidon(t = 5, F = 6, P = 52) of its four children, left to right. The
+leaves of protocol 3.6 are
+
+    account = poseidon(owner, publicKeyX, publicKeyY, nonce, feeBipsAMM,
+                       balancesRoot)                      t = 7, F = 6, P = 52
+    balance = poseidon(balance, weightAMM, storageRoot)   t = 5, F = 6, P = 52
+
+The account tree is 16 levels deep (32-bit account ids), the balance tree 8
+(16-bit token ids).
+
+A proof lists, from the leaf upwards, the three siblings of the node on every
+level in child order, leaving out the node itself. The node's position among
+its siblings is the next base-4 digit of the leaf index, least significant
+first. This is the flat layout of the relayer's exported proofs, so
+`QuadMerkleProof::from_flat` takes them as they are.
+*/
+
+use super::permutation::{FixedPoseidon, Poseidon};
+use crate::util::errors::MerkleError;
+use num_bigint::BigInt;
+
+pub const ACCOUNT_TREE_DEPTH: usize = 16;
+pub const BALANCE_TREE_DEPTH: usize = 8;
+
+const ARITY: usize = 4;
+
+lazy_static! {
+    static ref NODE_POSEIDON: FixedPoseidon<4> = FixedPoseidon::shared(6, 52);
+    static ref ACCOUNT_POSEIDON: FixedPoseidon<6> = FixedPoseidon::shared(6, 52);
+    static ref BALANCE_POSEIDON: Poseidon = Poseidon::shared(5, 6, 52);
+}
+
+pub fn hash_node(children: &[BigInt; ARITY]) -> BigInt {
+    NODE_POSEIDON.hash(children.clone())
+}
+
+pub fn account_leaf_hash(
+    owner: &BigInt,
+    public_key_x: &BigInt,
+    public_key_y: &BigInt,
+    nonce: u32,
+    fee_bips_amm: u8,
+    balances_root: &BigInt,
+) -> BigInt {
+    ACCOUNT_POSEIDON.hash([
+        owner.clone(),
+        public_key_x.clone(),
+        public_key_y.clone(),
+        BigInt::from(nonce),
+        BigInt::from(fee_bips_amm),
+        balances_root.clone(),
+    ])
+}
+
+pub fn balance_leaf_hash(balance: &BigInt, weight_amm: &BigInt, storage_root: &BigInt) -> BigInt {
+    let inputs = vec![balance.clone(), weight_amm.clone(), storage_root.clone()];
+    // Three inputs go into a width-5 instance, which is always accepted
+    BALANCE_POSEIDON.hash(inputs).unwrap()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuadMerkleProof {
+    index: u64,
+    leaf: BigInt,
+    siblings: Vec<[BigInt; 3]>,
+}
+
+impl QuadMerkleProof {
+    pub fn new(index: u64, leaf: BigInt, siblings: Vec<[BigInt; 3]>) -> Result<Self, MerkleError> {
+        // An index needs 2 bits per level
+        let depth = siblings.len();
+        if depth < 32 && index >> (2 * depth) != 0 {
+            return Err(MerkleError::IndexOutOfRange { index, depth });
+        }
+        Ok(QuadMerkleProof {
+            index,
+            leaf,
+            siblings,
+        })
+    }
+
+    pub fn from_flat(index: u64, leaf: BigInt, siblings: &[BigInt]) -> Result<Self, MerkleError> {
+        if !siblings.len().is_multiple_of(3) {
+            return Err(MerkleError::InvalidProofLength(siblings.len()));
+        }
+        let siblings = siblings
+            .chunks(3)
+            .map(|level| [level[0].clone(), level[1].clone(), level[2].clone()])
+            .collect();
+        Self::new(index, leaf, siblings)
+    }
+
+    pub fn depth(&self) -> usize {
+        self.siblings.len()
+    }
+
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    pub fn leaf(&self) -> &BigInt {
+        &self.leaf
+    }
+
+    // The root the proof leads to
+    pub fn root(&self) -> BigInt {
+        let mut node = self.leaf.clone();
+        let mut index = self.index;
+        for level in &self.siblings {
+            let position = (index % ARITY as u64) as usize;
+            let mut siblings = level.iter().cloned();
+            let children: [BigInt; ARITY] = std::array::from_fn(|i| {
+                if i == position {
+                    node.clone()
+                } else {
+                    siblings.next().unwrap()
+                }
+            });
+            node = hash_node(&children);
+            index /= ARITY as u64;
+        }
+        node
+    }
+
+    pub fn verify(&self, root: &BigInt) -> bool {
+        self.root() == *root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poseidon::merkle::MerkleRootBuilder;
+    use num_traits::Zero;
+
+    // Proof for `index` in a full tree of the given leaves, padded with 0
+    fn proof_for(leaves: &[BigInt], depth: usize, index: u64) -> QuadMerkleProof {
+        let mut level = leaves.to_vec();
+        level.resize(ARITY.pow(depth as u32), BigInt::zero());
+        let mut position = index as usize;
+        let mut siblings = Vec::new();
+        for _ in 0..depth {
+            let start = position - position % ARITY;
+            let others: Vec<BigInt> = (start..start + ARITY)
+                .filter(|&i| i != position)
+                .map(|i| level[i].clone())
+                .collect();
+            siblings.push([others[0].clone(), others[1].clone(), others[2].clone()]);
+            level = level
+                .chunks(ARITY)
+                .map(|children| hash_node(&[0, 1, 2, 3].map(|i| children[i].clone())))
+                .collect();
+            position /= ARITY;
+        }
+        QuadMerkleProof::new(index, leaves[index as usize].clone(), siblings).unwrap()
+    }
+
+    #[test]
+    fn proofs_lead_to_the_tree_root() {
+        let leaves: Vec<BigInt> = (0..11u64)
+            .map(|i| balance_leaf_hash(&BigInt::from(i * 1000), &BigInt::zero(), &BigInt::zero()))
+            .collect();
+        let mut builder = MerkleRootBuilder::quad();
+        builder.extend(leaves.clone());
+        let root = builder.finish_with_depth(3).unwrap();
+
+        for index in [0, 6, 10] {
+            let proof = proof_for(&leaves, 3, index);
+            assert!(proof.verify(&root));
+
+            let flat: Vec<BigInt> = proof.siblings.iter().flatten().cloned().collect();
+            assert_eq!(
+                QuadMerkleProof::from_flat(index, proof.leaf.clone(), &flat).unwrap(),
+                proof
+            );
+        }
+
+        let proof = proof_for(&leaves, 3, 6);
+        let moved = QuadMerkleProof::new(7, proof.leaf.clone(), proof.siblings.clone()).unwrap();
+        assert!(!moved.verify(&root));
+        let forged = QuadMerkleProof::new(6, leaves[5].clone(), proof.siblings).unwrap();
+        assert!(!forged.verify(&root));
+    }
+
+    #[test]
+    fn malformed_proofs_are_rejected() {
+        let siblings = vec![BigInt::zero(); 6];
+        assert_eq!(
+            QuadMerkleProof::from_flat(15, BigInt::zero(), &siblings[..5]),
+            Err(MerkleError::InvalidProofLength(5))
+        );
+        assert_eq!(
+            QuadMerkleProof::from_flat(16, BigInt::zero(), &siblings),
+            Err(MerkleError::IndexOutOfRange {
+                index: 16,
+                depth: 2
+            })
+        );
+        assert!(QuadMerkleProof::from_flat(15, BigInt::zero(), &siblings).is_ok());
+    }
+}
@@ -0,0 +1,185 @@
+/*
+An arkworks constraint-system gadget for the Poseidon permutation.
+
+The gadget takes its parameters from a native `Poseidon` instance (width,
+rounds, exponent, round constants and MDS matrix), so a hash computed by this
+crate and one enforced in a circuit cannot drift apart. Round structure as in
+permutation.rs: every round adds its constant to the whole state, applies the
+S-box to every element in the F/2 first and F/2 last rounds and to state[0]
+only in the P partial rounds, then mixes with the matrix.
+
+Each S-box x^5 costs 3 constraints; additions and the mixing layer are linear
+and free, so a permutation costs 3 * (F * t + P) constraints. `hash` pads with
+constants, whose first S-box is computed outside the circuit.
+*/
+
+use super::field::SNARK_SCALAR_FIELD;
+use super::permutation::Poseidon;
+use crate::util::errors::{ConversionError, GadgetError, PoseidonError};
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use num_bigint::BigUint;
+
+#[derive(Clone)]
+pub struct PoseidonGadget {
+    t: usize,
+    n_rounds_f: usize,
+    n_rounds_p: usize,
+    e: Vec<u64>,
+    constants_c: Vec<Fr>,
+    constants_m: Vec<Vec<Fr>>,
+}
+
+fn to_fr(value: &BigUint) -> Fr {
+    Fr::from_le_bytes_mod_order(&value.to_bytes_le())
+}
+
+impl PoseidonGadget {
+    // Instances over another modulus than SNARK_SCALAR_FIELD cannot be expressed
+    // over BN254's Fr
+    pub fn new(poseidon: &Poseidon) -> Result<Self, ConversionError> {
+        if poseidon.modulus() != SNARK_SCALAR_FIELD.magnitude() {
+            return Err(ConversionError::ModulusMismatch);
+        }
+        Ok(PoseidonGadget {
+            t: poseidon.width(),
+            n_rounds_f: poseidon.full_rounds(),
+            n_rounds_p: poseidon.partial_rounds(),
+            e: poseidon.exponent().to_u64_digits(),
+            constants_c: poseidon.round_constants().iter().map(to_fr).collect(),
+            constants_m: poseidon
+                .mds_matrix()
+                .iter()
+                .map(|row| row.iter().map(to_fr).collect())
+                .collect(),
+        })
+    }
+
+    // The gadget of `Poseidon::shared(t, F, P)`
+    pub fn shared(t: usize, n_rounds_f: usize, n_rounds_p: usize) -> Self {
+        Self::new(&Poseidon::shared(t, n_rounds_f, n_rounds_p))
+            .expect("shared instances are over SNARK_SCALAR_FIELD")
+    }
+
+    pub fn width(&self) -> usize {
+        self.t
+    }
+
+    // The permutation on a full state of t variables
+    pub fn permute(&self, state: &[FpVar<Fr>]) -> Result<Vec<FpVar<Fr>>, GadgetError> {
+        if state.len() != self.t {
+            return Err(PoseidonError::InputArityMismatch.into());
+        }
+        let half_f = self.n_rounds_f / 2;
+        let mut state = state.to_vec();
+        for (i, constant_c) in self.constants_c.iter().enumerate() {
+            for state_item in &mut state {
+                *state_item += *constant_c;
+            }
+            if i < half_f || i >= half_f + self.n_rounds_p {
+                for state_item in &mut state {
+                    *state_item = state_item.pow_by_constant(&self.e)?;
+                }
+            } else {
+                state[0] = state[0].pow_by_constant(&self.e)?;
+            }
+            state = self.mix(&state);
+        }
+        Ok(state)
+    }
+
+    fn mix(&self, state: &[FpVar<Fr>]) -> Vec<FpVar<Fr>> {
+        self.constants_m
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(state)
+                    .fold(FpVar::zero(), |sum, (constant, state_item)| {
+                        sum + state_item * *constant
+                    })
+            })
+            .collect()
+    }
+
+    // Enforces `Poseidon::hash`: 1 <= inputs.len() < t, zero padded
+    pub fn hash(&self, inputs: &[FpVar<Fr>]) -> Result<FpVar<Fr>, GadgetError> {
+        if inputs.is_empty() {
+            return Err(PoseidonError::EmptyInputError.into());
+        }
+        if inputs.len() >= self.t {
+            return Err(PoseidonError::InputsExceedRate.into());
+        }
+        let mut state = inputs.to_vec();
+        state.resize(self.t, FpVar::zero());
+        Ok(self.permute(&state)?.swap_remove(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_relations::gr1cs::ConstraintSystem;
+    use num_bigint::BigInt;
+
+    fn witnesses(
+        cs: &ark_relations::gr1cs::ConstraintSystemRef<Fr>,
+        values: &[u64],
+    ) -> Vec<FpVar<Fr>> {
+        values
+            .iter()
+            .map(|v| FpVar::new_witness(cs.clone(), || Ok(Fr::from(*v))).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn gadget_matches_native_hash() {
+        for (t, values) in [(3, vec![1u64, 2]), (13, (1..=12).collect::<Vec<_>>())] {
+            let native = Poseidon::shared(t, 6, 53)
+                .hash(values.iter().map(|v| BigInt::from(*v)))
+                .unwrap();
+
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let inputs = witnesses(&cs, &values);
+            let digest = PoseidonGadget::shared(t, 6, 53).hash(&inputs).unwrap();
+            assert_eq!(digest.value().unwrap(), to_fr(native.magnitude()));
+
+            let expected = FpVar::new_input(cs.clone(), || Ok(to_fr(native.magnitude()))).unwrap();
+            digest.enforce_equal(&expected).unwrap();
+            assert!(cs.is_satisfied().unwrap());
+            let padding = t - values.len();
+            assert_eq!(cs.num_constraints(), 3 * (6 * t + 53 - padding) + 1);
+        }
+    }
+
+    #[test]
+    fn wrong_digest_is_unsatisfiable() {
+        let gadget = PoseidonGadget::shared(3, 6, 53);
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let inputs = witnesses(&cs, &[1, 2]);
+        let digest = gadget.hash(&inputs).unwrap();
+        let wrong = FpVar::new_input(cs.clone(), || Ok(Fr::from(42u64))).unwrap();
+        digest.enforce_equal(&wrong).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        assert!(matches!(
+            gadget.hash(&witnesses(&cs, &[1, 2, 3])),
+            Err(GadgetError::Poseidon(PoseidonError::InputsExceedRate))
+        ));
+        let other_field = Poseidon::new(
+            BigInt::from(2u64.pow(61) - 1),
+            3,
+            8,
+            57,
+            "poseidon".to_string(),
+            BigInt::from(5),
+            None,
+            None,
+            60,
+        );
+        assert!(matches!(
+            PoseidonGadget::new(&other_field),
+            Err(ConversionError::ModulusMismatch)
+        ));
+    }
+}
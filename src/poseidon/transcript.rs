@@ -0,0 +1,186 @@
+// Fiat–Shamir transcripts over Poseidon.
+//
+// A duplex sponge over Poseidon(t = 6, F = 6, P = 52), the instance of the
+// EdDSA challenge: five rate elements and the capacity, which starts as the
+// domain tag of the protocol (commitment::domain_tag). Every operation is
+// encoded as field elements,
+//
+//     append elements:  1, tag(label), n, e_1, ..., e_n
+//     append bytes:     2, tag(label), len(bytes), 31-byte little-endian chunks
+//     challenge:        3, tag(label)
+//
+// and queued. A challenge pads the queue with a single 1 and zeros to whole
+// blocks, adds each block into the rate and permutes, and returns the first
+// state element; the state carries over, so every challenge depends on the
+// whole transcript before it. The encoding is prefix-free, so no two different
+// transcripts absorb the same elements.
+//
+// Labels and the domain are fixed per protocol, so a circuit takes their tags
+// as constants and recomputes the challenges with its stock Poseidon gadget.
+
+use super::commitment::domain_tag;
+use super::field::FQ;
+use super::jubjub::{Point, JUBJUB_L};
+use super::permutation::Poseidon;
+use lazy_static::lazy_static;
+use num_bigint::{BigInt, BigUint};
+use num_traits::{One, Zero};
+
+const WIDTH: usize = 6;
+const RATE: usize = WIDTH - 1;
+const CHUNK_SIZE: usize = 31;
+
+const OP_ELEMENTS: u8 = 1;
+const OP_BYTES: u8 = 2;
+const OP_CHALLENGE: u8 = 3;
+
+lazy_static! {
+    static ref TRANSCRIPT_POSEIDON: Poseidon = Poseidon::shared(WIDTH, 6, 52);
+}
+
+#[derive(Clone)]
+pub struct Transcript {
+    state: Vec<BigUint>,
+    pending: Vec<BigUint>,
+}
+
+impl Transcript {
+    // `domain` names the protocol and its version, e.g. "myprotocol/range-proof/v1"
+    pub fn new(domain: &str) -> Self {
+        let mut state = vec![BigUint::zero(); WIDTH];
+        state[RATE] = domain_tag(domain).n_uint().clone();
+        Transcript {
+            state,
+            pending: Vec::new(),
+        }
+    }
+
+    fn begin(&mut self, op: u8, label: &str) {
+        self.pending.push(BigUint::from(op));
+        self.pending.push(domain_tag(label).n_uint().clone());
+    }
+
+    pub fn append_element(&mut self, label: &str, element: &FQ) {
+        self.append_elements(label, std::slice::from_ref(element));
+    }
+
+    pub fn append_elements(&mut self, label: &str, elements: &[FQ]) {
+        self.begin(OP_ELEMENTS, label);
+        self.pending.push(BigUint::from(elements.len()));
+        self.pending
+            .extend(elements.iter().map(|element| element.n_uint().clone()));
+    }
+
+    // The coordinates x, y as two elements
+    pub fn append_point(&mut self, label: &str, point: &Point) {
+        self.append_elements(label, &[point.x().clone(), point.y().clone()]);
+    }
+
+    pub fn append_bytes(&mut self, label: &str, bytes: &[u8]) {
+        self.begin(OP_BYTES, label);
+        self.pending.push(BigUint::from(bytes.len()));
+        self.pending
+            .extend(bytes.chunks(CHUNK_SIZE).map(BigUint::from_bytes_le));
+    }
+
+    pub fn challenge(&mut self, label: &str) -> FQ {
+        self.begin(OP_CHALLENGE, label);
+        self.pending.push(BigUint::one());
+        self.pending
+            .resize(self.pending.len().div_ceil(RATE) * RATE, BigUint::zero());
+
+        let p = TRANSCRIPT_POSEIDON.modulus();
+        let mut state = std::mem::take(&mut self.state);
+        for block in self.pending.chunks(RATE) {
+            for (element, m) in state.iter_mut().zip(block) {
+                *element = (&*element + m) % p;
+            }
+            state = TRANSCRIPT_POSEIDON.permute_state(state);
+        }
+        self.pending.clear();
+        let challenge = FQ::from_biguint(state[0].clone());
+        self.state = state;
+        challenge
+    }
+
+    // A challenge reduced modulo JUBJUB_L, for use as a scalar of the prime-order
+    // subgroup; p is close to 8 * L, so it is statistically close to uniform
+    pub fn challenge_scalar(&mut self, label: &str) -> BigInt {
+        self.challenge(label).to_bigint() % &*JUBJUB_L
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fq(n: u64) -> FQ {
+        FQ::new(BigInt::from(n))
+    }
+
+    #[test]
+    fn challenges_follow_the_encoding() {
+        let mut transcript = Transcript::new("test/v1");
+        transcript.append_element("x", &fq(7));
+        let challenge = transcript.challenge("c");
+
+        // 1, tag(x), 1, 7 | 3, tag(c), 1, 0, 0, 0 into a capacity of tag(test/v1)
+        let tag = |s: &str| domain_tag(s).n_uint().clone();
+        let mut state = vec![BigUint::zero(); WIDTH];
+        state[RATE] = tag("test/v1");
+        let blocks = [
+            [1u8.into(), tag("x"), 1u8.into(), 7u8.into(), 3u8.into()],
+            [tag("c"), 1u8.into(), 0u8.into(), 0u8.into(), 0u8.into()],
+        ];
+        for block in blocks {
+            for (element, m) in state.iter_mut().zip(block) {
+                *element = (&*element + m) % TRANSCRIPT_POSEIDON.modulus();
+            }
+            state = TRANSCRIPT_POSEIDON.permute_state(state);
+        }
+        assert!(challenge == FQ::from_biguint(state[0].clone()));
+
+        // A second challenge continues from the first
+        let mut replay = Transcript::new("test/v1");
+        replay.append_element("x", &fq(7));
+        assert!(replay.challenge("c") == challenge);
+        assert!(replay.challenge("c") != challenge);
+        assert!(transcript.challenge_scalar("c") < *JUBJUB_L);
+    }
+
+    #[test]
+    fn transcripts_are_separated() {
+        let challenge = |build: &dyn Fn(&mut Transcript), domain: &str| {
+            let mut transcript = Transcript::new(domain);
+            build(&mut transcript);
+            transcript.challenge("c")
+        };
+        let base = challenge(&|t| t.append_elements("x", &[fq(1), fq(2)]), "a");
+        assert!(base != challenge(&|t| t.append_elements("x", &[fq(1), fq(2)]), "b"));
+        assert!(base != challenge(&|t| t.append_elements("y", &[fq(1), fq(2)]), "a"));
+        assert!(base != challenge(&|t| t.append_elements("x", &[fq(2), fq(1)]), "a"));
+        assert!(
+            base != challenge(
+                &|t| {
+                    t.append_element("x", &fq(1));
+                    t.append_element("x", &fq(2));
+                },
+                "a"
+            )
+        );
+        // Bytes and elements of the same value differ, as do byte lengths
+        let bytes = challenge(&|t| t.append_bytes("x", &[1]), "a");
+        assert!(bytes != challenge(&|t| t.append_element("x", &fq(1)), "a"));
+        assert!(bytes != challenge(&|t| t.append_bytes("x", &[1, 0]), "a"));
+        assert!(
+            challenge(&|t| t.append_point("R", &Point::generate()), "a")
+                == challenge(
+                    &|t| t.append_elements(
+                        "R",
+                        &[Point::generate().x().clone(), Point::generate().y().clone()]
+                    ),
+                    "a"
+                )
+        );
+    }
+}
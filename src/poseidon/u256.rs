@@ -0,0 +1,237 @@
+// Conversions between the crate's field elements / points and the U256 types
+// used by Ethereum tooling (`ruint` and `primitive-types`).
+//
+// Going from FQ to U256 only fails if a custom modulus produced a value wider than
+// 256 bits. Going from U256 to FQ rejects values that are not already reduced
+// below SNARK_SCALAR_FIELD, so a conversion never silently changes the value.
+// Points convert to and from their (x, y) coordinate pair.
+//
+// Keys and signatures are range-checked on the way in:
+//  - L2PublicKey from (x, y) runs the checks of L2PublicKey::new (on the curve,
+//    in the subgroup, not the neutral point).
+//  - Signature from (Rx, Ry, S) requires all three below SNARK_SCALAR_FIELD:
+//    signing keeps S as a field element like the coordinates.
+//  - KeyPair from a U256 private scalar rejects keys that are zero modulo
+//    JUBJUB_L. There is no conversion back: the scalar only leaves through
+//    hazmat::private_key_scalar.
+
+use super::{
+    eddsa::{is_signing_key, KeyPair, Signature},
+    field::FQ,
+    field::SNARK_SCALAR_FIELD,
+    jubjub::Point,
+};
+use crate::keygen::public_key::L2PublicKey;
+use crate::util::errors::ConversionError;
+use num_bigint::{BigInt, Sign};
+
+fn checked_field_element(bytes_le: &[u8]) -> Result<FQ, ConversionError> {
+    let n = BigInt::from_bytes_le(Sign::Plus, bytes_le);
+    if n >= *SNARK_SCALAR_FIELD {
+        return Err(ConversionError::ValueExceedsModulus);
+    }
+    Ok(FQ::new(n))
+}
+
+fn checked_private_key(bytes_le: &[u8]) -> Result<KeyPair, ConversionError> {
+    let n = BigInt::from_bytes_le(Sign::Plus, bytes_le);
+    if !is_signing_key(&n) {
+        return Err(ConversionError::ZeroKey);
+    }
+    Ok(KeyPair::new(n))
+}
+
+macro_rules! impl_u256_conversions {
+    ($u256:ty, $from_le:expr, $to_le:expr) => {
+        impl TryFrom<&FQ> for $u256 {
+            type Error = ConversionError;
+
+            fn try_from(value: &FQ) -> Result<Self, Self::Error> {
+                let bytes = value.n_uint().to_bytes_le();
+                if bytes.len() > 32 {
+                    return Err(ConversionError::ValueTooLarge);
+                }
+                Ok($from_le(&bytes))
+            }
+        }
+
+        impl TryFrom<FQ> for $u256 {
+            type Error = ConversionError;
+
+            fn try_from(value: FQ) -> Result<Self, Self::Error> {
+                <$u256>::try_from(&value)
+            }
+        }
+
+        impl TryFrom<$u256> for FQ {
+            type Error = ConversionError;
+
+            fn try_from(value: $u256) -> Result<Self, Self::Error> {
+                checked_field_element(&$to_le(&value))
+            }
+        }
+
+        impl TryFrom<&Point> for ($u256, $u256) {
+            type Error = ConversionError;
+
+            fn try_from(value: &Point) -> Result<Self, Self::Error> {
+                Ok((value.x().try_into()?, value.y().try_into()?))
+            }
+        }
+
+        impl TryFrom<($u256, $u256)> for Point {
+            type Error = ConversionError;
+
+            fn try_from(value: ($u256, $u256)) -> Result<Self, Self::Error> {
+                Ok(Point::new(value.0.try_into()?, value.1.try_into()?))
+            }
+        }
+
+        impl TryFrom<&L2PublicKey> for ($u256, $u256) {
+            type Error = ConversionError;
+
+            fn try_from(value: &L2PublicKey) -> Result<Self, Self::Error> {
+                value.point().try_into()
+            }
+        }
+
+        impl TryFrom<($u256, $u256)> for L2PublicKey {
+            type Error = ConversionError;
+
+            fn try_from(value: ($u256, $u256)) -> Result<Self, Self::Error> {
+                L2PublicKey::new(Point::try_from(value)?).map_err(ConversionError::InvalidPublicKey)
+            }
+        }
+
+        // (Rx, Ry, S)
+        impl TryFrom<&Signature> for ($u256, $u256, $u256) {
+            type Error = ConversionError;
+
+            fn try_from(value: &Signature) -> Result<Self, Self::Error> {
+                let (x, y) = value.image_of_r().try_into()?;
+                Ok((x, y, value.s().try_into()?))
+            }
+        }
+
+        impl TryFrom<($u256, $u256, $u256)> for Signature {
+            type Error = ConversionError;
+
+            fn try_from(value: ($u256, $u256, $u256)) -> Result<Self, Self::Error> {
+                let r = Point::try_from((value.0, value.1))?;
+                Ok(Signature::new(r, value.2.try_into()?))
+            }
+        }
+
+        impl TryFrom<$u256> for KeyPair {
+            type Error = ConversionError;
+
+            fn try_from(value: $u256) -> Result<Self, Self::Error> {
+                checked_private_key(&$to_le(&value))
+            }
+        }
+    };
+}
+
+#[cfg(feature = "ruint")]
+impl_u256_conversions!(
+    ruint::aliases::U256,
+    |bytes: &[u8]| ruint::aliases::U256::try_from_le_slice(bytes).unwrap(),
+    |value: &ruint::aliases::U256| value.to_le_bytes::<32>()
+);
+
+#[cfg(feature = "primitive-types")]
+impl_u256_conversions!(
+    primitive_types::U256,
+    primitive_types::U256::from_little_endian,
+    primitive_types::U256::to_little_endian
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poseidon::jubjub::JUBJUB_L;
+    use std::str::FromStr;
+
+    fn generator_x() -> BigInt {
+        BigInt::from_str(
+            "16540640123574156134436876038791482806971768689494387082833631921987005038935",
+        )
+        .unwrap()
+    }
+
+    #[cfg(feature = "ruint")]
+    #[test]
+    fn ruint_round_trip() {
+        use ruint::aliases::U256;
+
+        let value = U256::try_from(FQ::new(generator_x())).unwrap();
+        assert_eq!(value, U256::from_str(&generator_x().to_string()).unwrap());
+
+        let back = FQ::try_from(value).unwrap();
+        assert_eq!(back.to_bigint(), generator_x());
+
+        let (x, y) = <(U256, U256)>::try_from(&Point::generate()).unwrap();
+        let point = Point::try_from((x, y)).unwrap();
+        assert_eq!(point.as_scalar(), Point::generate().as_scalar());
+    }
+
+    #[cfg(feature = "ruint")]
+    #[test]
+    fn ruint_rejects_unreduced_values() {
+        use ruint::aliases::U256;
+
+        let field = U256::from_str(&SNARK_SCALAR_FIELD.to_string()).unwrap();
+        assert!(FQ::try_from(field).is_err());
+        assert!(FQ::try_from(U256::MAX).is_err());
+    }
+
+    #[cfg(feature = "ruint")]
+    #[test]
+    fn ruint_key_conversions_are_range_checked() {
+        use crate::poseidon::eddsa::{MessageHash, SignatureScheme};
+        use ruint::aliases::U256;
+
+        let keypair = KeyPair::try_from(U256::from(123456789u64)).unwrap();
+        assert!(*keypair.public_key() == Point::generate().mul_scalar(&BigInt::from(123456789)));
+        let order = U256::from_str(&JUBJUB_L.to_string()).unwrap();
+        assert!(matches!(
+            KeyPair::try_from(order),
+            Err(ConversionError::ZeroKey)
+        ));
+
+        let public_key = L2PublicKey::try_from(&keypair).unwrap();
+        let coordinates = <(U256, U256)>::try_from(&public_key).unwrap();
+        assert!(L2PublicKey::try_from(coordinates).unwrap() == public_key);
+        assert!(matches!(
+            L2PublicKey::try_from((U256::ZERO, U256::from(1u8))),
+            Err(ConversionError::InvalidPublicKey(_))
+        ));
+
+        let signed = SignatureScheme::sign_with_keypair(&keypair, &MessageHash::from_message("0"));
+        let (rx, ry, s) = <(U256, U256, U256)>::try_from(signed.sig()).unwrap();
+        let signature = Signature::try_from((rx, ry, s)).unwrap();
+        assert_eq!(signature.to_bytes(), signed.sig().to_bytes());
+        assert!(matches!(
+            Signature::try_from((rx, ry, U256::MAX)),
+            Err(ConversionError::ValueExceedsModulus)
+        ));
+    }
+
+    #[cfg(feature = "primitive-types")]
+    #[test]
+    fn primitive_types_round_trip() {
+        use primitive_types::U256;
+
+        let value = U256::try_from(FQ::new(generator_x())).unwrap();
+        assert_eq!(
+            value,
+            U256::from_dec_str(&generator_x().to_string()).unwrap()
+        );
+
+        let back = FQ::try_from(value).unwrap();
+        assert_eq!(back.to_bigint(), generator_x());
+
+        let field = U256::from_dec_str(&SNARK_SCALAR_FIELD.to_string()).unwrap();
+        assert!(FQ::try_from(field).is_err());
+    }
+}
@@ -0,0 +1,94 @@
+// Startup self-check.
+//
+// `self_check()` recomputes a few reference vectors that cover the whole signing
+// path (Poseidon, key derivation, API request and payload signatures) and compares
+// them with the values the crate is known to produce. Services can call it at boot
+// to fail fast if a dependency update or a platform quirk changed any output.
+
+use crate::keygen::l2_key::generate_l2_keys;
+use crate::poseidon::{
+    eddsa::{sign_api_request, sign_poseidon_inputs},
+    permutation::Poseidon,
+};
+use crate::util::errors::SelfCheckError;
+use num_bigint::BigInt;
+
+const KEYGEN_SIGNATURE: &str = "0xf8214f068c55d1bebf1fbefced91eba5f4bbe14315e1ad71f61f21e094f5853a12eba239aeaa77538ae458eebe49ca2b732d211bf0943095b3502a3b0e6a08cd1c";
+const L2_KEY: &str = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+
+fn expect(vector: &'static str, expected: &str, actual: String) -> Result<(), SelfCheckError> {
+    if actual != expected {
+        return Err(SelfCheckError::Mismatch {
+            vector,
+            expected: expected.to_string(),
+            actual,
+        });
+    }
+    Ok(())
+}
+
+pub fn self_check() -> Result<(), SelfCheckError> {
+    let poseidon = Poseidon::shared(9, 6, 53)
+        .hash(1u64..=8)
+        .map(|hash| hash.to_string())
+        .unwrap_or_else(|e| e.to_string());
+    expect(
+        "poseidon",
+        "1792233229836714442925799757877868602259716425270865187624398529027734741166",
+        poseidon,
+    )?;
+
+    let account = generate_l2_keys(KEYGEN_SIGNATURE.to_string())
+        .map_err(|e| SelfCheckError::Failed("keygen", e))?;
+    expect(
+        "keygen private key",
+        "0x001fa186947c8c644cd11078f67e0bb21656432f55c4df76997b6acab2abda7f",
+        account.private_key,
+    )?;
+    expect(
+        "keygen public key",
+        "0x29d178cdd6a40cd900c41565b6057a1d12c00a8c41ad367e2fe0100aab00fbe3",
+        account.public_key_x,
+    )?;
+
+    expect(
+        "request signature",
+        "0x15fdcda3ca2965d2ae43739cc6740e50c08d3f756c6161bcedb10fbc05290e000f3bc31e2293ba91ca7ac55cd20a86ae3541d3dfed63896cd474015ec60b8d40274f98b2d0a87ebf8cd0ee16dc9ec953a229cf0d6b2b61867ca80ba6e8ae1ed3",
+        sign_api_request(
+            "POST",
+            "https://api3.loopring.io/api/v3/apiKey",
+            [("accountId", "12345")],
+            L2_KEY,
+        )
+        .map_err(|e| SelfCheckError::Failed("request signature", e.to_string()))?,
+    )?;
+
+    expect(
+        "payload signature",
+        "0x0659e9406f7c3a0e1bd6ec42e69ca4a013e21253ff8abd216d9411b882b263502d99f4229cf3f10991e7999bf45b55f4afa9976e237df94378fd647fdb5a5eec0f944d06f57d08b23f3327334c43198a9c78d477a3f0f3e30f0c2c464f5319be",
+        sign_poseidon_inputs(
+            vec![BigInt::from(2), BigInt::from(5), BigInt::from(7)],
+            L2_KEY,
+        )
+        .map_err(|e| SelfCheckError::Failed("payload signature", e.to_string()))?,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn self_check_passes() {
+        assert_eq!(self_check(), Ok(()));
+    }
+
+    #[test]
+    fn mismatch_is_reported() {
+        let error = expect("poseidon", "1", "2".to_string()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Self-check poseidon failed: expected 1, got 2"
+        );
+    }
+}
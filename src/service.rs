@@ -0,0 +1,779 @@
+/*
+A small HTTP signing service, for teams that keep one internal signer instead of
+a key in every application (`service` feature).
+
+    GET  /v1/public-key    -> {"x": "0x...", "y": "0x..."}
+    POST /v1/keygen        {"signature": "0x<ECDSA signature of the key seed message>"}
+                           -> {"privateKey": "0x...", "publicKey": {"x": ..., "y": ...}}
+    POST /v1/sign-request  {"method": "GET", "url": "...", "params": [[k, v], ...]}
+                           or {"method": "POST", "url": "...", "body": "<exact body>"}
+                           -> {"signature": "0x..."}
+    POST /v1/sign-payload  a payload object in the payload-file format
+                           -> {"type": ..., "hash": "0x...", "signature": "0x..."}
+    POST /v1/verify        {"signature": "0x...", "publicKey": {"x": ..., "y": ...},
+                            and one of "hash", "payload" or "request"}
+                           -> {"valid": true}
+
+Request bodies are JSON; errors are {"error": "..."} with status 400 (bad
+request), 401 (missing or wrong token), 403 (refused by the policy), 404/405,
+413 (body over MAX_BODY_BYTES) or 431 (request line or headers over the limits).
+keygen is the derivation of `keygen::l2_key` and returns the derived key: the
+caller already holds the L1 signature it is derived from. Since a service that
+returns private keys should not do so by accident, the endpoint answers 404
+unless `enable_keygen` was called.
+
+With `require_token`, every connection must send `Authorization: Bearer
+<token>`; `handle` itself does not check it, it is for callers in the same
+process. Every request passes the SigningPolicy before anything is derived or
+signed, and every signature goes through an AuditedSigner, so the audit chain
+records each attempt under its operation ("api" for request signatures, the
+payload type for payloads). The server is plain HTTP/1.1 over std::net, one
+request per connection. `serve` runs a fixed pool of workers (DEFAULT_WORKERS,
+see `workers`), each handling one connection at a time, and every connection
+gets REQUEST_DEADLINE in total to send its request and receive the answer,
+however slowly it trickles bytes. A stalled client therefore holds up one worker
+for at most that long. Put it behind a TLS terminating proxy if it is reachable
+beyond localhost. gRPC is not provided.
+*/
+
+use crate::keygen::l2_key::generate_l2_keys;
+use crate::payload::{bundle::payload_hash, file::sign_entry, hash_to_hex};
+use crate::poseidon::eddsa::{verify_eddsa_hex, MessageHash, SignedMessage};
+use crate::poseidon::jubjub::Point;
+use crate::signer::audit::{AuditRecord, AuditSink, AuditedSigner};
+use crate::signer::stdio::request_base_string;
+use crate::signer::Signer;
+use crate::util::{errors::SignerError, hex};
+use serde_json::{json, Value};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub const MAX_BODY_BYTES: usize = 1 << 20;
+// Per line (request line or header) and number of header lines
+pub const MAX_LINE_BYTES: usize = 8 << 10;
+pub const MAX_HEADERS: usize = 64;
+// For reading the whole request and writing the response
+pub const REQUEST_DEADLINE: Duration = Duration::from_secs(10);
+pub const DEFAULT_WORKERS: usize = 4;
+
+// Decides whether a request may be served. `operation` is the endpoint name
+// ("keygen", "sign-request", "sign-payload", "verify", "public-key") and
+// `request` its parsed body (null for GET).
+pub trait SigningPolicy: Send + Sync {
+    fn check(&self, operation: &str, request: &Value) -> Result<(), String>;
+}
+
+pub struct AllowAll;
+
+impl SigningPolicy for AllowAll {
+    fn check(&self, _: &str, _: &Value) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+// Allows the listed operations, and for sign-payload only the listed payload
+// types; None allows all of them. The JSON form, e.g. for a config file, is
+//
+//     {"operations": ["public-key", "sign-payload", "verify"],
+//      "payloadTypes": ["order", "transfer"]}
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OperationPolicy {
+    pub operations: Option<Vec<String>>,
+    pub payload_types: Option<Vec<String>>,
+}
+
+fn string_list(value: &Value, name: &str) -> Result<Option<Vec<String>>, String> {
+    match value.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| {
+                item.as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| format!("{} must be a list of strings", name))
+            })
+            .collect::<Result<_, _>>()
+            .map(Some),
+        Some(_) => Err(format!("{} must be a list of strings", name)),
+    }
+}
+
+impl OperationPolicy {
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        if !value.is_object() {
+            return Err("A policy must be a JSON object".to_string());
+        }
+        Ok(OperationPolicy {
+            operations: string_list(value, "operations")?,
+            payload_types: string_list(value, "payloadTypes")?,
+        })
+    }
+}
+
+impl SigningPolicy for OperationPolicy {
+    fn check(&self, operation: &str, request: &Value) -> Result<(), String> {
+        let allows = |list: &Option<Vec<String>>, item: &str| match list {
+            Some(list) => list.iter().any(|allowed| allowed == item),
+            None => true,
+        };
+        if !allows(&self.operations, operation) {
+            return Err(format!("{} is not allowed", operation));
+        }
+        if operation == "sign-payload" {
+            let payload_type = request["type"].as_str().unwrap_or_default();
+            if !allows(&self.payload_types, payload_type) {
+                return Err(format!("Payload type {} is not allowed", payload_type));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Writes every audit record as one JSON line, e.g. to an append-only file
+pub struct JsonLinesSink<W: Write + Send>(Mutex<W>);
+
+impl<W: Write + Send> JsonLinesSink<W> {
+    pub fn new(writer: W) -> Self {
+        JsonLinesSink(Mutex::new(writer))
+    }
+}
+
+impl<W: Write + Send> AuditSink for JsonLinesSink<W> {
+    fn record(&self, record: &AuditRecord) {
+        let line = json!({
+            "sequence": record.sequence,
+            "timestamp": record.timestamp,
+            "type": record.payload_type,
+            "hash": hash_to_hex(&record.hash),
+            "publicKey": {"x": record.public_key_x, "y": record.public_key_y},
+            "outcome": match &record.outcome {
+                Ok(()) => "ok".to_string(),
+                Err(e) => e.to_string(),
+            },
+            "previous": format!("0x{}", ::hex::encode(record.previous)),
+            "digest": format!("0x{}", ::hex::encode(record.digest)),
+        });
+        let mut writer = self.0.lock().unwrap();
+        // An audit sink cannot fail the signature it records
+        let _ = writeln!(writer, "{}", line).and_then(|_| writer.flush());
+    }
+}
+
+struct Failure(u16, String);
+
+fn bad_request<E: ToString>(e: E) -> Failure {
+    Failure(400, e.to_string())
+}
+
+fn field<'a>(value: &'a Value, name: &str) -> Result<&'a Value, Failure> {
+    value
+        .get(name)
+        .ok_or_else(|| bad_request(format!("Missing field: {}", name)))
+}
+
+fn string_field<'a>(value: &'a Value, name: &str) -> Result<&'a str, Failure> {
+    field(value, name)?
+        .as_str()
+        .ok_or_else(|| bad_request(format!("{} must be a string", name)))
+}
+
+// Compares in time that depends only on the lengths
+fn same_token(given: &[u8], expected: &[u8]) -> bool {
+    given.len() == expected.len()
+        && given
+            .iter()
+            .zip(expected)
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+pub struct SigningService<S: Signer, A: AuditSink, P: SigningPolicy> {
+    signer: AuditedSigner<S, A>,
+    policy: P,
+    token: Option<String>,
+    keygen: bool,
+    workers: usize,
+    deadline: Duration,
+}
+
+impl<S: Signer, A: AuditSink, P: SigningPolicy> SigningService<S, A, P> {
+    pub fn new(signer: S, audit: A, policy: P) -> Self {
+        SigningService {
+            signer: AuditedSigner::new(signer, audit),
+            policy,
+            token: None,
+            keygen: false,
+            workers: DEFAULT_WORKERS,
+            deadline: REQUEST_DEADLINE,
+        }
+    }
+
+    // Connections must authenticate with `Authorization: Bearer <token>`
+    pub fn require_token(mut self, token: String) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    // Serves /v1/keygen, which returns derived private keys
+    pub fn enable_keygen(mut self) -> Self {
+        self.keygen = true;
+        self
+    }
+
+    // Number of connections `serve` handles at once, at least 1
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    // Time a connection has for its request and response
+    pub fn request_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    pub fn audit_sink(&self) -> &A {
+        self.signer.sink()
+    }
+
+    // Status and JSON body of the response to one request
+    pub fn handle(&self, method: &str, path: &str, body: &str) -> (u16, String) {
+        let (status, response) = match self.route(method, path, body) {
+            Ok(result) => (200, result),
+            Err(Failure(status, error)) => (status, json!({ "error": error })),
+        };
+        (status, response.to_string())
+    }
+
+    fn route(&self, method: &str, path: &str, body: &str) -> Result<Value, Failure> {
+        let operation = match path {
+            "/v1/public-key" => "public-key",
+            "/v1/keygen" if self.keygen => "keygen",
+            "/v1/sign-request" => "sign-request",
+            "/v1/sign-payload" => "sign-payload",
+            "/v1/verify" => "verify",
+            _ => return Err(Failure(404, format!("No endpoint {}", path))),
+        };
+        let expected = if operation == "public-key" {
+            "GET"
+        } else {
+            "POST"
+        };
+        if method != expected {
+            return Err(Failure(405, format!("{} expects {}", path, expected)));
+        }
+        let request: Value = if expected == "GET" {
+            Value::Null
+        } else {
+            serde_json::from_str(body).map_err(|e| bad_request(format!("Invalid JSON: {}", e)))?
+        };
+        self.policy
+            .check(operation, &request)
+            .map_err(|reason| Failure(403, reason))?;
+
+        match operation {
+            "public-key" => {
+                let public_key = self.signer.public_key();
+                Ok(json!({"x": public_key.x().to_hex(), "y": public_key.y().to_hex()}))
+            }
+            "keygen" => {
+                let account = generate_l2_keys(string_field(&request, "signature")?.to_string())
+                    .map_err(bad_request)?;
+                Ok(json!({
+                    "privateKey": account.private_key,
+                    "publicKey": {"x": account.public_key_x, "y": account.public_key_y},
+                }))
+            }
+            "sign-request" => {
+                let base = request_base_string(&request).map_err(bad_request)?;
+                let signed = self
+                    .signer
+                    .sign_as("api", &MessageHash::from_message(&base))
+                    .map_err(bad_request)?;
+                Ok(json!({"signature": signed.to_hex()}))
+            }
+            "sign-payload" => {
+                let signer = PayloadSigner {
+                    signer: &self.signer,
+                    payload_type: request["type"].as_str().unwrap_or_default(),
+                };
+                let (payload_type, hash, signature) =
+                    sign_entry(&signer, &request).map_err(bad_request)?;
+                Ok(json!({
+                    "type": payload_type,
+                    "hash": hash_to_hex(&hash),
+                    "signature": signature,
+                }))
+            }
+            _ => self.verify(&request),
+        }
+    }
+
+    fn verify(&self, request: &Value) -> Result<Value, Failure> {
+        let hash = if let Some(hash) = request.get("hash") {
+            hex::decode_bigint(
+                hash.as_str()
+                    .ok_or_else(|| bad_request("hash must be a string"))?,
+            )
+            .map_err(bad_request)?
+        } else if let Some(payload) = request.get("payload") {
+            payload_hash(payload).map_err(bad_request)?
+        } else if let Some(api_request) = request.get("request") {
+            let base = request_base_string(api_request).map_err(bad_request)?;
+            MessageHash::from_message(&base).as_bigint().clone()
+        } else {
+            return Err(bad_request("One of hash, payload or request is required"));
+        };
+        let public_key = field(request, "publicKey")?;
+        let valid = verify_eddsa_hex(
+            string_field(request, "signature")?,
+            string_field(public_key, "x")?,
+            string_field(public_key, "y")?,
+            &hash,
+        )
+        .map_err(bad_request)?;
+        Ok(json!({ "valid": valid }))
+    }
+
+    // Reads one request from the connection and answers it, within the
+    // request deadline
+    pub fn serve_connection(&self, stream: TcpStream) -> io::Result<()> {
+        let deadline = Instant::now() + self.deadline;
+        let mut reader = BufReader::new(DeadlineStream {
+            stream: stream.try_clone()?,
+            deadline,
+        });
+        let (status, body) = match read_request(&mut reader)? {
+            Ok(request) if !self.authorized(request.authorization.as_deref()) => (
+                401,
+                json!({"error": "Missing or invalid bearer token"}).to_string(),
+            ),
+            Ok(request) => self.handle(&request.method, &request.path, &request.body),
+            Err(Failure(status, error)) => (status, json!({ "error": error }).to_string()),
+        };
+        write_response(DeadlineStream { stream, deadline }, status, &body)
+    }
+
+    fn authorized(&self, authorization: Option<&str>) -> bool {
+        let expected = match &self.token {
+            Some(token) => token,
+            None => return true,
+        };
+        match authorization.and_then(|value| value.trim().strip_prefix("Bearer ")) {
+            Some(token) => same_token(token.trim().as_bytes(), expected.as_bytes()),
+            None => false,
+        }
+    }
+
+    // Serves connections on the worker pool until accepting fails in every
+    // worker, and returns the first of those errors
+    pub fn serve(&self, listener: TcpListener) -> io::Result<()> {
+        let listener = &listener;
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..self.workers)
+                .map(|_| {
+                    scope.spawn(move || -> io::Result<()> {
+                        for stream in listener.incoming() {
+                            // A broken or slow client connection only affects
+                            // that client
+                            let _ = self.serve_connection(stream?);
+                        }
+                        Ok(())
+                    })
+                })
+                .collect();
+            workers.into_iter().try_for_each(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+        })
+    }
+}
+
+// Lets sign_entry record payload signatures under their payload type
+struct PayloadSigner<'a, S: Signer, A: AuditSink> {
+    signer: &'a AuditedSigner<S, A>,
+    payload_type: &'a str,
+}
+
+impl<S: Signer, A: AuditSink> Signer for PayloadSigner<'_, S, A> {
+    fn public_key(&self) -> &Point {
+        self.signer.public_key()
+    }
+
+    fn sign(&self, hash: &MessageHash) -> Result<SignedMessage, SignerError> {
+        self.signer.sign_as(self.payload_type, hash)
+    }
+}
+
+// A connection whose reads and writes fail with TimedOut once the deadline has
+// passed, so the deadline bounds the whole exchange and not each read
+struct DeadlineStream {
+    stream: TcpStream,
+    deadline: Instant,
+}
+
+impl DeadlineStream {
+    fn remaining(&self) -> io::Result<Duration> {
+        match self.deadline.checked_duration_since(Instant::now()) {
+            Some(remaining) if !remaining.is_zero() => Ok(remaining),
+            _ => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "request deadline passed",
+            )),
+        }
+    }
+}
+
+impl io::Read for DeadlineStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream.set_read_timeout(Some(self.remaining()?))?;
+        self.stream.read(buf)
+    }
+}
+
+impl Write for DeadlineStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.set_write_timeout(Some(self.remaining()?))?;
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    authorization: Option<String>,
+    body: String,
+}
+
+// One line of at most MAX_LINE_BYTES, or None if the line is longer
+fn read_line<R: BufRead>(reader: &mut R) -> io::Result<Option<String>> {
+    let mut line = String::new();
+    io::Read::take(&mut *reader, MAX_LINE_BYTES as u64 + 1).read_line(&mut line)?;
+    Ok(if line.len() > MAX_LINE_BYTES {
+        None
+    } else {
+        Some(line)
+    })
+}
+
+fn too_large(what: &str) -> Failure {
+    Failure(431, format!("{} too large", what))
+}
+
+fn read_request<R: BufRead>(reader: &mut R) -> io::Result<Result<Request, Failure>> {
+    let request_line = match read_line(reader)? {
+        Some(line) => line,
+        None => return Ok(Err(too_large("Request line"))),
+    };
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = match (parts.next(), parts.next()) {
+        (Some(method), Some(path)) => (method.to_string(), path.to_string()),
+        _ => return Ok(Err(bad_request("Malformed request line"))),
+    };
+
+    let mut content_length = 0usize;
+    let mut authorization = None;
+    let mut headers = 0;
+    loop {
+        let header = match read_line(reader)? {
+            Some(header) => header,
+            None => return Ok(Err(too_large("Header"))),
+        };
+        if header.is_empty() || header.trim().is_empty() {
+            break;
+        }
+        headers += 1;
+        if headers > MAX_HEADERS {
+            return Ok(Err(too_large("Header section")));
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            let name = name.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                match value.trim().parse() {
+                    Ok(length) => content_length = length,
+                    Err(_) => return Ok(Err(bad_request("Invalid Content-Length"))),
+                }
+            } else if name.eq_ignore_ascii_case("authorization") {
+                authorization = Some(value.trim().to_string());
+            }
+        }
+    }
+    if content_length > MAX_BODY_BYTES {
+        return Ok(Err(Failure(413, "Request body too large".to_string())));
+    }
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body)?;
+    match String::from_utf8(body) {
+        Ok(body) => Ok(Ok(Request {
+            method,
+            path,
+            authorization,
+            body,
+        })),
+        Err(_) => Ok(Err(bad_request("Body is not UTF-8"))),
+    }
+}
+
+fn write_response<W: Write>(mut stream: W, status: u16, body: &str) -> io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        431 => "Request Header Fields Too Large",
+        _ => "Payload Too Large",
+    };
+    let challenge = if status == 401 {
+        "WWW-Authenticate: Bearer\r\n"
+    } else {
+        ""
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\n{}Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        challenge,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload::transfer::tests::transfer;
+    use crate::signer::audit::verify_chain;
+    use crate::signer::LocalSigner;
+    use std::io::Read;
+
+    const L2_KEY: &str = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+
+    const TRANSFER: &str = r#"{"type": "transfer",
+        "exchange": "0x0BABA1Ad5bE3a5C0a66E7ac838a129Bf948f1eA4",
+        "payerId": 11087, "payerAddr": "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        "payeeId": 0, "payeeAddr": "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+        "token": {"tokenId": 1, "volume": "1000000000000000000"},
+        "maxFee": {"tokenId": 0, "volume": "100000000000000"},
+        "validUntil": 1700000000, "storageId": 5}"#;
+
+    #[derive(Default)]
+    struct MemorySink(Mutex<Vec<AuditRecord>>);
+
+    impl AuditSink for MemorySink {
+        fn record(&self, record: &AuditRecord) {
+            self.0.lock().unwrap().push(record.clone());
+        }
+    }
+
+    // Only transfers up to storage id 10
+    struct StorageLimit;
+
+    impl SigningPolicy for StorageLimit {
+        fn check(&self, operation: &str, request: &Value) -> Result<(), String> {
+            match (operation, request["storageId"].as_u64()) {
+                ("sign-payload", Some(id)) if id > 10 => Err("storage id too high".to_string()),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    fn service() -> SigningService<LocalSigner, MemorySink, StorageLimit> {
+        SigningService::new(
+            LocalSigner::from_hex(L2_KEY).unwrap(),
+            MemorySink::default(),
+            StorageLimit,
+        )
+        .require_token("secret".to_string())
+    }
+
+    // Sends `request` over a local connection and returns the raw response
+    fn exchange(
+        service: &SigningService<LocalSigner, MemorySink, StorageLimit>,
+        request: String,
+    ) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let client = std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.write_all(request.as_bytes()).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        });
+        let (stream, _) = listener.accept().unwrap();
+        service.serve_connection(stream).unwrap();
+        client.join().unwrap()
+    }
+
+    fn call(
+        service: &SigningService<LocalSigner, MemorySink, StorageLimit>,
+        method: &str,
+        path: &str,
+        body: &str,
+    ) -> (u16, Value) {
+        let (status, body) = service.handle(method, path, body);
+        (status, serde_json::from_str(&body).unwrap())
+    }
+
+    #[test]
+    fn signs_verifies_and_audits() {
+        let service = service();
+        let (status, signed) = call(&service, "POST", "/v1/sign-payload", TRANSFER);
+        assert_eq!(status, 200);
+        assert_eq!(signed["signature"], transfer().sign(L2_KEY).unwrap());
+
+        let (_, public_key) = call(&service, "GET", "/v1/public-key", "");
+        let check = json!({
+            "payload": serde_json::from_str::<Value>(TRANSFER).unwrap(),
+            "signature": signed["signature"],
+            "publicKey": public_key,
+        });
+        let (status, verified) = call(&service, "POST", "/v1/verify", &check.to_string());
+        assert_eq!((status, verified["valid"].as_bool()), (200, Some(true)));
+
+        let mut tampered = check.clone();
+        tampered["payload"]["storageId"] = json!(7);
+        let (_, verified) = call(&service, "POST", "/v1/verify", &tampered.to_string());
+        assert_eq!(verified["valid"], false);
+
+        let api = json!({"method": "GET", "url": "https://api3.loopring.io/api/v3/apiKey",
+                         "params": [["accountId", "11087"]]});
+        let (status, _) = call(&service, "POST", "/v1/sign-request", &api.to_string());
+        assert_eq!(status, 200);
+
+        let records = service.audit_sink().0.lock().unwrap().clone();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].payload_type, "transfer");
+        assert_eq!(records[1].payload_type, "api");
+        assert!(verify_chain(&records));
+    }
+
+    #[test]
+    fn operation_policy_from_config() {
+        let policy = OperationPolicy::from_json(&json!({
+            "operations": ["sign-payload", "verify"], "payloadTypes": ["order"],
+        }))
+        .unwrap();
+        let transfer: Value = serde_json::from_str(TRANSFER).unwrap();
+        assert!(policy.check("verify", &Value::Null).is_ok());
+        assert!(policy
+            .check("sign-payload", &json!({"type": "order"}))
+            .is_ok());
+        assert!(policy.check("sign-payload", &transfer).is_err());
+        assert!(policy.check("keygen", &Value::Null).is_err());
+        assert!(OperationPolicy::default()
+            .check("keygen", &Value::Null)
+            .is_ok());
+        assert!(OperationPolicy::from_json(&json!({"operations": "verify"})).is_err());
+    }
+
+    #[test]
+    fn rejects_and_serves_over_tcp() {
+        let service = service();
+        let mut over_limit: Value = serde_json::from_str(TRANSFER).unwrap();
+        over_limit["storageId"] = json!(11);
+        let (status, body) = call(
+            &service,
+            "POST",
+            "/v1/sign-payload",
+            &over_limit.to_string(),
+        );
+        assert_eq!(
+            (status, body["error"].as_str()),
+            (403, Some("storage id too high"))
+        );
+        assert_eq!(call(&service, "GET", "/v1/sign-payload", "").0, 405);
+        assert_eq!(call(&service, "GET", "/v2/anything", "").0, 404);
+        // keygen only exists when enabled
+        assert_eq!(call(&service, "POST", "/v1/keygen", "{}").0, 404);
+        let service = service.enable_keygen();
+        assert_eq!(call(&service, "POST", "/v1/keygen", "{}").0, 400);
+        assert!(service.audit_sink().0.lock().unwrap().is_empty());
+
+        let request = |authorization: &str| {
+            format!(
+                "POST /v1/sign-payload HTTP/1.1\r\nHost: x\r\n{}Content-Length: {}\r\n\r\n{}",
+                authorization,
+                TRANSFER.len(),
+                TRANSFER
+            )
+        };
+        for authorization in ["", "Authorization: Bearer wrong\r\n"] {
+            let response = exchange(&service, request(authorization));
+            assert!(response.starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+        }
+        assert!(service.audit_sink().0.lock().unwrap().is_empty());
+        let long_header = format!(
+            "GET / HTTP/1.1\r\nX: {}\r\n\r\n",
+            "a".repeat(MAX_LINE_BYTES)
+        );
+        let many_headers = format!(
+            "GET / HTTP/1.1\r\n{}\r\n",
+            "X: a\r\n".repeat(MAX_HEADERS + 1)
+        );
+        for request in [long_header, many_headers] {
+            let read = read_request(&mut io::Cursor::new(request)).unwrap();
+            assert!(matches!(read, Err(Failure(431, _))));
+        }
+
+        let response = exchange(&service, request("Authorization: Bearer secret\r\n"));
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        let body: Value = serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(body["signature"], transfer().sign(L2_KEY).unwrap());
+    }
+
+    #[test]
+    fn slow_clients_are_cut_off_and_do_not_block_others() {
+        // A client trickling a byte at a time loses the connection at the
+        // deadline, not after a timeout per read
+        let slow = service().request_deadline(Duration::from_millis(300));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let trickle = std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            for byte in b"POST /v1/sign-payload HTTP/1.1\r\n" {
+                if stream.write_all(&[*byte]).is_err() {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(50));
+            }
+        });
+        let (stream, _) = listener.accept().unwrap();
+        let start = Instant::now();
+        let error = slow.serve_connection(stream).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_secs(1));
+        trickle.join().unwrap();
+
+        // While one worker waits on a silent client, another answers
+        let service = std::sync::Arc::new(service().workers(2));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = service.clone();
+        std::thread::spawn(move || server.serve(listener));
+        let _silent = TcpStream::connect(address).unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        stream
+            .write_all(b"GET /v1/public-key HTTP/1.1\r\nHost: x\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+    }
+}
@@ -0,0 +1,292 @@
+/*
+Audit trail for signing operations.
+
+An AuditedSigner reports every signing attempt, successful or not, to an
+AuditSink before returning. Records are hash-chained:
+
+    digest_i = sha256(digest_(i-1) || sequence || timestamp || payload type
+                      || message hash || public key || outcome)
+
+with digest_(-1) = 0, so a sink that stores them (file, database, remote log)
+lets `verify_chain` detect records that were removed, reordered or changed
+afterwards. Anchoring the latest digest somewhere else (e.g. a periodic
+checkpoint) also detects truncation at the end.
+*/
+
+use super::Signer;
+use crate::poseidon::{
+    eddsa::{MessageHash, SignedMessage},
+    jubjub::Point,
+};
+use crate::util::{
+    errors::SignerError,
+    helpers::to_bytes_32,
+    time::{Clock, SystemClock},
+};
+use num_bigint::BigInt;
+use sha2::{Digest, Sha256};
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditRecord {
+    pub sequence: u64,
+    // Seconds since the Unix epoch
+    pub timestamp: u64,
+    // e.g. "order", "transfer", "api"
+    pub payload_type: String,
+    pub hash: BigInt,
+    pub public_key_x: String,
+    pub public_key_y: String,
+    pub outcome: Result<(), SignerError>,
+    pub previous: [u8; 32],
+    pub digest: [u8; 32],
+}
+
+impl AuditRecord {
+    // None if the hash is not a 32-byte value, which no signed hash can be
+    fn compute_digest(&self) -> Option<[u8; 32]> {
+        Some(self.digest_with(to_bytes_32(&self.hash).ok()?))
+    }
+
+    fn digest_with(&self, hash: [u8; 32]) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(self.previous);
+        hasher.update(self.sequence.to_be_bytes());
+        hasher.update(self.timestamp.to_be_bytes());
+        hasher.update((self.payload_type.len() as u64).to_be_bytes());
+        hasher.update(self.payload_type.as_bytes());
+        hasher.update(hash);
+        hasher.update(self.public_key_x.as_bytes());
+        hasher.update(self.public_key_y.as_bytes());
+        match &self.outcome {
+            Ok(()) => hasher.update([0]),
+            Err(e) => {
+                hasher.update([1]);
+                hasher.update(e.to_string().as_bytes());
+            }
+        }
+        hasher.finalize().into()
+    }
+}
+
+// Checks that the records form one unbroken chain, starting at sequence 0
+pub fn verify_chain(records: &[AuditRecord]) -> bool {
+    let mut previous = [0u8; 32];
+    for (i, record) in records.iter().enumerate() {
+        if record.sequence != i as u64
+            || record.previous != previous
+            || Some(record.digest) != record.compute_digest()
+        {
+            return false;
+        }
+        previous = record.digest;
+    }
+    true
+}
+
+pub trait AuditSink: Send + Sync {
+    fn record(&self, record: &AuditRecord);
+
+    // The most recent record, for resuming the chain after a restart; sinks that
+    // cannot read their records back return None
+    fn last(&self) -> Option<AuditRecord> {
+        None
+    }
+}
+
+struct ChainState {
+    sequence: u64,
+    previous: [u8; 32],
+}
+
+pub struct AuditedSigner<S: Signer, A: AuditSink, C: Clock + Send + Sync = SystemClock> {
+    inner: S,
+    sink: A,
+    clock: C,
+    state: Mutex<ChainState>,
+}
+
+impl<S: Signer, A: AuditSink> AuditedSigner<S, A> {
+    pub fn new(inner: S, sink: A) -> Self {
+        Self::with_clock(inner, sink, SystemClock)
+    }
+
+    pub fn resume(inner: S, sink: A) -> Result<Self, SignerError> {
+        Self::resume_with_clock(inner, sink, SystemClock)
+    }
+}
+
+impl<S: Signer, A: AuditSink, C: Clock + Send + Sync> AuditedSigner<S, A, C> {
+    pub fn with_clock(inner: S, sink: A, clock: C) -> Self {
+        AuditedSigner {
+            inner,
+            sink,
+            clock,
+            state: Mutex::new(ChainState {
+                sequence: 0,
+                previous: [0; 32],
+            }),
+        }
+    }
+
+    // Continues the chain after the sink's last record instead of starting a new
+    // one at sequence 0, which would break `verify_chain` over the whole log. The
+    // last record must verify; an empty sink starts a new chain.
+    pub fn resume_with_clock(inner: S, sink: A, clock: C) -> Result<Self, SignerError> {
+        let state = match sink.last() {
+            Some(last) if Some(last.digest) == last.compute_digest() => ChainState {
+                sequence: last.sequence + 1,
+                previous: last.digest,
+            },
+            Some(_) => return Err(SignerError::AuditChainBroken),
+            None => ChainState {
+                sequence: 0,
+                previous: [0; 32],
+            },
+        };
+        Ok(AuditedSigner {
+            inner,
+            sink,
+            clock,
+            state: Mutex::new(state),
+        })
+    }
+
+    pub fn sink(&self) -> &A {
+        &self.sink
+    }
+
+    // Signs and records the operation under `payload_type`
+    pub fn sign_as(
+        &self,
+        payload_type: &str,
+        hash: &MessageHash,
+    ) -> Result<SignedMessage, SignerError> {
+        // Signing (possibly a remote call) happens outside the lock, so concurrent
+        // callers only wait for each other to append a record; sequence numbers
+        // follow the order of the records, not of the signing calls
+        let result = self.inner.sign(hash);
+        let mut state = self.state.lock().unwrap();
+
+        let public_key = self.inner.public_key();
+        let mut record = AuditRecord {
+            sequence: state.sequence,
+            timestamp: self.clock.now(),
+            payload_type: payload_type.to_string(),
+            hash: hash.as_bigint().clone(),
+            public_key_x: public_key.x().to_hex(),
+            public_key_y: public_key.y().to_hex(),
+            outcome: result.as_ref().map(|_| ()).map_err(|e| e.clone()),
+            previous: state.previous,
+            digest: [0; 32],
+        };
+        record.digest = record.digest_with(hash.to_bytes_le());
+        self.sink.record(&record);
+
+        state.sequence += 1;
+        state.previous = record.digest;
+        result
+    }
+}
+
+impl<S: Signer, A: AuditSink, C: Clock + Send + Sync> Signer for AuditedSigner<S, A, C> {
+    fn public_key(&self) -> &Point {
+        self.inner.public_key()
+    }
+
+    fn sign(&self, hash: &MessageHash) -> Result<SignedMessage, SignerError> {
+        self.sign_as("message", hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signer::LocalSigner;
+    use crate::util::time::FixedClock;
+
+    const L2_KEY: &str = "0x001fa186947c8c644cd11078f67e0bb21656432f55c4df76997b6acab2abda7f";
+
+    #[derive(Default)]
+    struct MemorySink(Mutex<Vec<AuditRecord>>);
+
+    impl AuditSink for MemorySink {
+        fn record(&self, record: &AuditRecord) {
+            self.0.lock().unwrap().push(record.clone());
+        }
+
+        fn last(&self) -> Option<AuditRecord> {
+            self.0.lock().unwrap().last().cloned()
+        }
+    }
+
+    struct FailingSigner(Point);
+
+    impl Signer for FailingSigner {
+        fn public_key(&self) -> &Point {
+            &self.0
+        }
+
+        fn sign(&self, _: &MessageHash) -> Result<SignedMessage, SignerError> {
+            Err(SignerError::Backend("unavailable".to_string()))
+        }
+    }
+
+    #[test]
+    fn every_signature_is_recorded() {
+        let signer = AuditedSigner::with_clock(
+            LocalSigner::from_hex(L2_KEY).unwrap(),
+            MemorySink::default(),
+            FixedClock(1700000000),
+        );
+        signer
+            .sign_as("transfer", &MessageHash::from_message("a"))
+            .unwrap();
+        signer.sign(&MessageHash::from_message("b")).unwrap();
+
+        let mut records = signer.sink().0.lock().unwrap().clone();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].payload_type, "transfer");
+        assert_eq!(records[1].timestamp, 1700000000);
+        assert_eq!(
+            records[0].public_key_x,
+            "0x29d178cdd6a40cd900c41565b6057a1d12c00a8c41ad367e2fe0100aab00fbe3"
+        );
+        assert!(verify_chain(&records));
+
+        records[0].payload_type = "order".to_string();
+        assert!(!verify_chain(&records));
+        assert!(!verify_chain(&records[1..]));
+    }
+
+    #[test]
+    fn failures_are_recorded() {
+        let signer = AuditedSigner::new(FailingSigner(Point::generate()), MemorySink::default());
+        assert!(signer.sign(&MessageHash::from_message("a")).is_err());
+
+        let records = signer.sink().0.lock().unwrap();
+        assert!(records[0].outcome.is_err());
+        assert!(verify_chain(&records));
+    }
+
+    #[test]
+    fn resumes_after_the_last_record() {
+        let key = || LocalSigner::from_hex(L2_KEY).unwrap();
+        let signer = AuditedSigner::resume(key(), MemorySink::default()).unwrap();
+        signer.sign(&MessageHash::from_message("a")).unwrap();
+        let records = signer.sink().0.lock().unwrap().clone();
+
+        // A restarted service appends to the same chain
+        let signer = AuditedSigner::resume(key(), MemorySink(Mutex::new(records))).unwrap();
+        signer.sign(&MessageHash::from_message("b")).unwrap();
+        let mut records = signer.sink().0.lock().unwrap().clone();
+        assert_eq!(records[1].sequence, 1);
+        assert!(verify_chain(&records));
+
+        records[1].timestamp += 1;
+        assert!(matches!(
+            AuditedSigner::resume(key(), MemorySink(Mutex::new(records))),
+            Err(SignerError::AuditChainBroken)
+        ));
+    }
+}
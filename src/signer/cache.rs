@@ -0,0 +1,171 @@
+/*
+Signature cache for idempotent retries.
+
+Signing is deterministic, so a retried submission of the same payload produces
+the same signature. A relaying gateway that sees many retries can skip the two
+scalar multiplications by keeping recent signatures in a SignatureCache, keyed
+by (public key fingerprint, message hash). The cache is bounded and evicts the
+least recently used entry.
+
+One cache can be shared (through an Arc) by the CachingSigners of several keys.
+*/
+
+use super::Signer;
+use crate::poseidon::{
+    eddsa::{MessageHash, SignedMessage},
+    jubjub::Point,
+    nonce_guard::fingerprint,
+};
+use crate::util::errors::SignerError;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+type CacheKey = ([u8; 32], [u8; 32]);
+
+struct Entries {
+    signatures: HashMap<CacheKey, (SignedMessage, u64)>,
+    // Last use of each key, oldest first
+    recency: BTreeMap<u64, CacheKey>,
+    tick: u64,
+}
+
+pub struct SignatureCache {
+    capacity: usize,
+    entries: Mutex<Entries>,
+}
+
+impl SignatureCache {
+    pub fn new(capacity: usize) -> Self {
+        SignatureCache {
+            capacity,
+            entries: Mutex::new(Entries {
+                signatures: HashMap::new(),
+                recency: BTreeMap::new(),
+                tick: 0,
+            }),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().signatures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn get(&self, key: &CacheKey) -> Option<SignedMessage> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.tick += 1;
+        let tick = entries.tick;
+        let (signed, last_used) = entries.signatures.get_mut(key)?;
+        let signed = signed.clone();
+        let previous = std::mem::replace(last_used, tick);
+        entries.recency.remove(&previous);
+        entries.recency.insert(tick, *key);
+        Some(signed)
+    }
+
+    fn insert(&self, key: CacheKey, signed: SignedMessage) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        entries.tick += 1;
+        let tick = entries.tick;
+        if let Some((_, previous)) = entries.signatures.insert(key, (signed, tick)) {
+            entries.recency.remove(&previous);
+        }
+        entries.recency.insert(tick, key);
+        while entries.signatures.len() > self.capacity {
+            let (_, oldest) = entries.recency.pop_first().unwrap();
+            entries.signatures.remove(&oldest);
+        }
+    }
+}
+
+pub struct CachingSigner<S: Signer> {
+    inner: S,
+    cache: Arc<SignatureCache>,
+    key_fingerprint: [u8; 32],
+}
+
+impl<S: Signer> CachingSigner<S> {
+    pub fn new(inner: S, cache: Arc<SignatureCache>) -> Self {
+        let public_key = inner.public_key();
+        let key_fingerprint = fingerprint(public_key.x(), public_key.y());
+        CachingSigner {
+            inner,
+            cache,
+            key_fingerprint,
+        }
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S: Signer> Signer for CachingSigner<S> {
+    fn public_key(&self) -> &Point {
+        self.inner.public_key()
+    }
+
+    fn sign(&self, hash: &MessageHash) -> Result<SignedMessage, SignerError> {
+        let key = (self.key_fingerprint, hash.to_bytes_le());
+        if let Some(signed) = self.cache.get(&key) {
+            return Ok(signed);
+        }
+        let signed = self.inner.sign(hash)?;
+        self.cache.insert(key, signed.clone());
+        Ok(signed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signer::LocalSigner;
+
+    const L2_KEY: &str = "0x001fa186947c8c644cd11078f67e0bb21656432f55c4df76997b6acab2abda7f";
+
+    #[test]
+    fn cached_signature_matches_inner() {
+        let cache = Arc::new(SignatureCache::new(8));
+        let signer = CachingSigner::new(LocalSigner::from_hex(L2_KEY).unwrap(), cache.clone());
+        let hash = MessageHash::from_message("retry");
+
+        let first = signer.sign(&hash).unwrap();
+        let second = signer.sign(&hash).unwrap();
+        assert_eq!(first.to_hex(), second.to_hex());
+        assert_eq!(first.to_hex(), signer.inner().sign(&hash).unwrap().to_hex());
+        assert_eq!(cache.len(), 1);
+
+        // Same message, other key: its own entry
+        let other = CachingSigner::new(LocalSigner::from_hex("0x01").unwrap(), cache.clone());
+        assert_ne!(other.sign(&hash).unwrap().to_hex(), first.to_hex());
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn cache_evicts_least_recently_used() {
+        let cache = Arc::new(SignatureCache::new(2));
+        let signer = CachingSigner::new(LocalSigner::from_hex(L2_KEY).unwrap(), cache.clone());
+        let hashes: Vec<_> = ["a", "b", "c"]
+            .iter()
+            .map(|m| MessageHash::from_message(m))
+            .collect();
+
+        signer.sign(&hashes[0]).unwrap();
+        signer.sign(&hashes[1]).unwrap();
+        // Touch "a" so that "b" is the oldest entry
+        signer.sign(&hashes[0]).unwrap();
+        signer.sign(&hashes[2]).unwrap();
+
+        let key = |hash: &MessageHash| (signer.key_fingerprint, hash.to_bytes_le());
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&key(&hashes[0])).is_some());
+        assert!(cache.get(&key(&hashes[1])).is_none());
+        assert!(cache.get(&key(&hashes[2])).is_some());
+    }
+}
@@ -0,0 +1,190 @@
+/*
+Where a service loads its L2 key from.
+
+A KeySource is usually taken from configuration as a single string:
+
+    env:LOOPRING_L2_KEY           hex key in an environment variable
+    file:/run/secrets/l2_key      hex key in a file (surrounding whitespace ignored)
+    keystore:/etc/loopring/key.json
+                                  encrypted keystore, passphrase prompted on the
+                                  terminal (requires the keystore feature)
+    keystore-env:PASSPHRASE_VAR:/etc/loopring/key.json
+                                  encrypted keystore, passphrase taken from the
+                                  environment variable PASSPHRASE_VAR
+*/
+
+use super::LocalSigner;
+use crate::util::errors::SignerError;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::{env, fs};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Passphrase {
+    Prompt,
+    Env(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeySource {
+    Env(String),
+    File(PathBuf),
+    Keystore {
+        path: PathBuf,
+        passphrase: Passphrase,
+    },
+}
+
+fn read_env(name: &str) -> Result<String, SignerError> {
+    env::var(name)
+        .map_err(|_| SignerError::Unavailable(format!("environment variable {} is not set", name)))
+}
+
+fn read_file(path: &Path) -> Result<String, SignerError> {
+    fs::read_to_string(path)
+        .map_err(|e| SignerError::Unavailable(format!("cannot read {}: {}", path.display(), e)))
+}
+
+impl KeySource {
+    pub fn load(&self) -> Result<LocalSigner, SignerError> {
+        match self {
+            KeySource::Env(name) => LocalSigner::from_hex(read_env(name)?.trim()),
+            KeySource::File(path) => LocalSigner::from_hex(read_file(path)?.trim()),
+            KeySource::Keystore { path, passphrase } => {
+                let keystore = read_file(path)?;
+                let passphrase = match passphrase {
+                    Passphrase::Env(name) => read_env(name)?,
+                    Passphrase::Prompt => prompt_passphrase(path)?,
+                };
+                load_keystore(&keystore, &passphrase)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "keystore")]
+fn prompt_passphrase(path: &Path) -> Result<String, SignerError> {
+    rpassword::prompt_password(format!("Passphrase for {}: ", path.display()))
+        .map_err(|e| SignerError::Unavailable(e.to_string()))
+}
+
+#[cfg(feature = "keystore")]
+fn load_keystore(keystore: &str, passphrase: &str) -> Result<LocalSigner, SignerError> {
+    // LocalSigner::new rejects invalid keys the same way the other sources do
+    LocalSigner::new(super::keystore::decrypt_keystore(keystore, passphrase)?)
+}
+
+#[cfg(not(feature = "keystore"))]
+fn keystore_unsupported() -> SignerError {
+    SignerError::Unavailable("keystore support requires the keystore feature".to_string())
+}
+
+#[cfg(not(feature = "keystore"))]
+fn prompt_passphrase(_: &Path) -> Result<String, SignerError> {
+    Err(keystore_unsupported())
+}
+
+#[cfg(not(feature = "keystore"))]
+fn load_keystore(_: &str, _: &str) -> Result<LocalSigner, SignerError> {
+    Err(keystore_unsupported())
+}
+
+impl FromStr for KeySource {
+    type Err = SignerError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let invalid = || SignerError::Unavailable(format!("invalid key source {:?}", source));
+        let (kind, rest) = source.split_once(':').ok_or_else(invalid)?;
+        if rest.is_empty() {
+            return Err(invalid());
+        }
+        match kind {
+            "env" => Ok(KeySource::Env(rest.to_string())),
+            "file" => Ok(KeySource::File(PathBuf::from(rest))),
+            "keystore" => Ok(KeySource::Keystore {
+                path: PathBuf::from(rest),
+                passphrase: Passphrase::Prompt,
+            }),
+            "keystore-env" => match rest.split_once(':') {
+                Some((name, path)) if !name.is_empty() && !path.is_empty() => {
+                    Ok(KeySource::Keystore {
+                        path: PathBuf::from(path),
+                        passphrase: Passphrase::Env(name.to_string()),
+                    })
+                }
+                _ => Err(invalid()),
+            },
+            _ => Err(invalid()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signer::Signer;
+
+    const L2_KEY: &str = "0x001fa186947c8c644cd11078f67e0bb21656432f55c4df76997b6acab2abda7f";
+
+    #[test]
+    fn key_sources_are_parsed() {
+        assert_eq!(
+            "env:L2_KEY".parse::<KeySource>().unwrap(),
+            KeySource::Env("L2_KEY".to_string())
+        );
+        assert_eq!(
+            "keystore-env:PASS:/tmp/key.json"
+                .parse::<KeySource>()
+                .unwrap(),
+            KeySource::Keystore {
+                path: PathBuf::from("/tmp/key.json"),
+                passphrase: Passphrase::Env("PASS".to_string()),
+            }
+        );
+        assert!("env:".parse::<KeySource>().is_err());
+        assert!("vault:x".parse::<KeySource>().is_err());
+    }
+
+    #[test]
+    fn key_is_loaded_from_env_and_file() {
+        env::set_var("LOOPRING_SIGN_TEST_L2_KEY", L2_KEY);
+        let from_env = KeySource::Env("LOOPRING_SIGN_TEST_L2_KEY".to_string())
+            .load()
+            .unwrap();
+
+        let path = env::temp_dir().join("loopring_sign_key_source_test");
+        fs::write(&path, format!("{}\n", L2_KEY)).unwrap();
+        let from_file = KeySource::File(path.clone()).load().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(from_env.public_key() == from_file.public_key());
+        assert!(matches!(
+            KeySource::Env("LOOPRING_SIGN_TEST_UNSET".to_string()).load(),
+            Err(SignerError::Unavailable(_))
+        ));
+    }
+
+    #[cfg(feature = "keystore")]
+    #[test]
+    fn key_is_loaded_from_keystore() {
+        let path = env::temp_dir().join("loopring_sign_keystore_test.json");
+        let keystore = crate::signer::keystore::encrypt_keystore(
+            L2_KEY,
+            "pass",
+            crate::signer::keystore::MIN_ITERATIONS,
+        )
+        .unwrap();
+        fs::write(&path, keystore).unwrap();
+        env::set_var("LOOPRING_SIGN_TEST_PASSPHRASE", "pass");
+
+        let source = KeySource::Keystore {
+            path: path.clone(),
+            passphrase: Passphrase::Env("LOOPRING_SIGN_TEST_PASSPHRASE".to_string()),
+        };
+        let signer = source.load();
+        fs::remove_file(&path).unwrap();
+        assert!(
+            signer.unwrap().public_key() == LocalSigner::from_hex(L2_KEY).unwrap().public_key()
+        );
+    }
+}
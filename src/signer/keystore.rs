@@ -0,0 +1,183 @@
+/*
+Passphrase-encrypted key files.
+
+A keystore is a small JSON document:
+
+    {
+      "version": 1,
+      "kdf": "pbkdf2-sha256",
+      "iterations": 600000,
+      "salt": "<32 hex>",
+      "nonce": "<24 hex>",
+      "ciphertext": "<hex>"
+    }
+
+The AES-256-GCM key is PBKDF2-HMAC-SHA256(passphrase, salt, iterations); the
+ciphertext is the sealed 32 byte big-endian private key with the tag appended.
+Salt and nonce are random for every keystore written by `encrypt_keystore`.
+
+Both directions require MIN_ITERATIONS <= iterations <= MAX_ITERATIONS: fewer
+leave the key effectively unprotected, and a file asking for billions would
+keep a loading service busy for hours.
+*/
+
+use crate::util::{errors::SignerError, hex};
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm, Nonce,
+};
+use num_bigint::{BigInt, Sign};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+pub const KEYSTORE_VERSION: u64 = 1;
+// OWASP recommendation for PBKDF2-HMAC-SHA256
+pub const DEFAULT_ITERATIONS: u32 = 600_000;
+pub const MIN_ITERATIONS: u32 = 100_000;
+// A few seconds on current hardware
+pub const MAX_ITERATIONS: u32 = 10_000_000;
+
+fn cipher(passphrase: &str, salt: &[u8], iterations: u32) -> Aes256Gcm {
+    let key = Zeroizing::new(pbkdf2::pbkdf2_hmac_array::<Sha256, 32>(
+        passphrase.as_bytes(),
+        salt,
+        iterations,
+    ));
+    Aes256Gcm::new(key.as_slice().into())
+}
+
+fn invalid(reason: &str) -> SignerError {
+    SignerError::InvalidKeystore(reason.to_string())
+}
+
+fn check_iterations(iterations: u32) -> Result<u32, SignerError> {
+    if !(MIN_ITERATIONS..=MAX_ITERATIONS).contains(&iterations) {
+        return Err(invalid(&format!(
+            "iterations must be between {} and {}",
+            MIN_ITERATIONS, MAX_ITERATIONS
+        )));
+    }
+    Ok(iterations)
+}
+
+pub fn encrypt_keystore(
+    hex_private_key: &str,
+    passphrase: &str,
+    iterations: u32,
+) -> Result<String, SignerError> {
+    check_iterations(iterations)?;
+    let private_key = super::parse_private_key(hex_private_key)?;
+    let (_, be) = private_key.to_bytes_be();
+    if be.len() > 32 {
+        return Err(SignerError::InvalidPrivateKey);
+    }
+    let mut plaintext = Zeroizing::new(vec![0u8; 32]);
+    plaintext[32 - be.len()..].copy_from_slice(&be);
+
+    let mut salt = [0u8; 16];
+    let mut nonce = [0u8; 12];
+    getrandom::getrandom(&mut salt)
+        .and_then(|_| getrandom::getrandom(&mut nonce))
+        .map_err(|e| SignerError::Backend(e.to_string()))?;
+
+    let ciphertext = cipher(passphrase, &salt, iterations)
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+        .map_err(|_| SignerError::DecryptionFailed)?;
+    let keystore = json!({
+        "version": KEYSTORE_VERSION,
+        "kdf": "pbkdf2-sha256",
+        "iterations": iterations,
+        "salt": ::hex::encode(salt),
+        "nonce": ::hex::encode(nonce),
+        "ciphertext": ::hex::encode(ciphertext),
+    });
+    Ok(serde_json::to_string_pretty(&keystore).unwrap())
+}
+
+pub fn decrypt_keystore(keystore: &str, passphrase: &str) -> Result<BigInt, SignerError> {
+    let keystore: Value = serde_json::from_str(keystore).map_err(|e| invalid(&e.to_string()))?;
+    if keystore["version"].as_u64() != Some(KEYSTORE_VERSION) {
+        return Err(invalid("unsupported version"));
+    }
+    if keystore["kdf"].as_str() != Some("pbkdf2-sha256") {
+        return Err(invalid("unsupported kdf"));
+    }
+    let iterations = keystore["iterations"]
+        .as_u64()
+        .and_then(|n| u32::try_from(n).ok())
+        .ok_or_else(|| invalid("iterations"))
+        .and_then(check_iterations)?;
+    let bytes = |name: &str| {
+        keystore[name]
+            .as_str()
+            .and_then(|s| hex::decode_bytes(s).ok())
+            .ok_or_else(|| invalid(name))
+    };
+    let salt = bytes("salt")?;
+    let nonce = bytes("nonce")?;
+    if nonce.len() != 12 {
+        return Err(invalid("nonce"));
+    }
+
+    let plaintext = Zeroizing::new(
+        cipher(passphrase, &salt, iterations)
+            .decrypt(Nonce::from_slice(&nonce), bytes("ciphertext")?.as_slice())
+            .map_err(|_| SignerError::DecryptionFailed)?,
+    );
+    Ok(BigInt::from_bytes_be(Sign::Plus, &plaintext))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const L2_KEY: &str = "0x001fa186947c8c644cd11078f67e0bb21656432f55c4df76997b6acab2abda7f";
+
+    #[test]
+    fn keystore_round_trip() {
+        let keystore = encrypt_keystore(L2_KEY, "correct horse", MIN_ITERATIONS).unwrap();
+        assert_eq!(
+            decrypt_keystore(&keystore, "correct horse").unwrap(),
+            hex::decode_bigint(L2_KEY).unwrap()
+        );
+        assert_eq!(
+            decrypt_keystore(&keystore, "wrong horse"),
+            Err(SignerError::DecryptionFailed)
+        );
+        // Fresh salt and nonce every time
+        assert_ne!(
+            keystore,
+            encrypt_keystore(L2_KEY, "correct horse", MIN_ITERATIONS).unwrap()
+        );
+    }
+
+    #[test]
+    fn malformed_keystores_are_rejected() {
+        assert!(matches!(
+            decrypt_keystore("{}", "x"),
+            Err(SignerError::InvalidKeystore(_))
+        ));
+        let keystore = encrypt_keystore(L2_KEY, "x", MIN_ITERATIONS).unwrap();
+        assert_eq!(
+            decrypt_keystore(&keystore.replace("pbkdf2-sha256", "scrypt"), "x"),
+            Err(SignerError::InvalidKeystore("unsupported kdf".to_string()))
+        );
+
+        for iterations in [0, 1, MIN_ITERATIONS - 1, MAX_ITERATIONS + 1, u32::MAX] {
+            assert!(matches!(
+                encrypt_keystore(L2_KEY, "x", iterations),
+                Err(SignerError::InvalidKeystore(_))
+            ));
+            let tampered = keystore.replace(
+                &format!("\"iterations\": {}", MIN_ITERATIONS),
+                &format!("\"iterations\": {}", iterations),
+            );
+            assert_ne!(tampered, keystore);
+            assert!(matches!(
+                decrypt_keystore(&tampered, "x"),
+                Err(SignerError::InvalidKeystore(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,181 @@
+/*
+A signer backed by an external key management service.
+
+The L2 key is stored as an envelope-encrypted blob: the key is sealed with
+AES-256-GCM under a data key, and the data key itself is only available wrapped
+by the KMS. Every signature unwraps the data key through the KMS, decrypts the
+L2 key, signs and wipes both again, so the plaintext key is never resident
+between signings.
+
+The KMS call is abstracted by KeyDecrypter, so any provider (AWS KMS, GCP KMS,
+Vault transit, an HSM) can be plugged in.
+*/
+
+use super::{check_private_key, Signer};
+use crate::poseidon::{
+    eddsa::{MessageHash, SignatureScheme, SignedMessage},
+    jubjub::Point,
+};
+use crate::util::errors::SignerError;
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm, Nonce,
+};
+use num_bigint::{BigInt, Sign};
+use zeroize::Zeroizing;
+
+pub trait KeyDecrypter: Send + Sync {
+    // Unwraps a data key with the KMS master key. The result must be 32 bytes.
+    fn decrypt_data_key(&self, wrapped_data_key: &[u8]) -> Result<Zeroizing<Vec<u8>>, SignerError>;
+}
+
+pub struct EnvelopeBlob {
+    pub wrapped_data_key: Vec<u8>,
+    pub nonce: [u8; 12],
+    // AES-256-GCM of the 32 byte big-endian private key, tag appended
+    pub ciphertext: Vec<u8>,
+}
+
+impl EnvelopeBlob {
+    // Provisioning side: seals a private key under a (plaintext) data key. The
+    // nonce must never be reused with the same data key.
+    pub fn seal(
+        data_key: &[u8; 32],
+        wrapped_data_key: Vec<u8>,
+        nonce: [u8; 12],
+        private_key: &BigInt,
+    ) -> Result<Self, SignerError> {
+        let (_, be) = private_key.to_bytes_be();
+        if be.len() > 32 {
+            return Err(SignerError::InvalidPrivateKey);
+        }
+        let mut plaintext = Zeroizing::new(vec![0u8; 32]);
+        plaintext[32 - be.len()..].copy_from_slice(&be);
+
+        let cipher = Aes256Gcm::new(data_key.into());
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+            .map_err(|_| SignerError::DecryptionFailed)?;
+        Ok(EnvelopeBlob {
+            wrapped_data_key,
+            nonce,
+            ciphertext,
+        })
+    }
+}
+
+pub struct KmsSigner<K: KeyDecrypter> {
+    kms: K,
+    blob: EnvelopeBlob,
+    public_key: Point,
+}
+
+impl<K: KeyDecrypter> KmsSigner<K> {
+    // Decrypts the key once to derive the public key, which also checks that the
+    // blob and the KMS fit together
+    pub fn new(kms: K, blob: EnvelopeBlob) -> Result<Self, SignerError> {
+        let private_key = Self::open(&kms, &blob)?;
+        let public_key = SignatureScheme::base_point().mul_scalar(&private_key);
+        Ok(KmsSigner {
+            kms,
+            blob,
+            public_key,
+        })
+    }
+
+    fn open(kms: &K, blob: &EnvelopeBlob) -> Result<BigInt, SignerError> {
+        let data_key = kms.decrypt_data_key(&blob.wrapped_data_key)?;
+        if data_key.len() != 32 {
+            return Err(SignerError::DecryptionFailed);
+        }
+        let cipher =
+            Aes256Gcm::new_from_slice(&data_key).map_err(|_| SignerError::DecryptionFailed)?;
+        let plaintext = Zeroizing::new(
+            cipher
+                .decrypt(Nonce::from_slice(&blob.nonce), blob.ciphertext.as_slice())
+                .map_err(|_| SignerError::DecryptionFailed)?,
+        );
+        if plaintext.len() != 32 {
+            return Err(SignerError::InvalidPrivateKey);
+        }
+        check_private_key(BigInt::from_bytes_be(Sign::Plus, &plaintext))
+    }
+}
+
+impl<K: KeyDecrypter> Signer for KmsSigner<K> {
+    fn public_key(&self) -> &Point {
+        &self.public_key
+    }
+
+    fn sign(&self, hash: &MessageHash) -> Result<SignedMessage, SignerError> {
+        let private_key = Self::open(&self.kms, &self.blob)?;
+        Ok(SignatureScheme::sign_hash(private_key, hash))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signer::LocalSigner;
+    use crate::util::hex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const L2_KEY: &str = "0x001fa186947c8c644cd11078f67e0bb21656432f55c4df76997b6acab2abda7f";
+    const DATA_KEY: [u8; 32] = [7; 32];
+
+    // Stands in for a KMS: "wrapping" is the data key prefixed with a key id
+    struct MockKms {
+        calls: AtomicUsize,
+    }
+
+    impl KeyDecrypter for MockKms {
+        fn decrypt_data_key(&self, wrapped: &[u8]) -> Result<Zeroizing<Vec<u8>>, SignerError> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            match wrapped.split_first() {
+                Some((1, data_key)) => Ok(Zeroizing::new(data_key.to_vec())),
+                _ => Err(SignerError::Backend("unknown key id".to_string())),
+            }
+        }
+    }
+
+    fn blob(wrapped_data_key: Vec<u8>) -> EnvelopeBlob {
+        let private_key = hex::decode_bigint(L2_KEY).unwrap();
+        EnvelopeBlob::seal(&DATA_KEY, wrapped_data_key, [1; 12], &private_key).unwrap()
+    }
+
+    #[test]
+    fn kms_signer_decrypts_per_signature() {
+        let kms = MockKms {
+            calls: AtomicUsize::new(0),
+        };
+        let wrapped = [&[1u8][..], &DATA_KEY[..]].concat();
+        let signer = KmsSigner::new(kms, blob(wrapped)).unwrap();
+
+        let hash = MessageHash::from_message("kms");
+        let local = LocalSigner::from_hex(L2_KEY).unwrap();
+        assert_eq!(
+            signer.sign(&hash).unwrap().to_hex(),
+            local.sign(&hash).unwrap().to_hex()
+        );
+        signer.sign(&hash).unwrap();
+        assert_eq!(signer.kms.calls.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn kms_signer_reports_failures() {
+        let kms = || MockKms {
+            calls: AtomicUsize::new(0),
+        };
+        assert!(matches!(
+            KmsSigner::new(kms(), blob(vec![2])),
+            Err(SignerError::Backend(_))
+        ));
+
+        // Wrong data key
+        let wrapped = [&[1u8][..], &[8u8; 32][..]].concat();
+        assert!(matches!(
+            KmsSigner::new(kms(), blob(wrapped)),
+            Err(SignerError::DecryptionFailed)
+        ));
+    }
+}
@@ -0,0 +1,147 @@
+/*
+Signers hold an L2 private key and sign message hashes with it.
+
+Code that signs (services, request builders) should depend on the Signer trait
+instead of a hex key, so the key can live in a LocalSigner, a KeyVault or an
+external key store without the caller noticing.
+
+Signers are Send + Sync: a single instance can sign concurrently from several
+threads (e.g. the handlers of a web server) without a Mutex around it.
+*/
+
+pub mod audit;
+pub mod cache;
+pub mod key_source;
+#[cfg(feature = "keystore")]
+pub mod keystore;
+#[cfg(feature = "kms")]
+pub mod kms;
+#[cfg(feature = "ff")]
+pub mod order;
+pub mod stdio;
+#[cfg(feature = "vault")]
+pub mod vault;
+pub mod verifying;
+
+use crate::poseidon::{
+    eddsa::{is_signing_key, signing_key, KeyPair, MessageHash, SignatureScheme, SignedMessage},
+    jubjub::Point,
+};
+use crate::util::errors::SignerError;
+use num_bigint::BigInt;
+
+pub trait Signer: Send + Sync {
+    fn public_key(&self) -> &Point;
+
+    fn sign(&self, hash: &MessageHash) -> Result<SignedMessage, SignerError>;
+}
+
+pub(crate) fn parse_private_key(hex_private_key: &str) -> Result<BigInt, SignerError> {
+    signing_key(hex_private_key).ok_or(SignerError::InvalidPrivateKey)
+}
+
+// Keys given as scalars go through the same check as hex keys
+pub(crate) fn check_private_key(private_key: BigInt) -> Result<BigInt, SignerError> {
+    if !is_signing_key(&private_key) {
+        return Err(SignerError::InvalidPrivateKey);
+    }
+    Ok(private_key)
+}
+
+// Keeps the key as a plain BigInt on the heap
+pub struct LocalSigner {
+    keypair: KeyPair,
+}
+
+impl LocalSigner {
+    pub fn new(private_key: BigInt) -> Result<Self, SignerError> {
+        Ok(LocalSigner {
+            keypair: KeyPair::new(check_private_key(private_key)?),
+        })
+    }
+
+    pub fn from_hex(hex_private_key: &str) -> Result<Self, SignerError> {
+        Self::new(parse_private_key(hex_private_key)?)
+    }
+
+    #[cfg(any(feature = "borsh", feature = "bincode"))]
+    pub(crate) fn private_scalar(&self) -> &BigInt {
+        self.keypair.private_key_scalar()
+    }
+}
+
+impl Signer for LocalSigner {
+    fn public_key(&self) -> &Point {
+        self.keypair.public_key()
+    }
+
+    fn sign(&self, hash: &MessageHash) -> Result<SignedMessage, SignerError> {
+        Ok(SignatureScheme::sign_with_keypair(&self.keypair, hash))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::hex;
+
+    const L2_KEY: &str = "0x001fa186947c8c644cd11078f67e0bb21656432f55c4df76997b6acab2abda7f";
+
+    #[test]
+    fn local_signer_signs_like_scheme() {
+        let signer = LocalSigner::from_hex(L2_KEY).unwrap();
+        let hash = MessageHash::from_message(
+            "GET&https%3A%2F%2Fapi3.loopring.io%2Fapi%2Fv3%2FapiKey&accountId%3D1",
+        );
+        let signed = signer.sign(&hash).unwrap();
+
+        assert_eq!(
+            signed.to_hex(),
+            SignatureScheme::sign_hash(hex::decode_bigint(L2_KEY).unwrap(), &hash).to_hex()
+        );
+        assert!(SignatureScheme::verify(
+            signer.public_key(),
+            signed.sig(),
+            hash.as_bigint()
+        ));
+    }
+
+    #[test]
+    fn local_signer_is_shareable() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<LocalSigner>();
+        assert_send_sync::<crate::poseidon::permutation::Poseidon>();
+        assert_send_sync::<Point>();
+
+        let signer = std::sync::Arc::new(LocalSigner::from_hex(L2_KEY).unwrap());
+        let expected = signer
+            .sign(&MessageHash::from_message("0"))
+            .unwrap()
+            .to_hex();
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let signer = signer.clone();
+                std::thread::spawn(move || {
+                    signer
+                        .sign(&MessageHash::from_message("0"))
+                        .unwrap()
+                        .to_hex()
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn local_signer_rejects_bad_keys() {
+        assert!(LocalSigner::from_hex("0x").is_err());
+        assert!(LocalSigner::from_hex("0x00").is_err());
+        assert!(LocalSigner::from_hex("0xzz").is_err());
+        let order = crate::poseidon::jubjub::JUBJUB_L.clone();
+        assert!(LocalSigner::from_hex(&hex::encode_secret(&order)).is_err());
+        assert!(LocalSigner::new(order * 2).is_err());
+        assert!(LocalSigner::new(BigInt::from(1)).is_ok());
+    }
+}
@@ -0,0 +1,362 @@
+/*
+A fast path for signing orders with one key.
+
+The generic path (`Order::sign`, `LocalSigner`) works on BigInt field elements
+and affine points, so every point addition of r * B costs a modular inversion
+and every Poseidon round allocates. OrderSigner does the work that does not
+depend on the order once, at construction:
+
+  * the order and challenge Poseidon instances, with their constants as
+    fixed-limb `FieldElement`s (Montgomery form, `ff` feature), permuted in
+    place over preallocated state buffers;
+  * a fixed-base table j * 16^w * B for 4-bit windows w, so r * B is one table
+    addition per window in extended coordinates and a single inversion (the
+    table does not depend on the key and is built once per process);
+  * the public key, in both representations.
+
+The nonce r and the signature are derived exactly as in
+`SignatureScheme::sign_hash`, so the output is the same signature as
+`Order::sign` with that key. The scratch buffers make signing take `&mut self`;
+use one OrderSigner per thread.
+*/
+
+use super::{check_private_key, parse_private_key};
+use crate::constants::{JUBJUB_A_VALUE, JUBJUB_D_VALUE};
+use crate::payload::order::Order;
+use crate::payload::version::{ProtocolVersion, VersionedPayload};
+use crate::poseidon::eddsa::{MessageHash, Signature, SignatureScheme, SignedMessage};
+use crate::poseidon::field::FQ;
+use crate::poseidon::fixed_field::FieldElement;
+use crate::poseidon::jubjub::{Point, JUBJUB_E, JUBJUB_L};
+use crate::poseidon::permutation::Poseidon;
+use crate::util::errors::{PayloadError, PoseidonError, SignerError};
+use ff::Field;
+use num_bigint::{BigInt, BigUint};
+
+// Number of field elements in an order preimage
+const ORDER_INPUTS: usize = 11;
+const WINDOW_BITS: u64 = 4;
+
+fn to_field_element(value: &BigUint) -> FieldElement {
+    // Constants and inputs are below the modulus
+    FieldElement::try_from(FQ::from_biguint(value.clone())).unwrap()
+}
+
+// One permutation with its constants in fixed-limb form and its two state
+// buffers
+struct FastPoseidon {
+    half_f: usize,
+    n_rounds_p: usize,
+    constants_c: Vec<FieldElement>,
+    constants_m: Vec<Vec<FieldElement>>,
+    state: Vec<FieldElement>,
+    mixed: Vec<FieldElement>,
+}
+
+impl FastPoseidon {
+    // The S-box is x^5, as in every shared instance
+    fn new(poseidon: &Poseidon) -> Self {
+        FastPoseidon {
+            half_f: poseidon.full_rounds() / 2,
+            n_rounds_p: poseidon.partial_rounds(),
+            constants_c: poseidon
+                .round_constants()
+                .iter()
+                .map(to_field_element)
+                .collect(),
+            constants_m: poseidon
+                .mds_matrix()
+                .iter()
+                .map(|row| row.iter().map(to_field_element).collect())
+                .collect(),
+            state: vec![FieldElement::ZERO; poseidon.width()],
+            mixed: vec![FieldElement::ZERO; poseidon.width()],
+        }
+    }
+
+    fn sbox(x: &mut FieldElement) {
+        let square = x.square();
+        *x *= square.square();
+    }
+
+    // Callers pass fewer than t inputs
+    fn hash(&mut self, inputs: &[FieldElement]) -> FieldElement {
+        self.state.fill(FieldElement::ZERO);
+        self.state[..inputs.len()].copy_from_slice(inputs);
+        for (i, constant_c) in self.constants_c.iter().enumerate() {
+            for word in self.state.iter_mut() {
+                *word += constant_c;
+            }
+            if i < self.half_f || i >= self.half_f + self.n_rounds_p {
+                self.state.iter_mut().for_each(Self::sbox);
+            } else {
+                Self::sbox(&mut self.state[0]);
+            }
+            for (mixed, row) in self.mixed.iter_mut().zip(&self.constants_m) {
+                *mixed = row
+                    .iter()
+                    .zip(&self.state)
+                    .fold(FieldElement::ZERO, |sum, (m, s)| sum + *m * s);
+            }
+            std::mem::swap(&mut self.state, &mut self.mixed);
+        }
+        self.state[0]
+    }
+}
+
+// A point in extended twisted Edwards coordinates: x = X/Z, y = Y/Z, T = XY/Z
+#[derive(Clone, Copy)]
+struct Extended {
+    x: FieldElement,
+    y: FieldElement,
+    t: FieldElement,
+    z: FieldElement,
+}
+
+// An affine table entry with d * x * y precomputed
+#[derive(Clone, Copy)]
+struct Affine {
+    x: FieldElement,
+    y: FieldElement,
+    dt: FieldElement,
+}
+
+struct Curve {
+    a: FieldElement,
+    d: FieldElement,
+}
+
+impl Curve {
+    fn new() -> Self {
+        Curve {
+            a: FieldElement::from(JUBJUB_A_VALUE),
+            d: FieldElement::from(JUBJUB_D_VALUE),
+        }
+    }
+
+    fn identity() -> Extended {
+        Extended {
+            x: FieldElement::ZERO,
+            y: FieldElement::ONE,
+            t: FieldElement::ZERO,
+            z: FieldElement::ONE,
+        }
+    }
+
+    // add-2008-hwcd with Z2 = 1, complete on Baby Jubjub (a square, d not)
+    fn add_affine(&self, p: &Extended, q: &Affine) -> Extended {
+        let a = p.x * q.x;
+        let b = p.y * q.y;
+        let c = p.t * q.dt;
+        let d = p.z;
+        let e = (p.x + p.y) * (q.x + q.y) - a - b;
+        let f = d - c;
+        let g = d + c;
+        let h = b - self.a * a;
+        Extended {
+            x: e * f,
+            y: g * h,
+            t: e * h,
+            z: f * g,
+        }
+    }
+
+    fn to_affine(&self, p: &Extended) -> Affine {
+        // Z is never zero for points of a complete curve
+        let z_inv = p.z.invert().unwrap();
+        let (x, y) = (p.x * z_inv, p.y * z_inv);
+        Affine {
+            x,
+            y,
+            dt: self.d * x * y,
+        }
+    }
+}
+
+fn point_to_affine(curve: &Curve, point: &Point) -> Affine {
+    let x = FieldElement::try_from(point.x()).unwrap();
+    let y = FieldElement::try_from(point.y()).unwrap();
+    Affine {
+        x,
+        y,
+        dt: curve.d * x * y,
+    }
+}
+
+// table[w][j] = j * 16^w * B
+fn base_table() -> Vec<[Affine; 16]> {
+    let curve = Curve::new();
+    let windows = JUBJUB_L.bits().div_ceil(WINDOW_BITS) as usize;
+    let mut table = Vec::with_capacity(windows);
+    let mut window_base = point_to_affine(&curve, &SignatureScheme::base_point());
+    for _ in 0..windows {
+        let mut row = [curve.to_affine(&Curve::identity()); 16];
+        let mut sum = Curve::identity();
+        for entry in row.iter_mut().skip(1) {
+            sum = curve.add_affine(&sum, &window_base);
+            *entry = curve.to_affine(&sum);
+        }
+        // 16 * window_base = 15 * window_base + window_base
+        window_base = curve.to_affine(&curve.add_affine(&sum, &window_base));
+        table.push(row);
+    }
+    table
+}
+
+lazy_static! {
+    // Shared by every OrderSigner
+    static ref BASE_TABLE: Vec<[Affine; 16]> = base_table();
+}
+
+fn affine_to_point(p: &Affine) -> Point {
+    Point::new(FQ::from(&p.x), FQ::from(&p.y))
+}
+
+pub struct OrderSigner {
+    private_key: BigInt,
+    public_key: Point,
+    public_key_x: FieldElement,
+    public_key_y: FieldElement,
+    curve: Curve,
+    order_poseidon: FastPoseidon,
+    challenge_poseidon: FastPoseidon,
+    inputs: Vec<FieldElement>,
+}
+
+impl OrderSigner {
+    pub fn new(private_key: BigInt) -> Result<Self, SignerError> {
+        let private_key = check_private_key(private_key)?;
+        let curve = Curve::new();
+        let public_key = SignatureScheme::base_point().mul_scalar(&private_key);
+        let public_key_affine = point_to_affine(&curve, &public_key);
+        Ok(OrderSigner {
+            private_key,
+            public_key,
+            public_key_x: public_key_affine.x,
+            public_key_y: public_key_affine.y,
+            curve,
+            order_poseidon: FastPoseidon::new(&Poseidon::shared(ORDER_INPUTS + 1, 6, 53)),
+            challenge_poseidon: FastPoseidon::new(&Poseidon::shared(6, 6, 52)),
+            inputs: Vec::with_capacity(ORDER_INPUTS),
+        })
+    }
+
+    pub fn from_hex(hex_private_key: &str) -> Result<Self, SignerError> {
+        Self::new(parse_private_key(hex_private_key)?)
+    }
+
+    pub fn public_key(&self) -> &Point {
+        &self.public_key
+    }
+
+    // r * B for 0 <= r < 2^(4 * windows), which covers every nonce (r < L)
+    fn mul_base(&self, scalar: &BigUint) -> Affine {
+        let mut acc = Curve::identity();
+        for (w, row) in BASE_TABLE.iter().enumerate() {
+            let digit = (0..WINDOW_BITS).fold(0usize, |digit, bit| {
+                digit | ((scalar.bit(w as u64 * WINDOW_BITS + bit) as usize) << bit)
+            });
+            if digit != 0 {
+                acc = self.curve.add_affine(&acc, &row[digit]);
+            }
+        }
+        self.curve.to_affine(&acc)
+    }
+
+    pub fn sign(&mut self, order: &Order) -> Result<SignedMessage, PayloadError> {
+        let preimage = order.poseidon_inputs_for(ProtocolVersion::CURRENT)?;
+        if preimage.len() != ORDER_INPUTS {
+            return Err(PoseidonError::InputArityMismatch.into());
+        }
+        self.inputs.clear();
+        self.inputs.extend(
+            preimage
+                .iter()
+                .map(|input| FieldElement::try_from(FQ::new(input.clone())).unwrap()),
+        );
+        let hash = self.order_poseidon.hash(&self.inputs);
+        let message = FQ::from(&hash).to_bigint();
+
+        let r = SignatureScheme::hash_secret(
+            FQ::new(self.private_key.clone()),
+            &MessageHash::new(message.clone()),
+        );
+        let image_of_r = self.mul_base(r.magnitude());
+
+        let challenge = self.challenge_poseidon.hash(&[
+            image_of_r.x,
+            image_of_r.y,
+            self.public_key_x,
+            self.public_key_y,
+            hash,
+        ]);
+        let t = FQ::from(&challenge).to_bigint();
+        let s = (r + &self.private_key * t) % &*JUBJUB_E;
+
+        Ok(SignedMessage::new(
+            self.public_key.clone(),
+            Signature::new(affine_to_point(&image_of_r), FQ::new(s)),
+            message,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const L2_KEY: &str = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+
+    fn order(storage_id: u32, taker: Option<&str>) -> Order {
+        Order {
+            exchange: "0x0BABA1Ad5bE3a5C0a66E7ac838a129Bf948f1eA4".to_string(),
+            storage_id,
+            account_id: 11087,
+            sell_token_id: 0,
+            buy_token_id: 1,
+            sell_volume: BigInt::from(1_000_000_000_000_000_000u64),
+            buy_volume: BigInt::from(3_000_000_000u64),
+            valid_until: 1700000000,
+            max_fee_bips: 60,
+            fill_amount_b_or_s: true,
+            taker: taker.map(|t| t.to_string()),
+        }
+    }
+
+    #[test]
+    fn signs_like_the_generic_path() {
+        let mut signer = OrderSigner::from_hex(L2_KEY).unwrap();
+        assert!(OrderSigner::new(JUBJUB_L.clone()).is_err());
+        assert!(OrderSigner::new(BigInt::from(0)).is_err());
+        for order in [
+            order(2, None),
+            order(4, Some("0x5e8a2c2d6fa8bc7a8e5d5a054b6bd1c0f7a4fe2c")),
+        ] {
+            let signed = signer.sign(&order).unwrap();
+            assert_eq!(signed.to_hex(), order.sign(L2_KEY).unwrap());
+            assert_eq!(*signed.msg(), order.hash().unwrap());
+            assert!(SignatureScheme::verify(
+                signer.public_key(),
+                signed.sig(),
+                signed.msg()
+            ));
+        }
+    }
+
+    #[test]
+    fn fixed_base_table_matches_scalar_multiplication() {
+        let signer = OrderSigner::from_hex(L2_KEY).unwrap();
+        let base = SignatureScheme::base_point();
+        let l = JUBJUB_L.magnitude();
+        for scalar in [
+            BigUint::from(0u8),
+            BigUint::from(1u8),
+            BigUint::from(0xfedcba98u32),
+            l - BigUint::from(1u8),
+        ] {
+            let expected = base.mul_scalar_uint(&scalar);
+            assert!(affine_to_point(&signer.mul_base(&scalar)) == expected);
+        }
+        assert!(OrderSigner::from_hex("0x00").is_err());
+    }
+}
@@ -0,0 +1,225 @@
+/*
+Line-delimited JSON signing protocol, for running the key in a subprocess.
+
+Services written in other languages can keep the L2 key out of their own
+process by spawning `loopring_sign serve-stdio --key <source>` and talking to
+it over its stdin/stdout. Every request is one JSON object on one line, and every
+request gets exactly one response line, in order:
+
+    -> {"id": 1, "method": "publicKey"}
+    <- {"id": 1, "result": {"x": "0x...", "y": "0x..."}}
+
+    -> {"id": 2, "method": "signPayload", "payload": {"type": "transfer", ...}}
+    <- {"id": 2, "result": {"type": "transfer", "hash": "0x...", "signature": "0x..."}}
+
+    -> {"id": 3, "method": "signRequest",
+        "request": {"method": "GET", "url": "https://...", "params": [["accountId", "1"]]}}
+    -> {"id": 4, "method": "signRequest",
+        "request": {"method": "POST", "url": "https://...", "body": "{\"a\":1}"}}
+    <- {"id": 3, "result": {"signature": "0x..."}}
+
+    <- {"id": 5, "error": "Missing field: storageId"}
+
+  * "id" is echoed as given (any JSON value, null when the line is not JSON);
+  * "payload" is an object in the payload-file format (see payload::file);
+  * "signRequest" signs the API signature base string: "params" is an ordered
+    list of [key, value] pairs (GET/DELETE, or POST/PUT built from pairs), and
+    "body" is the exact serialized body of a POST/PUT that will be sent;
+  * empty lines are ignored; the server stops at end of input.
+
+Failures to sign are responses, not exits, so one bad request does not take the
+signer down. Only I/O errors end `serve`.
+*/
+
+use super::Signer;
+use crate::payload::{file::sign_entry, hash_to_hex};
+use crate::poseidon::eddsa::MessageHash;
+use crate::util::encoding::EncodingProfile;
+use crate::util::helpers::{
+    body_signature_base_string, generate_signature_base_string, HttpMethod,
+};
+use serde_json::{json, Value};
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+fn string_field<'a>(value: &'a Value, name: &str) -> Result<&'a str, String> {
+    value
+        .get(name)
+        .and_then(Value::as_str)
+        .ok_or_else(|| format!("Missing field: {}", name))
+}
+
+pub(crate) fn request_base_string(request: &Value) -> Result<String, String> {
+    let method = string_field(request, "method")?;
+    let url = string_field(request, "url")?;
+    if let Some(body) = request.get("body") {
+        let body = body.as_str().ok_or("body must be a string")?;
+        let method = HttpMethod::from_str(method).map_err(|e| e.to_string())?;
+        return body_signature_base_string(method, url, body, EncodingProfile::default())
+            .map_err(|e| e.to_string());
+    }
+    let params = match request.get("params") {
+        Some(Value::Array(params)) => params
+            .iter()
+            .map(|pair| match pair.as_array().map(Vec::as_slice) {
+                Some([Value::String(key), Value::String(value)]) => Ok((key, value)),
+                _ => Err("params must be [key, value] string pairs".to_string()),
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        Some(_) => return Err("params must be a list".to_string()),
+        None => Vec::new(),
+    };
+    generate_signature_base_string(method, url, params).map_err(|e| e.to_string())
+}
+
+fn handle_request<S: Signer>(signer: &S, request: &Value) -> Result<Value, String> {
+    match string_field(request, "method")? {
+        "publicKey" => {
+            let public_key = signer.public_key();
+            Ok(json!({"x": public_key.x().to_hex(), "y": public_key.y().to_hex()}))
+        }
+        "signPayload" => {
+            let payload = request.get("payload").ok_or("Missing field: payload")?;
+            let (payload_type, hash, signature) = sign_entry(signer, payload)?;
+            Ok(json!({
+                "type": payload_type,
+                "hash": hash_to_hex(&hash),
+                "signature": signature,
+            }))
+        }
+        "signRequest" => {
+            let base =
+                request_base_string(request.get("request").ok_or("Missing field: request")?)?;
+            let signed = signer
+                .sign(&MessageHash::from_message(&base))
+                .map_err(|e| e.to_string())?;
+            Ok(json!({"signature": signed.to_hex()}))
+        }
+        other => Err(format!("Unknown method: {}", other)),
+    }
+}
+
+// The response line (without the newline) for one request line
+pub fn handle_line<S: Signer>(signer: &S, line: &str) -> String {
+    let response = match serde_json::from_str::<Value>(line) {
+        Ok(request) => {
+            let id = request.get("id").cloned().unwrap_or(Value::Null);
+            match handle_request(signer, &request) {
+                Ok(result) => json!({"id": id, "result": result}),
+                Err(error) => json!({"id": id, "error": error}),
+            }
+        }
+        Err(e) => json!({"id": null, "error": format!("Invalid request: {}", e)}),
+    };
+    response.to_string()
+}
+
+// Answers requests until the input ends; every response is flushed at once
+pub fn serve<S: Signer, R: BufRead, W: Write>(
+    signer: &S,
+    input: R,
+    mut output: W,
+) -> io::Result<()> {
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        writeln!(output, "{}", handle_line(signer, &line))?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload::transfer::tests::transfer;
+    use crate::poseidon::eddsa::SignatureScheme;
+    use crate::signer::LocalSigner;
+    use crate::util::hex;
+
+    const L2_KEY: &str = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+
+    const TRANSFER: &str = r#"{"type": "transfer",
+        "exchange": "0x0BABA1Ad5bE3a5C0a66E7ac838a129Bf948f1eA4",
+        "payerId": 11087, "payerAddr": "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        "payeeId": 0, "payeeAddr": "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+        "token": {"tokenId": 1, "volume": "1000000000000000000"},
+        "maxFee": {"tokenId": 0, "volume": "100000000000000"},
+        "validUntil": 1700000000, "storageId": 5}"#;
+
+    #[test]
+    fn answers_every_line_in_order() {
+        let signer = LocalSigner::from_hex(L2_KEY).unwrap();
+        let input = format!(
+            "{}\n\n{}\n{}\nnot json\n{}\n",
+            r#"{"id": 1, "method": "publicKey"}"#,
+            json!({"id": "t", "method": "signPayload",
+                   "payload": serde_json::from_str::<Value>(TRANSFER).unwrap()}),
+            r#"{"id": 3, "method": "signPayload", "payload": {"type": "order"}}"#,
+            r#"{"id": 4, "method": "rotateKey"}"#,
+        );
+        let mut output = Vec::new();
+        serve(&signer, input.as_bytes(), &mut output).unwrap();
+
+        let responses: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(responses.len(), 5);
+        assert_eq!(
+            responses[0]["result"]["x"],
+            signer.public_key().x().to_hex()
+        );
+        assert_eq!(responses[1]["id"], "t");
+        assert_eq!(
+            responses[1]["result"]["signature"],
+            transfer().sign(L2_KEY).unwrap()
+        );
+        assert_eq!(responses[2]["id"], 3);
+        assert!(responses[2]["error"].is_string());
+        assert!(responses[3]["id"].is_null() && responses[3]["error"].is_string());
+        assert_eq!(responses[4]["error"], "Unknown method: rotateKey");
+    }
+
+    #[test]
+    fn signs_request_base_strings() {
+        let signer = LocalSigner::from_hex(L2_KEY).unwrap();
+        let url = "https://api3.loopring.io/api/v3/apiKey";
+        let private_key = hex::decode_bigint(L2_KEY).unwrap();
+
+        let get = json!({"id": 1, "method": "signRequest",
+            "request": {"method": "GET", "url": url, "params": [["accountId", "11087"]]}});
+        let response: Value =
+            serde_json::from_str(&handle_line(&signer, &get.to_string())).unwrap();
+        let base = generate_signature_base_string("GET", url, [("accountId", "11087")]).unwrap();
+        assert_eq!(
+            response["result"]["signature"],
+            SignatureScheme::sign_message(private_key.clone(), &base).to_hex()
+        );
+
+        let post = json!({"id": 2, "method": "signRequest",
+            "request": {"method": "POST", "url": url, "body": "{\"accountId\":11087}"}});
+        let response: Value =
+            serde_json::from_str(&handle_line(&signer, &post.to_string())).unwrap();
+        let base = body_signature_base_string(
+            HttpMethod::Post,
+            url,
+            "{\"accountId\":11087}",
+            EncodingProfile::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            response["result"]["signature"],
+            SignatureScheme::sign_message(private_key, &base).to_hex()
+        );
+
+        let bad = json!({"id": 3, "method": "signRequest",
+            "request": {"method": "GET", "url": url, "params": [["k"]]}});
+        let response: Value =
+            serde_json::from_str(&handle_line(&signer, &bad.to_string())).unwrap();
+        assert!(response["error"].is_string());
+    }
+}
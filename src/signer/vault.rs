@@ -0,0 +1,161 @@
+/*
+A key vault for long-running services.
+
+Keys are stored as 32 little-endian bytes, each in its own pages from
+`memsec::malloc`: mlock'ed (never written to swap, excluded from core dumps where
+the OS supports it), between guard pages, and zeroed before they are unlocked and
+freed. Since locks apply to whole pages and do not nest, keys sharing a page of an
+ordinary allocation would be unlocked together when the first of them is removed;
+one allocation per key keeps every key locked for exactly its own lifetime. The
+keys are only reachable through `VaultSigner`, which implements Signer.
+
+Signing still has to materialize the scalar as a BigInt for the duration of the
+call; those temporaries are ordinary heap memory. The vault keeps the long-lived
+copy of the key out of swap, it does not make the signing arithmetic side-channel
+free.
+*/
+
+use super::{parse_private_key, Signer};
+use crate::poseidon::{
+    eddsa::{MessageHash, SignatureScheme, SignedMessage},
+    jubjub::Point,
+};
+use crate::util::errors::SignerError;
+use num_bigint::{BigInt, Sign};
+use std::collections::HashMap;
+use std::ptr::NonNull;
+
+struct LockedKey {
+    bytes: NonNull<[u8; 32]>,
+}
+
+// The allocation is owned by the LockedKey and only read through &self
+unsafe impl Send for LockedKey {}
+unsafe impl Sync for LockedKey {}
+
+impl LockedKey {
+    fn new(private_key: &BigInt) -> Result<Self, SignerError> {
+        let (_, le) = private_key.to_bytes_le();
+        if le.len() > 32 {
+            return Err(SignerError::InvalidPrivateKey);
+        }
+        let bytes = unsafe { memsec::malloc::<[u8; 32]>() }.ok_or(SignerError::LockFailed)?;
+        let key = LockedKey { bytes };
+        // memsec::malloc does not report a failed mlock; locking again is a no-op
+        // for pages it did lock. The key is only copied in once they are locked.
+        let ptr = key.bytes.as_ptr() as *mut u8;
+        if !unsafe { memsec::mlock(ptr, 32) } {
+            return Err(SignerError::LockFailed);
+        }
+        unsafe {
+            memsec::memzero(ptr, 32);
+            std::ptr::copy_nonoverlapping(le.as_ptr(), ptr, le.len());
+        }
+        Ok(key)
+    }
+
+    fn scalar(&self) -> BigInt {
+        BigInt::from_bytes_le(Sign::Plus, unsafe { self.bytes.as_ref() })
+    }
+}
+
+impl Drop for LockedKey {
+    fn drop(&mut self) {
+        // free zeroes the pages while unlocking them
+        unsafe { memsec::free(self.bytes) }
+    }
+}
+
+pub struct VaultSigner<'a> {
+    key: &'a LockedKey,
+    public_key: &'a Point,
+}
+
+impl Signer for VaultSigner<'_> {
+    fn public_key(&self) -> &Point {
+        self.public_key
+    }
+
+    fn sign(&self, hash: &MessageHash) -> Result<SignedMessage, SignerError> {
+        Ok(SignatureScheme::sign_hash(self.key.scalar(), hash))
+    }
+}
+
+// Keys are looked up by Loopring account id
+#[derive(Default)]
+pub struct KeyVault {
+    keys: HashMap<u32, (LockedKey, Point)>,
+}
+
+impl KeyVault {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    // Replaces (and wipes) a key already stored for the account
+    pub fn insert(&mut self, account_id: u32, hex_private_key: &str) -> Result<(), SignerError> {
+        let private_key = parse_private_key(hex_private_key)?;
+        let key = LockedKey::new(&private_key)?;
+        let public_key = SignatureScheme::base_point().mul_scalar(&private_key);
+        self.keys.insert(account_id, (key, public_key));
+        Ok(())
+    }
+
+    pub fn remove(&mut self, account_id: u32) -> bool {
+        self.keys.remove(&account_id).is_some()
+    }
+
+    pub fn signer(&self, account_id: u32) -> Option<VaultSigner<'_>> {
+        self.keys
+            .get(&account_id)
+            .map(|(key, public_key)| VaultSigner { key, public_key })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signer::LocalSigner;
+
+    const L2_KEY: &str = "0x001fa186947c8c644cd11078f67e0bb21656432f55c4df76997b6acab2abda7f";
+
+    #[test]
+    fn vault_signer_matches_local_signer() {
+        let mut vault = KeyVault::new();
+        vault.insert(10001, L2_KEY).unwrap();
+
+        let hash = MessageHash::from_message("vault");
+        let local = LocalSigner::from_hex(L2_KEY).unwrap();
+        let signer = vault.signer(10001).unwrap();
+        assert!(signer.public_key() == local.public_key());
+        assert_eq!(
+            signer.sign(&hash).unwrap().to_hex(),
+            local.sign(&hash).unwrap().to_hex()
+        );
+        assert!(vault.signer(10002).is_none());
+    }
+
+    #[test]
+    fn vault_removes_keys() {
+        let mut vault = KeyVault::new();
+        assert_eq!(vault.insert(1, "0x"), Err(SignerError::InvalidPrivateKey));
+        vault.insert(1, L2_KEY).unwrap();
+        vault.insert(2, L2_KEY).unwrap();
+        // Each key has pages of its own, so removing one leaves the other locked
+        let page = |id| vault.keys[&id].0.bytes.as_ptr() as usize / 4096;
+        assert_ne!(page(1), page(2));
+        assert!(vault.remove(1));
+        let hash = MessageHash::from_message("vault");
+        assert!(vault.signer(2).unwrap().sign(&hash).is_ok());
+        assert!(vault.remove(2));
+        assert!(vault.is_empty());
+    }
+}
@@ -0,0 +1,99 @@
+// Ethereum address parsing with EIP-55 checksum validation.
+// https://eips.ethereum.org/EIPS/eip-55
+//
+// All-lowercase and all-uppercase addresses carry no checksum and are accepted as
+// they are. Mixed-case addresses must match their checksum exactly, so a single
+// mistyped character is rejected before the address ends up in a signed payload.
+
+use crate::util::errors::AddressError;
+use sha3::{Digest, Keccak256};
+
+pub fn parse_address(address: &str) -> Result<[u8; 20], AddressError> {
+    let digits = address
+        .strip_prefix("0x")
+        .or_else(|| address.strip_prefix("0X"))
+        .ok_or(AddressError::MissingPrefix)?;
+    if digits.len() != 40 {
+        return Err(AddressError::InvalidLength);
+    }
+
+    let mut bytes = [0u8; 20];
+    hex::decode_to_slice(digits, &mut bytes).map_err(|_| AddressError::InvalidHex)?;
+
+    let has_lower = digits.chars().any(|c| c.is_ascii_lowercase());
+    let has_upper = digits.chars().any(|c| c.is_ascii_uppercase());
+    if has_lower && has_upper && to_checksum_address(&bytes)[2..] != *digits {
+        return Err(AddressError::InvalidChecksum);
+    }
+    Ok(bytes)
+}
+
+pub fn to_checksum_address(bytes: &[u8; 20]) -> String {
+    let lower = hex::encode(bytes);
+    let hash = Keccak256::digest(lower.as_bytes());
+
+    let checksummed: String = lower
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0x0f;
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect();
+    format!("0x{}", checksummed)
+}
+
+// Validates an address and returns it in checksummed form
+pub fn normalize_address(address: &str) -> Result<String, AddressError> {
+    parse_address(address).map(|bytes| to_checksum_address(&bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test vectors from EIP-55
+    const CHECKSUMMED: [&str; 4] = [
+        "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+        "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+        "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+    ];
+
+    #[test]
+    fn checksum_vectors() {
+        for address in CHECKSUMMED {
+            assert_eq!(normalize_address(address).unwrap(), address);
+            assert_eq!(normalize_address(&address.to_lowercase()).unwrap(), address);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_checksum() {
+        let typo = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD";
+        assert!(matches!(
+            parse_address(typo),
+            Err(AddressError::InvalidChecksum)
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_addresses() {
+        assert!(matches!(
+            parse_address("5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"),
+            Err(AddressError::MissingPrefix)
+        ));
+        assert!(matches!(
+            parse_address("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeA"),
+            Err(AddressError::InvalidLength)
+        ));
+        assert!(matches!(
+            parse_address("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeg"),
+            Err(AddressError::InvalidHex)
+        ));
+    }
+}
@@ -0,0 +1,129 @@
+// Deployment parameters of a Loopring exchange.
+//
+// Everything that differs between the mainnet exchange, a testnet deployment and
+// the Taiko-based deployment is collected in a ChainProfile: the chain id (EIP-712
+// domain), the exchange contract (payload preimages, keySeed, EIP-712 verifying
+// contract) and the API base URL (request signature base strings). Code that takes
+// a profile instead of the individual values switches networks by swapping the
+// profile.
+//
+// Only the mainnet deployment has fixed, published values. For Sepolia and Taiko
+// the exchange address and API URL of the deployment in use have to be supplied.
+
+use crate::keygen::key_seed::WalletKind;
+use crate::payload::eip712::{domain_separator, DOMAIN_NAME, DOMAIN_VERSION};
+use crate::util::{
+    address::{normalize_address, parse_address},
+    errors::PayloadError,
+};
+
+pub const MAINNET_CHAIN_ID: u64 = 1;
+pub const SEPOLIA_CHAIN_ID: u64 = 11155111;
+pub const TAIKO_CHAIN_ID: u64 = 167000;
+
+pub const MAINNET_EXCHANGE: &str = "0x0BABA1Ad5bE3a5C0a66E7ac838a129Bf948f1eA4";
+pub const MAINNET_API_BASE_URL: &str = "https://api3.loopring.io";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainProfile {
+    pub chain_id: u64,
+    // EIP-55 checksummed
+    pub exchange: String,
+    // Without trailing slash, e.g. "https://api3.loopring.io"
+    pub api_base_url: String,
+}
+
+impl ChainProfile {
+    pub fn new(chain_id: u64, exchange: &str, api_base_url: &str) -> Result<Self, PayloadError> {
+        let exchange = normalize_address(exchange)
+            .map_err(|e| PayloadError::InvalidAddress(exchange.to_string(), e))?;
+        Ok(ChainProfile {
+            chain_id,
+            exchange,
+            api_base_url: api_base_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    pub fn mainnet() -> Self {
+        ChainProfile::new(MAINNET_CHAIN_ID, MAINNET_EXCHANGE, MAINNET_API_BASE_URL).unwrap()
+    }
+
+    pub fn sepolia(exchange: &str, api_base_url: &str) -> Result<Self, PayloadError> {
+        ChainProfile::new(SEPOLIA_CHAIN_ID, exchange, api_base_url)
+    }
+
+    pub fn taiko(exchange: &str, api_base_url: &str) -> Result<Self, PayloadError> {
+        ChainProfile::new(TAIKO_CHAIN_ID, exchange, api_base_url)
+    }
+
+    // Full URL of an API path such as "/api/v3/apiKey", as used in the request
+    // signature base string
+    pub fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.api_base_url, path.trim_start_matches('/'))
+    }
+
+    pub fn key_seed(&self, wallet: &WalletKind, api_key_seed: Option<&str>, nonce: u32) -> String {
+        wallet.key_seed(api_key_seed, &self.exchange, nonce)
+    }
+
+    pub fn domain_separator(&self) -> Result<[u8; 32], PayloadError> {
+        domain_separator(DOMAIN_NAME, DOMAIN_VERSION, self.chain_id, &self.exchange)
+    }
+
+    // Rejects payloads built for another deployment
+    pub fn check_exchange(&self, exchange: &str) -> Result<(), PayloadError> {
+        let matches = match (parse_address(exchange), parse_address(&self.exchange)) {
+            (Ok(a), Ok(b)) => a == b,
+            (Err(e), _) => return Err(PayloadError::InvalidAddress(exchange.to_string(), e)),
+            _ => false,
+        };
+        if !matches {
+            return Err(PayloadError::InvalidField(
+                "exchange",
+                format!(
+                    "{} is not the exchange of chain {}",
+                    exchange, self.chain_id
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mainnet_profile() {
+        let mainnet = ChainProfile::mainnet();
+        assert_eq!(
+            mainnet.url("/api/v3/apiKey"),
+            "https://api3.loopring.io/api/v3/apiKey"
+        );
+        assert_eq!(
+            mainnet.key_seed(&WalletKind::Eoa, None, 1),
+            "Sign this message to access Loopring Exchange: 0x0BABA1Ad5bE3a5C0a66E7ac838a129Bf948f1eA4 with key nonce: 1"
+        );
+        assert!(mainnet
+            .check_exchange("0x0baba1ad5be3a5c0a66e7ac838a129bf948f1ea4")
+            .is_ok());
+    }
+
+    #[test]
+    fn testnet_profile_is_separate() {
+        let exchange = "0x2e76ebd1c7c0c8e7c2b875b6d505a260c525d25e";
+        let sepolia = ChainProfile::sepolia(exchange, "https://uat.example.org/").unwrap();
+        assert_eq!(sepolia.chain_id, SEPOLIA_CHAIN_ID);
+        assert_eq!(
+            sepolia.url("api/v3/apiKey"),
+            "https://uat.example.org/api/v3/apiKey"
+        );
+        assert_ne!(
+            sepolia.domain_separator().unwrap(),
+            ChainProfile::mainnet().domain_separator().unwrap()
+        );
+        assert!(sepolia.check_exchange(MAINNET_EXCHANGE).is_err());
+        assert!(ChainProfile::taiko("0x1234", "https://x").is_err());
+    }
+}
@@ -0,0 +1,181 @@
+// Percent-encoding for API signature base strings.
+//
+// A base string is "METHOD&<url>&<params>", so the url and the parameters are
+// encoded as components. Every byte is either kept or written as "%XX" with
+// uppercase digits; non-ASCII text is encoded byte by byte as UTF-8.
+//
+// Profiles:
+//
+//  * LoopringLegacy: what the relayer verifies today and what this crate has
+//    always produced. The url keeps everything except controls, ':' and '/'.
+//    Query keys and values are form-urlencoded first (space as '+', '*' kept)
+//    and the joined query is encoded again keeping only alphanumerics, so a ','
+//    in a value ends up as "%252C". POST/PUT bodies keep only alphanumerics.
+//  * StrictRfc3986: every part keeps only the RFC 3986 unreserved characters
+//    (ALPHA / DIGIT / "-" / "." / "_" / "~"). Query keys and values are encoded
+//    once, joined with '=' and '&', and the joined query is encoded again, so
+//    a literal '&' or '=' inside a value stays distinguishable.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum EncodingProfile {
+    #[default]
+    LoopringLegacy,
+    StrictRfc3986,
+}
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+fn is_unreserved(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~')
+}
+
+// Bytes the legacy url encoding keeps: printable ASCII other than ':' and '/'
+fn is_legacy_url_safe(byte: u8) -> bool {
+    (0x20..0x7f).contains(&byte) && byte != b':' && byte != b'/'
+}
+
+// application/x-www-form-urlencoded, as used for legacy query keys and values
+fn is_form_safe(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'*' | b'-' | b'.' | b'_')
+}
+
+// The single encoder behind every profile
+fn encode(input: &str, keep: fn(u8) -> bool, space_as_plus: bool) -> String {
+    let mut out = String::with_capacity(input.len());
+    for &byte in input.as_bytes() {
+        if keep(byte) {
+            out.push(byte as char);
+        } else if space_as_plus && byte == b' ' {
+            out.push('+');
+        } else {
+            out.push('%');
+            out.push(HEX_DIGITS[(byte >> 4) as usize] as char);
+            out.push(HEX_DIGITS[(byte & 0x0f) as usize] as char);
+        }
+    }
+    out
+}
+
+impl EncodingProfile {
+    pub fn encode_url(&self, url: &str) -> String {
+        match self {
+            EncodingProfile::LoopringLegacy => encode(url, is_legacy_url_safe, false),
+            EncodingProfile::StrictRfc3986 => encode(url, is_unreserved, false),
+        }
+    }
+
+    // `pairs` are encoded in the given order
+    pub fn encode_query(&self, pairs: &[(&str, &str)]) -> String {
+        let (keep, space_as_plus): (fn(u8) -> bool, bool) = match self {
+            EncodingProfile::LoopringLegacy => (is_form_safe, true),
+            EncodingProfile::StrictRfc3986 => (is_unreserved, false),
+        };
+        let query = pairs
+            .iter()
+            .map(|(key, value)| {
+                format!(
+                    "{}={}",
+                    encode(key, keep, space_as_plus),
+                    encode(value, keep, space_as_plus)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("&");
+        self.encode_body(&query)
+    }
+
+    // Encodes a serialized POST/PUT body (or an already joined query)
+    pub fn encode_body(&self, body: &str) -> String {
+        match self {
+            EncodingProfile::LoopringLegacy => encode(body, |b| b.is_ascii_alphanumeric(), false),
+            EncodingProfile::StrictRfc3986 => encode(body, is_unreserved, false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use percent_encoding::{percent_encode, AsciiSet, CONTROLS, NON_ALPHANUMERIC};
+    use url::form_urlencoded;
+
+    // Every ASCII character, a few multi-byte ones and the usual suspects in
+    // combination
+    fn tricky_inputs() -> Vec<String> {
+        let mut inputs: Vec<String> = (0u8..0x80).map(|b| (b as char).to_string()).collect();
+        inputs.extend(
+            [
+                "é", "€", "😀", "\u{7f}", "a b+c", "1,2,3", "x=y&z", "%2C", "~*!'()", "",
+            ]
+            .iter()
+            .map(|s| s.to_string()),
+        );
+        inputs
+    }
+
+    #[test]
+    fn legacy_matches_the_previous_encoders() {
+        const FRAGMENT: &AsciiSet = &CONTROLS.add(b':').add(b'/');
+        let legacy = EncodingProfile::LoopringLegacy;
+
+        for input in tricky_inputs() {
+            assert_eq!(
+                legacy.encode_url(&input),
+                percent_encode(input.as_bytes(), FRAGMENT).to_string(),
+                "url {:?}",
+                input
+            );
+
+            let pairs = [("k", input.as_str()), (input.as_str(), "v")];
+            let form = form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(&pairs)
+                .finish();
+            assert_eq!(
+                legacy.encode_query(&pairs),
+                percent_encode(form.as_bytes(), NON_ALPHANUMERIC).to_string(),
+                "query {:?}",
+                input
+            );
+
+            assert_eq!(
+                legacy.encode_body(&input),
+                percent_encode(input.as_bytes(), NON_ALPHANUMERIC).to_string(),
+                "body {:?}",
+                input
+            );
+        }
+        assert_eq!(
+            legacy.encode_query(&[("type", "123,45")]),
+            "type%3D123%252C45"
+        );
+    }
+
+    #[test]
+    fn strict_keeps_only_unreserved_characters() {
+        let strict = EncodingProfile::StrictRfc3986;
+
+        for input in tricky_inputs() {
+            let expected: String = input
+                .bytes()
+                .map(|b| {
+                    if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+                        (b as char).to_string()
+                    } else {
+                        format!("%{:02X}", b)
+                    }
+                })
+                .collect();
+            assert_eq!(strict.encode_url(&input), expected, "url {:?}", input);
+            assert_eq!(strict.encode_body(&input), expected, "body {:?}", input);
+        }
+
+        assert_eq!(
+            strict.encode_url("https://api3.loopring.io/api/v3/apiKey"),
+            "https%3A%2F%2Fapi3.loopring.io%2Fapi%2Fv3%2FapiKey"
+        );
+        assert_eq!(
+            strict.encode_query(&[("type", "1,2 ~é"), ("a&b", "x=y")]),
+            "type%3D1%252C2%2520~%25C3%25A9%26a%2526b%3Dx%253Dy"
+        );
+    }
+}
@@ -4,6 +4,9 @@ use std::fmt;
 pub enum PoseidonError {
     EmptyInputError,
     InputsExceedRate,
+    InputArityMismatch,
+    StateWidthMismatch,
+    InvalidPrivateKey,
 }
 
 impl fmt::Display for PoseidonError {
@@ -11,8 +14,897 @@ impl fmt::Display for PoseidonError {
         match self {
             PoseidonError::EmptyInputError => write!(f, "No inputs provided"),
             PoseidonError::InputsExceedRate => write!(f, "Inputs exceed the rate."),
+            PoseidonError::InputArityMismatch => {
+                write!(f, "All messages in a batch must have the same arity.")
+            }
+            PoseidonError::StateWidthMismatch => {
+                write!(f, "The state must have as many elements as the width.")
+            }
+            PoseidonError::InvalidPrivateKey => write!(f, "You didn't pass a valid private key"),
         }
     }
 }
 
 impl std::error::Error for PoseidonError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParameterError {
+    WidthTooSmall(usize),
+    InvalidFullRounds(usize),
+    NoPartialRounds,
+    // x^e is not a permutation of the field: gcd(e, p - 1) != 1
+    ExponentNotCoprime,
+    FieldTooSmall { bits: u64, security_target: usize },
+    // The round counts do not exceed the bound of the named attack
+    InsufficientRounds(&'static str),
+    ConstantsLength { expected: usize, actual: usize },
+    MatrixShape,
+    // The MDS matrix has no inverse mod p
+    SingularMatrix,
+    // The target only works over SNARK_SCALAR_FIELD
+    UnsupportedModulus,
+}
+
+impl fmt::Display for ParameterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParameterError::WidthTooSmall(t) => write!(f, "State width t = {} is below 2", t),
+            ParameterError::InvalidFullRounds(n) => {
+                write!(f, "Number of full rounds {} must be even and non-zero", n)
+            }
+            ParameterError::NoPartialRounds => write!(f, "At least one partial round is required"),
+            ParameterError::ExponentNotCoprime => {
+                write!(f, "S-box exponent must be coprime to p - 1")
+            }
+            ParameterError::FieldTooSmall {
+                bits,
+                security_target,
+            } => write!(
+                f,
+                "A {}-bit field cannot reach a {}-bit security target",
+                bits, security_target
+            ),
+            ParameterError::InsufficientRounds(attack) => {
+                write!(f, "Too few rounds to resist the {} attack", attack)
+            }
+            ParameterError::ConstantsLength { expected, actual } => {
+                write!(f, "Expected {} round constants, got {}", expected, actual)
+            }
+            ParameterError::MatrixShape => write!(f, "The MDS matrix must be t x t"),
+            ParameterError::SingularMatrix => write!(f, "The MDS matrix is not invertible"),
+            ParameterError::UnsupportedModulus => {
+                write!(f, "Parameters must be over SNARK_SCALAR_FIELD")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParameterError {}
+
+#[derive(Debug, Clone)]
+pub enum ConversionError {
+    ValueTooLarge,
+    ValueExceedsModulus,
+    ModulusMismatch,
+    // Decoded coordinates that do not satisfy the curve equation
+    NotOnCurve,
+    ZeroKey,
+    // Coordinates of a point that is not a valid public key
+    InvalidPublicKey(KeyValidationError),
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConversionError::ValueTooLarge => write!(f, "Value does not fit into 256 bits."),
+            ConversionError::ValueExceedsModulus => {
+                write!(f, "Value is not below the field modulus.")
+            }
+            ConversionError::ModulusMismatch => {
+                write!(f, "Field element is not defined over SNARK_SCALAR_FIELD.")
+            }
+            ConversionError::NotOnCurve => write!(f, "Point is not on the Baby Jubjub curve."),
+            ConversionError::ZeroKey => write!(f, "Private key is zero modulo the subgroup order."),
+            ConversionError::InvalidPublicKey(e) => write!(f, "Invalid public key: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+#[derive(Debug, Clone)]
+pub enum PayloadError {
+    InvalidAddress(String, AddressError),
+    InvalidPrivateKey,
+    MissingField(&'static str),
+    InvalidField(&'static str, String),
+    // The value does not fit into the field's bit width in the circuit
+    OutOfRange {
+        field: &'static str,
+        value: String,
+        bits: u32,
+    },
+    // The payload (named) has no layout in the requested protocol version
+    UnsupportedVersion(&'static str),
+    Poseidon(PoseidonError),
+}
+
+impl fmt::Display for PayloadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PayloadError::InvalidAddress(address, e) => {
+                write!(f, "Invalid address {}: {}", address, e)
+            }
+            PayloadError::InvalidPrivateKey => write!(f, "You didn't pass a valid private key"),
+            PayloadError::MissingField(field) => write!(f, "Missing field: {}", field),
+            PayloadError::InvalidField(field, reason) => {
+                write!(f, "Invalid field {}: {}", field, reason)
+            }
+            PayloadError::OutOfRange { field, value, bits } => {
+                write!(
+                    f,
+                    "Field {} = {} does not fit into {} bits",
+                    field, value, bits
+                )
+            }
+            PayloadError::UnsupportedVersion(payload) => {
+                write!(f, "{} has no layout in this protocol version", payload)
+            }
+            PayloadError::Poseidon(e) => write!(f, "Poseidon error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PayloadError {}
+
+impl From<PoseidonError> for PayloadError {
+    fn from(e: PoseidonError) -> Self {
+        PayloadError::Poseidon(e)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressError {
+    MissingPrefix,
+    InvalidLength,
+    InvalidHex,
+    InvalidChecksum,
+}
+
+impl fmt::Display for AddressError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AddressError::MissingPrefix => write!(f, "Address must start with 0x"),
+            AddressError::InvalidLength => write!(f, "Address must be 20 bytes long"),
+            AddressError::InvalidHex => write!(f, "Address contains non-hex characters"),
+            AddressError::InvalidChecksum => {
+                write!(f, "Address does not match its EIP-55 checksum")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AddressError {}
+
+#[derive(Debug, Clone)]
+pub enum TokenError {
+    UnknownToken(String),
+    InvalidAmount(String),
+    TooManyDecimals(String, u32),
+    VolumeOutOfRange(String),
+}
+
+impl fmt::Display for TokenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TokenError::UnknownToken(token) => write!(f, "Unknown token: {}", token),
+            TokenError::InvalidAmount(amount) => write!(f, "Invalid amount: {}", amount),
+            TokenError::TooManyDecimals(amount, decimals) => {
+                write!(f, "Amount {} has more than {} decimals", amount, decimals)
+            }
+            TokenError::VolumeOutOfRange(amount) => {
+                write!(f, "Amount {} does not fit into 96 bits", amount)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TokenError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignaturePart {
+    Rx,
+    Ry,
+    S,
+}
+
+impl fmt::Display for SignaturePart {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SignaturePart::Rx => write!(f, "Rx"),
+            SignaturePart::Ry => write!(f, "Ry"),
+            SignaturePart::S => write!(f, "S"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureError {
+    InvalidLength(usize),
+    InvalidHex(SignaturePart),
+    OutOfRange(SignaturePart),
+    InvalidPublicKey,
+    // The named point is not on the curve or not in the prime-order subgroup
+    InvalidPoint(&'static str),
+    MessageOutOfRange,
+    // The same nonce R was emitted for two different messages under one key
+    NonceReuse,
+}
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SignatureError::InvalidLength(len) => {
+                write!(f, "Signature must be 192 hex characters long, got {}", len)
+            }
+            SignatureError::InvalidHex(part) => {
+                write!(f, "Signature part {} contains non-hex characters", part)
+            }
+            SignatureError::OutOfRange(part) => {
+                write!(f, "Signature part {} is not below its modulus", part)
+            }
+            SignatureError::InvalidPublicKey => {
+                write!(
+                    f,
+                    "Public key coordinates must be 0x-prefixed 32-byte field elements"
+                )
+            }
+            SignatureError::InvalidPoint(point) => {
+                write!(f, "{} is not a point of the Baby Jubjub subgroup", point)
+            }
+            SignatureError::MessageOutOfRange => {
+                write!(f, "Message hash is not below SNARK_SCALAR_FIELD")
+            }
+            SignatureError::NonceReuse => {
+                write!(
+                    f,
+                    "Signature nonce was already used for a different message"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for SignatureError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HexError {
+    Empty,
+    InvalidCharacter(char),
+    // A secret that is not at most 64 hex digits; the input is not echoed
+    InvalidSecret,
+}
+
+impl fmt::Display for HexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HexError::Empty => write!(f, "Hex string contains no digits"),
+            HexError::InvalidCharacter(c) => write!(f, "Invalid hex character {:?}", c),
+            HexError::InvalidSecret => write!(f, "Secret is not a valid 32-byte hex string"),
+        }
+    }
+}
+
+impl std::error::Error for HexError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignerError {
+    InvalidPrivateKey,
+    // The key memory could not be locked (e.g. RLIMIT_MEMLOCK exhausted)
+    LockFailed,
+    // The key could not be decrypted with the given data key
+    DecryptionFailed,
+    // Raised by an external key store
+    Backend(String),
+    // The key source (environment variable, file, prompt) could not be read
+    Unavailable(String),
+    InvalidKeystore(String),
+    // A signature that does not verify against the signer's public key, caught
+    // by a VerifyingSigner before it was returned
+    VerificationFailed,
+    // The last audit record to resume from does not match its digest
+    AuditChainBroken,
+}
+
+impl fmt::Display for SignerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SignerError::InvalidPrivateKey => write!(f, "You didn't pass a valid private key"),
+            SignerError::LockFailed => write!(f, "Could not lock the key memory"),
+            SignerError::DecryptionFailed => write!(f, "Could not decrypt the private key"),
+            SignerError::Backend(e) => write!(f, "Key store error: {}", e),
+            SignerError::Unavailable(e) => write!(f, "Key not available: {}", e),
+            SignerError::InvalidKeystore(e) => write!(f, "Invalid keystore: {}", e),
+            SignerError::VerificationFailed => write!(
+                f,
+                "The signature does not verify against the signer's public key"
+            ),
+            SignerError::AuditChainBroken => {
+                write!(f, "The last audit record does not match its digest")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SignerError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelfCheckError {
+    Mismatch {
+        vector: &'static str,
+        expected: String,
+        actual: String,
+    },
+    Failed(&'static str, String),
+}
+
+impl fmt::Display for SelfCheckError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SelfCheckError::Mismatch {
+                vector,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Self-check {} failed: expected {}, got {}",
+                vector, expected, actual
+            ),
+            SelfCheckError::Failed(vector, e) => write!(f, "Self-check {} failed: {}", vector, e),
+        }
+    }
+}
+
+impl std::error::Error for SelfCheckError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MerkleError {
+    TooManyLeaves { depth: usize, leaves: u64 },
+    // The leaf index does not fit into a tree of the proof's depth
+    IndexOutOfRange { index: u64, depth: usize },
+    // Flat quad-tree proofs hold three siblings per level
+    InvalidProofLength(usize),
+    // A node needs at least two children
+    InvalidArity(usize),
+}
+
+impl fmt::Display for MerkleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MerkleError::TooManyLeaves { depth, leaves } => {
+                write!(
+                    f,
+                    "{} leaves do not fit into a tree of depth {}",
+                    leaves, depth
+                )
+            }
+            MerkleError::IndexOutOfRange { index, depth } => {
+                write!(
+                    f,
+                    "Leaf index {} is outside a tree of depth {}",
+                    index, depth
+                )
+            }
+            MerkleError::InvalidProofLength(len) => {
+                write!(
+                    f,
+                    "Proof of {} siblings is not a whole number of levels",
+                    len
+                )
+            }
+            MerkleError::InvalidArity(arity) => {
+                write!(
+                    f,
+                    "A Merkle tree node needs at least two children, not {}",
+                    arity
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for MerkleError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequestError {
+    UnsupportedMethod(String),
+    NoBody(&'static str),
+    // A body to canonicalize is not JSON
+    InvalidBody(String),
+    // A full request url with a malformed query
+    InvalidUrl(String),
+    // POST/PUT requests are signed from their body, not a query
+    NoQuery(&'static str),
+    InvalidPrivateKey,
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RequestError::UnsupportedMethod(method) => write!(
+                f,
+                "Unsupported HTTP method {:?}, expected GET, DELETE, POST or PUT",
+                method
+            ),
+            RequestError::NoBody(method) => {
+                write!(
+                    f,
+                    "{} requests are signed from the query, not a body",
+                    method
+                )
+            }
+            RequestError::InvalidBody(e) => write!(f, "Body is not valid JSON: {}", e),
+            RequestError::InvalidUrl(e) => write!(f, "Invalid request url: {}", e),
+            RequestError::NoQuery(method) => {
+                write!(
+                    f,
+                    "{} requests are signed from the body, not the query",
+                    method
+                )
+            }
+            RequestError::InvalidPrivateKey => write!(f, "You didn't pass a valid private key"),
+        }
+    }
+}
+
+impl std::error::Error for RequestError {}
+
+#[derive(Debug, Clone)]
+pub enum BundleError {
+    // Not a bundle: malformed JSON or a missing/mistyped field
+    Invalid(String),
+    UnsupportedSchema(u64),
+    Payload(PayloadError),
+    // The payload does not hash to the hash recorded in the bundle
+    HashMismatch { expected: String, actual: String },
+    Signature(SignatureError),
+    // The signature does not verify under the bundled public key
+    InvalidSignature,
+    Signer(SignerError),
+}
+
+impl fmt::Display for BundleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BundleError::Invalid(reason) => write!(f, "Invalid bundle: {}", reason),
+            BundleError::UnsupportedSchema(version) => {
+                write!(f, "Unsupported bundle schema {}", version)
+            }
+            BundleError::Payload(e) => write!(f, "Invalid bundle payload: {}", e),
+            BundleError::HashMismatch { expected, actual } => write!(
+                f,
+                "Bundle payload hashes to {}, but the bundle records {}",
+                actual, expected
+            ),
+            BundleError::Signature(e) => write!(f, "Invalid bundle signature: {}", e),
+            BundleError::InvalidSignature => {
+                write!(f, "Bundle signature does not match its public key")
+            }
+            BundleError::Signer(e) => write!(f, "Could not sign bundle: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for BundleError {}
+
+impl From<PayloadError> for BundleError {
+    fn from(e: PayloadError) -> Self {
+        BundleError::Payload(e)
+    }
+}
+
+impl From<SignatureError> for BundleError {
+    fn from(e: SignatureError) -> Self {
+        BundleError::Signature(e)
+    }
+}
+
+impl From<SignerError> for BundleError {
+    fn from(e: SignerError) -> Self {
+        BundleError::Signer(e)
+    }
+}
+
+// The batch item at `index` failed; nothing of the batch is returned
+#[derive(Debug, Clone)]
+pub enum BatchSignError {
+    Payload { index: usize, error: PayloadError },
+    Signer { index: usize, error: SignerError },
+}
+
+impl fmt::Display for BatchSignError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BatchSignError::Payload { index, error } => {
+                write!(f, "Invalid payload at batch index {}: {}", index, error)
+            }
+            BatchSignError::Signer { index, error } => {
+                write!(f, "Could not sign batch index {}: {}", index, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BatchSignError {}
+
+#[derive(Debug, Clone)]
+pub enum ClientError {
+    // The request did not get a response (connection, TLS, timeout)
+    Transport(String),
+    // The API answered with an error status or a non-zero result code
+    Api {
+        status: u16,
+        code: i64,
+        message: String,
+    },
+    InvalidResponse(String),
+    // The signer's key is not the key registered for the account
+    KeyMismatch(u32),
+    Payload(PayloadError),
+    Signer(SignerError),
+    Token(TokenError),
+    Request(RequestError),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClientError::Transport(e) => write!(f, "Request failed: {}", e),
+            ClientError::Api {
+                status,
+                code,
+                message,
+            } => write!(f, "API error {} (HTTP {}): {}", code, status, message),
+            ClientError::InvalidResponse(e) => write!(f, "Unexpected API response: {}", e),
+            ClientError::KeyMismatch(account_id) => write!(
+                f,
+                "The signer's key is not the L2 key of account {}",
+                account_id
+            ),
+            ClientError::Payload(e) => e.fmt(f),
+            ClientError::Signer(e) => e.fmt(f),
+            ClientError::Token(e) => e.fmt(f),
+            ClientError::Request(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<PayloadError> for ClientError {
+    fn from(e: PayloadError) -> Self {
+        ClientError::Payload(e)
+    }
+}
+
+impl From<SignerError> for ClientError {
+    fn from(e: SignerError) -> Self {
+        ClientError::Signer(e)
+    }
+}
+
+impl From<TokenError> for ClientError {
+    fn from(e: TokenError) -> Self {
+        ClientError::Token(e)
+    }
+}
+
+impl From<RequestError> for ClientError {
+    fn from(e: RequestError) -> Self {
+        ClientError::Request(e)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EcdhError {
+    // Zero modulo the subgroup order
+    InvalidPrivateKey,
+    // Not on the curve, or of small order
+    InvalidPublicKey,
+}
+
+impl fmt::Display for EcdhError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EcdhError::InvalidPrivateKey => write!(f, "You didn't pass a valid private key"),
+            EcdhError::InvalidPublicKey => {
+                write!(f, "Public key is not a point of the Baby Jubjub subgroup")
+            }
+        }
+    }
+}
+
+impl std::error::Error for EcdhError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyValidationError {
+    // The named value ("public key x", "private key", ...) is not hex
+    InvalidHex(&'static str),
+    // Not below SNARK_SCALAR_FIELD
+    OutOfRange(&'static str),
+    // Not 0x followed by exactly 64 digits, as the API expects
+    NotPadded(&'static str),
+    // Absent or empty, e.g. the key of an account that has not registered one
+    Missing(&'static str),
+    // Neither 0x-prefixed hex nor decimal
+    InvalidCoordinate(&'static str),
+    NotOnCurve,
+    // On the curve but of small order
+    NotInSubgroup,
+    // The neutral point, which is k·B only for k = 0
+    Identity,
+    // Zero modulo the subgroup order
+    InvalidPrivateKey,
+    // The public key of the given private key, for comparison
+    Mismatch {
+        public_key_x: String,
+        public_key_y: String,
+    },
+}
+
+impl fmt::Display for KeyValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeyValidationError::InvalidHex(value) => write!(f, "The {} is not hex", value),
+            KeyValidationError::OutOfRange(value) => {
+                write!(f, "The {} is not below SNARK_SCALAR_FIELD", value)
+            }
+            KeyValidationError::NotPadded(value) => write!(
+                f,
+                "The {} must be 0x followed by exactly 64 hex digits",
+                value
+            ),
+            KeyValidationError::Missing(value) => write!(f, "The {} is missing", value),
+            KeyValidationError::InvalidCoordinate(value) => {
+                write!(f, "The {} is neither 0x-prefixed hex nor decimal", value)
+            }
+            KeyValidationError::NotOnCurve => {
+                write!(f, "The public key is not a point of Baby Jubjub")
+            }
+            KeyValidationError::NotInSubgroup => write!(
+                f,
+                "The public key is of small order, not in the prime-order subgroup"
+            ),
+            KeyValidationError::Identity => {
+                write!(f, "The public key is the neutral point (0, 1)")
+            }
+            KeyValidationError::InvalidPrivateKey => {
+                write!(f, "The private key is zero modulo the subgroup order")
+            }
+            KeyValidationError::Mismatch {
+                public_key_x,
+                public_key_y,
+            } => write!(
+                f,
+                "The private key belongs to the public key ({}, {})",
+                public_key_x, public_key_y
+            ),
+        }
+    }
+}
+
+impl std::error::Error for KeyValidationError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeySeedError {
+    // Not r || s || v (65 bytes, v in {0, 1, 27, 28}), or no key recovers from it
+    InvalidSignature,
+    InvalidAddress(AddressError),
+    // The signature recovers to another address: another wallet signed, or the
+    // wallet signed another message. Both addresses are checksummed.
+    WrongSigner { expected: String, recovered: String },
+}
+
+impl fmt::Display for KeySeedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeySeedError::InvalidSignature => {
+                write!(f, "The keySeed signature is not a valid ECDSA signature")
+            }
+            KeySeedError::InvalidAddress(e) => write!(f, "Invalid address: {}", e),
+            KeySeedError::WrongSigner {
+                expected,
+                recovered,
+            } => write!(
+                f,
+                "The keySeed signature recovers to {} instead of {}; check the wallet and the keySeed message",
+                recovered, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for KeySeedError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportError {
+    InvalidJson(String),
+    // Neither a Loopring wallet nor a hello_loopring account export
+    UnknownFormat,
+    MissingField(&'static str),
+    InvalidField(&'static str, String),
+    InvalidAddress(AddressError),
+    // Not hex, or zero modulo the subgroup order
+    InvalidPrivateKey,
+    // The exported public key is not the one of the exported private key
+    KeyMismatch,
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImportError::InvalidJson(e) => write!(f, "The export is not valid JSON: {}", e),
+            ImportError::UnknownFormat => write!(f, "The export is not in a known format"),
+            ImportError::MissingField(field) => write!(f, "Missing field: {}", field),
+            ImportError::InvalidField(field, value) => {
+                write!(f, "Invalid field {}: {}", field, value)
+            }
+            ImportError::InvalidAddress(e) => write!(f, "Invalid address: {}", e),
+            ImportError::InvalidPrivateKey => write!(f, "You didn't pass a valid private key"),
+            ImportError::KeyMismatch => write!(
+                f,
+                "The exported public key does not belong to the exported private key"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PassphraseError {
+    EmptyPassphrase,
+    // Shorter than the 16 bytes RFC 9106 recommends
+    SaltTooShort(usize),
+    // Argon2 parameters outside of what RFC 9106 allows
+    InvalidParams(&'static str),
+    InvalidRecord(String),
+    // No salt could be drawn from the system's random source
+    Rng(String),
+}
+
+impl fmt::Display for PassphraseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PassphraseError::EmptyPassphrase => write!(f, "The passphrase is empty"),
+            PassphraseError::SaltTooShort(len) => {
+                write!(f, "The salt has {} bytes, at least 16 are needed", len)
+            }
+            PassphraseError::InvalidParams(reason) => {
+                write!(f, "Invalid Argon2 parameters: {}", reason)
+            }
+            PassphraseError::InvalidRecord(reason) => {
+                write!(f, "Invalid passphrase key record: {}", reason)
+            }
+            PassphraseError::Rng(e) => write!(f, "Could not generate a salt: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PassphraseError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CurveError {
+    // Coordinates and scalars are hashed as 32-byte words
+    ModulusTooLarge,
+    GeneratorNotOnCurve,
+    // The generator is the neutral point, or order * generator is not
+    GeneratorOrder,
+    // The challenge Poseidon is not of width 6 over the curve's modulus
+    ChallengeHash,
+}
+
+impl fmt::Display for CurveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CurveError::ModulusTooLarge => write!(f, "The modulus does not fit into 256 bits"),
+            CurveError::GeneratorNotOnCurve => write!(f, "The generator is not on the curve"),
+            CurveError::GeneratorOrder => {
+                write!(f, "The generator does not have the given prime order")
+            }
+            CurveError::ChallengeHash => write!(
+                f,
+                "The challenge hash must be a width-6 Poseidon over the curve's modulus"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CurveError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommitmentError {
+    // The opening does not produce the commitment
+    Mismatch,
+    // No randomness for a blinding factor
+    Unavailable(String),
+}
+
+impl fmt::Display for CommitmentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CommitmentError::Mismatch => write!(f, "Opening does not match the commitment"),
+            CommitmentError::Unavailable(e) => write!(f, "Blinding not available: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CommitmentError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PedersenError {
+    EmptyInput,
+    // Base point names are ASCII and at most 28 bytes long
+    InvalidName(String),
+    InvalidWindow(u8),
+    // More than 2^16 base points would be needed
+    TooManyWindows,
+    ScalarOutOfRange(String),
+}
+
+impl fmt::Display for PedersenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PedersenError::EmptyInput => write!(f, "Cannot hash an empty message"),
+            PedersenError::InvalidName(name) => write!(
+                f,
+                "Pedersen name {:?} must be ASCII and at most 28 bytes long",
+                name
+            ),
+            PedersenError::InvalidWindow(window) => {
+                write!(f, "Window {} does not fit into 3 bits", window)
+            }
+            PedersenError::TooManyWindows => write!(f, "Message is too long for Pedersen hashing"),
+            PedersenError::ScalarOutOfRange(scalar) => {
+                write!(f, "Scalar {} does not fit into the given bits", scalar)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PedersenError {}
+
+// Errors while synthesizing the Poseidon constraints (poseidon::r1cs)
+#[cfg(feature = "ark")]
+#[derive(Debug)]
+pub enum GadgetError {
+    Poseidon(PoseidonError),
+    Synthesis(ark_relations::gr1cs::SynthesisError),
+}
+
+#[cfg(feature = "ark")]
+impl fmt::Display for GadgetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GadgetError::Poseidon(e) => write!(f, "{}", e),
+            GadgetError::Synthesis(e) => write!(f, "Constraint synthesis failed: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "ark")]
+impl std::error::Error for GadgetError {}
+
+#[cfg(feature = "ark")]
+impl From<PoseidonError> for GadgetError {
+    fn from(e: PoseidonError) -> Self {
+        GadgetError::Poseidon(e)
+    }
+}
+
+#[cfg(feature = "ark")]
+impl From<ark_relations::gr1cs::SynthesisError> for GadgetError {
+    fn from(e: ark_relations::gr1cs::SynthesisError) -> Self {
+        GadgetError::Synthesis(e)
+    }
+}
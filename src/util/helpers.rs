@@ -1,16 +1,34 @@
 use indexmap::IndexMap;
 use num_bigint::BigInt;
 use num_bigint::Sign;
-use percent_encoding::{percent_encode, AsciiSet, CONTROLS, NON_ALPHANUMERIC};
-use serde_json;
+use serde_json::Value;
 use sha2::{Digest, Sha256};
-use url::form_urlencoded;
+use sha3::Keccak256;
 
 use crate::poseidon::field::SNARK_SCALAR_FIELD;
+use crate::poseidon::hasher::PoseidonHasher;
+use crate::util::encoding::EncodingProfile;
+use crate::util::errors::{ConversionError, RequestError};
+use crate::util::jcs;
+use std::str::FromStr;
 
-pub fn to_bytes_32(n: &BigInt) -> Vec<u8> {
+// The magnitude as 32 little-endian bytes. Values that do not fit are an
+// error instead of being cut, so an unreduced value cannot silently hash or sign
+// as a different one.
+pub fn to_bytes_32(n: &BigInt) -> Result<[u8; 32], ConversionError> {
     let (_, array) = n.to_bytes_le();
-    let mut data: Vec<u8> = vec![0; 32];
+    if array.len() > 32 {
+        return Err(ConversionError::ValueTooLarge);
+    }
+    let mut data = [0u8; 32];
+    data[..array.len()].copy_from_slice(&array);
+    Ok(data)
+}
+
+// Keeps only the low 32 bytes of larger values
+pub fn to_bytes_32_truncating(n: &BigInt) -> [u8; 32] {
+    let (_, array) = n.to_bytes_le();
+    let mut data = [0u8; 32];
     let bytes_to_copy = array.len().min(32);
     data[..bytes_to_copy].copy_from_slice(&array[..bytes_to_copy]);
     data
@@ -20,54 +38,404 @@ pub fn sha256_snark(signature_base: &str) -> BigInt {
     let mut hasher = Sha256::new();
     hasher.update(signature_base);
     let hash = BigInt::from_bytes_be(Sign::Plus, &hasher.finalize()[..]);
-    hash % SNARK_SCALAR_FIELD.clone()
+    hash % &*SNARK_SCALAR_FIELD
+}
+
+// How a signature base string becomes the field element that is signed.
+// Loopring uses Sha256Snark; the others are for forks of the API that hash
+// requests differently.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum RequestHash {
+    // int(sha256(base), big-endian) mod p
+    #[default]
+    Sha256Snark,
+    // int(keccak256(base), big-endian) mod p
+    Keccak256,
+    // The byte-oriented Poseidon of poseidon::hasher, already a field element
+    PoseidonBytes,
+}
+
+impl RequestHash {
+    pub fn hash(&self, signature_base: &str) -> BigInt {
+        match self {
+            RequestHash::Sha256Snark => sha256_snark(signature_base),
+            RequestHash::Keccak256 => {
+                let digest = Keccak256::digest(signature_base);
+                BigInt::from_bytes_be(Sign::Plus, &digest) % &*SNARK_SCALAR_FIELD
+            }
+            RequestHash::PoseidonBytes => {
+                let digest = PoseidonHasher::digest(signature_base);
+                BigInt::from_bytes_le(Sign::Plus, &digest)
+            }
+        }
+    }
 }
 
 // The algorithm for the API Request Signatures can be taken from:
 // https://docs-protocol.loopring.io/resources/request-signing/special-api-request-signatures
 
-pub fn generate_signature_base_string(
+// How GET/DELETE parameters are ordered before encoding. POST/PUT bodies are
+// ordered by `BodyOrdering` instead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParamOrdering {
+    // By key, comparing the UTF-8 bytes (i.e. code points). This is what the
+    // reference SDK does (`sorted(params.items())`): uppercase sorts before
+    // lowercase and non-ASCII keys after all ASCII ones.
+    #[default]
+    ByteWise,
+    // By key, ignoring ASCII case; keys that only differ in case keep the
+    // byte-wise order
+    CaseInsensitive,
+    // Keep the order the parameters were given in
+    Preserve,
+}
+
+impl ParamOrdering {
+    fn sort(&self, data: &mut [(&str, &str)]) {
+        match self {
+            ParamOrdering::ByteWise => data.sort_by(|a, b| a.0.cmp(b.0)),
+            ParamOrdering::CaseInsensitive => data.sort_by(|a, b| {
+                a.0.to_ascii_lowercase()
+                    .cmp(&b.0.to_ascii_lowercase())
+                    .then_with(|| a.0.cmp(b.0))
+            }),
+            ParamOrdering::Preserve => {}
+        }
+    }
+}
+
+// How POST/PUT params are ordered in the JSON body. `signature_base_string`
+// and the string-method functions always use `Insertion`; other orderings go
+// through `post_body`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BodyOrdering<'a> {
+    // The order the params were given in
+    #[default]
+    Insertion,
+    // By key, comparing the UTF-8 bytes
+    Sorted,
+    // The listed keys first, in that order, then any other keys in insertion
+    // order. Listed keys that are not present are skipped.
+    Template(&'a [&'a str]),
+    // By key as UTF-16 code units, which makes the body the RFC 8785 form of
+    // the params (see util::jcs)
+    Canonical,
+}
+
+impl BodyOrdering<'_> {
+    fn sort(&self, map: &mut IndexMap<&str, &str>) {
+        match self {
+            BodyOrdering::Insertion => {}
+            BodyOrdering::Sorted => map.sort_keys(),
+            BodyOrdering::Canonical => map.sort_by(|a, _, b, _| jcs::compare_keys(a, b)),
+            BodyOrdering::Template(keys) => {
+                let rank = |key: &str| keys.iter().position(|k| *k == key).unwrap_or(keys.len());
+                // Stable, so keys outside the template keep their order
+                map.sort_by(|a, _, b, _| rank(a).cmp(&rank(b)));
+            }
+        }
+    }
+}
+
+// A request parameter: any (key, value) pair of string-like types, or a reference
+// to one. This lets slices, Vecs of owned pairs and maps be passed as they are.
+pub trait ParamPair {
+    fn key(&self) -> &str;
+    fn value(&self) -> &str;
+}
+
+impl<K: AsRef<str>, V: AsRef<str>> ParamPair for (K, V) {
+    fn key(&self) -> &str {
+        self.0.as_ref()
+    }
+
+    fn value(&self) -> &str {
+        self.1.as_ref()
+    }
+}
+
+impl<T: ParamPair + ?Sized> ParamPair for &T {
+    fn key(&self) -> &str {
+        (**self).key()
+    }
+
+    fn value(&self) -> &str {
+        (**self).value()
+    }
+}
+
+// An ordered multimap of request parameters. It may be empty, for endpoints
+// without parameters, and keys may repeat (e.g. one `orderHash` per order).
+// GET/DELETE queries keep every pair, repeated keys in the order they were
+// pushed; POST/PUT bodies are JSON objects, where a repeated key keeps its
+// first position and its last value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Params(Vec<(String, String)>);
+
+impl Params {
+    pub fn new() -> Self {
+        Params(Vec::new())
+    }
+
+    pub fn push(&mut self, key: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        self.0.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.0
+            .iter()
+            .filter(move |(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for Params {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        Params(
+            iter.into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        )
+    }
+}
+
+impl IntoIterator for Params {
+    type Item = (String, String);
+    type IntoIter = std::vec::IntoIter<(String, String)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Params {
+    type Item = &'a (String, String);
+    type IntoIter = std::slice::Iter<'a, (String, String)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+// The methods the API signs requests for. GET/DELETE parameters are signed as
+// a query string, POST/PUT parameters as a JSON body. The API has no PATCH
+// endpoints, so there is no rule for how the relayer would sign one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HttpMethod {
+    Get,
+    Delete,
+    Post,
+    Put,
+}
+
+impl HttpMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HttpMethod::Get => "GET",
+            HttpMethod::Delete => "DELETE",
+            HttpMethod::Post => "POST",
+            HttpMethod::Put => "PUT",
+        }
+    }
+}
+
+// Case-insensitive, like the method names accepted by the string functions
+impl FromStr for HttpMethod {
+    type Err = RequestError;
+
+    fn from_str(method: &str) -> Result<Self, Self::Err> {
+        match method.to_uppercase().as_str() {
+            "GET" => Ok(HttpMethod::Get),
+            "DELETE" => Ok(HttpMethod::Delete),
+            "POST" => Ok(HttpMethod::Post),
+            "PUT" => Ok(HttpMethod::Put),
+            _ => Err(RequestError::UnsupportedMethod(method.to_string())),
+        }
+    }
+}
+
+// POST/PUT bodies keep the iteration order of `data`, so containers without a
+// stable order (HashMap) should only be used for GET/DELETE requests, or with
+// `post_body` and a sorted or template `BodyOrdering`
+pub fn generate_signature_base_string<I>(
     request_type: &str,
     url: &str,
-    data: &[(&str, &str)],
-) -> String {
-    const FRAGMENT: &AsciiSet = &CONTROLS.add(b':').add(b'/');
-    let method = request_type.to_uppercase();
+    data: I,
+) -> Result<String, RequestError>
+where
+    I: IntoIterator,
+    I::Item: ParamPair,
+{
+    generate_signature_base_string_with(request_type, url, data, ParamOrdering::default())
+}
 
-    let mut signature_base = format!("{}&{}&", &method, percent_encode(url.as_bytes(), FRAGMENT));
+pub fn generate_signature_base_string_with<I>(
+    request_type: &str,
+    url: &str,
+    data: I,
+    ordering: ParamOrdering,
+) -> Result<String, RequestError>
+where
+    I: IntoIterator,
+    I::Item: ParamPair,
+{
+    let method = HttpMethod::from_str(request_type)?;
+    Ok(signature_base_string(
+        method,
+        url,
+        data,
+        ordering,
+        EncodingProfile::default(),
+    ))
+}
+
+// The relayer verifies `EncodingProfile::LoopringLegacy`; see util::encoding
+pub fn signature_base_string<I>(
+    method: HttpMethod,
+    url: &str,
+    data: I,
+    ordering: ParamOrdering,
+    profile: EncodingProfile,
+) -> String
+where
+    I: IntoIterator,
+    I::Item: ParamPair,
+{
+    let pairs: Vec<I::Item> = data.into_iter().collect();
+    let data: Vec<(&str, &str)> = pairs.iter().map(|p| (p.key(), p.value())).collect();
+    let data = data.as_slice();
 
-    let params = match method.as_str() {
-        "GET" | "DELETE" => encode_get_delete_params(data),
-        "POST" | "PUT" => encode_post_put_params(data),
-        _ => "".to_string(),
+    let mut signature_base = format!("{}&{}&", method.as_str(), profile.encode_url(url));
+
+    let params = match method {
+        HttpMethod::Get | HttpMethod::Delete => encode_get_delete_params(data, ordering, profile),
+        HttpMethod::Post | HttpMethod::Put => encode_post_put_params(data, profile),
     };
     signature_base += params.as_str();
     signature_base
 }
 
-fn encode_get_delete_params(data: &[(&str, &str)]) -> String {
+fn encode_get_delete_params(
+    data: &[(&str, &str)],
+    ordering: ParamOrdering,
+    profile: EncodingProfile,
+) -> String {
     let mut sorted_data = data.to_vec();
-    sorted_data.sort_by(|a, b| a.0.cmp(&b.0));
+    ordering.sort(&mut sorted_data);
+    profile.encode_query(&sorted_data)
+}
+
+fn encode_post_put_params(data: &[(&str, &str)], profile: EncodingProfile) -> String {
+    profile.encode_body(&serialize_body(data, BodyOrdering::Insertion))
+}
+
+fn serialize_body(data: &[(&str, &str)], ordering: BodyOrdering) -> String {
+    // A repeated key keeps its first position and its last value
+    let mut map: IndexMap<_, _> = data.iter().cloned().collect();
+    ordering.sort(&mut map);
+    // Written by hand so that serializing cannot fail; strings are escaped by
+    // serde_json's Display
+    let members: Vec<String> = map
+        .iter()
+        .map(|(key, value)| format!("{}:{}", Value::from(*key), Value::from(*value)))
+        .collect();
+    format!("{{{}}}", members.join(","))
+}
 
-    let encoded_params = form_urlencoded::Serializer::new(String::new())
-        .extend_pairs(&sorted_data)
-        .finish();
+// The JSON body for POST/PUT params. The relayer hashes the body it receives,
+// so this exact string has to be sent, and signed with
+// `body_signature_base_string`.
+pub fn post_body<I>(data: I, ordering: BodyOrdering) -> String
+where
+    I: IntoIterator,
+    I::Item: ParamPair,
+{
+    let pairs: Vec<I::Item> = data.into_iter().collect();
+    let data: Vec<(&str, &str)> = pairs.iter().map(|p| (p.key(), p.value())).collect();
+    serialize_body(&data, ordering)
+}
+
+// Splits a full request url into the url the base string starts with (scheme,
+// host and path) and its query params, decoded as a form: '+' is a space and
+// %XX escapes are UTF-8 bytes. A fragment is dropped, a param without '=' has
+// an empty value.
+pub fn split_request_url(url: &str) -> Result<(&str, Params), RequestError> {
+    let url = url.split_once('#').map_or(url, |(url, _)| url);
+    let Some((base, query)) = url.split_once('?') else {
+        return Ok((url, Params::new()));
+    };
+    let params = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            Ok((form_decode(key)?, form_decode(value)?))
+        })
+        .collect::<Result<_, RequestError>>()?;
+    Ok((base, params))
+}
 
-    percent_encode(encoded_params.as_bytes(), NON_ALPHANUMERIC)
-        .to_string()
-        .replace("%2C", "%252C")
+fn form_decode(component: &str) -> Result<String, RequestError> {
+    let invalid = || RequestError::InvalidUrl(format!("bad escape in {:?}", component));
+    let mut bytes = Vec::with_capacity(component.len());
+    let mut rest = component.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let digits = rest
+                    .get(..2)
+                    .filter(|digits| digits.iter().all(u8::is_ascii_hexdigit))
+                    .ok_or_else(invalid)?;
+                // Two hex digits are ASCII and fit a byte
+                let digits = std::str::from_utf8(digits).unwrap();
+                bytes.push(u8::from_str_radix(digits, 16).unwrap());
+                rest = &rest[2..];
+            }
+            _ => bytes.push(byte),
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| invalid())
 }
 
-fn encode_post_put_params(data: &[(&str, &str)]) -> String {
-    let map: IndexMap<_, _> = data.iter().cloned().collect();
-    let json_string = serde_json::to_string(&map).expect("Failed to serialize");
-    let encoded_string: String =
-        percent_encode(json_string.as_bytes(), NON_ALPHANUMERIC).to_string();
-    encoded_string
-        .replace("!", "%21")
-        .replace("'", "%27")
-        .replace("(", "%28")
-        .replace(")", "%29")
+// Base string for a GET/DELETE request given as one url with its query; the
+// params are sorted and encoded as if they had been passed separately
+pub fn url_signature_base_string(request_type: &str, url: &str) -> Result<String, RequestError> {
+    let method = HttpMethod::from_str(request_type)?;
+    if matches!(method, HttpMethod::Post | HttpMethod::Put) {
+        return Err(RequestError::NoQuery(method.as_str()));
+    }
+    let (url, params) = split_request_url(url)?;
+    generate_signature_base_string(method.as_str(), url, params)
+}
+
+// Base string for a POST/PUT request whose body was already serialized, e.g.
+// by `post_body`. GET/DELETE requests are signed from their query instead.
+pub fn body_signature_base_string(
+    method: HttpMethod,
+    url: &str,
+    body: &str,
+    profile: EncodingProfile,
+) -> Result<String, RequestError> {
+    match method {
+        HttpMethod::Post | HttpMethod::Put => Ok(format!(
+            "{}&{}&{}",
+            method.as_str(),
+            profile.encode_url(url),
+            profile.encode_body(body)
+        )),
+        HttpMethod::Get | HttpMethod::Delete => Err(RequestError::NoBody(method.as_str())),
+    }
 }
 
 #[cfg(test)]
@@ -83,7 +451,8 @@ mod tests {
     fn test_base_signature() {
         let params: &[(&str, &str)] = &[("accountId", "11087")];
         let test =
-            generate_signature_base_string("get", "https://api3.loopring.io/api/v3/apiKey", params);
+            generate_signature_base_string("get", "https://api3.loopring.io/api/v3/apiKey", params)
+                .unwrap();
 
         assert_eq!(
             "GET&https%3A%2F%2Fapi3.loopring.io%2Fapi%2Fv3%2FapiKey&accountId%3D11087",
@@ -94,7 +463,8 @@ mod tests {
     fn test_base_signature_get_order() {
         let params: &[(&str, &str)] = &[("type", "12345"), ("accountId", "11087")];
         let test =
-            generate_signature_base_string("get", "https://api3.loopring.io/api/v3/apiKey", params);
+            generate_signature_base_string("get", "https://api3.loopring.io/api/v3/apiKey", params)
+                .unwrap();
 
         assert_eq!(
             "GET&https%3A%2F%2Fapi3.loopring.io%2Fapi%2Fv3%2FapiKey&accountId%3D11087%26type%3D12345",
@@ -105,7 +475,8 @@ mod tests {
     fn test_base_signature_get_comma() {
         let params: &[(&str, &str)] = &[("type", "123,45"), ("accountId", "11087")];
         let test =
-            generate_signature_base_string("get", "https://api3.loopring.io/api/v3/apiKey", params);
+            generate_signature_base_string("get", "https://api3.loopring.io/api/v3/apiKey", params)
+                .unwrap();
 
         assert_eq!(
             "GET&https%3A%2F%2Fapi3.loopring.io%2Fapi%2Fv3%2FapiKey&accountId%3D11087%26type%3D123%252C45",
@@ -119,9 +490,31 @@ mod tests {
             "POST",
             "https://api3.loopring.io/api/v3/apiKey",
             params,
-        );
+        )
+        .unwrap();
         assert_eq!("POST&https%3A%2F%2Fapi3.loopring.io%2Fapi%2Fv3%2FapiKey&%7B%22type%22%3A%2212345%22%2C%22accountId%22%3A%2211087%22%7D",test.as_str())
     }
+    #[test]
+    fn unsupported_methods_are_rejected() {
+        let url = "https://api3.loopring.io/api/v3/apiKey";
+        let params: &[(&str, &str)] = &[("accountId", "11087")];
+        assert_eq!(
+            generate_signature_base_string("PATCH", url, params),
+            Err(RequestError::UnsupportedMethod("PATCH".to_string()))
+        );
+        assert!(generate_signature_base_string("GTE", url, params).is_err());
+        assert_eq!(
+            generate_signature_base_string("delete", url, params).unwrap(),
+            signature_base_string(
+                HttpMethod::Delete,
+                url,
+                params,
+                ParamOrdering::default(),
+                EncodingProfile::default()
+            )
+        );
+    }
+
     #[test]
     fn sha256_snark_test() {
         let message = "GET&https%3A%2F%2Fapi3.loopring.io%2Fapi%2Fv3%2FapiKey&accountId%3D11087";
@@ -135,6 +528,23 @@ mod tests {
         )
     }
 
+    #[test]
+    fn request_hash_reductions() {
+        let message = "GET&https%3A%2F%2Fapi3.loopring.io%2Fapi%2Fv3%2FapiKey&accountId%3D11087";
+        assert_eq!(RequestHash::default().hash(message), sha256_snark(message));
+        let keccak = BigInt::from_bytes_be(Sign::Plus, &Keccak256::digest(message));
+        assert_eq!(
+            RequestHash::Keccak256.hash(message),
+            keccak % &*SNARK_SCALAR_FIELD
+        );
+        let poseidon = RequestHash::PoseidonBytes.hash(message);
+        assert!(poseidon < *SNARK_SCALAR_FIELD);
+        assert_eq!(
+            &to_bytes_32(&poseidon).unwrap()[..],
+            &PoseidonHasher::digest(message)[..]
+        );
+    }
+
     #[test]
     fn to_bytes_test() {
         let k = BigInt::one();
@@ -143,8 +553,8 @@ mod tests {
         )
         .unwrap();
 
-        let mut byte_array_0 = to_bytes_32(&k);
-        let byte_array_1 = to_bytes_32(&arg);
+        let mut byte_array_0 = to_bytes_32(&k).unwrap().to_vec();
+        let byte_array_1 = to_bytes_32(&arg).unwrap();
 
         byte_array_0.extend(byte_array_1);
         let expected = vec![
@@ -153,5 +563,193 @@ mod tests {
             106, 249, 114, 50, 52, 155, 182, 188, 18, 133, 216, 215, 20, 192, 45,
         ];
         assert_eq!(byte_array_0, expected);
+
+        let oversized = (BigInt::one() << 256) + &k;
+        assert!(to_bytes_32(&oversized).is_err());
+        assert_eq!(to_bytes_32_truncating(&oversized), to_bytes_32(&k).unwrap());
+    }
+
+    #[test]
+    fn test_param_ordering() {
+        let params: &[(&str, &str)] = &[("b", "1"), ("a", "2"), ("B", "3"), ("é", "4")];
+        let url = "https://api3.loopring.io/api/v3/apiKey";
+        let query = |ordering| {
+            let base = generate_signature_base_string_with("GET", url, params, ordering).unwrap();
+            base.rsplit('&').next().unwrap().to_string()
+        };
+
+        // Same order as Python's sorted() in the reference SDK
+        assert_eq!(
+            query(ParamOrdering::ByteWise),
+            "B%3D3%26a%3D2%26b%3D1%26%25C3%25A9%3D4"
+        );
+        assert_eq!(
+            query(ParamOrdering::CaseInsensitive),
+            "a%3D2%26B%3D3%26b%3D1%26%25C3%25A9%3D4"
+        );
+        assert_eq!(
+            query(ParamOrdering::Preserve),
+            "b%3D1%26a%3D2%26B%3D3%26%25C3%25A9%3D4"
+        );
+        assert_eq!(
+            generate_signature_base_string("GET", url, params),
+            generate_signature_base_string_with("GET", url, params, ParamOrdering::ByteWise)
+        );
+    }
+
+    #[test]
+    fn test_generic_param_containers() {
+        use std::collections::{BTreeMap, HashMap};
+
+        let url = "https://api3.loopring.io/api/v3/apiKey";
+        let expected = generate_signature_base_string(
+            "GET",
+            url,
+            &[("type", "12345"), ("accountId", "11087")],
+        );
+
+        let owned = vec![
+            ("type".to_string(), "12345".to_string()),
+            ("accountId".to_string(), "11087".to_string()),
+        ];
+        assert_eq!(generate_signature_base_string("GET", url, &owned), expected);
+
+        let hash_map: HashMap<String, String> = owned.iter().cloned().collect();
+        assert_eq!(
+            generate_signature_base_string("GET", url, &hash_map),
+            expected
+        );
+
+        let btree_map: BTreeMap<&str, String> = [
+            ("type", "12345".to_string()),
+            ("accountId", "11087".to_string()),
+        ]
+        .into();
+        assert_eq!(
+            generate_signature_base_string("GET", url, btree_map),
+            expected
+        );
+
+        assert_eq!(generate_signature_base_string("GET", url, owned), expected);
+    }
+
+    #[test]
+    fn empty_and_repeated_params() {
+        let url = "https://api3.loopring.io/api/v3/user/orders";
+        assert_eq!(
+            generate_signature_base_string("GET", url, Params::new()).unwrap(),
+            "GET&https%3A%2F%2Fapi3.loopring.io%2Fapi%2Fv3%2Fuser%2Forders&"
+        );
+        assert_eq!(
+            generate_signature_base_string("POST", url, Params::new()).unwrap(),
+            "POST&https%3A%2F%2Fapi3.loopring.io%2Fapi%2Fv3%2Fuser%2Forders&%7B%7D"
+        );
+
+        let mut params = Params::new();
+        params
+            .push("orderHash", "0xb")
+            .push("accountId", "1")
+            .push("orderHash", "0xa");
+        assert_eq!(
+            params.get_all("orderHash").collect::<Vec<_>>(),
+            ["0xb", "0xa"]
+        );
+        let base = generate_signature_base_string("DELETE", url, &params).unwrap();
+        assert!(base.ends_with("&accountId%3D1%26orderHash%3D0xb%26orderHash%3D0xa"));
+        assert_eq!(
+            params,
+            [
+                ("orderHash", "0xb"),
+                ("accountId", "1"),
+                ("orderHash", "0xa")
+            ]
+            .into_iter()
+            .collect()
+        );
+    }
+
+    #[test]
+    fn body_orderings() {
+        let data = [("type", "1"), ("accountId", "2"), ("memo", "3")];
+        assert_eq!(
+            post_body(data, BodyOrdering::Insertion),
+            r#"{"type":"1","accountId":"2","memo":"3"}"#
+        );
+        assert_eq!(
+            post_body(data, BodyOrdering::Sorted),
+            r#"{"accountId":"2","memo":"3","type":"1"}"#
+        );
+        assert_eq!(
+            post_body(data, BodyOrdering::Template(&["memo", "missing", "type"])),
+            r#"{"memo":"3","type":"1","accountId":"2"}"#
+        );
+        // UTF-16 order puts U+FB33 after U+1F600, UTF-8 order before it
+        let data = [("\u{fb33}", "1"), ("\u{1f600}", "2"), ("a\n", "3")];
+        let canonical = post_body(data, BodyOrdering::Canonical);
+        assert_eq!(
+            canonical,
+            "{\"a\\n\":\"3\",\"\u{1f600}\":\"2\",\"\u{fb33}\":\"1\"}"
+        );
+        assert_eq!(jcs::canonicalize_body(&canonical).unwrap(), canonical);
+
+        let url = "https://api3.loopring.io/api/v3/apiKey";
+        let body = post_body(data, BodyOrdering::Insertion);
+        assert_eq!(
+            body_signature_base_string(HttpMethod::Post, url, &body, EncodingProfile::default()),
+            generate_signature_base_string("POST", url, data)
+        );
+        assert_eq!(
+            body_signature_base_string(HttpMethod::Get, url, &body, EncodingProfile::default()),
+            Err(RequestError::NoBody("GET"))
+        );
+    }
+
+    #[test]
+    fn post_params_match_serde_json() {
+        let data = [
+            ("memo", "say \"hi\"\n"),
+            ("accountId", "1"),
+            ("memo", "last"),
+        ];
+        let map: IndexMap<_, _> = data.iter().cloned().collect();
+        let expected =
+            EncodingProfile::LoopringLegacy.encode_body(&serde_json::to_string(&map).unwrap());
+        assert_eq!(
+            encode_post_put_params(&data, EncodingProfile::LoopringLegacy),
+            expected
+        );
+    }
+
+    #[test]
+    fn request_urls() {
+        let url = "https://api3.loopring.io/api/v3/user/balances";
+        let query = "?tokens=0%2C1&accountId=1&memo=a+b&flag&&x=%E2%82%AC#top";
+        let full_url = format!("{}{}", url, query);
+        let (base, params) = split_request_url(&full_url).unwrap();
+        assert_eq!(base, url);
+        let expected = [
+            ("tokens", "0,1"),
+            ("accountId", "1"),
+            ("memo", "a b"),
+            ("flag", ""),
+            ("x", "\u{20ac}"),
+        ];
+        assert_eq!(params, expected.into_iter().collect());
+        assert_eq!(
+            url_signature_base_string("get", &format!("{}?tokens=0,1&accountId=1", url)),
+            generate_signature_base_string("GET", url, [("accountId", "1"), ("tokens", "0,1")])
+        );
+        assert_eq!(split_request_url(url).unwrap(), (url, Params::new()));
+
+        for bad in ["?a=%2", "?a=%zz", "?a=%+1", "?a=%FF"] {
+            assert!(matches!(
+                split_request_url(&format!("{}{}", url, bad)),
+                Err(RequestError::InvalidUrl(_))
+            ));
+        }
+        assert_eq!(
+            url_signature_base_string("POST", url),
+            Err(RequestError::NoQuery("POST"))
+        );
     }
 }
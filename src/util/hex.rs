@@ -0,0 +1,195 @@
+// Tolerant hex parsing for keys, signatures and seeds.
+//
+// Values are pasted from wallets, API responses and config files, so the same
+// value shows up as "0xABcd...", "0Xabcd...", "abcd..." or with a line break in
+// the middle. Everything is normalized the same way before decoding:
+//
+//  * whitespace anywhere in the string is ignored
+//  * an optional 0x / 0X prefix is removed
+//  * digits may be upper- or lowercase
+//  * an odd number of digits is read as if it had a leading zero
+//
+// Anything else (no digits at all, non-hex characters) is a HexError.
+//
+// Secrets (private keys) go through `decode_secret` / `encode_secret` instead.
+// They accept the same shapes, but map every digit without branches or table
+// lookups that depend on its value, and decode into a fixed 32 bytes, so the
+// timing only depends on the length of the input. Errors do not echo the
+// offending character.
+
+use crate::util::errors::HexError;
+use num_bigint::{BigInt, Sign};
+
+// Removes whitespace and the prefix without validating the digits, for callers
+// that split fixed-width values (e.g. signatures) before decoding the parts
+pub fn strip(input: &str) -> String {
+    let compact: String = input.chars().filter(|c| !c.is_whitespace()).collect();
+    match compact
+        .strip_prefix("0x")
+        .or_else(|| compact.strip_prefix("0X"))
+    {
+        Some(digits) => digits.to_string(),
+        None => compact,
+    }
+}
+
+// Returns the lowercase digits without prefix, padded to an even length
+pub fn normalize(input: &str) -> Result<String, HexError> {
+    let digits = strip(input);
+    if digits.is_empty() {
+        return Err(HexError::Empty);
+    }
+    if let Some(c) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(HexError::InvalidCharacter(c));
+    }
+
+    let mut normalized = String::with_capacity(digits.len() + 1);
+    if digits.len() % 2 == 1 {
+        normalized.push('0');
+    }
+    normalized.push_str(&digits.to_ascii_lowercase());
+    Ok(normalized)
+}
+
+pub fn decode_bytes(input: &str) -> Result<Vec<u8>, HexError> {
+    let normalized = normalize(input)?;
+    // normalize only lets through an even number of hex digits
+    Ok(::hex::decode(normalized).unwrap())
+}
+
+pub fn decode_bigint(input: &str) -> Result<BigInt, HexError> {
+    Ok(BigInt::from_bytes_be(Sign::Plus, &decode_bytes(input)?))
+}
+
+// 0xff if low <= c <= high, 0 otherwise
+fn ct_in_range(c: u8, low: u8, high: u8) -> u8 {
+    let below = (c as i16) - (low as i16);
+    let above = (high as i16) - (c as i16);
+    // Either difference is negative exactly when c is outside the range
+    !(((below | above) >> 8) as u8)
+}
+
+// The digit's value and a mask that is 0xff if it is a hex digit
+fn ct_decode_nibble(c: u8) -> (u8, u8) {
+    let lower = c | 0x20;
+    let is_digit = ct_in_range(c, b'0', b'9');
+    let is_letter = ct_in_range(lower, b'a', b'f');
+    let value = (is_digit & c.wrapping_sub(b'0')) | (is_letter & lower.wrapping_sub(b'a' - 10));
+    (value, is_digit | is_letter)
+}
+
+// Lowercase digit of a value below 16
+fn ct_encode_nibble(n: u8) -> u8 {
+    // 0xff if n > 9
+    let letter = ((9i16 - n as i16) >> 8) as u8;
+    n + b'0' + (letter & (b'a' - b'0' - 10))
+}
+
+// Decodes up to 64 digits into 32 big-endian bytes
+pub fn decode_secret_bytes(input: &str) -> Result<[u8; 32], HexError> {
+    let digits = strip(input);
+    let digits = digits.as_bytes();
+    if digits.is_empty() {
+        return Err(HexError::Empty);
+    }
+    if digits.len() > 64 {
+        return Err(HexError::InvalidSecret);
+    }
+
+    let mut bytes = [0u8; 32];
+    let mut valid = 0xffu8;
+    // Digit i (from the right) goes into the low or high half of byte 31 - i / 2
+    for (i, &c) in digits.iter().rev().enumerate() {
+        let (value, is_hex) = ct_decode_nibble(c);
+        valid &= is_hex;
+        bytes[31 - i / 2] |= value << (4 * (i % 2));
+    }
+    if valid != 0xff {
+        return Err(HexError::InvalidSecret);
+    }
+    Ok(bytes)
+}
+
+pub fn decode_secret(input: &str) -> Result<BigInt, HexError> {
+    Ok(BigInt::from_bytes_be(
+        Sign::Plus,
+        &decode_secret_bytes(input)?,
+    ))
+}
+
+// 0x-prefixed, 64 lowercase digits
+pub fn encode_secret_bytes(bytes: &[u8; 32]) -> String {
+    let mut encoded = Vec::with_capacity(66);
+    encoded.extend_from_slice(b"0x");
+    for &byte in bytes {
+        encoded.push(ct_encode_nibble(byte >> 4));
+        encoded.push(ct_encode_nibble(byte & 0x0f));
+    }
+    // Only ASCII digits and letters were pushed
+    String::from_utf8(encoded).unwrap()
+}
+
+// Secrets are scalars below JUBJUB_L and always fit into 32 bytes; a wider
+// value is a caller bug, and release builds keep only its low 32 bytes
+pub fn encode_secret(value: &BigInt) -> String {
+    let (_, le) = value.to_bytes_le();
+    debug_assert!(le.len() <= 32, "secret wider than 32 bytes");
+    let mut bytes = [0u8; 32];
+    for (i, byte) in le.iter().take(32).enumerate() {
+        bytes[31 - i] = *byte;
+    }
+    encode_secret_bytes(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_common_shapes() {
+        for input in ["0x0abc", "0X0ABC", "abc", " 0x0a\nbc ", "0xABC"] {
+            assert_eq!(decode_bytes(input).unwrap(), vec![0x0a, 0xbc], "{}", input);
+        }
+        assert_eq!(decode_bigint("0x00ff").unwrap(), BigInt::from(255));
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert_eq!(decode_bytes("0x"), Err(HexError::Empty));
+        assert_eq!(decode_bytes("  "), Err(HexError::Empty));
+        assert_eq!(decode_bytes("0xabg"), Err(HexError::InvalidCharacter('g')));
+        assert_eq!(decode_bytes("-1"), Err(HexError::InvalidCharacter('-')));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "secret wider than 32 bytes")]
+    fn rejects_wide_secrets() {
+        encode_secret(&(BigInt::from(1) << 256));
+    }
+
+    #[test]
+    fn secrets_round_trip() {
+        let key = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+        let value = decode_secret(key).unwrap();
+        assert_eq!(value, decode_bigint(key).unwrap());
+        assert_eq!(encode_secret(&value), key);
+        assert_eq!(decode_secret(" 0XABC").unwrap(), BigInt::from(0xabc));
+        assert_eq!(
+            encode_secret(&BigInt::from(0xabc)),
+            format!("0x{:0>64}", "abc")
+        );
+
+        assert_eq!(decode_secret("0x"), Err(HexError::Empty));
+        assert_eq!(decode_secret("0xabg"), Err(HexError::InvalidSecret));
+        assert_eq!(decode_secret("0x:0"), Err(HexError::InvalidSecret));
+        assert_eq!(decode_secret(&"1".repeat(65)), Err(HexError::InvalidSecret));
+        for c in 0..=255u8 {
+            let (value, valid) = ct_decode_nibble(c);
+            match (c as char).to_digit(16) {
+                Some(digit) => assert_eq!((value, valid), (digit as u8, 0xff)),
+                None => assert_eq!(valid, 0),
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+// JSON Canonicalization Scheme (RFC 8785) for POST/PUT bodies.
+//
+// The relayer hashes the body exactly as it is sent, so a signature only matches
+// if signer and sender agree on every byte. JCS fixes that byte string for any
+// JSON value: no whitespace, object members sorted by their keys as UTF-16 code
+// units, strings with the minimal escaping of ECMAScript's JSON.stringify, and
+// numbers as ECMAScript prints IEEE doubles. Libraries in other languages
+// (canonicalize for JavaScript, jcs for Python, ...) produce the same string, so
+// clients that sign a canonicalized body get identical signatures.
+//
+// Canonicalization is opt-in: bodies from `post_body` keep their ordering unless
+// BodyOrdering::Canonical is used, and a canonicalized body has to be the one
+// that is sent. Integers beyond 2^53 lose precision as they do in JSON.stringify;
+// send them as strings. A repeated key keeps its last value.
+
+use crate::util::errors::RequestError;
+use serde_json::Value;
+use std::cmp::Ordering;
+
+// The RFC 8785 order of two member names
+pub(crate) fn compare_keys(a: &str, b: &str) -> Ordering {
+    a.encode_utf16().cmp(b.encode_utf16())
+}
+
+pub fn canonicalize(value: &Value) -> String {
+    let mut out = String::new();
+    write_value(value, &mut out);
+    out
+}
+
+// Parses and canonicalizes a serialized body
+pub fn canonicalize_body(body: &str) -> Result<String, RequestError> {
+    let value: Value =
+        serde_json::from_str(body).map_err(|e| RequestError::InvalidBody(e.to_string()))?;
+    Ok(canonicalize(&value))
+}
+
+fn write_value(value: &Value, out: &mut String) {
+    match value {
+        Value::Null | Value::Bool(_) | Value::String(_) => {
+            // serde_json escapes strings like JSON.stringify: the short forms for
+            // \b \t \n \f \r, \u00xx for other controls, everything else literal
+            out.push_str(&value.to_string())
+        }
+        Value::Number(n) => out.push_str(&format_number(n.as_f64().unwrap_or(0.0))),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(members) => {
+            let mut members: Vec<_> = members.iter().collect();
+            members.sort_by(|(a, _), (b, _)| compare_keys(a, b));
+            out.push('{');
+            for (i, (key, member)) in members.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::from(key.as_str()).to_string());
+                out.push(':');
+                write_value(member, out);
+            }
+            out.push('}');
+        }
+    }
+}
+
+// Number::toString of ECMAScript (ECMA-262, 7.1.12.1) for a finite double.
+// Rust's `{:e}` gives the same shortest round-trip digits, only the placement
+// of the decimal point differs.
+fn format_number(x: f64) -> String {
+    if x == 0.0 {
+        return "0".to_string();
+    }
+    let sign = if x < 0.0 { "-" } else { "" };
+    let scientific = format!("{:e}", x.abs());
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    // x = 0.digits * 10^n
+    let n = exponent.parse::<i32>().unwrap() + 1;
+    let k = digits.len() as i32;
+
+    let body = if k <= n && n <= 21 {
+        format!("{}{}", digits, "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{}", "0".repeat((-n) as usize), digits)
+    } else {
+        let e = n - 1;
+        let exponent = format!("e{}{}", if e < 0 { '-' } else { '+' }, e.abs());
+        match digits.split_at(1) {
+            (first, "") => format!("{}{}", first, exponent),
+            (first, rest) => format!("{}.{}{}", first, rest, exponent),
+        }
+    };
+    format!("{}{}", sign, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_print_like_ecmascript() {
+        // The number samples of RFC 8785, appendix B
+        let samples: [(f64, &str); 14] = [
+            (0.0, "0"),
+            (-0.0, "0"),
+            (5e-324, "5e-324"),
+            (-5e-324, "-5e-324"),
+            (1.7976931348623157e308, "1.7976931348623157e+308"),
+            (9007199254740992.0, "9007199254740992"),
+            (-9007199254740992.0, "-9007199254740992"),
+            (295147905179352830000.0, "295147905179352830000"),
+            (9.999999999999997e22, "9.999999999999997e+22"),
+            (1e23, "1e+23"),
+            (1e21, "1e+21"),
+            (999999999999999700000.0, "999999999999999700000"),
+            (0.000001, "0.000001"),
+            (0.0000001, "1e-7"),
+        ];
+        for (x, expected) in samples {
+            assert_eq!(format_number(x), expected, "{:e}", x);
+        }
+        assert_eq!(format_number(333333333.3333333), "333333333.3333333");
+        assert_eq!(format_number(-1.5), "-1.5");
+    }
+
+    #[test]
+    fn bodies_are_canonical() {
+        // RFC 8785, section 3.2.2
+        let body = r#"{
+            "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+            "string": "€$\u000F\u000aA'B\u0022\u005c\u005c\u0022\/",
+            "literals": [null, true, false]
+        }"#;
+        assert_eq!(
+            canonicalize_body(body).unwrap(),
+            r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+        );
+
+        // Sorted by UTF-16 code units: U+1F600 (a surrogate pair) before U+FB33
+        let body = r#"{"דּ": 2, "😀": 1, "a": {"z": 0, "b": [{}]}, "A": 3}"#;
+        assert_eq!(
+            canonicalize_body(body).unwrap(),
+            "{\"A\":3,\"a\":{\"b\":[{}],\"z\":0},\"\u{1f600}\":1,\"\u{fb33}\":2}"
+        );
+        assert!(matches!(
+            canonicalize_body("{\"a\":"),
+            Err(RequestError::InvalidBody(_))
+        ));
+    }
+}
@@ -0,0 +1,92 @@
+// Modular arithmetic helpers.
+//
+// num-bigint's modpow and modinv panic on a zero modulus, and inverting by
+// Fermat (n^(p-2) mod p) quietly turns 0 into 0 and is only right for prime p.
+// These return None instead, so a non-invertible value is a visible case at the
+// call site rather than a panic or a wrong field element.
+
+use num_bigint::{BigInt, BigUint};
+use num_traits::{Euclid, Zero};
+
+// The n with a * n = 1 mod m, or None if there is none (gcd(a, m) != 1, which
+// includes a = 0 mod m, or m = 0). Works for any modulus, prime or not.
+pub fn mod_inverse(a: &BigUint, m: &BigUint) -> Option<BigUint> {
+    if m.is_zero() {
+        return None;
+    }
+    a.modinv(m)
+}
+
+// base^exponent mod m, or None for m = 0
+pub fn mod_pow(base: &BigUint, exponent: &BigUint, m: &BigUint) -> Option<BigUint> {
+    if m.is_zero() {
+        return None;
+    }
+    Some(base.modpow(exponent, m))
+}
+
+// a mod m in [0, m), also for negative a (-1 mod p is p - 1), or None for m = 0
+pub fn rem_euclid(a: &BigInt, m: &BigUint) -> Option<BigUint> {
+    if m.is_zero() {
+        return None;
+    }
+    Some(a.rem_euclid(&BigInt::from(m.clone())).magnitude().clone())
+}
+
+// (a - b) mod m without going negative, or None for m = 0
+pub fn mod_sub(a: &BigUint, b: &BigUint, m: &BigUint) -> Option<BigUint> {
+    if m.is_zero() {
+        return None;
+    }
+    Some((a % m + m - b % m) % m)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poseidon::field::SNARK_SCALAR_FIELD;
+    use num_traits::One;
+
+    #[test]
+    fn inverts_only_invertible_values() {
+        let p = SNARK_SCALAR_FIELD.to_biguint().unwrap();
+        let a = BigUint::from(123456789u32);
+        let inverse = mod_inverse(&a, &p).unwrap();
+        assert_eq!(&a * &inverse % &p, BigUint::one());
+        assert_eq!(
+            mod_pow(&a, &(&p - 2u8), &p),
+            Some(inverse),
+            "agrees with Fermat for a prime modulus"
+        );
+
+        assert_eq!(mod_inverse(&BigUint::zero(), &p), None);
+        assert_eq!(mod_inverse(&p, &p), None);
+        // Composite modulus: 4 shares a factor with 10, 3 does not
+        let ten = BigUint::from(10u8);
+        assert_eq!(mod_inverse(&BigUint::from(4u8), &ten), None);
+        assert_eq!(
+            mod_inverse(&BigUint::from(3u8), &ten),
+            Some(BigUint::from(7u8))
+        );
+        assert_eq!(mod_inverse(&a, &BigUint::zero()), None);
+        assert_eq!(mod_pow(&a, &a, &BigUint::zero()), None);
+    }
+
+    #[test]
+    fn reduces_into_range() {
+        let m = BigUint::from(7u8);
+        assert_eq!(rem_euclid(&BigInt::from(-1), &m), Some(BigUint::from(6u8)));
+        assert_eq!(rem_euclid(&BigInt::from(-14), &m), Some(BigUint::zero()));
+        assert_eq!(rem_euclid(&BigInt::from(15), &m), Some(BigUint::one()));
+        assert_eq!(rem_euclid(&BigInt::from(3), &BigUint::zero()), None);
+
+        assert_eq!(
+            mod_sub(&BigUint::from(2u8), &BigUint::from(5u8), &m),
+            Some(BigUint::from(4u8))
+        );
+        assert_eq!(
+            mod_sub(&BigUint::from(20u8), &BigUint::from(6u8), &m),
+            Some(BigUint::zero())
+        );
+    }
+}
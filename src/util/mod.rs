@@ -1,2 +1,10 @@
+pub mod address;
+pub mod chain;
+pub mod encoding;
 pub mod errors;
+pub mod fixtures;
 pub mod helpers;
+pub mod hex;
+pub mod jcs;
+pub mod math;
+pub mod time;
@@ -0,0 +1,123 @@
+// Expiry timestamps for payloads.
+//
+// Loopring's validUntil (and validSince) fields are 32-bit Unix timestamps in
+// whole seconds. Payload code takes the current time from a `Clock` instead of
+// calling SystemTime directly, so tests and deterministic replays can pin it.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub trait Clock {
+    // Seconds since the Unix epoch
+    fn now(&self) -> u64;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0)
+    }
+}
+
+pub struct FixedClock(pub u64);
+
+impl Clock for FixedClock {
+    fn now(&self) -> u64 {
+        self.0
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> u64 {
+        (**self).now()
+    }
+}
+
+// `now + ttl`. The current time is truncated to whole seconds and a fractional
+// ttl is rounded up, so the payload never expires earlier than requested.
+// Returns None if the result does not fit into the 32-bit field.
+pub fn valid_until(clock: &impl Clock, ttl: Duration) -> Option<u32> {
+    let ttl_secs = ttl.as_secs() + u64::from(ttl.subsec_nanos() > 0);
+    let timestamp = clock.now().checked_add(ttl_secs)?;
+    u32::try_from(timestamp).ok()
+}
+
+pub fn valid_since(clock: &impl Clock) -> Option<u32> {
+    u32::try_from(clock.now()).ok()
+}
+
+// ISO 8601 in UTC, e.g. "2023-11-14T22:13:20Z"
+pub fn format_utc(timestamp: u64) -> String {
+    let (days, seconds) = (timestamp / 86_400, timestamp % 86_400);
+    // Days to a proleptic Gregorian date, counted in 400-year eras from
+    // 0000-03-01 so that leap days fall at the end of each year
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3_600,
+        seconds % 3_600 / 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_until_with_fixed_clock() {
+        let clock = FixedClock(1_700_000_000);
+
+        assert_eq!(
+            valid_until(&clock, Duration::from_secs(60 * 60 * 24 * 30)),
+            Some(1_702_592_000)
+        );
+        assert_eq!(
+            valid_until(&clock, Duration::from_millis(1500)),
+            Some(1_700_000_002)
+        );
+        assert_eq!(valid_since(&clock), Some(1_700_000_000));
+    }
+
+    #[test]
+    fn valid_until_overflow() {
+        let clock = FixedClock(u32::MAX as u64);
+        assert_eq!(valid_until(&clock, Duration::from_secs(1)), None);
+        assert_eq!(
+            valid_until(&FixedClock(u64::MAX), Duration::from_secs(1)),
+            None
+        );
+    }
+
+    #[test]
+    fn system_clock_is_after_2023() {
+        assert!(SystemClock.now() > 1_672_531_200);
+    }
+
+    #[test]
+    fn formats_utc() {
+        assert_eq!(format_utc(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_utc(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_utc(1_700_000_000), "2023-11-14T22:13:20Z");
+        assert_eq!(format_utc(u32::MAX as u64), "2106-02-07T06:28:15Z");
+    }
+}
@@ -0,0 +1,310 @@
+/*
+JavaScript bindings (`wasm` feature), for wasm32-unknown-unknown:
+
+    wasm-pack build --target web -- --features wasm
+
+The feature enables getrandom's `js` backend, so random keys
+(generateDualAuthKey) come from crypto.getRandomValues in browsers and from the
+crypto module in Node. previewPayload renders what a payload authorizes for an
+approval dialog (see payload::preview). runDiagnostics runs the randomized
+round trips of the diagnostics module, to check a new runtime before relying
+on it.
+
+Payloads and requests are passed as JSON strings in the payload-file format
+(see payload::file) and the stdio request format (see signer::stdio); results
+are plain objects. Failures are thrown as `Error`s with a stable `code` next to
+the message, so callers can branch on them:
+
+    try {
+        signPayload(JSON.stringify(transfer), l2Key);
+    } catch (e) {
+        if (e.code === "INVALID_PAYLOAD") { ... }
+    }
+
+Codes: INVALID_INPUT (not JSON, malformed hex), INVALID_KEY, INVALID_PAYLOAD,
+INVALID_REQUEST, INVALID_SIGNATURE, KEY_DERIVATION, RANDOMNESS_UNAVAILABLE and
+SIGNER (any other key store failure). Invalid input never panics.
+*/
+
+use crate::diagnostics::run_random_diagnostics;
+use crate::keygen::{dual_auth::DualAuthKey, l2_key};
+use crate::payload::preview::SigningPreview;
+use crate::payload::token::{TokenInfo, TokenRegistry};
+use crate::payload::{bundle::payload_hash, hash_to_hex};
+use crate::poseidon::eddsa::{verify_eddsa_hex, MessageHash};
+use crate::signer::stdio::request_base_string;
+use crate::signer::{LocalSigner, Signer};
+use crate::util::errors::{PayloadError, SignatureError, SignerError};
+use crate::util::hex;
+use crate::util::time::Clock;
+use serde_json::{json, Value};
+use wasm_bindgen::prelude::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WasmError {
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl WasmError {
+    fn new(code: &'static str, message: impl ToString) -> Self {
+        WasmError {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<PayloadError> for WasmError {
+    fn from(e: PayloadError) -> Self {
+        match e {
+            PayloadError::InvalidPrivateKey => WasmError::new("INVALID_KEY", e),
+            _ => WasmError::new("INVALID_PAYLOAD", e),
+        }
+    }
+}
+
+impl From<SignerError> for WasmError {
+    fn from(e: SignerError) -> Self {
+        match e {
+            SignerError::InvalidPrivateKey => WasmError::new("INVALID_KEY", e),
+            _ => WasmError::new("SIGNER", e),
+        }
+    }
+}
+
+impl From<SignatureError> for WasmError {
+    fn from(e: SignatureError) -> Self {
+        WasmError::new("INVALID_SIGNATURE", e)
+    }
+}
+
+// An Error with the code as its `code` property
+impl From<WasmError> for JsValue {
+    fn from(e: WasmError) -> Self {
+        let error = js_sys::Error::new(&e.message);
+        let _ = js_sys::Reflect::set(&error, &"code".into(), &e.code.into());
+        error.into()
+    }
+}
+
+fn parse_json(input: &str) -> Result<Value, WasmError> {
+    serde_json::from_str(input).map_err(|e| WasmError::new("INVALID_INPUT", e))
+}
+
+fn key_pair(private_key: String, signer: &impl Signer) -> Value {
+    let public_key = signer.public_key();
+    json!({
+        "privateKey": private_key,
+        "publicKey": {"x": public_key.x().to_hex(), "y": public_key.y().to_hex()},
+    })
+}
+
+fn to_js(value: &Value) -> JsValue {
+    // serde_json output is always valid JSON
+    js_sys::JSON::parse(&value.to_string()).unwrap_or(JsValue::NULL)
+}
+
+fn l2_keys(signature: &str) -> Result<Value, WasmError> {
+    let account = l2_key::generate_l2_keys(signature.to_string())
+        .map_err(|e| WasmError::new("KEY_DERIVATION", e))?;
+    Ok(json!({
+        "privateKey": account.private_key,
+        "publicKey": {"x": account.public_key_x, "y": account.public_key_y},
+    }))
+}
+
+fn dual_auth_key() -> Result<Value, WasmError> {
+    let key = DualAuthKey::generate().map_err(|e| match e {
+        SignerError::Unavailable(_) => WasmError::new("RANDOMNESS_UNAVAILABLE", e),
+        e => e.into(),
+    })?;
+    Ok(key_pair(key.private_key_hex(), &key))
+}
+
+fn sign_payload_json(payload: &str, private_key: &str) -> Result<Value, WasmError> {
+    let payload = parse_json(payload)?;
+    let signer = LocalSigner::from_hex(private_key)?;
+    let hash = payload_hash(&payload)?;
+    let signed = signer.sign(&MessageHash::new(hash.clone()))?;
+    Ok(json!({
+        "type": payload["type"],
+        "hash": hash_to_hex(&hash),
+        "signature": signed.to_hex(),
+    }))
+}
+
+fn sign_request_json(request: &str, private_key: &str) -> Result<String, WasmError> {
+    let request = parse_json(request)?;
+    let signer = LocalSigner::from_hex(private_key)?;
+    let base = request_base_string(&request).map_err(|e| WasmError::new("INVALID_REQUEST", e))?;
+    Ok(signer.sign(&MessageHash::from_message(&base))?.to_hex())
+}
+
+fn verify(
+    signature: &str,
+    public_key_x: &str,
+    public_key_y: &str,
+    hash: &str,
+) -> Result<bool, WasmError> {
+    let hash = hex::decode_bigint(hash).map_err(|e| WasmError::new("INVALID_INPUT", e))?;
+    Ok(verify_eddsa_hex(
+        signature,
+        public_key_x,
+        public_key_y,
+        &hash,
+    )?)
+}
+
+// SystemTime is not available on wasm32-unknown-unknown
+struct JsClock;
+
+impl Clock for JsClock {
+    fn now(&self) -> u64 {
+        (js_sys::Date::now() / 1000.0) as u64
+    }
+}
+
+// `tokens` in the form of GET /api/v3/exchange/tokens: [{tokenId, symbol, decimals}]
+fn preview_json(payload: &str, tokens: &str, clock: &impl Clock) -> Result<Value, WasmError> {
+    let payload = parse_json(payload)?;
+    let tokens = match parse_json(tokens)? {
+        Value::Array(tokens) => tokens
+            .iter()
+            .map(|token| {
+                match (
+                    token["tokenId"]
+                        .as_u64()
+                        .and_then(|id| u32::try_from(id).ok()),
+                    token["symbol"].as_str(),
+                    token["decimals"]
+                        .as_u64()
+                        .and_then(|d| u32::try_from(d).ok()),
+                ) {
+                    (Some(id), Some(symbol), Some(decimals)) => {
+                        Ok(TokenInfo::new(id, symbol, decimals))
+                    }
+                    _ => Err(WasmError::new(
+                        "INVALID_INPUT",
+                        "tokens must be {tokenId, symbol, decimals} objects",
+                    )),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        _ => return Err(WasmError::new("INVALID_INPUT", "tokens must be a list")),
+    };
+    let tokens = TokenRegistry::with_tokens(tokens);
+    Ok(SigningPreview::for_value(&payload, &tokens, clock)?.to_json())
+}
+
+// The account key derived from the owner's keySeed signature
+#[wasm_bindgen(js_name = generateL2Keys)]
+pub fn generate_l2_keys(signature: &str) -> Result<JsValue, JsValue> {
+    Ok(to_js(&l2_keys(signature)?))
+}
+
+#[wasm_bindgen(js_name = generateDualAuthKey)]
+pub fn generate_dual_auth_key() -> Result<JsValue, JsValue> {
+    Ok(to_js(&dual_auth_key()?))
+}
+
+// {type, hash, signature} for a transfer or order
+#[wasm_bindgen(js_name = signPayload)]
+pub fn sign_payload(payload: &str, private_key: &str) -> Result<JsValue, JsValue> {
+    Ok(to_js(&sign_payload_json(payload, private_key)?))
+}
+
+// The X-API-SIG value of an API request
+#[wasm_bindgen(js_name = signRequest)]
+pub fn sign_request(request: &str, private_key: &str) -> Result<String, JsValue> {
+    Ok(sign_request_json(request, private_key)?)
+}
+
+// What signing `payload` authorizes, for approval dialogs (see payload::preview)
+#[wasm_bindgen(js_name = previewPayload)]
+pub fn preview_payload(payload: &str, tokens: &str) -> Result<JsValue, JsValue> {
+    Ok(to_js(&preview_json(payload, tokens, &JsClock)?))
+}
+
+// {seed, iterations, checksRun, ok, failures: [{check, iteration, detail}]}
+#[wasm_bindgen(js_name = runDiagnostics)]
+pub fn run_diagnostics(iterations: u32) -> Result<JsValue, JsValue> {
+    let report = run_random_diagnostics(iterations as usize)
+        .map_err(|e| WasmError::new("RANDOMNESS_UNAVAILABLE", e))?;
+    Ok(to_js(&report.to_json()))
+}
+
+#[wasm_bindgen(js_name = verifySignature)]
+pub fn verify_signature(
+    signature: &str,
+    public_key_x: &str,
+    public_key_y: &str,
+    hash: &str,
+) -> Result<bool, JsValue> {
+    Ok(verify(signature, public_key_x, public_key_y, hash)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload::transfer::tests::transfer;
+    use crate::util::time::FixedClock;
+
+    const L2_KEY: &str = "0x087d254d02a857d215c4c14d72521f8ab6a81ec8f0107eaf16093ebb7c70dc50";
+
+    const TRANSFER: &str = r#"{"type": "transfer",
+        "exchange": "0x0BABA1Ad5bE3a5C0a66E7ac838a129Bf948f1eA4",
+        "payerId": 11087, "payerAddr": "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        "payeeId": 0, "payeeAddr": "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+        "token": {"tokenId": 1, "volume": "1000000000000000000"},
+        "maxFee": {"tokenId": 0, "volume": "100000000000000"},
+        "validUntil": 1700000000, "storageId": 5}"#;
+
+    #[test]
+    fn signs_and_verifies_payloads() {
+        let signed = sign_payload_json(TRANSFER, L2_KEY).unwrap();
+        assert_eq!(signed["signature"], transfer().sign(L2_KEY).unwrap());
+
+        let public_key = LocalSigner::from_hex(L2_KEY).unwrap().public_key().clone();
+        let (x, y) = (public_key.x().to_hex(), public_key.y().to_hex());
+        let signature = signed["signature"].as_str().unwrap();
+        let hash = signed["hash"].as_str().unwrap();
+        assert_eq!(verify(signature, &x, &y, hash), Ok(true));
+        assert_eq!(verify(signature, &x, &y, "0x01"), Ok(false));
+
+        let tokens = r#"[{"tokenId": 0, "symbol": "ETH", "decimals": 18},
+                         {"tokenId": 1, "symbol": "LRC", "decimals": 18}]"#;
+        let preview = preview_json(TRANSFER, tokens, &FixedClock(0)).unwrap();
+        assert_eq!(preview["amounts"][0]["display"], "1 LRC");
+        assert_eq!(preview["hash"], signed["hash"]);
+
+        let key = dual_auth_key().unwrap();
+        assert!(key["privateKey"].as_str().unwrap().starts_with("0x"));
+    }
+
+    #[test]
+    fn maps_failures_to_codes() {
+        let code = |result: Result<Value, WasmError>| result.unwrap_err().code;
+        assert_eq!(code(sign_payload_json("{", L2_KEY)), "INVALID_INPUT");
+        assert_eq!(code(sign_payload_json(TRANSFER, "0xzz")), "INVALID_KEY");
+        assert_eq!(
+            code(sign_payload_json(r#"{"type": "transfer"}"#, L2_KEY)),
+            "INVALID_PAYLOAD"
+        );
+        assert_eq!(code(l2_keys("not hex")), "KEY_DERIVATION");
+        assert_eq!(
+            sign_request_json(r#"{"method": "GET"}"#, L2_KEY)
+                .unwrap_err()
+                .code,
+            "INVALID_REQUEST"
+        );
+        assert_eq!(
+            code(preview_json(TRANSFER, "{}", &FixedClock(0))),
+            "INVALID_INPUT"
+        );
+        assert_eq!(
+            verify("0x00", "0x01", "0x02", "0x03").unwrap_err().code,
+            "INVALID_SIGNATURE"
+        );
+    }
+}